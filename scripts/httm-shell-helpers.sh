@@ -0,0 +1,21 @@
+# HTTM Shell Helpers
+#
+# Source these helpers from your shell's rc file like so:
+#
+#   eval "$(httm --shell-helpers)"
+
+# cds - cd into the most recent unique snapshot version of the working directory,
+# or of the directory specified, as found by httm
+cds() {
+
+  local snap_dir
+  snap_dir="$(command httm --no-live --raw "${1:-$PWD}" | tail -n 1)"
+
+  if [ -z "$snap_dir" ]; then
+    echo "cds: httm could not find a snapshot version of ${1:-$PWD}" >&2
+    return 1
+  fi
+
+  cd "$snap_dir"
+
+}
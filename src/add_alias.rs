@@ -0,0 +1,280 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeSet,
+    ffi::{OsStr, OsString},
+    fs::{read_dir, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::forensic::deny_if_forensic;
+use crate::parse_aliases::{get_aliases_file_path, read_aliases_file, ALIASES_FILE_NAME};
+use crate::parse_mounts::parse_datasets;
+use crate::parse_snaps::precompute_snap_mounts_for_dataset;
+use crate::utility::HttmError;
+use crate::HttmResult;
+
+// probe the remote dir the way a lookup would, suggest a local dir for it, and, once the user
+// agrees, add the alias to the aliases file, so it needn't be given again with MAP_ALIASES
+pub fn add_alias(raw_remote_dir: &OsStr) -> HttmResult<()> {
+    deny_if_forensic("add an alias")?;
+
+    let remote_dir = Path::new(raw_remote_dir).canonicalize().map_err(|err| {
+        HttmError::with_context(
+            &format!("httm could not find the remote dir {:?}", raw_remote_dir),
+            err,
+        )
+    })?;
+
+    if !remote_dir.is_dir() {
+        let msg = format!("The remote dir {:?} is not a directory.", remote_dir);
+        return Err(HttmError::new(&msg));
+    }
+
+    // httm only reads the snapshots of datasets it finds mounted, so the remote dir should be one
+    let (map_of_datasets, _vec_of_filter_dirs) = parse_datasets()?;
+
+    let dataset_info = match map_of_datasets.get(&remote_dir) {
+        Some(dataset_info) => dataset_info.clone(),
+        None => {
+            let msg = format!(
+                "The remote dir {:?} is not the mount of any supported dataset, so httm would find no snapshots for an alias of it.  \
+                Specify the mount of the dataset, such as a network share, exactly as httm --list-datasets lists it.",
+                remote_dir
+            );
+            return Err(HttmError::new(&msg));
+        }
+    };
+
+    let snap_mounts = precompute_snap_mounts_for_dataset(&remote_dir, &dataset_info, None, None)
+        .map_err(|err| {
+            HttmError::with_context(
+                &format!(
+                    "httm could not read the snapshots within the remote dir {:?}",
+                    remote_dir
+                ),
+                err,
+            )
+        })?;
+
+    // stdin may well be our input, so we ask at the terminal
+    let mut tty = File::options()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|_| {
+            HttmError::new("httm must be run from a terminal to add an alias interactively.")
+        })?;
+
+    writeln!(
+        tty,
+        "The remote dir {:?} is the {} {} dataset {:?}, with {} snapshot(s).",
+        remote_dir,
+        dataset_info.mount_type,
+        dataset_info.fs_type,
+        dataset_info.name,
+        snap_mounts.len()
+    )?;
+
+    if snap_mounts.is_empty() && !ask_consent(&mut tty, "httm can find no versions of files on a dataset without snapshots.  Add an alias anyway?")? {
+        return Err(HttmError::new("User declined to add an alias.  No alias was added."));
+    }
+
+    let suggested_local_dir = suggest_local_dir(&remote_dir)?;
+
+    let answer = ask(
+        &mut tty,
+        &format!(
+            "Which local dir holds the live versions of the files on these snapshots? [{}] ",
+            suggested_local_dir.to_string_lossy()
+        ),
+    )?;
+
+    let raw_local_dir = if answer.is_empty() {
+        suggested_local_dir
+    } else {
+        PathBuf::from(answer)
+    };
+
+    let local_dir = raw_local_dir.canonicalize().map_err(|err| {
+        HttmError::with_context(
+            &format!("httm could not find the local dir {:?}", raw_local_dir),
+            err,
+        )
+    })?;
+
+    if !local_dir.is_dir() {
+        let msg = format!("The local dir {:?} is not a directory.", local_dir);
+        return Err(HttmError::new(&msg));
+    }
+
+    // each alias is stored as <LOCAL_DIR>:<REMOTE_DIR>
+    if local_dir.to_string_lossy().contains(':') {
+        let msg = format!(
+            "The local dir {:?} contains a ':', which httm cannot distinguish from the delimiter between an alias's dirs.",
+            local_dir
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    // a local dir which shares nothing with the newest snapshot is most likely the wrong dir
+    if let Some((num_found, num_entries)) = sample_newest_snap(&local_dir, &snap_mounts) {
+        writeln!(
+            tty,
+            "{} of the {} entries of {:?} are also found on the newest snapshot.",
+            num_found, num_entries, local_dir
+        )?;
+
+        if num_found == 0
+            && num_entries > 0
+            && !ask_consent(
+                &mut tty,
+                "This may not be the local dir whose versions are on these snapshots.  Add the alias anyway?",
+            )?
+        {
+            return Err(HttmError::new(
+                "User declined to add an alias.  No alias was added.",
+            ));
+        }
+    }
+
+    let alias = format!(
+        "{}:{}",
+        local_dir.to_string_lossy(),
+        remote_dir.to_string_lossy()
+    );
+
+    if !ask_consent(
+        &mut tty,
+        &format!(
+            "httm will add the alias {:?} to ~/{}.  Continue?",
+            alias, ALIASES_FILE_NAME
+        ),
+    )? {
+        return Err(HttmError::new(
+            "User declined to add an alias.  No alias was added.",
+        ));
+    }
+
+    write_alias(&local_dir, &alias)?;
+
+    eprintln!("httm: added alias: {}", alias);
+
+    std::process::exit(0)
+}
+
+// a remote share is most often mounted at some dir which ends with the path of its live
+// dir, like "/mnt/server/home/alice" for "/home/alice", so we try the longest such path first
+fn suggest_local_dir(remote_dir: &Path) -> HttmResult<PathBuf> {
+    let components: Vec<&OsStr> = remote_dir
+        .components()
+        .skip(1)
+        .map(|component| component.as_os_str())
+        .collect();
+
+    let opt_suggestion = (1..components.len())
+        .map(|skip| Path::new("/").join(components[skip..].iter().collect::<PathBuf>()))
+        .find(|candidate| candidate.is_dir() && !candidate.starts_with(remote_dir));
+
+    match opt_suggestion {
+        Some(suggestion) => Ok(suggestion),
+        None => Ok(std::env::current_dir()?),
+    }
+}
+
+// of the entries of the local dir, how many are also found on the newest snapshot
+fn sample_newest_snap(local_dir: &Path, snap_mounts: &[PathBuf]) -> Option<(usize, usize)> {
+    let newest_snap_mount = snap_mounts.iter().max_by_key(|snap_mount| {
+        snap_mount
+            .metadata()
+            .and_then(|md| md.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    })?;
+
+    let read_names = |dir: &Path| -> Option<BTreeSet<OsString>> {
+        Some(
+            read_dir(dir)
+                .ok()?
+                .flatten()
+                .map(|entry| entry.file_name())
+                .collect(),
+        )
+    };
+
+    let local_names = read_names(local_dir)?;
+    let snap_names = read_names(newest_snap_mount)?;
+
+    Some((
+        local_names.intersection(&snap_names).count(),
+        local_names.len(),
+    ))
+}
+
+fn ask(tty: &mut File, prompt: &str) -> HttmResult<String> {
+    write!(tty, "{}", prompt)?;
+    tty.flush()?;
+
+    let mut answer = String::new();
+    BufReader::new(&*tty).read_line(&mut answer)?;
+
+    Ok(answer.trim().to_owned())
+}
+
+fn ask_consent(tty: &mut File, prompt: &str) -> HttmResult<bool> {
+    let answer = ask(tty, &format!("{} (YES/NO) ", prompt))?;
+
+    Ok(matches!(answer.to_ascii_uppercase().as_ref(), "YES" | "Y"))
+}
+
+// any alias already given for the same local dir is replaced, as only one could be used
+fn write_alias(local_dir: &Path, alias: &str) -> HttmResult<()> {
+    let aliases_file_path = get_aliases_file_path()?;
+
+    let mut aliases: Vec<String> = read_aliases_file(&aliases_file_path)?
+        .into_iter()
+        .map(|(_line_num, alias)| alias)
+        .filter(|existing| {
+            existing
+                .split_once(':')
+                .map(|(existing_local_dir, _remote_dir)| Path::new(existing_local_dir) != local_dir)
+                .unwrap_or(true)
+        })
+        .collect();
+
+    aliases.push(alias.to_owned());
+
+    let aliases_buffer: String = aliases.iter().map(|alias| format!("{}\n", alias)).collect();
+
+    // should overwrite the file always, we just read in all the old aliases
+    let mut aliases_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&aliases_file_path)
+        .map_err(|err| {
+            HttmError::with_context(
+                "httm could not write to the aliases file in the user's home directory",
+                err,
+            )
+        })?;
+    aliases_file.write_all(aliases_buffer.as_bytes())?;
+
+    Ok(())
+}
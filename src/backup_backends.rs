@@ -0,0 +1,1112 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    process::{Command as ExecProcess, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use lazy_static::lazy_static;
+use rayon::prelude::*;
+use serde_json::Value;
+use time::UtcOffset;
+use which::which;
+
+use crate::display::{display_human_size, get_fancy_border_string};
+use crate::forensic::deny_if_forensic;
+use crate::lookup_versions::{get_snap_provenance, versions_lookup_exec};
+use crate::restrict_owner::{as_invoking_user, deny_if_restricted_to_owner, deny_unless_owned};
+use crate::utility::{
+    get_date, parse_date_time, print_output_buf, DateFormat, HttmError, PathData,
+};
+use crate::{Config, HttmResult};
+
+// any backend not built in is an executable upon the PATH, named for the backend, such as
+// "httm-backend-s3" for "--backup-backend=s3", see ExternalSource.  httm is only a binary, so
+// such an executable is the one way to add a backend, short of patching httm itself
+const EXTERNAL_SOURCE_PREFIX: &str = "httm-backend-";
+
+// a source of snapshots other than a dataset's, such as the repository of a chunk-based backup
+// tool, which httm may ask for the versions of live paths, and restore from
+trait SnapshotSource: Send + Sync {
+    // the name by which "--backup-backend" selects the source, and labels its snapshots
+    fn name(&self) -> &str;
+
+    // every snapshot which may hold a version of the live path
+    fn enumerate_snapshots(&self, live_path: &Path) -> HttmResult<Vec<SourceSnapshot>>;
+
+    // the version of the live path within the snapshot, if the snapshot holds one
+    fn resolve_version_path(
+        &self,
+        snapshot: &SourceSnapshot,
+        live_path: &Path,
+    ) -> HttmResult<Option<SourceVersion>>;
+
+    // write the contents of the version to out
+    fn read_version(&self, version: &SourceVersion, live_path: &Path, out: Stdio)
+        -> HttmResult<()>;
+
+    // copy the version to a new file at dest, with the version's modify time
+    fn restore_version(
+        &self,
+        version: &SourceVersion,
+        live_path: &Path,
+        dest: &Path,
+    ) -> HttmResult<()> {
+        // don't let the user rewrite one restore over another, and, restricted to the invoking
+        // user, the new file is created as, and so belongs to, them
+        let new_file = as_invoking_user(|| {
+            OpenOptions::new().write(true).create_new(true).open(dest)
+        })
+        .map_err(|err| match err.kind() {
+                std::io::ErrorKind::AlreadyExists => HttmError::new(&format!(
+                    "httm will not restore to {:?}, as a file with the same path name already exists. Quitting.",
+                    dest
+                )),
+                _ => HttmError::with_context(&format!("httm could not restore to {:?}", dest), err),
+            })?;
+        let restored_file = new_file.try_clone()?;
+
+        if let Err(err) = self.read_version(version, live_path, Stdio::from(new_file)) {
+            let _ = as_invoking_user(|| std::fs::remove_file(dest));
+            return Err(err);
+        }
+
+        restored_file.set_modified(version.modify_time)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SourceSnapshot {
+    // e.g. a kopia snapshot's id, or a duplicacy revision's number
+    id: String,
+    snap_time: SystemTime,
+    // the live dir of which this is a snapshot, e.g. a kopia source, or a duplicacy repository
+    root: PathBuf,
+    // whatever else the source needs to find paths within the snapshot
+    locator: String,
+}
+
+#[derive(Debug, Clone)]
+struct SourceVersion {
+    snapshot: SourceSnapshot,
+    modify_time: SystemTime,
+    size: u64,
+    // whatever the source needs to read the version back, e.g. a kopia object id
+    locator: String,
+}
+
+lazy_static! {
+    static ref BUILT_IN_SOURCES: Vec<Arc<dyn SnapshotSource>> = vec![
+        Arc::new(KopiaSource::default()),
+        Arc::new(DuplicacySource::default()),
+    ];
+}
+
+fn get_snapshot_source(name: &str) -> HttmResult<Arc<dyn SnapshotSource>> {
+    if let Some(source) = BUILT_IN_SOURCES.iter().find(|source| source.name() == name) {
+        return Ok(source.clone());
+    }
+
+    let external_program = format!("{}{}", EXTERNAL_SOURCE_PREFIX, name);
+
+    match which(&external_program) {
+        Ok(command) => Ok(Arc::new(ExternalSource {
+            name: name.to_owned(),
+            command,
+        })),
+        Err(_) => {
+            let built_in: Vec<&str> = BUILT_IN_SOURCES
+                .iter()
+                .map(|source| source.name())
+                .collect();
+
+            let msg = format!(
+                "httm knows no backup backend named {:?}.  The backends built in are: {}, and any other may be added as an executable named {:?} upon the PATH.",
+                name,
+                built_in.join(", "),
+                external_program
+            );
+            Err(HttmError::new(&msg))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupQuery {
+    // only a coverage report may ask more than one backend
+    pub backends: Vec<String>,
+    // restore the version within this snapshot, rather than display every version
+    pub opt_restore_snapshot: Option<String>,
+    // report which versions are upon local snapshots, which within the backends, and which both
+    pub opt_coverage: bool,
+}
+
+pub fn backup_exec(config: &Config, query: &BackupQuery) -> HttmResult<()> {
+    // each backend is another program, run as we are, which may read whatever we may
+    deny_if_restricted_to_owner("run a backup backend")?;
+
+    let sources: Vec<Arc<dyn SnapshotSource>> = query
+        .backends
+        .iter()
+        .map(|backend| get_snapshot_source(backend))
+        .collect::<HttmResult<_>>()?;
+
+    if query.opt_coverage {
+        return display_coverage(config, &sources);
+    }
+
+    let source = sources
+        .first()
+        .expect("clap requires a BACKEND with BACKUP_BACKEND");
+
+    match &query.opt_restore_snapshot {
+        Some(snapshot) => restore_backup_versions(config, source.as_ref(), snapshot),
+        None => display_backup_versions(config, source.as_ref()),
+    }
+}
+
+fn get_source_versions(
+    source: &dyn SnapshotSource,
+    live_path: &Path,
+) -> HttmResult<Vec<SourceVersion>> {
+    // a backup keeps no owner we could check, so only the owner of the live file may look
+    deny_unless_owned("look up the backups of", live_path)?;
+
+    let snapshots = source.enumerate_snapshots(live_path)?;
+
+    let mut versions: Vec<SourceVersion> = snapshots
+        .par_iter()
+        .map(|snapshot| source.resolve_version_path(snapshot, live_path))
+        .collect::<HttmResult<Vec<Option<SourceVersion>>>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    versions.sort_by_key(|version| version.snapshot.snap_time);
+
+    Ok(versions)
+}
+
+fn display_backup_versions(config: &Config, source: &dyn SnapshotSource) -> HttmResult<()> {
+    let mut out_buffer = String::new();
+
+    config.paths.iter().try_for_each(|pathdata| {
+        let mut versions = get_source_versions(source, &pathdata.path_buf)?;
+
+        // like any other lookup, only those versions which differ from the version before
+        versions.dedup_by(|later, earlier| {
+            later.size == earlier.size && later.modify_time == earlier.modify_time
+        });
+
+        if versions.is_empty() {
+            return Err(HttmError::NoVersions {
+                msg: format!(
+                    "httm could not find any versions of {:?} within the {} repository.",
+                    pathdata.path_buf,
+                    source.name()
+                ),
+                path: Some(pathdata.path_buf.clone()),
+            });
+        }
+
+        out_buffer.push_str(&format_backup_versions(
+            config,
+            source.name(),
+            pathdata,
+            &versions,
+        ));
+        Ok(())
+    })?;
+
+    print_output_buf(out_buffer)
+}
+
+fn format_backup_versions(
+    config: &Config,
+    source_name: &str,
+    pathdata: &PathData,
+    versions: &[SourceVersion],
+) -> String {
+    let path = pathdata.path_buf.to_string_lossy();
+
+    if config.opt_raw || config.opt_zeros {
+        let delimiter = if config.opt_zeros { '\0' } else { '\n' };
+
+        return versions
+            .iter()
+            .map(|version| {
+                format!(
+                    "{}:{}\t{}{}",
+                    source_name, version.snapshot.id, path, delimiter
+                )
+            })
+            .collect();
+    }
+
+    // snapshot versions, each labeled with the snapshot restore would ask for, then the live version
+    let rows: Vec<(String, String, String)> = versions
+        .iter()
+        .map(|version| {
+            (
+                get_date(config, &version.modify_time, DateFormat::Display),
+                display_human_size(&version.size),
+                format!("{}:{}", source_name, version.snapshot.id),
+            )
+        })
+        .chain(pathdata.metadata().map(|path_metadata| {
+            (
+                get_date(config, &path_metadata.modify_time, DateFormat::Display),
+                display_human_size(&path_metadata.size),
+                "live".to_owned(),
+            )
+        }))
+        .collect();
+
+    let date_width = rows
+        .iter()
+        .map(|(date, _size, _label)| date.len())
+        .max()
+        .unwrap_or_default();
+    let size_width = rows
+        .iter()
+        .map(|(_date, size, _label)| size.len())
+        .max()
+        .unwrap_or_default();
+
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|(date, size, label)| {
+            format!(
+                "{:<date_width$}  {:>size_width$}  \"{}\"  {}\n",
+                date, size, path, label
+            )
+        })
+        .collect();
+
+    let border_len = lines
+        .iter()
+        .map(|line| line.trim_end().chars().count())
+        .max()
+        .unwrap_or_default();
+    let fancy_border_string = get_fancy_border_string(border_len);
+
+    let mut buffer = fancy_border_string.clone();
+    lines.iter().enumerate().for_each(|(idx, line)| {
+        if idx == versions.len() {
+            buffer.push_str(&fancy_border_string);
+        }
+        buffer.push_str(line);
+    });
+    buffer.push_str(&fancy_border_string);
+
+    buffer
+}
+
+// as an interactive restore does, a copy of the version is placed in the working directory,
+// named for the file and its modify time
+fn restore_backup_versions(
+    config: &Config,
+    source: &dyn SnapshotSource,
+    requested_snapshot: &str,
+) -> HttmResult<()> {
+    deny_if_forensic("restore from a backup")?;
+
+    // a snapshot may be given just as it is labeled, with the name of its backend
+    let requested_snapshot = requested_snapshot
+        .strip_prefix(&format!("{}:", source.name()))
+        .unwrap_or(requested_snapshot);
+
+    config.paths.iter().try_for_each(|pathdata| {
+        let versions = get_source_versions(source, &pathdata.path_buf)?;
+        let version = select_version(&versions, requested_snapshot).ok_or_else(|| {
+            HttmError::NoVersions {
+                msg: format!(
+                    "httm could not find a version of {:?} within the {} snapshot {:?}, or more than one snapshot begins with {:?}.",
+                    pathdata.path_buf, source.name(), requested_snapshot, requested_snapshot
+                ),
+                path: Some(pathdata.path_buf.clone()),
+            }
+        })?;
+
+        let file_name = pathdata
+            .path_buf
+            .file_name()
+            .ok_or_else(|| HttmError::new("httm could not obtain a file name for the path given"))?
+            .to_string_lossy()
+            .into_owned();
+
+        let new_file_path_buf = config.pwd.path_buf.join(format!(
+            "{}.httm_restored.{}",
+            file_name,
+            get_date(config, &version.modify_time, DateFormat::Timestamp)
+        ));
+
+        source.restore_version(version, &pathdata.path_buf, &new_file_path_buf)?;
+
+        eprintln!(
+            "httm copied a file from a {} backup:\n\n\
+            \tfrom: {:?}, within snapshot {}\n\
+            \tto:   {:?}\n\n\
+            Restore completed successfully.",
+            source.name(),
+            pathdata.path_buf,
+            version.snapshot.id,
+            new_file_path_buf
+        );
+
+        Ok(())
+    })
+}
+
+// one unique version, by modify time and size, and, of each place it might be found, where it was
+#[derive(Debug, Clone)]
+struct CoverageRow {
+    modify_time: SystemTime,
+    size: u64,
+    // the snapshot version upon a local dataset, if any
+    opt_local: Option<PathData>,
+    // the snapshot which holds the version, for each backend, in the order requested
+    offsite: Vec<Option<String>>,
+}
+
+impl CoverageRow {
+    fn status(&self) -> &'static str {
+        let is_offsite = self.offsite.iter().any(Option::is_some);
+
+        match (self.opt_local.is_some(), is_offsite) {
+            (true, true) => "both",
+            (true, false) => "local-only",
+            (false, _) => "offsite-only",
+        }
+    }
+}
+
+// some backends, like duplicacy, keep modify times no finer than whole seconds, so a version
+// is matched across a local snapshot and a backend by its modify time only to the second
+fn get_coverage_key(modify_time: SystemTime, size: u64) -> (u64, u64) {
+    let secs = modify_time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    (secs, size)
+}
+
+fn display_coverage(config: &Config, sources: &[Arc<dyn SnapshotSource>]) -> HttmResult<()> {
+    let mut out_buffer = String::new();
+
+    config.paths.iter().try_for_each(|pathdata| {
+        let rows = get_coverage_rows(config, sources, pathdata)?;
+
+        if rows.is_empty() {
+            return Err(HttmError::NoVersions {
+                msg: format!(
+                    "httm could not find any versions of {:?}, either upon a local snapshot or within any backend requested.",
+                    pathdata.path_buf
+                ),
+                path: Some(pathdata.path_buf.clone()),
+            });
+        }
+
+        out_buffer.push_str(&format_coverage(config, sources, pathdata, &rows));
+        Ok(())
+    })?;
+
+    print_output_buf(out_buffer)
+}
+
+fn get_coverage_rows(
+    config: &Config,
+    sources: &[Arc<dyn SnapshotSource>],
+    pathdata: &PathData,
+) -> HttmResult<Vec<CoverageRow>> {
+    // a path with no versions at all, in one place or another, is just what a coverage report is for
+    let local_versions = match versions_lookup_exec(config, std::slice::from_ref(pathdata)) {
+        Ok(snaps_and_live_set) => snaps_and_live_set[0].clone(),
+        Err(HttmError::NoVersions { .. }) => Vec::new(),
+        Err(err) => return Err(err),
+    };
+
+    let mut rows: BTreeMap<(u64, u64), CoverageRow> = BTreeMap::new();
+
+    local_versions.into_iter().for_each(|version| {
+        let md = version.md_infallible();
+        rows.entry(get_coverage_key(md.modify_time, md.size))
+            .or_insert_with(|| CoverageRow {
+                modify_time: md.modify_time,
+                size: md.size,
+                opt_local: None,
+                offsite: vec![None; sources.len()],
+            })
+            .opt_local
+            .get_or_insert(version);
+    });
+
+    sources.iter().enumerate().try_for_each(|(idx, source)| {
+        let versions = match get_source_versions(source.as_ref(), &pathdata.path_buf) {
+            Ok(versions) => versions,
+            Err(HttmError::NoVersions { .. }) => Vec::new(),
+            Err(err) => return Err(err),
+        };
+
+        // versions are oldest first, so each names the first snapshot to hold it
+        versions.into_iter().for_each(|version| {
+            rows.entry(get_coverage_key(version.modify_time, version.size))
+                .or_insert_with(|| CoverageRow {
+                    modify_time: version.modify_time,
+                    size: version.size,
+                    opt_local: None,
+                    offsite: vec![None; sources.len()],
+                })
+                .offsite[idx]
+                .get_or_insert(version.snapshot.id);
+        });
+
+        Ok(())
+    })?;
+
+    Ok(rows.into_values().collect())
+}
+
+fn format_coverage(
+    config: &Config,
+    sources: &[Arc<dyn SnapshotSource>],
+    pathdata: &PathData,
+    rows: &[CoverageRow],
+) -> String {
+    let path = pathdata.path_buf.to_string_lossy();
+
+    if config.opt_raw || config.opt_zeros {
+        let delimiter = if config.opt_zeros { '\0' } else { '\n' };
+
+        return rows
+            .iter()
+            .map(|row| {
+                format!(
+                    "{}\t{}\t{}\t{}{}",
+                    row.status(),
+                    get_date(config, &row.modify_time, DateFormat::Timestamp),
+                    row.size,
+                    path,
+                    delimiter
+                )
+            })
+            .collect();
+    }
+
+    let header: Vec<String> = ["DATE", "SIZE", "STATUS", "LOCAL"]
+        .iter()
+        .map(|column| column.to_string())
+        .chain(sources.iter().map(|source| source.name().to_uppercase()))
+        .collect();
+
+    let mut table: Vec<Vec<String>> = vec![header];
+
+    table.extend(rows.iter().map(|row| {
+        let local = match &row.opt_local {
+            Some(version) => get_snap_name(config, version, pathdata),
+            None => "-".to_owned(),
+        };
+
+        [
+            get_date(config, &row.modify_time, DateFormat::Display),
+            display_human_size(&row.size),
+            row.status().to_owned(),
+            local,
+        ]
+        .into_iter()
+        .chain(
+            row.offsite
+                .iter()
+                .map(|opt_id| opt_id.clone().unwrap_or_else(|| "-".to_owned())),
+        )
+        .collect()
+    }));
+
+    let widths: Vec<usize> = (0..table[0].len())
+        .map(|column| {
+            table
+                .iter()
+                .map(|cells| cells[column].chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let lines: Vec<String> = table
+        .iter()
+        .map(|cells| {
+            let line: Vec<String> = cells
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:<width$}", cell, width = *width))
+                .collect();
+            format!("{}\n", line.join("  ").trim_end())
+        })
+        .collect();
+
+    let border_len = lines
+        .iter()
+        .map(|line| line.trim_end().chars().count())
+        .max()
+        .unwrap_or_default();
+    let fancy_border_string = get_fancy_border_string(border_len);
+
+    let count = |status: &str| rows.iter().filter(|row| row.status() == status).count();
+
+    // the newest version is the one most likely lost, should the local pool be lost too
+    let newest_local = rows.iter().rev().find(|row| row.opt_local.is_some());
+    let newest_warning = match newest_local {
+        Some(row) if row.status() == "local-only" => format!(
+            "  The newest local version, of {}, is within no backend.",
+            get_date(config, &row.modify_time, DateFormat::Display)
+        ),
+        _ => String::new(),
+    };
+
+    let mut buffer = format!("{:?}\n", pathdata.path_buf);
+    buffer.push_str(&fancy_border_string);
+    buffer.push_str(&lines[0]);
+    buffer.push_str(&fancy_border_string);
+    lines[1..].iter().for_each(|line| buffer.push_str(line));
+    buffer.push_str(&fancy_border_string);
+    buffer.push_str(&format!(
+        "{} version(s): {} both, {} local only, {} offsite only.{}\n\n",
+        rows.len(),
+        count("both"),
+        count("local-only"),
+        count("offsite-only"),
+        newest_warning
+    ));
+
+    buffer
+}
+
+// a local snapshot version is labeled with the name of its snapshot, as restore would show it
+fn get_snap_name(config: &Config, version: &PathData, live_pathdata: &PathData) -> String {
+    get_snap_provenance(
+        config,
+        &version.path_buf,
+        std::slice::from_ref(live_pathdata),
+    )
+    .map(|provenance| provenance.snap_name)
+    .unwrap_or_else(|| version.path_buf.to_string_lossy().into_owned())
+}
+
+// an exact match, or else the only snapshot which begins with what was requested
+fn select_version<'a>(
+    versions: &'a [SourceVersion],
+    requested_snapshot: &str,
+) -> Option<&'a SourceVersion> {
+    if let Some(version) = versions
+        .iter()
+        .find(|version| version.snapshot.id == requested_snapshot)
+    {
+        return Some(version);
+    }
+
+    let mut candidates = versions
+        .iter()
+        .filter(|version| version.snapshot.id.starts_with(requested_snapshot));
+
+    match (candidates.next(), candidates.next()) {
+        (Some(version), None) => Some(version),
+        _ => None,
+    }
+}
+
+// kopia snapshots each source path, and lists the snapshots of every source as JSON.  Within a
+// snapshot, each directory is itself an object, a JSON listing of its entries, so we walk from
+// the snapshot's root entry to the path.  Objects are content addressed, so a directory which
+// is unchanged between snapshots need only be read once.
+#[derive(Default)]
+struct KopiaSource {
+    dir_cache: Mutex<HashMap<String, Option<Value>>>,
+}
+
+impl KopiaSource {
+    fn read_dir_object(&self, object_id: &str) -> HttmResult<Option<Value>> {
+        if let Some(cached) = self
+            .dir_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(object_id)
+        {
+            return Ok(cached.clone());
+        }
+
+        let opt_dir_listing = serde_json::from_slice(&run_for_output(
+            "kopia",
+            ExecProcess::new(get_command("kopia")?).args(["show", object_id]),
+        )?)
+        .ok();
+
+        self.dir_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(object_id.to_owned(), opt_dir_listing.clone());
+
+        Ok(opt_dir_listing)
+    }
+}
+
+impl SnapshotSource for KopiaSource {
+    fn name(&self) -> &str {
+        "kopia"
+    }
+
+    fn enumerate_snapshots(&self, live_path: &Path) -> HttmResult<Vec<SourceSnapshot>> {
+        let manifests: Value = serde_json::from_slice(&run_for_output(
+            "kopia",
+            ExecProcess::new(get_command("kopia")?).args(["snapshot", "list", "--json"]),
+        )?)?;
+
+        let manifests = manifests.as_array().cloned().unwrap_or_default();
+
+        // the most proximate source which contains the path
+        let source_path: PathBuf = manifests
+            .iter()
+            .filter_map(|manifest| manifest["source"]["path"].as_str())
+            .map(Path::new)
+            .filter(|source_path| live_path.starts_with(source_path))
+            .max_by_key(|source_path| source_path.components().count())
+            .map(|source_path| source_path.to_path_buf())
+            .ok_or_else(|| HttmError::NoVersions {
+                msg: format!(
+                    "httm could not find any kopia snapshot source which contains {:?}.",
+                    live_path
+                ),
+                path: Some(live_path.to_path_buf()),
+            })?;
+
+        let snapshots = manifests
+            .iter()
+            .filter(|manifest| {
+                manifest["source"]["path"].as_str().map(Path::new) == Some(&source_path)
+            })
+            .filter_map(|manifest| {
+                Some(SourceSnapshot {
+                    id: manifest["id"].as_str()?.to_owned(),
+                    snap_time: parse_rfc3339(manifest["startTime"].as_str()?)?,
+                    root: source_path.clone(),
+                    locator: manifest["rootEntry"].to_string(),
+                })
+            })
+            .collect();
+
+        Ok(snapshots)
+    }
+
+    fn resolve_version_path(
+        &self,
+        snapshot: &SourceSnapshot,
+        live_path: &Path,
+    ) -> HttmResult<Option<SourceVersion>> {
+        let relative_path = match live_path.strip_prefix(&snapshot.root) {
+            Ok(relative_path) => relative_path,
+            Err(_) => return Ok(None),
+        };
+
+        // a source may be a single file, and then the root entry is that file
+        let mut entry: Value = serde_json::from_str(&snapshot.locator)?;
+
+        for component in relative_path.components() {
+            let name = component.as_os_str().to_string_lossy();
+
+            let opt_dir_listing = match (entry["type"].as_str(), entry["obj"].as_str()) {
+                (Some("d"), Some(object_id)) => self.read_dir_object(object_id)?,
+                _ => None,
+            };
+
+            let opt_entry = opt_dir_listing.and_then(|dir_listing| {
+                dir_listing["entries"]
+                    .as_array()?
+                    .iter()
+                    .find(|entry| entry["name"].as_str() == Some(name.as_ref()))
+                    .cloned()
+            });
+
+            match opt_entry {
+                Some(found) => entry = found,
+                None => return Ok(None),
+            }
+        }
+
+        if entry["type"].as_str() != Some("f") {
+            return Ok(None);
+        }
+
+        let opt_version = (|| {
+            Some(SourceVersion {
+                snapshot: snapshot.clone(),
+                modify_time: parse_rfc3339(entry["mtime"].as_str()?)?,
+                size: entry["size"].as_u64().unwrap_or_default(),
+                locator: entry["obj"].as_str()?.to_owned(),
+            })
+        })();
+
+        Ok(opt_version)
+    }
+
+    fn read_version(
+        &self,
+        version: &SourceVersion,
+        live_path: &Path,
+        out: Stdio,
+    ) -> HttmResult<()> {
+        let mut process = ExecProcess::new(get_command("kopia")?);
+        process.args(["show", &version.locator]);
+
+        run_for_contents("kopia", &mut process, live_path, version, out)
+    }
+}
+
+// duplicacy backs up a repository, a directory initialized with a ".duplicacy" dir, and must
+// be run from within it.  A single list of every revision, with the files of each, is enough,
+// and, with TZ set to UTC, its times are in UTC.  Lines are like:
+//
+// Snapshot docs revision 2 created at 2022-08-01 12:00
+//     1024 2022-07-30 11:22:33 4f7c...e1a9 notes/todo.txt
+#[derive(Default)]
+struct DuplicacySource {
+    listing_cache: Mutex<HashMap<PathBuf, Arc<Vec<DuplicacyRevision>>>>,
+}
+
+struct DuplicacyRevision {
+    revision: String,
+    snap_time: SystemTime,
+    // relative path -> (size, modify time)
+    files: HashMap<String, (u64, SystemTime)>,
+}
+
+impl DuplicacySource {
+    fn get_revisions(&self, repository: &Path) -> HttmResult<Arc<Vec<DuplicacyRevision>>> {
+        if let Some(cached) = self
+            .listing_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(repository)
+        {
+            return Ok(cached.clone());
+        }
+
+        let listing = run_for_output(
+            "duplicacy",
+            ExecProcess::new(get_command("duplicacy")?)
+                .current_dir(repository)
+                .env("TZ", "UTC")
+                .args(["list", "-files"]),
+        )?;
+
+        let revisions = Arc::new(parse_duplicacy_listing(&String::from_utf8_lossy(&listing)));
+
+        self.listing_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(repository.to_path_buf(), revisions.clone());
+
+        Ok(revisions)
+    }
+}
+
+impl SnapshotSource for DuplicacySource {
+    fn name(&self) -> &str {
+        "duplicacy"
+    }
+
+    fn enumerate_snapshots(&self, live_path: &Path) -> HttmResult<Vec<SourceSnapshot>> {
+        let repository = get_duplicacy_repository(live_path)?;
+
+        let snapshots = self
+            .get_revisions(&repository)?
+            .iter()
+            .map(|revision| SourceSnapshot {
+                id: revision.revision.clone(),
+                snap_time: revision.snap_time,
+                root: repository.clone(),
+                locator: String::new(),
+            })
+            .collect();
+
+        Ok(snapshots)
+    }
+
+    fn resolve_version_path(
+        &self,
+        snapshot: &SourceSnapshot,
+        live_path: &Path,
+    ) -> HttmResult<Option<SourceVersion>> {
+        let relative_path = match live_path.strip_prefix(&snapshot.root) {
+            Ok(relative_path) => relative_path.to_string_lossy().into_owned(),
+            Err(_) => return Ok(None),
+        };
+
+        let revisions = self.get_revisions(&snapshot.root)?;
+
+        let opt_version = revisions
+            .iter()
+            .find(|revision| revision.revision == snapshot.id)
+            .and_then(|revision| revision.files.get(&relative_path))
+            .map(|(size, modify_time)| SourceVersion {
+                snapshot: snapshot.clone(),
+                modify_time: *modify_time,
+                size: *size,
+                locator: relative_path.clone(),
+            });
+
+        Ok(opt_version)
+    }
+
+    fn read_version(
+        &self,
+        version: &SourceVersion,
+        live_path: &Path,
+        out: Stdio,
+    ) -> HttmResult<()> {
+        let mut process = ExecProcess::new(get_command("duplicacy")?);
+        process.current_dir(&version.snapshot.root).args([
+            "cat",
+            "-r",
+            &version.snapshot.id,
+            &version.locator,
+        ]);
+
+        run_for_contents("duplicacy", &mut process, live_path, version, out)
+    }
+}
+
+fn parse_duplicacy_listing(listing: &str) -> Vec<DuplicacyRevision> {
+    let mut revisions: Vec<DuplicacyRevision> = Vec::new();
+
+    listing.lines().for_each(|line| {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if let ["Snapshot", _id, "revision", revision, "created", "at", date, time, ..] =
+            tokens.as_slice()
+        {
+            if let Some(snap_time) = parse_date_time(date, time, UtcOffset::UTC) {
+                revisions.push(DuplicacyRevision {
+                    revision: revision.to_string(),
+                    snap_time,
+                    files: HashMap::new(),
+                });
+            }
+            return;
+        }
+
+        let revision = match revisions.last_mut() {
+            Some(revision) => revision,
+            None => return,
+        };
+
+        // a dir has no hash, and so is skipped
+        if let [size, date, time, hash, ..] = tokens.as_slice() {
+            if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return;
+            }
+
+            // the path is all which follows the hash, and may itself contain spaces
+            let opt_path = line
+                .find(hash)
+                .map(|hash_idx| line[hash_idx + hash.len()..].trim_start());
+
+            if let (Some(path), Ok(size), Some(modify_time)) = (
+                opt_path,
+                size.parse::<u64>(),
+                parse_date_time(date, time, UtcOffset::UTC),
+            ) {
+                revision.files.insert(path.to_owned(), (size, modify_time));
+            }
+        }
+    });
+
+    revisions
+}
+
+// a backend added without patching httm, as an executable upon the PATH, named for the
+// backend, which answers three subcommands, each given the live path last:
+//
+// httm-backend-NAME snapshots LIVE_PATH
+//   prints a JSON array of snapshots, each like {"id": "..", "time": SECS, "root": "/live/dir", "locator": ".."}
+// httm-backend-NAME resolve SNAPSHOT_ID SNAPSHOT_LOCATOR LIVE_PATH
+//   prints the version within the snapshot as JSON, like {"modify_time": SECS, "size": BYTES, "locator": ".."}, or null
+// httm-backend-NAME read SNAPSHOT_ID VERSION_LOCATOR LIVE_PATH
+//   writes the contents of the version to stdout
+//
+// where each time is in seconds since the epoch, and each locator is opaque to httm, and optional
+struct ExternalSource {
+    name: String,
+    command: PathBuf,
+}
+
+impl SnapshotSource for ExternalSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn enumerate_snapshots(&self, live_path: &Path) -> HttmResult<Vec<SourceSnapshot>> {
+        let answer: Value = serde_json::from_slice(&run_for_output(
+            &self.name,
+            ExecProcess::new(&self.command)
+                .arg("snapshots")
+                .arg(live_path),
+        )?)?;
+
+        let snapshots = answer
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|snapshot| {
+                Some(SourceSnapshot {
+                    id: snapshot["id"].as_str()?.to_owned(),
+                    snap_time: SystemTime::UNIX_EPOCH
+                        + Duration::from_secs(snapshot["time"].as_u64()?),
+                    root: PathBuf::from(snapshot["root"].as_str().unwrap_or_default()),
+                    locator: snapshot["locator"].as_str().unwrap_or_default().to_owned(),
+                })
+            })
+            .collect();
+
+        Ok(snapshots)
+    }
+
+    fn resolve_version_path(
+        &self,
+        snapshot: &SourceSnapshot,
+        live_path: &Path,
+    ) -> HttmResult<Option<SourceVersion>> {
+        let answer: Value = serde_json::from_slice(&run_for_output(
+            &self.name,
+            ExecProcess::new(&self.command)
+                .args(["resolve", &snapshot.id, &snapshot.locator])
+                .arg(live_path),
+        )?)?;
+
+        if answer.is_null() {
+            return Ok(None);
+        }
+
+        let opt_version = (|| {
+            Some(SourceVersion {
+                snapshot: snapshot.clone(),
+                modify_time: SystemTime::UNIX_EPOCH
+                    + Duration::from_secs(answer["modify_time"].as_u64()?),
+                size: answer["size"].as_u64().unwrap_or_default(),
+                locator: answer["locator"].as_str().unwrap_or_default().to_owned(),
+            })
+        })();
+
+        Ok(opt_version)
+    }
+
+    fn read_version(
+        &self,
+        version: &SourceVersion,
+        live_path: &Path,
+        out: Stdio,
+    ) -> HttmResult<()> {
+        let mut process = ExecProcess::new(&self.command);
+        process
+            .args(["read", &version.snapshot.id, &version.locator])
+            .arg(live_path);
+
+        run_for_contents(&self.name, &mut process, live_path, version, out)
+    }
+}
+
+fn get_command(program: &str) -> HttmResult<PathBuf> {
+    which(program).map_err(|_| HttmError::CommandNotFound(program.to_owned()))
+}
+
+fn run_for_output(program: &str, process: &mut ExecProcess) -> HttmResult<Vec<u8>> {
+    let output = process.output()?;
+
+    if !output.status.success() {
+        let msg = format!(
+            "{} exited unsuccessfully: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    Ok(output.stdout)
+}
+
+fn run_for_contents(
+    program: &str,
+    process: &mut ExecProcess,
+    live_path: &Path,
+    version: &SourceVersion,
+    out: Stdio,
+) -> HttmResult<()> {
+    let status = process.stdout(out).status()?;
+
+    if !status.success() {
+        return Err(HttmError::new(&format!(
+            "{} could not read the version of {:?} within snapshot {}.",
+            program, live_path, version.snapshot.id
+        )));
+    }
+
+    Ok(())
+}
+
+fn get_duplicacy_repository(live_path: &Path) -> HttmResult<PathBuf> {
+    live_path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.join(".duplicacy").exists())
+        .map(|repository| repository.to_path_buf())
+        .ok_or_else(|| HttmError::NoVersions {
+            msg: format!(
+                "httm could not find any duplicacy repository which contains {:?}.",
+                live_path
+            ),
+            path: Some(live_path.to_path_buf()),
+        })
+}
+
+// like 2022-08-01T12:00:00.123456789Z, or 2022-08-01T14:00:00+02:00
+fn parse_rfc3339(timestamp: &str) -> Option<SystemTime> {
+    let (date, rest) = timestamp.split_once('T')?;
+
+    let (time, offset) = if let Some(time) = rest.strip_suffix('Z') {
+        (time, UtcOffset::UTC)
+    } else {
+        let offset_idx = rest.rfind(['+', '-'])?;
+        let (time, offset) = rest.split_at(offset_idx);
+        let sign: i8 = if offset.starts_with('-') { -1 } else { 1 };
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let offset = UtcOffset::from_hms(
+            sign * hours.parse::<i8>().ok()?,
+            sign * minutes.parse::<i8>().ok()?,
+            0,
+        )
+        .ok()?;
+        (time, offset)
+    };
+
+    parse_date_time(date, time, offset)
+}
@@ -0,0 +1,149 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use crate::display::display_human_size;
+use crate::interactive::select_restore_view;
+use crate::lookup_versions::versions_lookup_exec;
+use crate::sandbox::exec_on_temp_copy;
+use crate::utility::{get_date, DateFormat, HttmError, PathData};
+use crate::{Config, HttmResult};
+
+enum Verdict {
+    Good,
+    Bad,
+}
+
+// like git bisect, find the first bad version of a file, where the live file is presumed bad,
+// by asking the user, or a test command, whether each version presented is good or bad
+pub fn bisect_exec(config: &Config, opt_test_command: &Option<String>) -> HttmResult<()> {
+    if config.paths.len() != 1 {
+        return Err(HttmError::new(
+            "httm can only bisect the versions of exactly one file at a time.",
+        ));
+    }
+
+    let [mut snap_versions, _live_versions] = versions_lookup_exec(config, &config.paths)?;
+
+    if snap_versions.is_empty() {
+        return Err(HttmError::NoVersions {
+            msg: "httm could find no snapshot versions to bisect.".to_owned(),
+            path: config
+                .paths
+                .first()
+                .map(|pathdata| pathdata.path_buf.clone()),
+        });
+    }
+
+    // versions may be sorted by some other key, but a bisect only makes sense in time
+    snap_versions.sort_by_key(|version| version.md_infallible().modify_time);
+
+    // the live file is the bad version just past all our snapshot versions, and there is
+    // a presumed good version just before them, so neither is ever tested
+    let mut last_good: Option<usize> = None;
+    let mut first_bad: usize = snap_versions.len();
+    let mut lower_bound: usize = 0;
+
+    while lower_bound < first_bad {
+        let idx = lower_bound + (first_bad - lower_bound) / 2;
+        let remaining = first_bad - lower_bound;
+
+        let verdict = match opt_test_command {
+            Some(test_command) => run_test_command(test_command, &snap_versions[idx])?,
+            None => ask_for_verdict(config, &snap_versions[idx], remaining)?,
+        };
+
+        match verdict {
+            Verdict::Good => {
+                last_good = Some(idx);
+                lower_bound = idx + 1;
+            }
+            Verdict::Bad => first_bad = idx,
+        }
+    }
+
+    if let Some(idx) = last_good {
+        eprintln!(
+            "httm bisect: the last good version is {:?}",
+            snap_versions[idx].path_buf
+        );
+    }
+
+    match snap_versions.get(first_bad) {
+        Some(version) => {
+            eprintln!("httm bisect: the first bad version is:");
+            println!("\"{}\"", version.path_buf.to_string_lossy());
+        }
+        None => {
+            eprintln!(
+                "httm bisect: every snapshot version is good, \
+                so the problem was introduced after the newest snapshot was taken."
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn ask_for_verdict(config: &Config, version: &PathData, remaining: usize) -> HttmResult<Verdict> {
+    let metadata = version.md_infallible();
+
+    let preview_buffer = format!(
+        "httm bisect has {} version(s) left to consider.  Is this version good or bad?\n\n\
+        \tversion: {:?}\n\
+        \tdate:    {}\n\
+        \tsize:    {}\n\n\
+        ──────────────────────────────────────────────────────────────────────────────\n\
+        GOOD\n\
+        BAD",
+        remaining,
+        version.path_buf,
+        get_date(config, &metadata.modify_time, DateFormat::Display),
+        display_human_size(&metadata.size),
+    );
+
+    // loop until user answers good or bad
+    loop {
+        let verdict = select_restore_view(config, &preview_buffer, true)?.to_ascii_uppercase();
+
+        match verdict.as_ref() {
+            "GOOD" => break Ok(Verdict::Good),
+            "BAD" => break Ok(Verdict::Bad),
+            // if not good or bad, then noop and continue to the next iter of loop
+            _ => {}
+        }
+    }
+}
+
+// like git bisect run, an exit status of 0 means good
+fn run_test_command(test_command: &str, version: &PathData) -> HttmResult<Verdict> {
+    let verdict = if exec_on_temp_copy(test_command, &version.path_buf)?.success() {
+        Verdict::Good
+    } else {
+        Verdict::Bad
+    };
+
+    eprintln!(
+        "httm bisect: {:?} is {}",
+        version.path_buf,
+        match verdict {
+            Verdict::Good => "good",
+            Verdict::Bad => "bad",
+        }
+    );
+
+    Ok(verdict)
+}
@@ -0,0 +1,186 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::path::PathBuf;
+
+use crate::forensic::deny_if_forensic;
+use crate::interactive::select_restore_view;
+use crate::open_files::open_files_advisory;
+use crate::progress::{display_copy_failures, CopyFailure};
+use crate::restore_attributes::restore_special_attributes;
+use crate::restrict_owner::deny_unless_restorable;
+use crate::set_aside::{get_set_aside_path, restore_over_live};
+use crate::utility::HttmError;
+use crate::{Config, HttmResult};
+
+// what to do when a file of the same name already exists where a file would be restored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    Skip,
+    Rename,
+    Overwrite,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedRestore {
+    pub from: PathBuf,
+    // None, when a conflict is to be skipped
+    pub opt_to: Option<PathBuf>,
+    // None, when there is no conflict at all
+    pub opt_conflict: Option<ConflictPolicy>,
+}
+
+// show the whole plan, and, only once the user consents, restore every file planned
+pub fn bulk_restore_exec(config: &Config, plan: &[PlannedRestore]) -> HttmResult<()> {
+    deny_if_forensic("restore files")?;
+
+    plan.iter()
+        .filter_map(|planned| planned.opt_to.as_ref().map(|to| (&planned.from, to)))
+        .try_for_each(|(from, to)| deny_unless_restorable(from, to))?;
+
+    // as with an interactive restore, each live file overwritten is moved out of the way first
+    let set_aside_paths: Vec<Option<PathBuf>> = plan
+        .iter()
+        .map(|planned| match &planned.opt_to {
+            Some(to) => get_set_aside_path(
+                config,
+                to,
+                planned.opt_conflict == Some(ConflictPolicy::Overwrite),
+            ),
+            None => Ok(None),
+        })
+        .collect::<HttmResult<_>>()?;
+
+    plan.iter()
+        .zip(set_aside_paths.iter())
+        .filter_map(|(planned, opt_set_aside_path)| {
+            planned.opt_to.as_ref().zip(opt_set_aside_path.as_ref())
+        })
+        .try_for_each(|(to, set_aside_path)| deny_unless_restorable(to, set_aside_path))?;
+
+    if !confirm_plan(config, plan, &set_aside_paths)? {
+        eprintln!("User declined restore.  No files were restored.");
+        std::process::exit(0)
+    }
+
+    // each dir restored reports the files within it which could not be restored, once the plan is done
+    let copy_failures: Vec<Vec<CopyFailure>> = plan
+        .iter()
+        .zip(set_aside_paths.iter())
+        .filter_map(|(planned, opt_set_aside_path)| {
+            planned
+                .opt_to
+                .as_ref()
+                .map(|to| (&planned.from, to, opt_set_aside_path))
+        })
+        .map(|(from, to, opt_set_aside_path)| {
+            let copy_failures = restore_over_live(from, to, opt_set_aside_path.as_deref())?;
+            let attribute_notes = restore_special_attributes(from, to);
+            eprintln!("Restored: {:?} -> {:?}", from, to);
+            attribute_notes
+                .iter()
+                .for_each(|note| eprintln!("Warning: {}", note));
+            Ok(copy_failures)
+        })
+        .collect::<HttmResult<Vec<Vec<CopyFailure>>>>()?;
+
+    let num_restored = copy_failures.len();
+    let copy_failures: Vec<CopyFailure> = copy_failures.into_iter().flatten().collect();
+
+    if !copy_failures.is_empty() {
+        display_copy_failures(&copy_failures);
+
+        let msg = format!(
+            "Restore completed with errors.  httm restored {} of {} file(s) planned, but could not restore the {} file(s) within them listed above.",
+            num_restored,
+            plan.len(),
+            copy_failures.len()
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    eprintln!(
+        "Restore completed successfully.  httm restored {} of {} file(s) planned.",
+        num_restored,
+        plan.len()
+    );
+
+    std::process::exit(0)
+}
+
+fn confirm_plan(
+    config: &Config,
+    plan: &[PlannedRestore],
+    set_aside_paths: &[Option<PathBuf>],
+) -> HttmResult<bool> {
+    let plan_buffer: String = plan
+        .iter()
+        .zip(set_aside_paths.iter())
+        .map(|(planned, opt_set_aside_path)| match &planned.opt_to {
+            Some(to) => {
+                let label = match planned.opt_conflict {
+                    Some(ConflictPolicy::Overwrite) => "  (overwrites the existing file)",
+                    Some(ConflictPolicy::Rename) => "  (renamed, as the file already exists)",
+                    Some(ConflictPolicy::Skip) | None => "",
+                };
+                let set_aside_line = opt_set_aside_path
+                    .as_ref()
+                    .map(|set_aside_path| format!("\n\taside: {:?}", set_aside_path))
+                    .unwrap_or_default();
+                format!(
+                    "\tfrom: {:?}\n\tto:   {:?}{}{}\n\n",
+                    planned.from, to, label, set_aside_line
+                )
+            }
+            None => format!("\tskip: {:?}  (the file already exists)\n\n", planned.from),
+        })
+        .collect();
+
+    // is anything using the files we are about to restore over?  warn, or refuse, before we ask
+    let open_files_warnings: String = plan
+        .iter()
+        .filter(|planned| planned.opt_conflict == Some(ConflictPolicy::Overwrite))
+        .filter_map(|planned| planned.opt_to.as_ref())
+        .map(|to| {
+            open_files_advisory(config, to).map(|opt_warning| opt_warning.unwrap_or_default())
+        })
+        .collect::<HttmResult<Vec<String>>>()?
+        .concat();
+
+    let preview_buffer = format!(
+        "httm will restore the following files from ZFS snapshots:\n\n\
+        {}\
+        {}\
+        Before httm restores these files, it would like your consent. Continue? (YES/NO)\n\
+        ──────────────────────────────────────────────────────────────────────────────\n\
+        YES\n\
+        NO",
+        plan_buffer, open_files_warnings
+    );
+
+    // loop until user consents or doesn't
+    loop {
+        let user_consent = select_restore_view(config, &preview_buffer, true)?.to_ascii_uppercase();
+
+        match user_consent.as_ref() {
+            "YES" | "Y" => break Ok(true),
+            "NO" | "N" => break Ok(false),
+            // if not yes or no, then noop and continue to the next iter of loop
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,506 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    ffi::OsString,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use clap::ArgMatches;
+use which::which;
+
+use crate::doctor::{print_findings_and_exit, Finding};
+use crate::parse_aliases::{get_aliases_file_path, read_aliases_file, ALIASES_FILE_NAME};
+use crate::parse_mounts::parse_datasets;
+use crate::pins::{get_pins_file_path, PINS_FILE_NAME};
+use crate::utility::get_fs_type_from_hidden_dir;
+use crate::{HttmResult, MapOfDatasets, BTRFS_SNAPPER_HIDDEN_DIRECTORY, ZFS_SNAPSHOT_DIRECTORY};
+
+// a setting may be given on the command line or in the environment, and which one
+// httm uses differs by setting, so we keep where each value came from, to say so
+struct ConfigSource {
+    name: String,
+    values: Vec<String>,
+}
+
+impl ConfigSource {
+    fn from_arg(matches: &ArgMatches, arg_name: &str, long: &str) -> Option<Self> {
+        matches.values_of_os(arg_name).map(|values| ConfigSource {
+            name: format!("--{}", long),
+            values: values
+                .map(|value| value.to_string_lossy().into_owned())
+                .collect(),
+        })
+    }
+
+    fn from_env(var: &str) -> Option<Self> {
+        std::env::var_os(var).map(|value| ConfigSource {
+            name: var.to_owned(),
+            values: value
+                .to_string_lossy()
+                .split_terminator(',')
+                .map(|str| str.to_owned())
+                .collect(),
+        })
+    }
+
+    fn entry_context(&self, idx: usize, value: &str) -> String {
+        format!("{}, entry {} ({:?})", self.name, idx + 1, value)
+    }
+}
+
+// parse each source of configuration the way httm would, but, rather than stop at the first
+// problem, or warn and carry on, report every problem with where it was found, and then exit
+pub fn check_config(matches: &ArgMatches) -> HttmResult<()> {
+    // aliases are for when one's datasets aren't mounted locally, so finding none isn't a failure here
+    let (map_of_datasets, opt_datasets_finding) = match parse_datasets() {
+        Ok((map_of_datasets, _vec_of_filter_dirs)) => (map_of_datasets, None),
+        Err(err) => (
+            MapOfDatasets::new(),
+            Some(Finding::Warn(
+                err.to_string(),
+                "no setting which names the mount of a dataset can be valid.  Try httm --doctor."
+                    .to_owned(),
+            )),
+        ),
+    };
+
+    let mut findings: Vec<Finding> = Vec::new();
+
+    findings.extend(check_aliases_file(&map_of_datasets));
+    findings.extend(check_map_aliases(matches, &map_of_datasets));
+    findings.extend(check_remote_dir(matches, &map_of_datasets));
+    findings.extend(check_snap_dirs(matches, &map_of_datasets));
+    findings.extend(check_external_roots(matches, &map_of_datasets));
+    findings.extend(check_pins());
+    findings.extend(check_privileged_helper());
+
+    if findings.is_empty() {
+        findings.push(Finding::Ok(
+            "no configuration was given, on the command line or in the environment, beyond httm's defaults"
+                .to_owned(),
+        ));
+    }
+
+    findings.extend(opt_datasets_finding);
+
+    print_findings_and_exit(&findings, "httm check config")
+}
+
+// an env var overrides the command line for MAP_ALIASES, unlike every other setting
+fn check_map_aliases(matches: &ArgMatches, map_of_datasets: &MapOfDatasets) -> Vec<Finding> {
+    let opt_arg_source = ConfigSource::from_arg(matches, "MAP_ALIASES", "map-aliases");
+    let opt_env_source = ConfigSource::from_env("HTTM_MAP_ALIASES");
+
+    let mut findings = Vec::new();
+
+    if let (Some(arg_source), Some(env_source)) = (&opt_arg_source, &opt_env_source) {
+        findings.push(Finding::Warn(
+            format!(
+                "{} is ignored, as {} is also set",
+                arg_source.name, env_source.name
+            ),
+            format!(
+                "httm prefers {} to {}.  Unset one or the other.",
+                env_source.name, arg_source.name
+            ),
+        ));
+    }
+
+    let source = match opt_env_source.or(opt_arg_source) {
+        Some(source) => source,
+        None => return findings,
+    };
+
+    source.values.iter().enumerate().for_each(|(idx, value)| {
+        let context = source.entry_context(idx, value);
+
+        match value.split_once(':') {
+            Some((local_dir, remote_dir)) => findings.extend(check_alias(
+                &context,
+                Path::new(local_dir),
+                Path::new(remote_dir),
+                map_of_datasets,
+            )),
+            None => findings.push(Finding::Fail(
+                format!(
+                    "{}: has no delimiter (':') between its local and remote dirs",
+                    context
+                ),
+                "specify each alias as <LOCAL_DIR>:<REMOTE_DIR>, delimited by a comma, ','."
+                    .to_owned(),
+            )),
+        }
+    });
+
+    findings
+}
+
+// aliases added with "--add-alias" are used along with any given explicitly
+fn check_aliases_file(map_of_datasets: &MapOfDatasets) -> Vec<Finding> {
+    let aliases = match get_aliases_file_path().and_then(|path| read_aliases_file(&path)) {
+        Ok(aliases) => aliases,
+        Err(err) => {
+            return vec![Finding::Fail(
+                err.to_string(),
+                format!(
+                    "httm reads aliases added with --add-alias from ~/{}.  Check its permissions.",
+                    ALIASES_FILE_NAME
+                ),
+            )]
+        }
+    };
+
+    aliases
+        .iter()
+        .flat_map(|(line_num, alias)| {
+            let context = format!("~/{}, line {} ({:?})", ALIASES_FILE_NAME, line_num, alias);
+
+            match alias.split_once(':') {
+                Some((local_dir, remote_dir)) => {
+                    check_alias(
+ &context,
+ Path::new(local_dir),
+ Path::new(remote_dir),
+ map_of_datasets,
+ )
+                }
+                None => vec![Finding::Fail(
+                    format!(
+                        "{}: has no delimiter (':') between its local and remote dirs",
+                        context
+                    ),
+                    "httm would refuse to run.  Fix or remove this line, or add the alias again with --add-alias."
+                        .to_owned(),
+                )],
+            }
+        })
+        .collect()
+}
+
+fn check_remote_dir(matches: &ArgMatches, map_of_datasets: &MapOfDatasets) -> Vec<Finding> {
+    let get_value = |arg_name: &str, long: &str, vars: &[&str]| -> Option<(String, OsString)> {
+        match matches.value_of_os(arg_name) {
+            Some(value) => Some((format!("--{}", long), value.to_os_string())),
+            None => vars
+                .iter()
+                .find_map(|var| std::env::var_os(var).map(|value| ((*var).to_owned(), value))),
+        }
+    };
+
+    // HTTM_SNAP_POINT is the legacy name of HTTM_REMOTE_DIR
+    let opt_remote_dir = get_value(
+        "REMOTE_DIR",
+        "remote-dir",
+        &["HTTM_REMOTE_DIR", "HTTM_SNAP_POINT"],
+    );
+    let opt_local_dir = get_value("LOCAL_DIR", "local-dir", &["HTTM_LOCAL_DIR"]);
+
+    match (opt_remote_dir, opt_local_dir) {
+        (Some((remote_source, remote_dir)), opt_local_dir) => {
+            let context = match &opt_local_dir {
+                Some((local_source, _local_dir)) => {
+                    format!("{} and {}", remote_source, local_source)
+                }
+                None => remote_source,
+            };
+
+            // the local dir defaults to the working dir
+            let local_dir = match opt_local_dir {
+                Some((_local_source, local_dir)) => PathBuf::from(local_dir),
+                None => match std::env::current_dir() {
+                    Ok(pwd) => pwd,
+                    Err(err) => {
+                        return vec![Finding::Fail(
+                            format!("{}: httm could not read the working directory, which is the default LOCAL_DIR: {}", context, err),
+                            "specify the local dir with --local-dir or HTTM_LOCAL_DIR.".to_owned(),
+                        )]
+                    }
+                },
+            };
+
+            check_alias(
+                &context,
+                &local_dir,
+                Path::new(&remote_dir),
+                map_of_datasets,
+            )
+        }
+        (None, Some((local_source, local_dir))) => vec![Finding::Warn(
+            format!(
+                "{}: {:?} is ignored, as no remote dir is set",
+                local_source, local_dir
+            ),
+            "a local dir is only of use along with --remote-dir or HTTM_REMOTE_DIR.".to_owned(),
+        )],
+        (None, None) => Vec::new(),
+    }
+}
+
+fn check_alias(
+    context: &str,
+    local_dir: &Path,
+    remote_dir: &Path,
+    map_of_datasets: &MapOfDatasets,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if !local_dir.is_dir() {
+        findings.push(Finding::Fail(
+            format!(
+                "{}: the local dir {:?} does not exist, or is not a directory",
+                context, local_dir
+            ),
+            "httm would ignore this alias.  Check the path, or whether a share is mounted there."
+                .to_owned(),
+        ));
+    }
+
+    if !remote_dir.is_dir() {
+        findings.push(Finding::Fail(
+            format!(
+                "{}: the remote dir {:?} does not exist, or is not a directory",
+                context, remote_dir
+            ),
+            "httm would ignore this alias.  Check the path, or whether a share is mounted there."
+                .to_owned(),
+        ));
+
+        return findings;
+    }
+
+    // httm only reads the snapshots of datasets it finds mounted, like a network share
+    if !map_of_datasets.contains_key(remote_dir) {
+        findings.push(Finding::Fail(
+            format!(
+                "{}: the remote dir {:?} is not the mount of any supported dataset",
+                context, remote_dir
+            ),
+            "httm would find no snapshots for this alias.  A remote dir is the mount of a dataset, such as a network share, exactly as httm --list-datasets lists it."
+                .to_owned(),
+        ));
+    }
+
+    match get_fs_type_from_hidden_dir(remote_dir) {
+        Ok(fs_type) if findings.is_empty() => findings.push(Finding::Ok(format!(
+            "{}: {:?} resolves to {:?}, a {} dataset",
+            context, local_dir, remote_dir, fs_type
+        ))),
+        Ok(_fs_type) => (),
+        Err(_) => findings.push(Finding::Fail(
+            format!(
+                "{}: the remote dir {:?} contains no hidden snapshot directory",
+                context, remote_dir
+            ),
+            format!(
+                "httm would ignore this alias.  A remote dir is the mount of a dataset, which contains a {:?} or a {:?} directory.",
+                ZFS_SNAPSHOT_DIRECTORY, BTRFS_SNAPPER_HIDDEN_DIRECTORY
+            ),
+        )),
+    }
+
+    findings
+}
+
+fn check_snap_dirs(matches: &ArgMatches, map_of_datasets: &MapOfDatasets) -> Vec<Finding> {
+    let source = match ConfigSource::from_arg(matches, "SNAP_DIRS", "snap-dirs")
+        .or_else(|| ConfigSource::from_env("HTTM_SNAP_DIRS"))
+    {
+        Some(source) => source,
+        None => return Vec::new(),
+    };
+
+    source
+        .values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let context = source.entry_context(idx, value);
+
+            let (mount, snap_dir) = match value.split_once(':') {
+                Some((mount, snap_dir)) => (Path::new(mount), Path::new(snap_dir)),
+                None => {
+                    return Finding::Fail(
+                        format!("{}: has no delimiter (':') between its mount and its snap dir", context),
+                        "specify each snap dir as <MOUNT>:<SNAP_DIR>, delimited by a comma, ','."
+                            .to_owned(),
+                    )
+                }
+            };
+
+            if snap_dir.is_absolute() {
+                return Finding::Fail(
+                    format!("{}: the snap dir {:?} is absolute", context, snap_dir),
+                    "httm would refuse to run.  A snap dir is relative to its mount, like \".zfs/snapshot\"."
+                        .to_owned(),
+                );
+            }
+
+            if !map_of_datasets.contains_key(mount) {
+                return Finding::Fail(
+                    format!("{}: {:?} is not the mount of any supported dataset", context, mount),
+                    "httm would ignore this snap dir.  Specify the mount exactly as httm --debug lists it."
+                        .to_owned(),
+                );
+            }
+
+            let full_snap_dir = mount.join(snap_dir);
+
+            if full_snap_dir.is_dir() {
+                Finding::Ok(format!(
+                    "{}: snapshots of {:?} will be read from {:?}",
+                    context, mount, full_snap_dir
+                ))
+            } else {
+                Finding::Fail(
+                    format!("{}: the snap dir {:?} does not exist, or is not a directory", context, full_snap_dir),
+                    "httm would find no snapshots of this dataset.  Check the path, or whether the server exports it."
+                        .to_owned(),
+                )
+            }
+        })
+        .collect()
+}
+
+fn check_external_roots(matches: &ArgMatches, map_of_datasets: &MapOfDatasets) -> Vec<Finding> {
+    let source = match ConfigSource::from_arg(matches, "EXTERNAL_ROOTS", "external-roots")
+        .or_else(|| ConfigSource::from_env("HTTM_EXTERNAL_ROOTS"))
+    {
+        Some(source) => source,
+        None => return Vec::new(),
+    };
+
+    source
+        .values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let context = source.entry_context(idx, value);
+
+            let external_root = match Path::new(value).canonicalize() {
+                Ok(external_root) => external_root,
+                Err(err) => {
+                    return Finding::Fail(
+                        format!("{}: the external root could not be found: {}", context, err),
+                        "check the path, or whether the disk or share is mounted.".to_owned(),
+                    )
+                }
+            };
+
+            let num_datasets = map_of_datasets
+                .keys()
+                .filter(|mount| mount.starts_with(&external_root))
+                .count();
+
+            if num_datasets == 0 {
+                Finding::Warn(
+                    format!(
+                        "{}: no supported dataset is mounted beneath {:?}",
+                        context, external_root
+                    ),
+                    "httm ignores an external root until a dataset is mounted beneath it."
+                        .to_owned(),
+                )
+            } else {
+                Finding::Ok(format!(
+                    "{}: {} supported dataset(s) are mounted beneath {:?}",
+                    context, num_datasets, external_root
+                ))
+            }
+        })
+        .collect()
+}
+
+// the pins file is written by httm, but nothing stops one from editing it by hand
+fn check_pins() -> Vec<Finding> {
+    let pins_file_path = match get_pins_file_path() {
+        Ok(pins_file_path) => pins_file_path,
+        Err(_) => return Vec::new(),
+    };
+
+    if !pins_file_path.exists() {
+        return Vec::new();
+    }
+
+    let buffer = match read_to_string(&pins_file_path) {
+        Ok(buffer) => buffer,
+        Err(err) => {
+            return vec![Finding::Fail(
+                format!(
+                    "the pins file {:?} could not be read: {}",
+                    pins_file_path, err
+                ),
+                format!(
+                    "httm --pin and --pinned read and write ~/{}.  Check its permissions.",
+                    PINS_FILE_NAME
+                ),
+            )]
+        }
+    };
+
+    buffer
+        .lines()
+        .enumerate()
+        .filter(|(_idx, line)| !line.is_empty())
+        .filter_map(|(idx, line)| {
+            let context = format!("~/{}, line {} ({:?})", PINS_FILE_NAME, idx + 1, line);
+            let pin = Path::new(line);
+
+            if !pin.is_absolute() {
+                Some(Finding::Fail(
+                    format!("{}: the pinned path is not absolute", context),
+                    "httm pins each path by its absolute path.  Fix or remove this line, or pin the path again."
+                        .to_owned(),
+                ))
+            } else if !pin.exists() {
+                // a deleted file is exactly the sort of thing one pins
+                Some(Finding::Warn(
+                    format!("{}: the pinned path does not exist live", context),
+                    "httm can still find deleted versions of a pinned path, but may browse only those pins which exist."
+                        .to_owned(),
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn check_privileged_helper() -> Option<Finding> {
+    let elevate_command = std::env::var("HTTM_PRIVILEGED_HELPER").ok()?;
+
+    // an empty helper is the same as none
+    let elevate_program = elevate_command.split_whitespace().next()?;
+
+    let finding = match which(elevate_program) {
+        Ok(path) => Finding::Ok(format!(
+            "HTTM_PRIVILEGED_HELPER: {:?} found at {:?}",
+            elevate_program, path
+        )),
+        Err(_) => Finding::Fail(
+            format!(
+                "HTTM_PRIVILEGED_HELPER: the command {:?} could not be found",
+                elevate_program
+            ),
+            "specify a means of elevation which is on the PATH, like \"sudo -n\" or \"pkexec\"."
+                .to_owned(),
+        ),
+    };
+
+    Some(finding)
+}
@@ -0,0 +1,63 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{fs::OpenOptions, io::Write};
+
+use crate::HttmResult;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// OSC 52 asks the terminal itself to set the clipboard, so this works just as well over ssh,
+// where any clipboard on the remote system is of no use to anyone
+pub fn copy_to_clipboard(text: &str) -> HttmResult<()> {
+    let osc52 = format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()));
+
+    // tmux swallows OSC 52, unless it is wrapped to be passed through to the terminal
+    let sequence = if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;\x1b{}\x1b\\", osc52)
+    } else {
+        osc52
+    };
+
+    // stdout may be a pipe, but the tty is always the terminal
+    let mut tty = OpenOptions::new().write(true).open("/dev/tty")?;
+    tty.write_all(sequence.as_bytes())?;
+    tty.flush()?;
+
+    Ok(())
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    bytes
+        .chunks(3)
+        .flat_map(|chunk| {
+            let triple = chunk.iter().enumerate().fold(0u32, |triple, (idx, byte)| {
+                triple | (*byte as u32) << (16 - 8 * idx)
+            });
+
+            // a chunk of n bytes encodes to n + 1 chars, the rest are padding
+            (0..4).map(move |idx| {
+                if idx <= chunk.len() {
+                    BASE64_ALPHABET[(triple >> (18 - 6 * idx) & 0x3f) as usize] as char
+                } else {
+                    '='
+                }
+            })
+        })
+        .collect()
+}
@@ -15,16 +15,35 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
-use std::{borrow::Cow, collections::BTreeMap};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ffi::OsStr,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
 
+use clap::crate_version;
+use itertools::Itertools;
 use number_prefix::NumberPrefix;
+use rayon::prelude::*;
+use serde::Serialize;
 use terminal_size::{terminal_size, Height, Width};
 
+use crate::git_xref::GitWorkTree;
+use crate::line_diff::{get_side_by_side, read_lines};
+use crate::lookup_blame::get_blame;
+use crate::lookup_dir_summary::get_dir_summary_for_dirs;
 use crate::lookup_file_mounts::get_mounts_for_files;
+use crate::lookup_versions::{
+    get_dataset_name, get_snap_names, get_snap_sources, get_snap_times, get_version_entries,
+    versions_lookup_exec, LookupStats,
+};
 use crate::utility::{
-    get_date, paint_string, print_output_buf, DateFormat, PathData, PHANTOM_DATE, PHANTOM_SIZE,
+    get_date, paint_string, print_output_buf, DateFormat, HttmError, PathData, PathMetadata,
+    PHANTOM_DATE, PHANTOM_PATH_METADATA, PHANTOM_SIZE,
 };
-use crate::{Config, HttmResult, SnapsAndLiveSet};
+use crate::{Config, DedupVersionsBy, HttmResult, NumVersionsFormat, SnapsAndLiveSet};
 
 // 2 space wide padding - used between date and size, and size and path
 const PRETTY_FIXED_WIDTH_PADDING: &str = "  ";
@@ -32,18 +51,45 @@ const PRETTY_FIXED_WIDTH_PADDING: &str = "  ";
 const PRETTY_FIXED_WIDTH_PADDING_LEN_X2: usize = PRETTY_FIXED_WIDTH_PADDING.len() * 2;
 // tab padding used in not so pretty
 const NOT_SO_PRETTY_FIXED_WIDTH_PADDING: &str = "\t";
+// lines which first appeared in the live file in blame mode
+const LIVE_LABEL: &str = "live";
 // and we add 2 quotation marks to the path when we format
 const QUOTATION_MARKS_LEN: usize = 2;
+// matrix cells: a day with a new version, or a day without
+const MATRIX_CHANGED_MARK: &str = "●";
+const MATRIX_UNCHANGED_MARK: &str = "·";
+// git xref: whether a version's contents were ever committed, and how much of a commit hash to show
+const GIT_COMMITTED_MARK: &str = "committed";
+const GIT_NEVER_COMMITTED_MARK: &str = "NEVER COMMITTED";
+const GIT_SHORT_COMMIT_LEN: usize = 8;
+// a version found on an external root, rather than on a local dataset
+const EXTERNAL_LABEL: &str = "[external]";
+// a SHA-256 is 32 bytes, printed as hex
+const SHA256_HEX_LEN: usize = 64;
+// a version whose mode, owner or group differs from the version before
+const PERMS_CHANGED_LABEL: &str = "[mode/owner changed]";
+// precedes the creation time of the snapshot which holds a version
+const SNAP_TIME_LABEL: &str = "captured ";
+
+// when there is no terminal to measure, a side-by-side diff is as wide as two 80 column files
+const SIDE_BY_SIDE_DEFAULT_WIDTH: usize = 163;
+// " | " between the old and new columns
+const SIDE_BY_SIDE_MARKER_WIDTH: usize = 3;
+
+pub const DEFAULT_DIR_SIZE_MAX_ENTRIES: usize = 100_000;
 
 struct PaddingCollection {
     size_padding_len: usize,
+    snap_name_padding_len: usize,
     fancy_border_string: String,
     phantom_date_pad_str: String,
     phantom_size_pad_str: String,
 }
 
 pub fn display_exec(config: &Config, snaps_and_live_set: &SnapsAndLiveSet) -> HttmResult<String> {
-    let output_buffer = if config.opt_raw || config.opt_zeros {
+    let output_buffer = if config.opt_json {
+        display_json(config, snaps_and_live_set)?
+    } else if config.opt_raw || config.opt_zeros {
         display_raw(config, snaps_and_live_set)?
     } else {
         display_formatted(config, snaps_and_live_set)?
@@ -68,8 +114,130 @@ fn display_raw(config: &Config, snaps_and_live_set: &SnapsAndLiveSet) -> HttmRes
     Ok(write_out_buffer)
 }
 
+#[derive(Debug, Serialize)]
+struct JsonListing {
+    versions: Vec<JsonVersion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    httm_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generated_at: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonVersion {
+    path: String,
+    size: u64,
+    modify_time: u64,
+    date: String,
+    is_live: bool,
+    // none for a path on no dataset we know, or a version found on an external root
+    dataset: Option<String>,
+    // none for a live version
+    snapshot: Option<String>,
+    // the creation time of the snapshot, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snap_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+// the same versions, in the same order, as the table, one object per version,
+// so that a program needn't parse our padding
+fn display_json(config: &Config, snaps_and_live_set: &SnapsAndLiveSet) -> HttmResult<String> {
+    let snap_sources = get_snap_sources(config, &snaps_and_live_set[1]);
+    let opt_snap_times = get_opt_snap_times(config, snaps_and_live_set);
+
+    let as_secs = |time: &SystemTime| -> u64 {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    };
+
+    let versions: Vec<JsonVersion> = snaps_and_live_set
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, pathdata_set)| {
+            // a SnapsAndLiveSet is an array of 2 - idx 0 are the snaps, 1 is the live versions
+            let is_live = idx == 1;
+
+            pathdata_set.iter().map(move |pathdata| (is_live, pathdata))
+        })
+        .map(|(is_live, pathdata)| {
+            let path_metadata = pathdata.md_infallible();
+
+            let (dataset, snapshot) = if is_live {
+                let dataset = config
+                    .dataset_collection
+                    .get_proximate_dataset(pathdata)
+                    .ok()
+                    .map(|mount| get_dataset_name(config, &mount));
+                (dataset, None)
+            } else {
+                match snap_sources.get(&pathdata.path_buf) {
+                    Some((snap_name, dataset)) => (Some(dataset.clone()), Some(snap_name.clone())),
+                    None => (None, None),
+                }
+            };
+
+            JsonVersion {
+                path: pathdata.path_buf.to_string_lossy().into_owned(),
+                size: path_metadata.size,
+                modify_time: as_secs(&path_metadata.modify_time),
+                date: get_date(config, &path_metadata.modify_time, DateFormat::Display),
+                is_live,
+                dataset,
+                snapshot,
+                snap_time: opt_snap_times
+                    .as_ref()
+                    .and_then(|snap_times| snap_times.get(&pathdata.path_buf))
+                    .map(as_secs),
+                sha256: config
+                    .opt_chain_of_custody
+                    .then(|| pathdata.content_hash().map(|hash| hash.to_owned()))
+                    .flatten(),
+            }
+        })
+        .collect();
+
+    let listing = JsonListing {
+        versions,
+        httm_version: config
+            .opt_chain_of_custody
+            .then(|| crate_version!().to_owned()),
+        generated_at: config
+            .opt_chain_of_custody
+            .then(|| as_secs(&SystemTime::now())),
+    };
+
+    Ok(format!("{}\n", serde_json::to_string(&listing)?))
+}
+
 fn display_formatted(config: &Config, snaps_and_live_set: &SnapsAndLiveSet) -> HttmResult<String> {
-    let padding_collection = calculate_pretty_padding(config, snaps_and_live_set);
+    // walk every version's tree at once, before we need the sizes for padding
+    if let Some(max_entries) = config.opt_dir_size {
+        snaps_and_live_set
+            .iter()
+            .flatten()
+            .collect::<Vec<&PathData>>()
+            .par_iter()
+            .for_each(|pathdata| {
+                let _ = pathdata.recursive_size(max_entries);
+            });
+    }
+
+    let perms_changed = get_perms_changed(config, snaps_and_live_set);
+
+    let opt_snap_names = get_opt_snap_names(config, snaps_and_live_set);
+
+    let opt_snap_times = get_opt_snap_times(config, snaps_and_live_set);
+
+    let padding_collection = calculate_pretty_padding(
+        config,
+        snaps_and_live_set,
+        &perms_changed,
+        opt_snap_names.as_ref(),
+        opt_snap_times.is_some(),
+    );
 
     let write_out_buffer = snaps_and_live_set.iter().enumerate().fold(
         String::new(),
@@ -81,7 +249,23 @@ fn display_formatted(config: &Config, snaps_and_live_set: &SnapsAndLiveSet) -> H
             let pathdata_set_buffer: String = pathdata_set
                 .iter()
                 .map(|pathdata| {
-                    display_pathdata(config, pathdata, is_live_set, &padding_collection)
+                    let is_perms_changed = perms_changed.contains(&pathdata.path_buf);
+                    let opt_snap_name = opt_snap_names
+                        .as_ref()
+                        .map(|snap_names| get_snap_name_label(snap_names, pathdata, is_live_set));
+                    // a live version has no snapshot, so its snap time is just padding
+                    let opt_snap_time = opt_snap_times
+                        .as_ref()
+                        .map(|snap_times| snap_times.get(&pathdata.path_buf));
+                    display_pathdata(
+                        config,
+                        pathdata,
+                        is_live_set,
+                        is_perms_changed,
+                        opt_snap_name,
+                        opt_snap_time,
+                        &padding_collection,
+                    )
                 })
                 .collect();
 
@@ -109,6 +293,9 @@ fn display_pathdata(
     config: &Config,
     pathdata: &PathData,
     is_live_set: bool,
+    is_perms_changed: bool,
+    opt_snap_name: Option<&str>,
+    opt_snap_time: Option<Option<&SystemTime>>,
     padding_collection: &PaddingCollection,
 ) -> String {
     // obtain metadata for timestamp and size
@@ -120,7 +307,7 @@ fn display_pathdata(
         //
         // we use a dummy instead of a None value here.  Basically, sometimes, we want
         // to print the request even if a live file does not exist
-        let size = if pathdata.metadata.is_some() {
+        let size = if pathdata.metadata().is_some() {
             display_human_size(&path_metadata.size)
         } else {
             padding_collection.phantom_size_pad_str.to_owned()
@@ -131,7 +318,7 @@ fn display_pathdata(
     // print with padding and pretty border lines and ls colors
     } else {
         let size = {
-            let size = if pathdata.metadata.is_some() {
+            let size = if pathdata.metadata().is_some() {
                 display_human_size(&path_metadata.size)
             } else {
                 padding_collection.phantom_size_pad_str.to_owned()
@@ -161,22 +348,279 @@ fn display_pathdata(
         (size, path, padding)
     };
 
-    let display_date = if pathdata.metadata.is_some() {
+    let display_date = if pathdata.metadata().is_some() {
         get_date(config, &path_metadata.modify_time, DateFormat::Display)
     } else {
         padding_collection.phantom_date_pad_str.to_owned()
     };
 
+    let display_snap_time = match opt_snap_time {
+        Some(Some(snap_time)) => Cow::Owned(format!(
+            "{}{}{}",
+            SNAP_TIME_LABEL,
+            get_date(config, snap_time, DateFormat::Display),
+            display_padding
+        )),
+        Some(None) => Cow::Owned(format!(
+            "{:<width$}{}{}",
+            "",
+            padding_collection.phantom_date_pad_str,
+            display_padding,
+            width = SNAP_TIME_LABEL.len()
+        )),
+        None => Cow::Borrowed(""),
+    };
+
+    let display_label = if !is_live_set && is_external(config, pathdata) {
+        Cow::Owned(format!("{}{}", display_padding, EXTERNAL_LABEL))
+    } else {
+        Cow::Borrowed("")
+    };
+
+    // the size and date of a dir say little of what it holds, so say what it holds
+    let display_manifest = match get_manifest_label(config, pathdata) {
+        Some(manifest_label) => Cow::Owned(format!("{}{}", display_padding, manifest_label)),
+        None => Cow::Borrowed(""),
+    };
+
+    // a dir, or a deleted live file, has no contents to hash, so just pad
+    let display_hash = if config.opt_chain_of_custody {
+        let hash = pathdata.content_hash().unwrap_or_default();
+        Cow::Owned(format!(
+            "{:<width$}{}",
+            hash,
+            display_padding,
+            width = SHA256_HEX_LEN
+        ))
+    } else {
+        Cow::Borrowed("")
+    };
+
+    // a deleted live file has no mode or owner, so just pad
+    let display_perms = if config.opt_perms {
+        let perms = if pathdata.metadata().is_some() {
+            format_perms(&path_metadata)
+        } else {
+            format!(
+                "{:<width$}",
+                "",
+                width = format_perms(&PHANTOM_PATH_METADATA).len()
+            )
+        };
+        Cow::Owned(format!("{}{}", perms, display_padding))
+    } else {
+        Cow::Borrowed("")
+    };
+
+    let display_snap_name = match opt_snap_name {
+        Some(snap_name) if config.opt_no_pretty => {
+            Cow::Owned(format!("{}{}", snap_name, display_padding))
+        }
+        Some(snap_name) => Cow::Owned(format!(
+            "{:<width$}{}",
+            snap_name,
+            display_padding,
+            width = padding_collection.snap_name_padding_len
+        )),
+        None => Cow::Borrowed(""),
+    };
+
+    let display_perms_label = if is_perms_changed {
+        Cow::Owned(format!("{}{}", display_padding, PERMS_CHANGED_LABEL))
+    } else {
+        Cow::Borrowed("")
+    };
+
+    format!(
+        "{}{}{}{}{}{}{}{}{}{}{}{}\n",
+        display_date,
+        display_padding,
+        display_snap_time,
+        display_size,
+        display_padding,
+        display_perms,
+        display_hash,
+        display_snap_name,
+        display_path,
+        display_manifest,
+        display_label,
+        display_perms_label
+    )
+}
+
+// only when every snapshot's copy is its own version do we name the snapshot of each version
+fn get_opt_snap_names(
+    config: &Config,
+    snaps_and_live_set: &SnapsAndLiveSet,
+) -> Option<HashMap<PathBuf, String>> {
+    if config.dedup_versions_by != DedupVersionsBy::Nothing {
+        return None;
+    }
+
+    Some(get_snap_names(config, &snaps_and_live_set[1]))
+}
+
+fn get_opt_snap_times(
+    config: &Config,
+    snaps_and_live_set: &SnapsAndLiveSet,
+) -> Option<HashMap<PathBuf, SystemTime>> {
+    if !config.opt_snap_time {
+        return None;
+    }
+
+    Some(get_snap_times(config, &snaps_and_live_set[1]))
+}
+
+fn get_snap_name_label<'a>(
+    snap_names: &'a HashMap<PathBuf, String>,
+    pathdata: &PathData,
+    is_live_set: bool,
+) -> &'a str {
+    match snap_names.get(&pathdata.path_buf) {
+        Some(snap_name) => snap_name,
+        None if is_live_set => LIVE_LABEL,
+        None => "",
+    }
+}
+
+// versions are in order, the snaps first and then the live file, and the versions of each file together,
+// so a version's mode, owner or group has changed when it differs from the version before of the same name
+fn get_perms_changed(config: &Config, snaps_and_live_set: &SnapsAndLiveSet) -> BTreeSet<PathBuf> {
+    if !config.opt_perms {
+        return BTreeSet::new();
+    }
+
+    let mut previous_perms: HashMap<&OsStr, (u32, u32, u32)> = HashMap::new();
+
+    snaps_and_live_set
+        .iter()
+        .flatten()
+        .filter_map(|pathdata| {
+            let metadata = pathdata.metadata()?;
+            let perms = (metadata.mode, metadata.uid, metadata.gid);
+            let file_name = pathdata.path_buf.file_name()?;
+
+            match previous_perms.insert(file_name, perms) {
+                Some(previous) if previous != perms => Some(pathdata.path_buf.clone()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// like ls, eg. "-rw-------  1000:1000"
+fn format_perms(path_metadata: &PathMetadata) -> String {
     format!(
-        "{}{}{}{}{}\n",
-        display_date, display_padding, display_size, display_padding, display_path
+        "{}  {:>5}:{:<5}",
+        display_mode(path_metadata.mode),
+        path_metadata.uid,
+        path_metadata.gid
     )
 }
 
+fn display_mode(mode: u32) -> String {
+    let file_type = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o100000 => '-',
+        0o010000 => 'p',
+        0o140000 => 's',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        _ => '?',
+    };
+
+    // each triplet of permission bits, and the special bit which may replace its execute bit
+    let triplets = [
+        (0o400, 0o200, 0o100, 0o4000, 's'),
+        (0o040, 0o020, 0o010, 0o2000, 's'),
+        (0o004, 0o002, 0o001, 0o1000, 't'),
+    ];
+
+    let permissions: String = triplets
+        .iter()
+        .flat_map(|&(read, write, execute, special, special_char)| {
+            let execute_char = match (mode & execute != 0, mode & special != 0) {
+                (true, true) => special_char,
+                (false, true) => special_char.to_ascii_uppercase(),
+                (true, false) => 'x',
+                (false, false) => '-',
+            };
+
+            [
+                if mode & read != 0 { 'r' } else { '-' },
+                if mode & write != 0 { 'w' } else { '-' },
+                execute_char,
+            ]
+        })
+        .collect();
+
+    format!("{}{}", file_type, permissions)
+}
+
+fn get_manifest_label(config: &Config, pathdata: &PathData) -> Option<String> {
+    pathdata.dir_manifest().map(|dir_manifest| {
+        let entries = if dir_manifest.num_entries == 1 {
+            "entry"
+        } else {
+            "entries"
+        };
+
+        let recursive_size = config
+            .opt_dir_size
+            .and_then(|max_entries| pathdata.recursive_size(max_entries))
+            .map(|recursive_size| {
+                let at_least = if recursive_size.is_partial {
+                    "at least "
+                } else {
+                    ""
+                };
+
+                format!(
+                    ", {}{} in all",
+                    at_least,
+                    display_human_size(&recursive_size.total_size)
+                )
+            })
+            .unwrap_or_default();
+
+        format!(
+            "[{} {}, {}{}]",
+            dir_manifest.num_entries,
+            entries,
+            display_human_size(&dir_manifest.total_size),
+            recursive_size
+        )
+    })
+}
+
+fn is_external(config: &Config, pathdata: &PathData) -> bool {
+    config
+        .dataset_collection
+        .vec_of_external_roots
+        .iter()
+        .any(|external_root| pathdata.path_buf.starts_with(external_root))
+}
+
 fn calculate_pretty_padding(
     config: &Config,
     snaps_and_live_set: &SnapsAndLiveSet,
+    perms_changed: &BTreeSet<PathBuf>,
+    opt_snap_names: Option<&HashMap<PathBuf, String>>,
+    is_snap_time: bool,
 ) -> PaddingCollection {
+    // the live label is the only name which may be longer than every snapshot name
+    let snap_name_padding_len = opt_snap_names
+        .map(|snap_names| {
+            snap_names
+                .values()
+                .map(|snap_name| snap_name.chars().count())
+                .chain([LIVE_LABEL.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .unwrap_or_default();
+
     // calculate padding and borders for display later
     let (size_padding_len, fancy_border_len) = snaps_and_live_set.iter().flatten().fold(
         (0usize, 0usize),
@@ -195,10 +639,56 @@ fn calculate_pretty_padding(
                 (date, size, path)
             };
 
+            let display_label_len = if is_external(config, pathdata) {
+                PRETTY_FIXED_WIDTH_PADDING.len() + EXTERNAL_LABEL.len()
+            } else {
+                0
+            };
+            let display_manifest_len = get_manifest_label(config, pathdata)
+                .map(|manifest_label| PRETTY_FIXED_WIDTH_PADDING.len() + manifest_label.len())
+                .unwrap_or(0);
+
             let display_size_len = display_human_size(&path_metadata.size).len();
+            let display_hash_len = if config.opt_chain_of_custody {
+                SHA256_HEX_LEN + PRETTY_FIXED_WIDTH_PADDING.len()
+            } else {
+                0
+            };
+
+            let display_perms_len = if config.opt_perms {
+                format_perms(&path_metadata).len() + PRETTY_FIXED_WIDTH_PADDING.len()
+            } else {
+                0
+            };
+            let display_perms_label_len = if perms_changed.contains(&pathdata.path_buf) {
+                PRETTY_FIXED_WIDTH_PADDING.len() + PERMS_CHANGED_LABEL.len()
+            } else {
+                0
+            };
+
+            let display_snap_name_len = if opt_snap_names.is_some() {
+                snap_name_padding_len + PRETTY_FIXED_WIDTH_PADDING.len()
+            } else {
+                0
+            };
+
+            // the same width as a modify time, and every time displays with the same width
+            let display_snap_time_len = if is_snap_time {
+                SNAP_TIME_LABEL.len() + display_date.len() + PRETTY_FIXED_WIDTH_PADDING.len()
+            } else {
+                0
+            };
+
             let formatted_line_len = display_date.len()
                 + display_size.len()
                 + display_path.len()
+                + display_label_len
+                + display_manifest_len
+                + display_hash_len
+                + display_perms_len
+                + display_perms_label_len
+                + display_snap_name_len
+                + display_snap_time_len
                 + PRETTY_FIXED_WIDTH_PADDING_LEN_X2
                 + QUOTATION_MARKS_LEN;
 
@@ -208,26 +698,7 @@ fn calculate_pretty_padding(
         },
     );
 
-    let fancy_border_string: String = {
-        let get_max_sized_border = || {
-            // Active below is the most idiomatic Rust, but it maybe slower than the commented portion
-            // (0..fancy_border_len).map(|_| "─").collect()
-            format!("{:─<width$}\n", "", width = fancy_border_len)
-        };
-
-        match terminal_size() {
-            Some((Width(width), Height(_height))) => {
-                if (width as usize) < fancy_border_len {
-                    // Active below is the most idiomatic Rust, but it maybe slower than the commented portion
-                    // (0..width as usize).map(|_| "─").collect()
-                    format!("{:─<width$}\n", "", width = width as usize)
-                } else {
-                    get_max_sized_border()
-                }
-            }
-            None => get_max_sized_border(),
-        }
-    };
+    let fancy_border_string: String = get_fancy_border_string(fancy_border_len);
 
     let phantom_date_pad_str = format!(
         "{:<width$}",
@@ -242,12 +713,813 @@ fn calculate_pretty_padding(
 
     PaddingCollection {
         size_padding_len,
+        snap_name_padding_len,
         fancy_border_string,
         phantom_date_pad_str,
         phantom_size_pad_str,
     }
 }
 
+pub fn get_fancy_border_string(fancy_border_len: usize) -> String {
+    let get_max_sized_border = || {
+        // Active below is the most idiomatic Rust, but it maybe slower than the commented portion
+        // (0..fancy_border_len).map(|_| "─").collect()
+        format!("{:─<width$}\n", "", width = fancy_border_len)
+    };
+
+    match terminal_size() {
+        Some((Width(width), Height(_height))) => {
+            if (width as usize) < fancy_border_len {
+                // Active below is the most idiomatic Rust, but it maybe slower than the commented portion
+                // (0..width as usize).map(|_| "─").collect()
+                format!("{:─<width$}\n", "", width = width as usize)
+            } else {
+                get_max_sized_border()
+            }
+        }
+        None => get_max_sized_border(),
+    }
+}
+
+// a unique version is held by every snapshot taken while it was live, so, of each version, by
+// its modify time and size, the creation time of the oldest snapshot which holds it
+fn get_first_captured_times(
+    config: &Config,
+    pathdata: &PathData,
+) -> BTreeMap<(SystemTime, u64), SystemTime> {
+    let snap_times = get_snap_times(config, std::slice::from_ref(pathdata));
+
+    get_version_entries(config, pathdata)
+        .into_iter()
+        .filter_map(|version_entry| {
+            let creation_time = snap_times.get(&version_entry.pathdata.path_buf)?;
+            let metadata = version_entry.pathdata.metadata()?;
+            Some(((metadata.modify_time, metadata.size), *creation_time))
+        })
+        .fold(BTreeMap::new(), |mut map, (key, creation_time)| {
+            map.entry(key)
+                .and_modify(|first_captured: &mut SystemTime| {
+                    *first_captured = (*first_captured).min(creation_time)
+                })
+                .or_insert(creation_time);
+            map
+        })
+}
+
+// a matrix of files x days, where each marked cell is a day upon which a snapshot first
+// captured a unique version of that file -- useful for correlating a single change made
+// across several files at once, as files changed together are captured by the same snapshot
+pub fn display_version_matrix(config: &Config, path_set: &[PathData]) -> HttmResult<String> {
+    // lookup each path individually, as we need to know which versions belong to which file
+    //
+    // don't want a single file that has never existed to kill the whole matrix, so a failed
+    // lookup here is just empty row
+    let dates_for_paths: Vec<BTreeSet<String>> = path_set
+        .iter()
+        .map(|pathdata| {
+            versions_lookup_exec(config, std::slice::from_ref(pathdata))
+                .map(|snaps_and_live_set| {
+                    let first_captured = get_first_captured_times(config, pathdata);
+
+                    snaps_and_live_set[0]
+                        .iter()
+                        .map(|snap_version| {
+                            let metadata = snap_version.md_infallible();
+
+                            // a version upon a snapshot we know no creation time for, such as
+                            // upon a user defined alias, is bucketed by its modify time
+                            let time = first_captured
+                                .get(&(metadata.modify_time, metadata.size))
+                                .unwrap_or(&metadata.modify_time);
+
+                            get_date(config, time, DateFormat::Day)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+        .collect();
+
+    // day formatted date strings sort lexically in the same order as they do chronologically
+    let all_dates: BTreeSet<&String> = dates_for_paths.iter().flatten().collect();
+
+    if all_dates.is_empty() {
+        return Err(HttmError::new(
+            "httm could not find any snapshot versions for the specified files, so there is no matrix to display.",
+        ));
+    }
+
+    let column_labels: Vec<String> = (1..=path_set.len()).map(|idx| idx.to_string()).collect();
+
+    let write_out_buffer = if config.opt_no_pretty {
+        let legend: String = column_labels
+            .iter()
+            .zip(path_set.iter())
+            .map(|(label, pathdata)| {
+                format!(
+                    "{}{}{}\n",
+                    label,
+                    NOT_SO_PRETTY_FIXED_WIDTH_PADDING,
+                    pathdata.path_buf.to_string_lossy()
+                )
+            })
+            .collect();
+
+        let header = format!(
+            "{}{}\n",
+            NOT_SO_PRETTY_FIXED_WIDTH_PADDING,
+            column_labels.join(NOT_SO_PRETTY_FIXED_WIDTH_PADDING)
+        );
+
+        let rows: String = all_dates
+            .iter()
+            .map(|date| {
+                let cells: Vec<&str> = dates_for_paths
+                    .iter()
+                    .map(|dates| {
+                        if dates.contains(*date) {
+                            MATRIX_CHANGED_MARK
+                        } else {
+                            ""
+                        }
+                    })
+                    .collect();
+                format!(
+                    "{}{}{}\n",
+                    date,
+                    NOT_SO_PRETTY_FIXED_WIDTH_PADDING,
+                    cells.join(NOT_SO_PRETTY_FIXED_WIDTH_PADDING)
+                )
+            })
+            .collect();
+
+        legend + &header + &rows
+    } else {
+        let date_width = all_dates
+            .iter()
+            .map(|date| date.len())
+            .max()
+            .unwrap_or_default();
+        let cell_width = column_labels
+            .iter()
+            .map(|label| label.len())
+            .max()
+            .unwrap_or_default();
+
+        let legend: Vec<String> = column_labels
+            .iter()
+            .zip(path_set.iter())
+            .map(|(label, pathdata)| {
+                format!(
+                    "{:>width$}{}\"{}\"\n",
+                    label,
+                    PRETTY_FIXED_WIDTH_PADDING,
+                    pathdata.path_buf.to_string_lossy(),
+                    width = cell_width
+                )
+            })
+            .collect();
+
+        let format_row = |first_column: &str, cells: Vec<&str>| -> String {
+            let cells: String = cells
+                .into_iter()
+                .map(|cell| {
+                    format!(
+                        "{}{:>width$}",
+                        PRETTY_FIXED_WIDTH_PADDING,
+                        cell,
+                        width = cell_width
+                    )
+                })
+                .collect();
+            format!("{:<width$}{}\n", first_column, cells, width = date_width)
+        };
+
+        let header = format_row(
+            "",
+            column_labels.iter().map(|label| label.as_str()).collect(),
+        );
+
+        let rows: String = all_dates
+            .iter()
+            .map(|date| {
+                let cells: Vec<&str> = dates_for_paths
+                    .iter()
+                    .map(|dates| {
+                        if dates.contains(*date) {
+                            MATRIX_CHANGED_MARK
+                        } else {
+                            MATRIX_UNCHANGED_MARK
+                        }
+                    })
+                    .collect();
+                format_row(date, cells)
+            })
+            .collect();
+
+        let fancy_border_len = legend
+            .iter()
+            .map(|line| line.chars().count())
+            .chain([header.chars().count()])
+            .max()
+            .unwrap_or_default();
+        let fancy_border_string = get_fancy_border_string(fancy_border_len);
+
+        fancy_border_string.clone()
+            + &legend.concat()
+            + &fancy_border_string
+            + &header
+            + &rows
+            + &fancy_border_string
+    };
+
+    Ok(write_out_buffer)
+}
+
+pub fn display_git_xref(config: &Config, path_set: &[PathData]) -> HttmResult<String> {
+    let write_out_buffer = path_set
+        .iter()
+        .map(|pathdata| {
+            // lookup each path individually, as each may live in a different work tree
+            let snaps_and_live_set = versions_lookup_exec(config, std::slice::from_ref(pathdata))?;
+            let git_work_tree = GitWorkTree::new(&pathdata.path_buf)?;
+
+            // a SnapsAndLiveSet is an array of 2 - idx 0 are the snaps, 1 is the live versions
+            let lines_for_sets: Vec<Vec<[String; 5]>> = snaps_and_live_set
+                .iter()
+                .map(|pathdata_set| {
+                    pathdata_set
+                        .iter()
+                        .filter(|version| version
+.metadata().is_some())
+                        .map(|version| {
+                            let git_xref = git_work_tree.xref(version);
+                            let path_metadata = version.md_infallible();
+                            let commit = git_xref
+                                .opt_commit
+                                .map(|commit| commit.chars().take(GIT_SHORT_COMMIT_LEN).collect())
+                                .unwrap_or_else(|| "-".to_owned());
+                            let status = if git_xref.is_committed {
+                                GIT_COMMITTED_MARK
+                            } else {
+                                GIT_NEVER_COMMITTED_MARK
+                            };
+                            [
+                                get_date(config, &path_metadata.modify_time, DateFormat::Display),
+                                display_human_size(&path_metadata.size),
+                                commit,
+                                status.to_owned(),
+                                version.path_buf.to_string_lossy().into_owned(),
+                            ]
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let buffer: String = if config.opt_no_pretty {
+                lines_for_sets
+                    .iter()
+                    .flatten()
+                    .map(|line| line.join(NOT_SO_PRETTY_FIXED_WIDTH_PADDING) + "\n")
+                    .collect()
+            } else {
+                let size_width = lines_for_sets
+                    .iter()
+                    .flatten()
+                    .map(|[_date, size, _commit, _status, _path]| size.len())
+                    .max()
+                    .unwrap_or_default();
+
+                let formatted_sets: Vec<String> = lines_for_sets
+                    .iter()
+                    .map(|lines| {
+                        lines
+                            .iter()
+                            .map(|[date, size, commit, status, path]| {
+                                format!(
+                                    "{}{pad}{:>size_width$}{pad}{:<commit_width$}{pad}{:<status_width$}{pad}\"{}\"\n",
+                                    date,
+                                    size,
+                                    commit,
+                                    status,
+                                    path,
+                                    pad = PRETTY_FIXED_WIDTH_PADDING,
+                                    size_width = size_width,
+                                    commit_width = GIT_SHORT_COMMIT_LEN,
+                                    status_width = GIT_NEVER_COMMITTED_MARK.len()
+                                )
+                            })
+                            .collect()
+                    })
+                    .collect();
+
+                let fancy_border_len = formatted_sets
+                    .iter()
+                    .flat_map(|set| set.lines())
+                    .map(|line| line.chars().count())
+                    .max()
+                    .unwrap_or_default();
+                let fancy_border_string = get_fancy_border_string(fancy_border_len);
+
+                // print fancy border string above, below and between sets
+                formatted_sets
+                    .iter()
+                    .filter(|set| !set.is_empty())
+                    .fold(fancy_border_string.clone(), |buffer, set| {
+                        buffer + set + &fancy_border_string
+                    })
+            };
+
+            Ok(buffer)
+        })
+        .collect::<HttmResult<String>>()?;
+
+    Ok(write_out_buffer)
+}
+
+pub fn display_redundancy(config: &Config, path_set: &[PathData]) -> HttmResult<String> {
+    let write_out_buffer: String = path_set
+        .iter()
+        .map(|pathdata| {
+            // group every copy of each unique version, each copy found on a different dataset
+            let mut copies_of_versions: BTreeMap<(SystemTime, u64), BTreeSet<String>> =
+                BTreeMap::new();
+            get_version_entries(config, pathdata)
+                .into_iter()
+                .for_each(|version_entry| {
+                    let metadata = version_entry.pathdata.md_infallible();
+                    copies_of_versions
+                        .entry((metadata.modify_time, metadata.size))
+                        .or_default()
+                        .insert(version_entry.dataset);
+                });
+
+            let lines: Vec<[String; 4]> = copies_of_versions
+                .iter()
+                .map(|((modify_time, size), datasets)| {
+                    let num_copies = if datasets.len() == 1 {
+                        "1 copy".to_owned()
+                    } else {
+                        format!("{} copies", datasets.len())
+                    };
+                    [
+                        get_date(config, modify_time, DateFormat::Display),
+                        display_human_size(size),
+                        num_copies,
+                        datasets.iter().join(", "),
+                    ]
+                })
+                .collect();
+
+            let requested_path_string = pathdata.path_buf.to_string_lossy();
+
+            if config.opt_no_pretty {
+                let buffer: String = lines
+                    .iter()
+                    .map(|line| line.join(NOT_SO_PRETTY_FIXED_WIDTH_PADDING) + "\n")
+                    .collect();
+                format!("{}:\n{}", requested_path_string, buffer)
+            } else {
+                let size_width = lines
+                    .iter()
+                    .map(|[_date, size, _num_copies, _datasets]| size.len())
+                    .max()
+                    .unwrap_or_default();
+                let num_copies_width = lines
+                    .iter()
+                    .map(|[_date, _size, num_copies, _datasets]| num_copies.len())
+                    .max()
+                    .unwrap_or_default();
+
+                let formatted_lines: String = lines
+                    .iter()
+                    .map(|[date, size, num_copies, datasets]| {
+                        format!(
+                            "{}{pad}{:>size_width$}{pad}{:>num_copies_width$}{pad}{}\n",
+                            date,
+                            size,
+                            num_copies,
+                            datasets,
+                            pad = PRETTY_FIXED_WIDTH_PADDING,
+                            size_width = size_width,
+                            num_copies_width = num_copies_width
+                        )
+                    })
+                    .collect();
+
+                let header = format!("\"{}\"\n", requested_path_string);
+                let fancy_border_len = formatted_lines
+                    .lines()
+                    .chain(header.lines())
+                    .map(|line| line.chars().count())
+                    .max()
+                    .unwrap_or_default();
+                let fancy_border_string = get_fancy_border_string(fancy_border_len);
+
+                let mut buffer = fancy_border_string.clone() + &header + &fancy_border_string;
+                if !formatted_lines.is_empty() {
+                    buffer += &formatted_lines;
+                    buffer += &fancy_border_string;
+                }
+                buffer
+            }
+        })
+        .collect();
+
+    Ok(write_out_buffer)
+}
+
+pub fn display_stats(lookup_stats: &LookupStats, elapsed: Duration) -> String {
+    let stats = [
+        (
+            "Datasets searched:",
+            lookup_stats.datasets_searched.to_string(),
+        ),
+        (
+            "Snapshots scanned:",
+            lookup_stats.snapshots_scanned.to_string(),
+        ),
+        ("Versions found:", lookup_stats.versions_found.to_string()),
+        ("Unique versions:", lookup_stats.unique_versions.to_string()),
+        ("Elapsed time:", format!("{:.3}s", elapsed.as_secs_f64())),
+    ];
+
+    let label_padding_len = stats
+        .iter()
+        .map(|(label, _value)| label.len())
+        .max()
+        .unwrap_or_default();
+
+    stats
+        .iter()
+        .map(|(label, value)| {
+            format!(
+                "{:<width$}{}{}\n",
+                label,
+                PRETTY_FIXED_WIDTH_PADDING,
+                value,
+                width = label_padding_len
+            )
+        })
+        .collect()
+}
+
+pub fn display_dir_summary(config: &Config) -> HttmResult<()> {
+    let dir_summary_for_dirs = get_dir_summary_for_dirs(config)?;
+
+    let write_out_buffer: String = dir_summary_for_dirs
+        .iter()
+        .map(|(requested_dir, summaries)| {
+            let lines: Vec<(String, String, String, String)> = summaries
+                .iter()
+                .map(|summary| {
+                    let date = get_date(config, &summary.latest_modify_time, DateFormat::Display);
+                    let num_changed = match (summary.is_baseline, summary.num_changed) {
+                        (true, 1) => "1 file, baseline".to_owned(),
+                        (true, num_files) => format!("{} files, baseline", num_files),
+                        (false, 1) => "1 file changed".to_owned(),
+                        (false, num_changed) => format!("{} files changed", num_changed),
+                    };
+                    let changed_bytes = display_human_size(&summary.changed_bytes);
+                    let path = summary.snap_dir.to_string_lossy().into_owned();
+                    (date, num_changed, changed_bytes, path)
+                })
+                .collect();
+
+            let requested_dir_string = requested_dir.path_buf.to_string_lossy();
+
+            if config.opt_no_pretty {
+                let summary_buffer: String = lines
+                    .into_iter()
+                    .map(|(date, num_changed, changed_bytes, path)| {
+                        format!(
+                            "{}{pad}{}{pad}{}{pad}{}\n",
+                            date,
+                            num_changed,
+                            changed_bytes,
+                            path,
+                            pad = NOT_SO_PRETTY_FIXED_WIDTH_PADDING
+                        )
+                    })
+                    .collect();
+                format!("{}:\n{}", requested_dir_string, summary_buffer)
+            } else {
+                let num_changed_len = lines
+                    .iter()
+                    .map(|(_date, num_changed, _changed_bytes, _path)| num_changed.len())
+                    .max()
+                    .unwrap_or_default();
+                let changed_bytes_len = lines
+                    .iter()
+                    .map(|(_date, _num_changed, changed_bytes, _path)| changed_bytes.len())
+                    .max()
+                    .unwrap_or_default();
+
+                let formatted_lines: Vec<String> = lines
+                    .into_iter()
+                    .map(|(date, num_changed, changed_bytes, path)| {
+                        format!(
+                            "{}{pad}{:>num_changed_len$}{pad}{:>changed_bytes_len$}{pad}\"{}\"\n",
+                            date,
+                            num_changed,
+                            changed_bytes,
+                            path,
+                            pad = PRETTY_FIXED_WIDTH_PADDING,
+                            num_changed_len = num_changed_len,
+                            changed_bytes_len = changed_bytes_len
+                        )
+                    })
+                    .collect();
+
+                let header = format!("\"{}\"\n", requested_dir_string);
+                let fancy_border_len = formatted_lines
+                    .iter()
+                    .chain([&header])
+                    .map(|line| line.chars().count())
+                    .max()
+                    .unwrap_or_default();
+                let fancy_border_string = get_fancy_border_string(fancy_border_len);
+
+                let mut summary_buffer = fancy_border_string.clone() + &header;
+                summary_buffer += &fancy_border_string;
+                if !formatted_lines.is_empty() {
+                    summary_buffer += &formatted_lines.concat();
+                    summary_buffer += &fancy_border_string;
+                }
+                summary_buffer
+            }
+        })
+        .collect();
+
+    print_output_buf(write_out_buffer)?;
+
+    Ok(())
+}
+
+// only how many unique snapshot versions each path has, for scripts which would find those
+// files with no versions at all, and needn't parse a whole listing to do so
+pub fn display_num_versions(config: &Config, format: &NumVersionsFormat) -> HttmResult<()> {
+    let rows: Vec<(usize, Option<(SystemTime, SystemTime)>)> = config
+        .paths
+        .par_iter()
+        .map(|pathdata| {
+            // a path with no versions, and no live file, is still just a path with no versions
+            let snap_versions = match versions_lookup_exec(config, std::slice::from_ref(pathdata)) {
+                Ok(snaps_and_live_set) => snaps_and_live_set[0].clone(),
+                Err(HttmError::NoVersions { .. }) => Vec::new(),
+                Err(err) => return Err(err),
+            };
+
+            let opt_oldest_and_newest = snap_versions
+                .iter()
+                .map(|version| version.md_infallible().modify_time)
+                .minmax()
+                .into_option();
+
+            Ok((snap_versions.len(), opt_oldest_and_newest))
+        })
+        .collect::<HttmResult<_>>()?;
+
+    let display_dates = |opt_oldest_and_newest: &Option<(SystemTime, SystemTime)>,
+                         date_format: DateFormat|
+     -> (String, String) {
+        match opt_oldest_and_newest {
+            Some((oldest, newest)) => (
+                get_date(config, oldest, date_format),
+                get_date(config, newest, date_format),
+            ),
+            None => ("-".to_owned(), "-".to_owned()),
+        }
+    };
+
+    let write_out_buffer: String = if config.opt_raw || config.opt_zeros {
+        let delimiter = if config.opt_zeros { '\0' } else { '\n' };
+
+        config
+            .paths
+            .iter()
+            .zip(rows.iter())
+            .map(|(pathdata, (num_versions, opt_oldest_and_newest))| {
+                let path = pathdata.path_buf.to_string_lossy();
+
+                match format {
+                    NumVersionsFormat::Count => {
+                        format!("{}\t{}{}", num_versions, path, delimiter)
+                    }
+                    NumVersionsFormat::WithDates => {
+                        let (oldest, newest) =
+                            display_dates(opt_oldest_and_newest, DateFormat::Timestamp);
+                        format!(
+                            "{}\t{}\t{}\t{}{}",
+                            num_versions, oldest, newest, path, delimiter
+                        )
+                    }
+                }
+            })
+            .collect()
+    } else {
+        let count_width = rows
+            .iter()
+            .map(|(num_versions, _)| num_versions.to_string().len())
+            .max()
+            .unwrap_or_default();
+        let date_width = get_date(config, &PHANTOM_DATE, DateFormat::Display).len();
+
+        config
+            .paths
+            .iter()
+            .zip(rows.iter())
+            .map(|(pathdata, (num_versions, opt_oldest_and_newest))| {
+                let dates = match format {
+                    NumVersionsFormat::Count => String::new(),
+                    NumVersionsFormat::WithDates => {
+                        let (oldest, newest) =
+                            display_dates(opt_oldest_and_newest, DateFormat::Display);
+                        format!(
+                            "{:<date_width$}{}{:<date_width$}{}",
+                            oldest, PRETTY_FIXED_WIDTH_PADDING, newest, PRETTY_FIXED_WIDTH_PADDING,
+                        )
+                    }
+                };
+
+                format!(
+                    "{:>count_width$}{}{}\"{}\"\n",
+                    num_versions,
+                    PRETTY_FIXED_WIDTH_PADDING,
+                    dates,
+                    pathdata.path_buf.to_string_lossy()
+                )
+            })
+            .collect()
+    };
+
+    print_output_buf(write_out_buffer)
+}
+
+pub fn display_blame(config: &Config) -> HttmResult<()> {
+    let write_out_buffer = config
+        .paths
+        .iter()
+        .map(|pathdata| {
+            let blame = get_blame(config, pathdata)?;
+
+            let origins: Vec<(String, String)> = blame
+                .origins
+                .iter()
+                .map(|origin| {
+                    let date = get_date(config, &origin.modify_time, DateFormat::Display);
+                    let name = origin
+                        .opt_snap_name
+                        .clone()
+                        .unwrap_or_else(|| LIVE_LABEL.to_owned());
+                    (date, name)
+                })
+                .collect();
+
+            let path_string = pathdata.path_buf.to_string_lossy();
+
+            let buffer = if config.opt_no_pretty {
+                let blame_buffer: String = blame
+                    .lines
+                    .iter()
+                    .map(|blame_line| {
+                        let (date, name) = &origins[blame_line.origin_idx];
+                        format!(
+                            "{}{pad}{}{pad}{}\n",
+                            date,
+                            name,
+                            blame_line.line,
+                            pad = NOT_SO_PRETTY_FIXED_WIDTH_PADDING
+                        )
+                    })
+                    .collect();
+                format!("{}:\n{}", path_string, blame_buffer)
+            } else {
+                let name_len = origins
+                    .iter()
+                    .map(|(_date, name)| name.chars().count())
+                    .max()
+                    .unwrap_or_default();
+
+                let formatted_lines: Vec<String> = blame
+                    .lines
+                    .iter()
+                    .map(|blame_line| {
+                        let (date, name) = &origins[blame_line.origin_idx];
+                        format!(
+                            "{}{pad}{:<name_len$}{pad}| {}\n",
+                            date,
+                            name,
+                            blame_line.line,
+                            pad = PRETTY_FIXED_WIDTH_PADDING,
+                            name_len = name_len
+                        )
+                    })
+                    .collect();
+
+                let header = format!("\"{}\"\n", path_string);
+                let fancy_border_len = formatted_lines
+                    .iter()
+                    .chain([&header])
+                    .map(|line| line.chars().count())
+                    .max()
+                    .unwrap_or_default();
+                let fancy_border_string = get_fancy_border_string(fancy_border_len);
+
+                let mut blame_buffer = fancy_border_string.clone() + &header;
+                blame_buffer += &fancy_border_string;
+                if !formatted_lines.is_empty() {
+                    blame_buffer += &formatted_lines.concat();
+                    blame_buffer += &fancy_border_string;
+                }
+                blame_buffer
+            };
+
+            Ok(buffer)
+        })
+        .collect::<HttmResult<String>>()?;
+
+    print_output_buf(write_out_buffer)?;
+
+    Ok(())
+}
+
+// two versions, side by side, the older on the left, with a marker between, like sdiff,
+// "|" for a changed line, "<" for a line only on the left, and ">" for a line only on the right
+pub fn display_side_by_side(
+    config: &Config,
+    old_pathdata: &PathData,
+    new_pathdata: &PathData,
+) -> HttmResult<String> {
+    let old_lines = read_lines(&old_pathdata.path_buf)?;
+    let new_lines = read_lines(&new_pathdata.path_buf)?;
+
+    let old_strs: Vec<&str> = old_lines.iter().map(String::as_str).collect();
+    let new_strs: Vec<&str> = new_lines.iter().map(String::as_str).collect();
+    let rows = get_side_by_side(&old_strs, &new_strs)?;
+
+    let total_width = match terminal_size() {
+        Some((Width(width), Height(_height))) => width as usize,
+        None => SIDE_BY_SIDE_DEFAULT_WIDTH,
+    };
+    // each side gets half of what remains once we make room for the marker
+    let column_width = total_width.saturating_sub(SIDE_BY_SIDE_MARKER_WIDTH) / 2;
+
+    let fit_to_column = |line: &str| -> String {
+        let line = line.replace('\t', "    ");
+        format!(
+            "{:<width$}",
+            line.chars().take(column_width).collect::<String>(),
+            width = column_width
+        )
+    };
+
+    let get_label = |pathdata: &PathData| {
+        let date = get_date(
+            config,
+            &pathdata.md_infallible().modify_time,
+            DateFormat::Display,
+        );
+        format!("{}  \"{}\"", date, pathdata.path_buf.to_string_lossy())
+    };
+
+    let header = format!(
+        "{}   {}\n",
+        fit_to_column(&get_label(old_pathdata)),
+        fit_to_column(&get_label(new_pathdata)).trim_end()
+    );
+
+    let fancy_border_string = get_fancy_border_string(column_width * 2 + SIDE_BY_SIDE_MARKER_WIDTH);
+
+    let body: String = rows
+        .iter()
+        .map(|row| {
+            let old_line = row.opt_old_idx.map_or("", |idx| old_strs[idx]);
+            let new_line = row.opt_new_idx.map_or("", |idx| new_strs[idx]);
+
+            let marker = match (row.opt_old_idx, row.opt_new_idx) {
+                _ if row.is_same => " ",
+                (Some(_), Some(_)) => "|",
+                (Some(_), None) => "<",
+                _ => ">",
+            };
+
+            let row = format!(
+                "{} {} {}",
+                fit_to_column(old_line),
+                marker,
+                fit_to_column(new_line)
+            );
+            format!("{}\n", row.trim_end())
+        })
+        .collect();
+
+    Ok(format!(
+        "{}{}{}{}{}",
+        fancy_border_string, header, fancy_border_string, body, fancy_border_string
+    ))
+}
+
 pub fn display_mounts_for_files(config: &Config) -> HttmResult<()> {
     let mounts_for_files = get_mounts_for_files(config)?;
 
@@ -293,8 +1565,7 @@ fn display_ordered_map(
             .collect()
     } else {
         let padding = map
-            .iter()
-            .map(|(key, _values)| key)
+            .keys()
             .max_by_key(|key| key.path_buf.to_string_lossy().len())
             .map_or_else(|| 0usize, |key| key.path_buf.to_string_lossy().len());
 
@@ -327,7 +1598,27 @@ fn display_ordered_map(
     Ok(write_out_buffer)
 }
 
-fn display_human_size(size: &u64) -> String {
+// which version of httm produced a listing, and when, so a listing may stand on its own as a record
+pub fn display_custody_header(config: &Config) -> String {
+    let generated_at = get_date(config, &SystemTime::now(), DateFormat::Display);
+
+    if config.opt_no_pretty {
+        format!(
+            "# httm {}{}generated {}\n",
+            crate_version!(),
+            NOT_SO_PRETTY_FIXED_WIDTH_PADDING,
+            generated_at
+        )
+    } else {
+        format!(
+            "Listing by httm {}, generated {}, with the SHA-256 of each version:\n",
+            crate_version!(),
+            generated_at
+        )
+    }
+}
+
+pub fn display_human_size(size: &u64) -> String {
     let size = *size as f64;
 
     match NumberPrefix::binary(size) {
@@ -0,0 +1,300 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::path::{Path, PathBuf};
+
+use which::which;
+
+use crate::parse_mounts::parse_datasets;
+use crate::parse_snaps::{
+    get_btrfs_root_mount_path, parse_snap_dirs_from_env, precompute_snap_mounts_for_dataset,
+};
+use crate::proximate_dataset::get_proximate_dataset;
+use crate::utility::{print_output_buf, PathData};
+use crate::{
+    FilesystemType, HttmResult, MapOfDatasets, MapOfSnaps, MountType,
+    BTRFS_SNAPPER_HIDDEN_DIRECTORY, ZFS_SNAPSHOT_DIRECTORY,
+};
+
+pub enum Finding {
+    Ok(String),
+    Warn(String, String),
+    Fail(String, String),
+}
+
+impl Finding {
+    pub fn is_fail(&self) -> bool {
+        matches!(self, Finding::Fail(_, _))
+    }
+
+    pub fn display(&self) -> String {
+        match self {
+            Finding::Ok(msg) => format!("[ok]   {}\n", msg),
+            Finding::Warn(msg, hint) => format!("[warn] {}\n       hint: {}\n", msg, hint),
+            Finding::Fail(msg, hint) => format!("[fail] {}\n       hint: {}\n", msg, hint),
+        }
+    }
+}
+
+// check the environment for each of the usual reasons httm finds nothing,
+// print what we find, and then exit, with a failure if anything is broken
+pub fn doctor() -> HttmResult<()> {
+    let mut findings: Vec<Finding> = Vec::new();
+
+    let map_of_datasets = match parse_datasets() {
+        Ok((map_of_datasets, _vec_of_filter_dirs)) => {
+            findings.push(Finding::Ok(format!(
+                "httm found {} supported dataset(s) mounted on the system",
+                map_of_datasets.len()
+            )));
+            map_of_datasets
+        }
+        Err(err) => {
+            findings.push(Finding::Fail(
+                err.to_string(),
+                "httm only finds ZFS and btrfs filesystems, or network shares of those, which are mounted.  \
+                If your snapshots are elsewhere, try specifying them with SNAP_POINT/LOCAL_DIR or MAP_ALIASES."
+                    .to_owned(),
+            ));
+            MapOfDatasets::new()
+        }
+    };
+
+    findings.extend(check_commands(&map_of_datasets));
+    findings.push(check_privileges());
+
+    let (snap_findings, map_of_snaps) = check_snap_dirs(&map_of_datasets);
+    findings.extend(snap_findings);
+
+    if !map_of_datasets.is_empty() {
+        findings.push(check_sample_lookup(&map_of_datasets, &map_of_snaps));
+    }
+
+    print_findings_and_exit(&findings, "httm doctor")
+}
+
+pub fn print_findings_and_exit(findings: &[Finding], checked_by: &str) -> HttmResult<()> {
+    let num_failed = findings.iter().filter(|finding| finding.is_fail()).count();
+
+    let mut output_buf: String = findings.iter().map(Finding::display).collect();
+    output_buf += &format!("{} found {} problem(s).\n", checked_by, num_failed);
+    print_output_buf(output_buf)?;
+
+    if num_failed == 0 {
+        std::process::exit(0)
+    } else {
+        std::process::exit(1)
+    }
+}
+
+fn check_commands(map_of_datasets: &MapOfDatasets) -> Vec<Finding> {
+    let has_fs_type = |fs_type: FilesystemType| {
+        map_of_datasets
+            .values()
+            .any(|dataset_info| dataset_info.fs_type == fs_type)
+    };
+
+    let has_local_btrfs = map_of_datasets.values().any(|dataset_info| {
+        dataset_info.fs_type == FilesystemType::Btrfs && dataset_info.mount_type == MountType::Local
+    });
+
+    let zfs_finding = match which("zfs") {
+        Ok(path) => Finding::Ok(format!("'zfs' command found at {:?}", path)),
+        Err(_) if has_fs_type(FilesystemType::Zfs) => Finding::Warn(
+            "'zfs' command not found, but ZFS datasets are mounted".to_owned(),
+            "the 'zfs' command is only required to take snapshots with --snap.".to_owned(),
+        ),
+        Err(_) => {
+            Finding::Ok("'zfs' command not found, and no ZFS datasets are mounted".to_owned())
+        }
+    };
+
+    let btrfs_finding = match which("btrfs") {
+        Ok(path) => Finding::Ok(format!("'btrfs' command found at {:?}", path)),
+        Err(_) if has_local_btrfs => Finding::Fail(
+            "'btrfs' command not found, but local btrfs datasets are mounted".to_owned(),
+            "httm uses the 'btrfs' command to list the snapshots of local btrfs datasets.  Install btrfs-progs."
+                    .to_owned(),
+        ),
+        Err(_) => Finding::Ok(
+            "'btrfs' command not found, and no local btrfs datasets are mounted".to_owned(),
+        ),
+    };
+
+    vec![zfs_finding, btrfs_finding]
+}
+
+fn check_privileges() -> Finding {
+    // safe, geteuid() cannot fail and has no side effects
+    let euid = unsafe { libc::geteuid() };
+
+    if euid == 0 {
+        Finding::Ok("httm is running with root privileges".to_owned())
+    } else {
+        Finding::Warn(
+            format!("httm is running without root privileges (euid {})", euid),
+            "listing btrfs snapshots and taking snapshots with --snap usually require root.  \
+            Versions of files you may not read live will not be readable on snapshots either."
+                .to_owned(),
+        )
+    }
+}
+
+fn check_snap_dirs(map_of_datasets: &MapOfDatasets) -> (Vec<Finding>, MapOfSnaps) {
+    let opt_root_mount_path = get_btrfs_root_mount_path(map_of_datasets);
+
+    let mut map_of_snaps = MapOfSnaps::new();
+    let mut findings = Vec::new();
+
+    // the doctor takes no other args, so user defined snap dirs may only come from the env
+    let opt_map_of_snap_dirs = match parse_snap_dirs_from_env() {
+        Ok(opt_map_of_snap_dirs) => opt_map_of_snap_dirs,
+        Err(err) => {
+            findings.push(Finding::Fail(
+                format!("the snap dirs set in HTTM_SNAP_DIRS are invalid: {}", err),
+                "specify each snap dir as <MOUNT>:<SNAP_DIR>, delimited by a comma, ','."
+                    .to_owned(),
+            ));
+            None
+        }
+    };
+
+    let snap_dir_findings = map_of_datasets
+        .iter()
+        .map(|(mount, dataset_info)| {
+            let opt_snap_dir = opt_map_of_snap_dirs
+                .as_ref()
+                .and_then(|map_of_snap_dirs| map_of_snap_dirs.get(mount));
+
+            match precompute_snap_mounts_for_dataset(
+                mount,
+                dataset_info,
+                opt_root_mount_path,
+                opt_snap_dir,
+            ) {
+                Ok(snap_mounts) if snap_mounts.is_empty() => {
+                    map_of_snaps.insert(mount.clone(), snap_mounts);
+                    Finding::Warn(
+                        format!(
+                            "dataset {:?} mounted at {:?} has no snapshots",
+                            dataset_info.name, mount
+                        ),
+                        "httm can only find versions of files on snapshots which exist.  Take a snapshot of this dataset."
+                    .to_owned(),
+                    )
+                }
+                Ok(snap_mounts) => {
+                    let finding = Finding::Ok(format!(
+                        "dataset {:?} mounted at {:?} has {} visible snapshot(s)",
+                        dataset_info.name,
+                        mount,
+                        snap_mounts.len()
+                    ));
+                    map_of_snaps.insert(mount.clone(), snap_mounts);
+                    finding
+                }
+                Err(err) => Finding::Fail(
+                    format!(
+                        "snapshots of dataset {:?} mounted at {:?} are not visible: {}",
+                        dataset_info.name, mount, err
+                    ),
+                    snap_dir_hint(&dataset_info.fs_type),
+                ),
+            }
+        });
+
+    findings.extend(snap_dir_findings);
+
+    (findings, map_of_snaps)
+}
+
+fn snap_dir_hint(fs_type: &FilesystemType) -> String {
+    match fs_type {
+        FilesystemType::Zfs => format!(
+            "httm reads ZFS snapshots from the {:?} directory at the dataset's mount.  \
+            For a network share, make sure the server exports that directory.  \
+            If the dataset's snapdir property is \"disabled\", try again with \"--enable-snapdir\".  \
+            If the snapshots are elsewhere, specify where with HTTM_SNAP_DIRS.",
+            ZFS_SNAPSHOT_DIRECTORY
+        ),
+        FilesystemType::Btrfs => format!(
+            "httm reads btrfs snapshots with the 'btrfs' command, or, for network shares and \
+            where no root subvolume is mounted, from the snapper {:?} directory at the dataset's mount.",
+            BTRFS_SNAPPER_HIDDEN_DIRECTORY
+        ),
+    }
+}
+
+// a quick lookup of the working directory, the way httm would do it, to show
+// httm finds what the other checks say it should
+fn check_sample_lookup(map_of_datasets: &MapOfDatasets, map_of_snaps: &MapOfSnaps) -> Finding {
+    let pwd = match std::env::current_dir() {
+        Ok(pwd) => PathData::from(pwd.as_path()),
+        Err(err) => {
+            return Finding::Warn(
+                format!(
+                    "httm could not read the working directory for a sample lookup: {}",
+                    err
+                ),
+                "run httm --doctor from a directory on a snapshotted dataset.".to_owned(),
+            )
+        }
+    };
+
+    let proximate_dataset_mount = match get_proximate_dataset(&pwd, map_of_datasets) {
+        Ok(mount) => mount,
+        Err(_) => {
+            return Finding::Warn(
+                format!(
+                    "the working directory {:?} is not on any supported dataset, so no sample lookup was made",
+                    pwd.path_buf
+                ),
+                "run httm --doctor from a directory on a snapshotted dataset."
+                    .to_owned(),
+            )
+        }
+    };
+
+    let relative_path = pwd
+        .path_buf
+        .strip_prefix(&proximate_dataset_mount)
+        .unwrap_or_else(|_| Path::new(""));
+
+    let versions: Vec<PathBuf> = map_of_snaps
+        .get(&proximate_dataset_mount)
+        .into_iter()
+        .flatten()
+        .map(|snap_mount| snap_mount.join(relative_path))
+        .filter(|snap_path| snap_path.exists())
+        .collect();
+
+    if versions.is_empty() {
+        Finding::Warn(
+            format!(
+                "a sample lookup of the working directory {:?} found no snapshot versions",
+                pwd.path_buf
+            ),
+            "the directory may be newer than every snapshot of its dataset.".to_owned(),
+        )
+    } else {
+        Finding::Ok(format!(
+            "a sample lookup of the working directory {:?} found {} snapshot version(s)",
+            pwd.path_buf,
+            versions.len()
+        ))
+    }
+}
@@ -0,0 +1,189 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    process::{Command as ExecProcess, Stdio},
+};
+
+use which::which;
+
+use crate::forensic::audit;
+use crate::restrict_owner::{deny_if_restricted_to_owner, open_owned};
+use crate::utility::{httm_is_dir, HttmError, PathData};
+use crate::{Config, ExtractRequest, HttmResult};
+
+// a tar archive has its magic bytes at an offset, after the name of its first member
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_MAGIC: &[u8] = b"ustar";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+// tar reads each of these compressions for itself
+const COMPRESSED_TAR_MAGIC_BYTES: [&[u8]; 4] =
+    [b"\x1F\x8B", b"BZh", b"\xFD7zXZ\x00", b"\x28\xB5\x2F\xFD"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+// only part of a version is wanted, so we read only that part, rather than copy the whole
+// of a multi-GB version, and write it to stdout, for the user to redirect wherever they like
+pub fn extract_exec(config: &Config, extract_request: &ExtractRequest) -> HttmResult<()> {
+    let pathdata = match config.paths.as_slice() {
+        [pathdata] => pathdata,
+        _ => {
+            return Err(HttmError::new(
+                "httm can only extract from exactly one version at a time.",
+            ))
+        }
+    };
+
+    if pathdata.metadata().is_none() {
+        return Err(HttmError::new(&format!(
+            "httm could not find the version specified: {:?}",
+            pathdata.path_buf
+        )));
+    }
+
+    if httm_is_dir(pathdata) {
+        return Err(HttmError::new(&format!(
+            "httm can only extract from a file, and this is a directory: {:?}",
+            pathdata.path_buf
+        )));
+    }
+
+    match extract_request {
+        ExtractRequest::ByteRange { start, len } => extract_byte_range(pathdata, *start, *len),
+        ExtractRequest::Members(members) => extract_members(pathdata, members),
+    }
+}
+
+fn extract_byte_range(pathdata: &PathData, start: u64, len: u64) -> HttmResult<()> {
+    let size = pathdata.md_infallible().size;
+
+    if start >= size {
+        return Err(HttmError::new(&format!(
+            "httm can't extract from an offset of {} bytes, as the version is only {} bytes long.",
+            start, size
+        )));
+    }
+
+    audit("read", &pathdata.path_buf);
+    let mut file = open_owned("read", &pathdata.path_buf)?;
+    file.seek(SeekFrom::Start(start))?;
+
+    write_to_stdout(file.take(len))
+}
+
+// the whole of a version, like "git show rev:file", so a version may be piped to another program
+pub fn cat_version(version_path: &Path) -> HttmResult<()> {
+    if version_path.is_dir() {
+        return Err(HttmError::new(&format!(
+            "httm can only write the contents of a file to stdout, and this is a directory: {:?}",
+            version_path
+        )));
+    }
+
+    audit("read", version_path);
+    let file = open_owned("read", version_path)?;
+
+    write_to_stdout(file)
+}
+
+fn write_to_stdout(mut reader: impl Read) -> HttmResult<()> {
+    let out = std::io::stdout();
+    let mut out_locked = out.lock();
+    std::io::copy(&mut reader, &mut out_locked)?;
+    out_locked.flush()?;
+
+    Ok(())
+}
+
+fn extract_members(pathdata: &PathData, members: &[String]) -> HttmResult<()> {
+    // tar and unzip run with our privileges, which, via sudo, may not be the user's
+    deny_if_restricted_to_owner("run another program to extract members")?;
+
+    // tar would read a member like "--checkpoint-action=exec=sh" as an option, and unzip has
+    // no "--" to end its options, so no member may look like an option at all
+    if let Some(member) = members.iter().find(|member| member.starts_with('-')) {
+        return Err(HttmError::new(&format!(
+            "httm will not extract the member {:?}, as a member may not begin with a '-'.",
+            member
+        )));
+    }
+
+    let (program, args, opt_end_of_options): (&str, &[&str], Option<&str>) =
+        match get_archive_format(&pathdata.path_buf)? {
+            // each member is written to stdout, rather than to the working directory
+            Some(ArchiveFormat::Tar) => ("tar", &["-xOf"], Some("--")),
+            Some(ArchiveFormat::Zip) => ("unzip", &["-p"], None),
+        None => {
+            return Err(HttmError::new(&format!(
+                "httm can only extract members from a tar or a zip archive, and {:?} appears to be neither.",
+                pathdata.path_buf
+            )))
+        }
+    };
+
+    let command = which(program).map_err(|_| HttmError::CommandNotFound(program.to_owned()))?;
+
+    audit("read", &pathdata.path_buf);
+    let status = ExecProcess::new(command)
+        .args(args)
+        .arg(&pathdata.path_buf)
+        .args(opt_end_of_options)
+        .args(members)
+        .stdout(Stdio::inherit())
+        .status()?;
+
+    if !status.success() {
+        return Err(HttmError::new(&format!(
+            "{} could not extract the members requested from {:?}.",
+            program, pathdata.path_buf
+        )));
+    }
+
+    Ok(())
+}
+
+fn get_archive_format(path: &Path) -> HttmResult<Option<ArchiveFormat>> {
+    let mut header = Vec::with_capacity(TAR_MAGIC_OFFSET + TAR_MAGIC.len());
+
+    audit("read", path);
+    File::open(path)?
+        .take((TAR_MAGIC_OFFSET + TAR_MAGIC.len()) as u64)
+        .read_to_end(&mut header)?;
+
+    let is_tar = header.get(TAR_MAGIC_OFFSET..) == Some(TAR_MAGIC)
+        || COMPRESSED_TAR_MAGIC_BYTES
+            .iter()
+            .any(|magic| header.starts_with(magic));
+
+    let archive_format = if header.starts_with(ZIP_MAGIC) {
+        Some(ArchiveFormat::Zip)
+    } else if is_tar {
+        Some(ArchiveFormat::Tar)
+    } else {
+        None
+    };
+
+    Ok(archive_format)
+}
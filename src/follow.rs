@@ -0,0 +1,67 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeSet,
+    thread::sleep,
+    time::{Duration, SystemTime},
+};
+
+use crate::display::display_exec;
+use crate::lookup_versions::get_version_entries;
+use crate::utility::{print_output_buf, PathData};
+use crate::{Config, HttmResult};
+
+pub const DEFAULT_FOLLOW_INTERVAL: Duration = Duration::from_secs(5);
+
+// like "tail -f", print the versions which exist now, and then each new version as its snapshot appears
+pub fn follow_exec(config: &Config, interval: &Duration) -> HttmResult<()> {
+    // key: (path requested, modify time, size) of every unique version printed so far
+    let mut seen: BTreeSet<(PathData, SystemTime, u64)> = BTreeSet::new();
+    let mut config = config.clone();
+
+    loop {
+        config
+            .paths
+            .iter()
+            .map(|pathdata| {
+                let mut new_versions: Vec<PathData> = get_version_entries(&config, pathdata)
+                    .into_iter()
+                    .map(|version_entry| version_entry.pathdata)
+                    .filter(|version| {
+                        let metadata = version.md_infallible();
+                        seen.insert((pathdata.clone(), metadata.modify_time, metadata.size))
+                    })
+                    .collect();
+
+                if new_versions.is_empty() {
+                    return Ok(());
+                }
+
+                new_versions.sort_by_key(|version| version.md_infallible().modify_time);
+
+                let output_buf = display_exec(&config, &[new_versions, Vec::new()])?;
+                print_output_buf(output_buf)
+            })
+            .collect::<HttmResult<Vec<()>>>()?;
+
+        sleep(*interval);
+
+        // snap mounts were only known as of startup, so look again for any new snapshots
+        config.dataset_collection.map_of_snaps.clear();
+    }
+}
@@ -0,0 +1,107 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::utility::HttmError;
+use crate::HttmResult;
+
+// once set, httm is in forensic mode, and every path it touches is recorded here
+static AUDIT_LOG: OnceCell<Mutex<File>> = OnceCell::new();
+
+pub fn init_forensic(audit_log_path: &Path) -> HttmResult<()> {
+    // append, so a log may accumulate the whole of an investigation, across many sessions
+    let mut audit_log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_log_path)
+        .map_err(|err| {
+            let msg = format!(
+                "httm will not run in forensic mode without an audit log, and could not open {:?}: {}",
+                audit_log_path, err
+            );
+            HttmError::new(&msg)
+        })?;
+
+    let args: Vec<String> = std::env::args().collect();
+    writeln!(
+        audit_log,
+        "# {}\tsession\tpid {}: {}",
+        get_timestamp(),
+        std::process::id(),
+        args.join(" ")
+    )?;
+
+    AUDIT_LOG
+        .set(Mutex::new(audit_log))
+        .map_err(|_| HttmError::new("httm forensic mode may only be initialized once."))
+}
+
+pub fn is_forensic() -> bool {
+    AUDIT_LOG.get().is_some()
+}
+
+// every operation which would change the system, however slightly, checks in here first
+pub fn deny_if_forensic(operation: &str) -> HttmResult<()> {
+    if is_forensic() {
+        let msg = format!(
+            "httm will not {} in forensic mode, as forensic mode is strictly read-only.",
+            operation
+        );
+        return Err(HttmError::PermissionDenied { msg, path: None });
+    }
+
+    Ok(())
+}
+
+// operations are short words, like "stat", "list" or "read"
+pub fn audit(operation: &str, path: &Path) {
+    if let Some(audit_log) = AUDIT_LOG.get() {
+        if let Ok(mut audit_log) = audit_log.lock() {
+            // a forensic session which cannot record what it touches should not continue
+            if let Err(err) = writeln!(
+                audit_log,
+                "{}\t{}\t{}",
+                get_timestamp(),
+                operation,
+                path.to_string_lossy()
+            ) {
+                eprintln!(
+                    "Error: httm could not write to the forensic audit log: {}",
+                    err
+                );
+                std::process::exit(1)
+            }
+        }
+    }
+}
+
+fn get_timestamp() -> String {
+    let duration = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    format!("{}.{:03}", duration.as_secs(), duration.subsec_millis())
+}
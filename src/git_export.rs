@@ -0,0 +1,234 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeSet,
+    fs::{copy, create_dir_all},
+    path::{Path, PathBuf},
+    process::Command as ExecProcess,
+    time::SystemTime,
+};
+
+use which::which;
+
+use crate::forensic::deny_if_forensic;
+use crate::git_xref::exec_git;
+use crate::lookup_versions::{get_creation_times_for_entries, get_version_entries};
+use crate::utility::{httm_is_dir, HttmError, PathData};
+use crate::{Config, HttmResult};
+
+// each commit records the snapshot version it was made from, so a later export
+// into the same repo only commits those versions which are new since
+const SNAPSHOT_TRAILER: &str = "Httm-Snapshot-Path: ";
+const COMMIT_IDENTITY_NAME: &str = "httm";
+const COMMIT_IDENTITY_EMAIL: &str = "httm@localhost";
+
+struct ExportVersion {
+    creation_time: SystemTime,
+    snap_name: String,
+    version_path: PathBuf,
+    // the path of the file within the repo's work tree
+    repo_relative_path: PathBuf,
+}
+
+pub fn export_to_git(config: &Config, repo_dir: &Path) -> HttmResult<()> {
+    deny_if_forensic("export to a git repository")?;
+
+    let git_command = which("git").map_err(|_| HttmError::CommandNotFound("git".to_owned()))?;
+
+    let (dirs, files): (Vec<&PathData>, Vec<&PathData>) = config
+        .paths
+        .iter()
+        .partition(|pathdata| httm_is_dir(*pathdata));
+
+    if !dirs.is_empty() {
+        eprintln!(
+            "httm can only export the versions of files to a git repository, \
+        so the following paths will be ignored: "
+        );
+
+        dirs.iter()
+            .for_each(|pathdata| eprintln!("{}", pathdata.path_buf.to_string_lossy()));
+    }
+
+    if files.is_empty() {
+        return Err(HttmError::new(
+            "httm could not find any files to export to a git repository.",
+        ));
+    }
+
+    create_dir_all(repo_dir)?;
+
+    // only init a repo of our own, we don't want to commit to some repo which merely contains our dir
+    if !repo_dir.join(".git").exists() {
+        exec_git(&git_command, repo_dir, &["init", "--quiet"])?;
+    }
+
+    let already_exported = get_already_exported(&git_command, repo_dir);
+
+    // interleave the histories of all files, so the repo's history is in snapshot order
+    let mut export_versions: Vec<ExportVersion> = files
+        .into_iter()
+        .flat_map(|pathdata| get_export_versions(config, pathdata))
+        .filter(|export_version| !already_exported.contains(&export_version.version_path))
+        .collect();
+    export_versions.sort_by(|a, b| {
+        (a.creation_time, &a.repo_relative_path).cmp(&(b.creation_time, &b.repo_relative_path))
+    });
+
+    let num_committed = export_versions
+        .iter()
+        .map(|export_version| commit_version(&git_command, repo_dir, export_version))
+        .collect::<HttmResult<Vec<bool>>>()?
+        .into_iter()
+        .filter(|is_committed| *is_committed)
+        .count();
+
+    println!(
+        "httm committed {} new version(s) to the git repository at: {:?}",
+        num_committed, repo_dir
+    );
+
+    Ok(())
+}
+
+fn get_export_versions(config: &Config, pathdata: &PathData) -> Vec<ExportVersion> {
+    let version_entries = get_version_entries(config, pathdata);
+    let creation_times = get_creation_times_for_entries(config, &version_entries);
+
+    // same as the live path, but relative to the root, so files with the same name don't collide
+    let repo_relative_path: PathBuf = pathdata
+        .path_buf
+        .components()
+        .filter(|component| matches!(component, std::path::Component::Normal(_)))
+        .collect();
+
+    let mut export_versions: Vec<ExportVersion> = version_entries
+        .into_iter()
+        .map(|version_entry| {
+            // when no creation time can be had, the modify time is the next best thing
+            let creation_time = creation_times
+                .get(&version_entry.snap_mount)
+                .copied()
+                .unwrap_or_else(|| version_entry.pathdata.md_infallible().modify_time);
+
+            ExportVersion {
+                creation_time,
+                snap_name: version_entry.snap_name,
+                version_path: version_entry.pathdata.path_buf,
+                repo_relative_path: repo_relative_path.clone(),
+            }
+        })
+        .collect();
+
+    // identical versions may exist on multiple datasets, so keep just the first of each
+    export_versions.sort_by_key(|export_version| export_version.creation_time);
+    let mut seen = BTreeSet::new();
+    export_versions.retain(|export_version| {
+        let metadata = PathData::from(export_version.version_path.as_path()).md_infallible();
+        seen.insert((metadata.modify_time, metadata.size))
+    });
+
+    export_versions
+}
+
+fn get_already_exported(git_command: &Path, repo_dir: &Path) -> BTreeSet<PathBuf> {
+    // a new repo has no HEAD, and so nothing exported, which is no error
+    exec_git(git_command, repo_dir, &["log", "--format=%B", "HEAD"])
+        .map(|output| {
+            output
+                .lines()
+                .filter_map(|line| line.strip_prefix(SNAPSHOT_TRAILER))
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// returns whether a commit was made, no commit is made when the contents are unchanged
+fn commit_version(
+    git_command: &Path,
+    repo_dir: &Path,
+    export_version: &ExportVersion,
+) -> HttmResult<bool> {
+    let work_tree_path = repo_dir.join(&export_version.repo_relative_path);
+
+    if let Some(parent) = work_tree_path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    copy(&export_version.version_path, &work_tree_path)?;
+
+    let relative_path_str = export_version.repo_relative_path.to_string_lossy();
+
+    exec_git(git_command, repo_dir, &["add", "--", &relative_path_str])?;
+
+    let status = exec_git(
+        git_command,
+        repo_dir,
+        &["status", "--porcelain", "--", &relative_path_str],
+    )?;
+
+    if status.trim().is_empty() {
+        return Ok(false);
+    }
+
+    let commit_date = format!(
+        "@{} +0000",
+        export_version
+            .creation_time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    );
+
+    let message = format!(
+        "{}: {}\n\n{}{}",
+        export_version.snap_name,
+        relative_path_str,
+        SNAPSHOT_TRAILER,
+        export_version.version_path.to_string_lossy()
+    );
+
+    // commit only the path given, in case the user has staged something else in the meantime
+    let process_output = ExecProcess::new(git_command)
+        .arg("-C")
+        .arg(repo_dir)
+        .args([
+            "commit",
+            "--quiet",
+            "-m",
+            &message,
+            "--",
+            &relative_path_str,
+        ])
+        .env("GIT_AUTHOR_NAME", COMMIT_IDENTITY_NAME)
+        .env("GIT_AUTHOR_EMAIL", COMMIT_IDENTITY_EMAIL)
+        .env("GIT_AUTHOR_DATE", &commit_date)
+        .env("GIT_COMMITTER_NAME", COMMIT_IDENTITY_NAME)
+        .env("GIT_COMMITTER_EMAIL", COMMIT_IDENTITY_EMAIL)
+        .env("GIT_COMMITTER_DATE", &commit_date)
+        .output()?;
+
+    if !process_output.status.success() {
+        let stderr_string = std::str::from_utf8(&process_output.stderr)?.trim();
+        let msg = "The 'git' command issued the following error: ".to_owned() + stderr_string;
+        return Err(HttmError::new(&msg));
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,231 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process::Command as ExecProcess,
+    time::{Duration, SystemTime},
+};
+
+use which::which;
+
+use crate::forensic::audit;
+use crate::utility::{HttmError, PathData};
+use crate::HttmResult;
+
+// how a single file version relates to the history of the git work tree in which it lives
+#[derive(Debug, Clone)]
+pub struct GitXref {
+    // commit which was checked out at the version's modify time, if known
+    pub opt_commit: Option<String>,
+    // whether the contents of the version were ever committed to any branch
+    pub is_committed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitWorkTree {
+    git_command: PathBuf,
+    toplevel: PathBuf,
+    relative_path: PathBuf,
+    // both newest first, as git prints them
+    reflog: Vec<(SystemTime, String)>,
+    commit_log: Vec<(SystemTime, String)>,
+    // every blob ever committed at the relative path
+    committed_blobs: BTreeSet<String>,
+}
+
+impl GitWorkTree {
+    pub fn new(live_path: &Path) -> HttmResult<Self> {
+        let git_command = which("git").map_err(|_| HttmError::CommandNotFound("git".to_owned()))?;
+
+        // a deleted live file has no dir of its own, so find the nearest dir which still exists
+        let search_dir = live_path
+            .ancestors()
+            .skip(1)
+            .find(|ancestor| ancestor.is_dir())
+            .ok_or_else(|| {
+                HttmError::new(
+                    "httm could not find a directory in which to search for a git work tree.",
+                )
+            })?;
+
+        let toplevel = PathBuf::from(
+            exec_git(&git_command, search_dir, &["rev-parse", "--show-toplevel"])
+                .map_err(|_| {
+                    let msg = format!("Path is not within a git work tree: {:?}", live_path);
+                    HttmError::new(&msg)
+                })?
+                .trim(),
+        );
+
+        let relative_path = live_path
+            .strip_prefix(&toplevel)
+            .map_err(|_| {
+                HttmError::new("httm could not determine the path relative to the git work tree.")
+            })?
+            .to_path_buf();
+        let relative_path_str = relative_path.to_string_lossy();
+
+        // a new repo has no HEAD, and so no reflog and no commits, which is no error
+        let reflog = exec_git(
+            &git_command,
+            &toplevel,
+            &[
+                "reflog",
+                "show",
+                "--format=%H%x09%gd",
+                "--date=unix",
+                "HEAD",
+            ],
+        )
+        .map(|output| parse_reflog(&output))
+        .unwrap_or_default();
+
+        let commit_log = exec_git(
+            &git_command,
+            &toplevel,
+            &["log", "--format=%H%x09%ct", "HEAD"],
+        )
+        .map(|output| parse_commit_log(&output))
+        .unwrap_or_default();
+
+        let committed_blobs = exec_git(
+            &git_command,
+            &toplevel,
+            &[
+                "log",
+                "--all",
+                "--format=",
+                "--raw",
+                "--no-abbrev",
+                "--",
+                &relative_path_str,
+            ],
+        )
+        .map(|output| parse_committed_blobs(&output))
+        .unwrap_or_default();
+
+        Ok(GitWorkTree {
+            git_command,
+            toplevel,
+            relative_path,
+            reflog,
+            commit_log,
+            committed_blobs,
+        })
+    }
+
+    pub fn xref(&self, pathdata: &PathData) -> GitXref {
+        let opt_commit = pathdata.metadata().and_then(|metadata| {
+            self.checked_out_at(&metadata.modify_time)
+                .map(|commit| commit.to_owned())
+        });
+
+        let is_committed = self
+            .hash_object(&pathdata.path_buf)
+            .map(|blob| self.committed_blobs.contains(&blob))
+            .unwrap_or(false);
+
+        GitXref {
+            opt_commit,
+            is_committed,
+        }
+    }
+
+    fn checked_out_at(&self, time: &SystemTime) -> Option<&str> {
+        // the reflog knows what was actually checked out, but may have expired,
+        // so fall back to the latest commit on HEAD which preceded the time given
+        self.reflog
+            .iter()
+            .chain(self.commit_log.iter())
+            .find(|(entry_time, _commit)| entry_time <= time)
+            .map(|(_entry_time, commit)| commit.as_str())
+    }
+
+    fn hash_object(&self, path: &Path) -> HttmResult<String> {
+        audit("read", path);
+        // hash with the filters of the live path, so line endings, etc., match what git would commit
+        let path_arg = format!("--path={}", self.relative_path.to_string_lossy());
+        let file_arg = path.to_string_lossy();
+        let blob = exec_git(
+            &self.git_command,
+            &self.toplevel,
+            &["hash-object", &path_arg, &file_arg],
+        )?;
+
+        Ok(blob.trim().to_owned())
+    }
+}
+
+pub fn exec_git(git_command: &Path, dir: &Path, args: &[&str]) -> HttmResult<String> {
+    let process_output = ExecProcess::new(git_command)
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()?;
+
+    if !process_output.status.success() {
+        let stderr_string = std::str::from_utf8(&process_output.stderr)?.trim();
+        let msg = "The 'git' command issued the following error: ".to_owned() + stderr_string;
+        return Err(HttmError::new(&msg));
+    }
+
+    Ok(std::str::from_utf8(&process_output.stdout)?.to_owned())
+}
+
+fn from_unix_secs(secs: &str) -> Option<SystemTime> {
+    secs.parse::<u64>()
+        .ok()
+        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+// lines like: "<commit>\tHEAD@{<unix time>}"
+fn parse_reflog(output: &str) -> Vec<(SystemTime, String)> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(commit, selector)| {
+            selector
+                .split_once('{')
+                .and_then(|(_ref, rest)| rest.strip_suffix('}'))
+                .and_then(from_unix_secs)
+                .map(|time| (time, commit.to_owned()))
+        })
+        .collect()
+}
+
+// lines like: "<commit>\t<unix time>"
+fn parse_commit_log(output: &str) -> Vec<(SystemTime, String)> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(commit, secs)| from_unix_secs(secs).map(|time| (time, commit.to_owned())))
+        .collect()
+}
+
+// lines like: ":100644 100644 <old blob> <new blob> M\t<path>"
+fn parse_committed_blobs(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .filter(|line| line.starts_with(':'))
+        .flat_map(|line| line.split_whitespace().skip(2).take(2))
+        // an all zeros blob is no blob, as when a file is added or deleted
+        .filter(|blob| !blob.chars().all(|c| c == '0'))
+        .map(|blob| blob.to_owned())
+        .collect()
+}
@@ -0,0 +1,318 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command as ExecProcess,
+};
+
+use serde_json::Value;
+use which::which;
+
+use crate::display::display_human_size;
+use crate::serve_stdio::{exec_method, Method};
+use crate::utility::{HttmError, PathData};
+use crate::{Config, HttmResult};
+
+const RESTORE_COPY_TAG: &str = "copy";
+const RESTORE_OVERWRITE_TAG: &str = "overwrite";
+
+// a desktop context menu has no terminal, so we ask with whichever dialog program the desktop
+// provides, or, when there is none, print the listing of "--serve-stdio" for some other program
+enum GuiDialog {
+    Zenity(PathBuf),
+    Kdialog(PathBuf),
+    Json,
+}
+
+struct DialogRow {
+    tag: String,
+    columns: Vec<String>,
+}
+
+pub fn gui_helper_exec(config: &Config, requested_path: &Path) -> HttmResult<()> {
+    let dialog = GuiDialog::detect();
+
+    let res = gui_helper(config, requested_path, &dialog);
+
+    if let Err(err) = &res {
+        dialog.show_error(&err.to_string());
+    }
+
+    res
+}
+
+fn gui_helper(config: &Config, requested_path: &Path, dialog: &GuiDialog) -> HttmResult<()> {
+    let path = config.pwd.path_buf.join(requested_path);
+
+    let listing = exec_method(config, Method::ListVersions { path: path.clone() })?;
+
+    if matches!(dialog, GuiDialog::Json) {
+        println!("{}", listing);
+        return Ok(());
+    }
+
+    let rows: Vec<DialogRow> = listing["versions"]
+        .as_array()
+        .map(|versions| versions.iter().filter_map(get_version_row).collect())
+        .unwrap_or_default();
+
+    if rows.is_empty() {
+        return Err(HttmError::NoVersions {
+            msg: format!("httm could not find any snapshot versions of {:?}.", path),
+            path: Some(path.to_path_buf()),
+        });
+    }
+
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned());
+
+    let title = format!("httm: previous versions of {}", file_name);
+
+    let version = match dialog.choose(
+        &title,
+        "Select a version to restore:",
+        &["Date", "Size", "Snapshot"],
+        &rows,
+    )? {
+        Some(version) => PathBuf::from(version),
+        None => return Ok(()),
+    };
+
+    let actions = [
+        DialogRow {
+            tag: RESTORE_COPY_TAG.to_owned(),
+            columns: vec![format!("Restore a copy beside {}", file_name)],
+        },
+        DialogRow {
+            tag: RESTORE_OVERWRITE_TAG.to_owned(),
+            columns: vec![format!("Overwrite {}", file_name)],
+        },
+    ];
+
+    let overwrite = match dialog
+        .choose(
+            &title,
+            "How should httm restore this version?",
+            &["Action"],
+            &actions,
+        )?
+        .as_deref()
+    {
+        Some(RESTORE_OVERWRITE_TAG) => true,
+        Some(_) => false,
+        None => return Ok(()),
+    };
+
+    // we are most likely started in some directory not of the user's choosing,
+    // so a copy is restored beside the live file, rather than to our pwd
+    let live_dir = path
+        .parent()
+        .map(|parent| parent.to_path_buf())
+        .unwrap_or_else(|| config.pwd.path_buf.clone());
+
+    let restore_config = Config {
+        pwd: PathData::from(live_dir.as_path()),
+        ..config.clone()
+    };
+
+    let result = exec_method(
+        &restore_config,
+        Method::Restore {
+            version,
+            path,
+            overwrite,
+        },
+    )?;
+
+    let mut msg = format!(
+        "httm restored:\n{}\nto:\n{}",
+        result["from"].as_str().unwrap_or_default(),
+        result["to"].as_str().unwrap_or_default()
+    );
+
+    if let Some(warning) = result["warning"].as_str() {
+        msg.push_str("\n\n");
+        msg.push_str(warning);
+    }
+
+    dialog.show_info(&title, &msg);
+
+    Ok(())
+}
+
+fn get_version_row(version: &Value) -> Option<DialogRow> {
+    let tag = version["path"].as_str()?.to_owned();
+
+    let columns = vec![
+        version["date"].as_str().unwrap_or_default().to_owned(),
+        version["size"]
+            .as_u64()
+            .map(|size| display_human_size(&size))
+            .unwrap_or_default(),
+        version["snapshot"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned(),
+    ];
+
+    Some(DialogRow { tag, columns })
+}
+
+impl GuiDialog {
+    fn detect() -> Self {
+        let has_display =
+            std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some();
+
+        if !has_display {
+            return GuiDialog::Json;
+        }
+
+        let is_kde = std::env::var("XDG_CURRENT_DESKTOP")
+            .map(|desktop| desktop.contains("KDE"))
+            .unwrap_or(false);
+
+        match (is_kde, which("zenity").ok(), which("kdialog").ok()) {
+            (true, _, Some(kdialog_command)) => GuiDialog::Kdialog(kdialog_command),
+            (_, Some(zenity_command), _) => GuiDialog::Zenity(zenity_command),
+            (_, None, Some(kdialog_command)) => GuiDialog::Kdialog(kdialog_command),
+            (_, None, None) => GuiDialog::Json,
+        }
+    }
+
+    // returns the tag of the row chosen, or None if the user cancelled
+    fn choose(
+        &self,
+        title: &str,
+        text: &str,
+        column_names: &[&str],
+        rows: &[DialogRow],
+    ) -> HttmResult<Option<String>> {
+        let (command, args): (&Path, Vec<OsString>) = match self {
+            GuiDialog::Zenity(zenity_command) => {
+                let mut args: Vec<OsString> = vec![
+                    "--list".into(),
+                    "--no-markup".into(),
+                    "--width=800".into(),
+                    "--height=400".into(),
+                    format!("--title={}", title).into(),
+                    format!("--text={}", text).into(),
+                    // the tag is the first column, hidden, and is what zenity prints
+                    "--column=Tag".into(),
+                    "--hide-column=1".into(),
+                    "--print-column=1".into(),
+                ];
+                args.extend(
+                    column_names
+                        .iter()
+                        .map(|column_name| format!("--column={}", column_name).into()),
+                );
+                args.extend(rows.iter().flat_map(|row| {
+                    std::iter::once(row.tag.clone().into())
+                        .chain(row.columns.iter().map(|column| column.into()))
+                }));
+
+                (zenity_command, args)
+            }
+            GuiDialog::Kdialog(kdialog_command) => {
+                let mut args: Vec<OsString> =
+                    vec!["--title".into(), title.into(), "--menu".into(), text.into()];
+                args.extend(
+                    rows.iter()
+                        .flat_map(|row| [row.tag.clone().into(), row.columns.join("    ").into()]),
+                );
+
+                (kdialog_command, args)
+            }
+            GuiDialog::Json => return Ok(None),
+        };
+
+        let process_output = ExecProcess::new(command).args(args).output()?;
+
+        // each dialog exits non-zero when the user cancels
+        if !process_output.status.success() {
+            return Ok(None);
+        }
+
+        let chosen = std::str::from_utf8(&process_output.stdout)?.trim_end_matches('\n');
+
+        if chosen.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(chosen.to_owned()))
+    }
+
+    fn show_info(&self, title: &str, msg: &str) {
+        let opt_command_and_args = match self {
+            GuiDialog::Zenity(zenity_command) => Some((
+                zenity_command,
+                vec![
+                    "--info".to_owned(),
+                    "--no-markup".to_owned(),
+                    format!("--title={}", title),
+                    format!("--text={}", msg),
+                ],
+            )),
+            GuiDialog::Kdialog(kdialog_command) => Some((
+                kdialog_command,
+                vec![
+                    "--title".to_owned(),
+                    title.to_owned(),
+                    "--msgbox".to_owned(),
+                    msg.to_owned(),
+                ],
+            )),
+            GuiDialog::Json => None,
+        };
+
+        if let Some((command, args)) = opt_command_and_args {
+            let _ = ExecProcess::new(command).args(args).status();
+        }
+    }
+
+    fn show_error(&self, msg: &str) {
+        let opt_command_and_args = match self {
+            GuiDialog::Zenity(zenity_command) => Some((
+                zenity_command,
+                vec![
+                    "--error".to_owned(),
+                    "--no-markup".to_owned(),
+                    "--title=httm".to_owned(),
+                    format!("--text={}", msg),
+                ],
+            )),
+            GuiDialog::Kdialog(kdialog_command) => Some((
+                kdialog_command,
+                vec![
+                    "--title".to_owned(),
+                    "httm".to_owned(),
+                    "--error".to_owned(),
+                    msg.to_owned(),
+                ],
+            )),
+            GuiDialog::Json => None,
+        };
+
+        if let Some((command, args)) = opt_command_and_args {
+            let _ = ExecProcess::new(command).args(args).status();
+        }
+    }
+}
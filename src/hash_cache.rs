@@ -0,0 +1,329 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::HashMap,
+    fs::{remove_file, rename, File, Metadata, OpenOptions},
+    io::{Read, Write},
+    os::unix::fs::{MetadataExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+    process::Command as ExecProcess,
+    sync::Mutex,
+};
+
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
+use which::which;
+
+use crate::display::display_human_size;
+use crate::forensic::{deny_if_forensic, is_forensic};
+use crate::restrict_owner::{deny_if_restricted_to_owner, is_restricted_to_owner};
+use crate::utility::{get_home_dir, HttmError};
+use crate::{HttmResult, ZFS_SNAPSHOT_DIRECTORY};
+
+// each hash is stored on its own line, as <SNAPSHOT GUID>\t<INODE>\t<ALGORITHM>\t<HASH>
+pub const HASH_CACHE_FILE_NAME: &str = ".httm-hash-cache";
+
+// a file on a snapshot never changes, so, so long as the snapshot is the same snapshot, and not
+// another since given the same name, which its GUID tells us, neither does its hash
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HashCacheKey {
+    snap_guid: String,
+    ino: u64,
+    // the algorithm, and whether the hash is partial, as each gives a different hash
+    hash_kind: String,
+}
+
+static IS_HASH_CACHE_DISABLED: OnceCell<()> = OnceCell::new();
+
+lazy_static! {
+    // key: dataset mount, val: the GUID of each of its snapshots, by snap name
+    static ref SNAP_GUIDS: Mutex<HashMap<PathBuf, Option<HashMap<String, String>>>> =
+        Mutex::new(HashMap::new());
+    // read in full on first use, and appended to, line by line, thereafter
+    static ref HASH_CACHE: Mutex<Option<HashMap<HashCacheKey, String>>> = Mutex::new(None);
+    static ref HASH_CACHE_WRITER: Mutex<Option<File>> = Mutex::new(None);
+}
+
+pub fn disable_hash_cache() {
+    let _ = IS_HASH_CACHE_DISABLED.set(());
+}
+
+pub fn get_hash_cache_file_path() -> HttmResult<PathBuf> {
+    let home_dir = get_home_dir()?;
+    Ok(home_dir.join(HASH_CACHE_FILE_NAME))
+}
+
+// only a file on a ZFS snapshot has a key, as only a snapshot is immutable, and, in forensic mode,
+// every hash is computed anew, and nothing is written.  Restricted to the invoking user, as root,
+// we'd read and write a cache within a home dir which may well be theirs, so there is no cache
+pub fn get_hash_cache_key(
+    path: &Path,
+    metadata: &Metadata,
+    hash_kind: &str,
+) -> Option<HashCacheKey> {
+    if IS_HASH_CACHE_DISABLED.get().is_some() || is_forensic() || is_restricted_to_owner() {
+        return None;
+    }
+
+    // e.g. /srv/.zfs/snapshot/snap_8a86e4fc_prepApt/etc/fstab
+    let snap_mount = path.ancestors().find(|ancestor| {
+        ancestor
+            .parent()
+            .map(|parent| parent.ends_with(ZFS_SNAPSHOT_DIRECTORY))
+            .unwrap_or(false)
+    })?;
+    let snap_name = snap_mount.file_name()?.to_string_lossy();
+    let dataset_mount = snap_mount.parent()?.parent()?.parent()?;
+
+    let snap_guid = get_snap_guid(dataset_mount, &snap_name)?;
+
+    Some(HashCacheKey {
+        snap_guid,
+        ino: metadata.ino(),
+        hash_kind: hash_kind.to_owned(),
+    })
+}
+
+fn get_snap_guid(dataset_mount: &Path, snap_name: &str) -> Option<String> {
+    let mut snap_guids = SNAP_GUIDS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    // a dataset whose GUIDs we could not read is only asked once
+    snap_guids
+        .entry(dataset_mount.to_path_buf())
+        .or_insert_with(|| get_zfs_snap_guids(dataset_mount).ok())
+        .as_ref()?
+        .get(snap_name)
+        .cloned()
+}
+
+// key: snap name, val: GUID, as reported by "zfs list"
+fn get_zfs_snap_guids(dataset_mount: &Path) -> HttmResult<HashMap<String, String>> {
+    let zfs_command = which("zfs").map_err(|_| HttmError::CommandNotFound("zfs".to_owned()))?;
+
+    let command_output = ExecProcess::new(zfs_command)
+        .args([
+            "list",
+            "-H",
+            "-p",
+            "-t",
+            "snapshot",
+            "-d",
+            "1",
+            "-o",
+            "name,guid",
+        ])
+        .arg(dataset_mount)
+        .output()?;
+
+    if !command_output.status.success() {
+        let msg = format!(
+            "httm could not read the snapshot GUIDs of the dataset mounted at {:?}",
+            dataset_mount
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    let snap_guids = std::str::from_utf8(&command_output.stdout)?
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(full_name, guid)| {
+            let (_dataset, snap_name) = full_name.split_once('@')?;
+            Some((snap_name.to_owned(), guid.trim().to_owned()))
+        })
+        .collect();
+
+    Ok(snap_guids)
+}
+
+pub fn get_cached_hash(key: &HashCacheKey) -> Option<String> {
+    let mut hash_cache = HASH_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    hash_cache
+        .get_or_insert_with(load_hash_cache)
+        .get(key)
+        .cloned()
+}
+
+// a cache we cannot write is no cache at all, but it should never fail a lookup
+pub fn cache_hash(key: HashCacheKey, hash: &str) {
+    let line = format!(
+        "{}\t{}\t{}\t{}\n",
+        key.snap_guid, key.ino, key.hash_kind, hash
+    );
+
+    // loaded first, as a load may write the cache anew, which we'd otherwise append to in vain
+    HASH_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get_or_insert_with(load_hash_cache)
+        .insert(key, hash.to_owned());
+
+    let mut writer = HASH_CACHE_WRITER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    if writer.is_none() {
+        *writer = get_hash_cache_file_path()
+            .ok()
+            .and_then(|hash_cache_file_path| {
+                OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .mode(0o600)
+                    .custom_flags(libc::O_NOFOLLOW)
+                    .open(hash_cache_file_path)
+                    .ok()
+            });
+    }
+
+    // each line is appended whole, so two runs at once may only ever repeat a hash
+    if let Some(file) = writer.as_mut() {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+// two runs at once may each append the same hash, so, once loaded, a cache with any hash repeated
+// is written anew, each hash once, lest it grow without end
+fn load_hash_cache() -> HashMap<HashCacheKey, String> {
+    let (hash_cache, num_lines) = read_hash_cache();
+
+    if num_lines > hash_cache.len() {
+        let _ = rewrite_hash_cache(&hash_cache);
+    }
+
+    hash_cache
+}
+
+// no cache file, or one we cannot read, is the same as an empty cache, and, as with each write,
+// we never follow a symlink to somewhere else
+fn read_hash_cache() -> (HashMap<HashCacheKey, String>, usize) {
+    let mut buffer = String::new();
+
+    let is_read = get_hash_cache_file_path()
+        .ok()
+        .and_then(|hash_cache_file_path| {
+            OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NOFOLLOW)
+                .open(hash_cache_file_path)
+                .ok()
+        })
+        .and_then(|mut file| file.read_to_string(&mut buffer).ok())
+        .is_some();
+
+    if !is_read {
+        return (HashMap::new(), 0);
+    }
+
+    let num_lines = buffer.lines().count();
+    let hash_cache = buffer.lines().filter_map(parse_hash_cache_line).collect();
+
+    (hash_cache, num_lines)
+}
+
+// written beside the cache, then renamed over it, so a reader never sees half a cache
+fn rewrite_hash_cache(hash_cache: &HashMap<HashCacheKey, String>) -> HttmResult<()> {
+    let hash_cache_file_path = get_hash_cache_file_path()?;
+    let tmp_file_path = hash_cache_file_path.with_file_name(format!(
+        "{}.{}",
+        HASH_CACHE_FILE_NAME,
+        std::process::id()
+    ));
+
+    let buffer: String = hash_cache
+        .iter()
+        .map(|(key, hash)| {
+            format!(
+                "{}\t{}\t{}\t{}\n",
+                key.snap_guid, key.ino, key.hash_kind, hash
+            )
+        })
+        .collect();
+
+    let mut tmp_file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&tmp_file_path)?;
+
+    if let Err(err) = tmp_file
+        .write_all(buffer.as_bytes())
+        .and_then(|_| rename(&tmp_file_path, &hash_cache_file_path))
+    {
+        let _ = remove_file(&tmp_file_path);
+        return Err(err.into());
+    }
+
+    Ok(())
+}
+
+fn parse_hash_cache_line(line: &str) -> Option<(HashCacheKey, String)> {
+    let mut fields = line.split('\t');
+
+    let key = HashCacheKey {
+        snap_guid: fields.next()?.to_owned(),
+        ino: fields.next()?.parse().ok()?,
+        hash_kind: fields.next()?.to_owned(),
+    };
+    let hash = fields.next()?.to_owned();
+
+    Some((key, hash))
+}
+
+pub fn exec_hash_cache(command: &str) -> HttmResult<()> {
+    let hash_cache_file_path = get_hash_cache_file_path()?;
+
+    match command {
+        "clear" => {
+            deny_if_forensic("clear the hash cache")?;
+            deny_if_restricted_to_owner("clear the hash cache")?;
+
+            match remove_file(&hash_cache_file_path) {
+                Ok(_) => eprintln!("httm: cleared the hash cache at {:?}", hash_cache_file_path),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    eprintln!("httm: the hash cache is already empty")
+                }
+                Err(err) => {
+                    return Err(HttmError::with_context(
+                        "httm could not remove the hash cache in the user's home directory",
+                        err,
+                    ))
+                }
+            }
+        }
+        _ => {
+            let (num_hashes, len) = match hash_cache_file_path.metadata() {
+                Ok(md) => (read_hash_cache().0.len(), md.len()),
+                Err(_) => (0, 0),
+            };
+
+            println!(
+                "{} hashes, in {}, at {:?}",
+                num_hashes,
+                display_human_size(&len),
+                hash_cache_file_path
+            );
+        }
+    }
+
+    std::process::exit(0)
+}
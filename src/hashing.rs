@@ -0,0 +1,368 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::HashMap,
+    fs::{symlink_metadata, File, Metadata},
+    io::{Read, Seek, SeekFrom},
+    os::unix::fs::MetadataExt,
+    path::Path,
+    sync::{Condvar, Mutex},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh64::Xxh64;
+
+use crate::forensic::audit;
+use crate::hash_cache::{cache_hash, get_cached_hash, get_hash_cache_key};
+use crate::utility::{get_zfs_snapshotted_dataset, HttmError};
+use crate::HttmResult;
+
+// a partial hash reads only this much of the start, and of the end, of a file
+const PARTIAL_HASH_LEN: u64 = 1024 * 1024;
+const READ_BUF_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Xxhash,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Xxhash => "xxhash",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+// how versions are hashed, when compared by their contents, a chain of custody is always a full SHA-256
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashPolicy {
+    pub algorithm: HashAlgorithm,
+    // only the size, and the first and last PARTIAL_HASH_LEN, of an enormous file
+    pub is_partial: bool,
+}
+
+impl Default for HashPolicy {
+    fn default() -> Self {
+        HashPolicy {
+            algorithm: HashAlgorithm::Sha256,
+            is_partial: false,
+        }
+    }
+}
+
+static HASH_POLICY: OnceCell<HashPolicy> = OnceCell::new();
+
+pub fn init_hash_policy(hash_policy: HashPolicy) -> HttmResult<()> {
+    HASH_POLICY
+        .set(hash_policy)
+        .map_err(|_| HttmError::new("httm's hash policy may only be initialized once."))
+}
+
+pub fn get_hash_policy() -> HashPolicy {
+    HASH_POLICY.get().copied().unwrap_or_default()
+}
+
+// the hash by which two versions are compared, as a lowercase hex string, each algorithm's
+// hashes are only ever compared to each other, within the same run
+pub fn get_comparison_hash(path: &Path) -> HttmResult<String> {
+    let hash_policy = get_hash_policy();
+
+    hash_file(path, hash_policy.algorithm, hash_policy.is_partial)
+}
+
+// SHA-256 of a file's whole contents, as a lowercase hex string, dirs and other non-files have no hash
+pub fn get_content_hash(path: &Path) -> HttmResult<String> {
+    hash_file(path, HashAlgorithm::Sha256, false)
+}
+
+fn hash_file(path: &Path, algorithm: HashAlgorithm, is_partial: bool) -> HttmResult<String> {
+    let metadata = symlink_metadata(path)?;
+    if !metadata.is_file() {
+        return Err(HttmError::new("Only files have a content hash."));
+    }
+
+    let mut hasher: Box<dyn ContentHasher> = match algorithm {
+        HashAlgorithm::Sha256 => Box::new(Sha256::new()),
+        HashAlgorithm::Xxhash => Box::new(Xxh64::new(0)),
+        HashAlgorithm::Blake3 => Box::new(blake3::Hasher::new()),
+    };
+
+    let hash_kind = if is_partial {
+        format!("{},partial", algorithm.as_str())
+    } else {
+        algorithm.as_str().to_owned()
+    };
+
+    let opt_cache_key = get_hash_cache_key(path, &metadata, &hash_kind);
+
+    if let Some(hash) = opt_cache_key.as_ref().and_then(get_cached_hash) {
+        return Ok(hash);
+    }
+
+    // held until this file is hashed, so no more than the budgeted number of files are read at once
+    let _device_slot = DeviceSlot::acquire(get_device_key(path, &metadata));
+
+    audit("read", path);
+    let mut file = File::open(path)?;
+    let len = metadata.len();
+
+    if is_partial && len > 2 * PARTIAL_HASH_LEN {
+        // the size, as two files which share a start and an end may still differ in length
+        hasher.update(&len.to_le_bytes());
+        hash_reader(&mut (&mut file).take(PARTIAL_HASH_LEN), hasher.as_mut())?;
+        file.seek(SeekFrom::End(-(PARTIAL_HASH_LEN as i64)))?;
+        hash_reader(&mut file, hasher.as_mut())?;
+    } else {
+        hash_reader(&mut file, hasher.as_mut())?;
+    }
+
+    let hash = hasher.finalize_hex();
+
+    if let Some(cache_key) = opt_cache_key {
+        cache_hash(cache_key, &hash);
+    }
+
+    Ok(hash)
+}
+
+fn hash_reader(reader: &mut impl Read, hasher: &mut dyn ContentHasher) -> HttmResult<()> {
+    let mut buf = vec![0u8; READ_BUF_LEN];
+
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+
+        wait_for_io_budget(read);
+
+        hasher.update(&buf[..read]);
+    }
+}
+
+// how hard hashing, from every thread at once, may press upon the storage beneath, so hashing
+// the versions of a whole dir doesn't starve whatever else is running on the same pool
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HashIoBudget {
+    pub opt_bytes_per_sec: Option<u64>,
+    pub opt_reads_per_sec: Option<u64>,
+    pub opt_files_per_device: Option<usize>,
+}
+
+static HASH_IO_BUDGET: OnceCell<HashIoBudget> = OnceCell::new();
+
+lazy_static! {
+    // the earliest instant at which the next read may begin, shared by every hashing thread
+    static ref NEXT_READ_AT: Mutex<Option<Instant>> = Mutex::new(None);
+    // the number of files being hashed on each device
+    static ref DEVICE_SLOTS: (Mutex<HashMap<u64, usize>>, Condvar) =
+        (Mutex::new(HashMap::new()), Condvar::new());
+}
+
+pub fn init_hash_io_budget(hash_io_budget: HashIoBudget) -> HttmResult<()> {
+    HASH_IO_BUDGET
+        .set(hash_io_budget)
+        .map_err(|_| HttmError::new("httm's hash I/O budget may only be initialized once."))
+}
+
+// each read is given its own slice of time, at the budgeted rate, after that of the read before it,
+// and waits for its slice to begin, so the total rate of all threads together stays within budget
+fn wait_for_io_budget(bytes: usize) {
+    let hash_io_budget = match HASH_IO_BUDGET.get() {
+        Some(hash_io_budget) => hash_io_budget,
+        None => return,
+    };
+
+    let bytes_cost = hash_io_budget
+        .opt_bytes_per_sec
+        .map(|bytes_per_sec| Duration::from_secs_f64(bytes as f64 / bytes_per_sec as f64))
+        .unwrap_or_default();
+    let read_cost = hash_io_budget
+        .opt_reads_per_sec
+        .map(|reads_per_sec| Duration::from_secs_f64(1f64 / reads_per_sec as f64))
+        .unwrap_or_default();
+    let cost = bytes_cost.max(read_cost);
+
+    if cost.is_zero() {
+        return;
+    }
+
+    // time not spent reading is not saved up for a later burst
+    let start = {
+        let mut next_read_at = NEXT_READ_AT
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        let start = next_read_at.map_or(now, |next_read_at| next_read_at.max(now));
+        *next_read_at = Some(start + cost);
+        start
+    };
+
+    let now = Instant::now();
+    if start > now {
+        sleep(start - now);
+    }
+}
+
+// each snapshot is mounted as its own filesystem, with its own device number, so a version within
+// a ZFS snapshot counts against the device of its dataset's mount, which all its snapshots share
+fn get_device_key(path: &Path, metadata: &Metadata) -> u64 {
+    get_zfs_snapshotted_dataset(path)
+        .and_then(|dataset_mount| dataset_mount.metadata().ok())
+        .map_or(metadata.dev(), |dataset_md| dataset_md.dev())
+}
+
+struct DeviceSlot {
+    device_key: u64,
+}
+
+impl DeviceSlot {
+    fn acquire(device_key: u64) -> Option<Self> {
+        let files_per_device = HASH_IO_BUDGET.get()?.opt_files_per_device?;
+
+        let (lock, condvar) = &*DEVICE_SLOTS;
+        let mut in_use = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        while in_use.get(&device_key).copied().unwrap_or_default() >= files_per_device {
+            in_use = condvar
+                .wait(in_use)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+
+        *in_use.entry(device_key).or_default() += 1;
+
+        Some(DeviceSlot { device_key })
+    }
+}
+
+impl Drop for DeviceSlot {
+    fn drop(&mut self) {
+        let (lock, condvar) = &*DEVICE_SLOTS;
+        let mut in_use = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(count) = in_use.get_mut(&self.device_key) {
+            *count = count.saturating_sub(1);
+        }
+
+        drop(in_use);
+        condvar.notify_all();
+    }
+}
+
+// a rate of bytes per second, like "50M" or "1GiB", in binary units, with an optional "/s"
+pub fn parse_byte_rate(raw: &str) -> HttmResult<u64> {
+    let trimmed = raw.trim();
+    let trimmed = trimmed.strip_suffix("/s").unwrap_or(trimmed);
+    let upper = trimmed.to_ascii_uppercase();
+    let without_b = upper
+        .strip_suffix("IB")
+        .or_else(|| upper.strip_suffix('B'))
+        .unwrap_or(&upper);
+
+    let (digits, multiplier) = match without_b.chars().last() {
+        Some('K') => (&without_b[..without_b.len() - 1], 1u64 << 10),
+        Some('M') => (&without_b[..without_b.len() - 1], 1u64 << 20),
+        Some('G') => (&without_b[..without_b.len() - 1], 1u64 << 30),
+        Some('T') => (&without_b[..without_b.len() - 1], 1u64 << 40),
+        _ => (without_b, 1u64),
+    };
+
+    match digits.trim().parse::<u64>() {
+        Ok(num) if num > 0 => num
+            .checked_mul(multiplier)
+            .ok_or_else(|| HttmError::new(&format!("The rate {:?} is too large.", raw))),
+        _ => Err(HttmError::new(&format!(
+            "httm could not parse {:?} as a rate of bytes per second, like \"50M\" or \"1G\".",
+            raw
+        ))),
+    }
+}
+
+trait ContentHasher {
+    fn update(&mut self, bytes: &[u8]);
+    fn finalize_hex(self: Box<Self>) -> String;
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl ContentHasher for Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        Digest::update(self, bytes);
+    }
+    fn finalize_hex(self: Box<Self>) -> String {
+        to_hex(&self.finalize())
+    }
+}
+
+impl ContentHasher for Xxh64 {
+    fn update(&mut self, bytes: &[u8]) {
+        Xxh64::update(self, bytes);
+    }
+    fn finalize_hex(self: Box<Self>) -> String {
+        format!("{:016x}", self.digest())
+    }
+}
+
+impl ContentHasher for blake3::Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        blake3::Hasher::update(self, bytes);
+    }
+    fn finalize_hex(self: Box<Self>) -> String {
+        self.finalize().to_hex().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_hex(mut hasher: Box<dyn ContentHasher>, input: &[u8]) -> String {
+        hasher.update(input);
+        hasher.finalize_hex()
+    }
+
+    // the hashes are cached as hex strings, so each must be printed as the reference tools print it
+    #[test]
+    fn hashes_are_printed_as_the_reference_tools_print_them() {
+        assert_eq!(
+            hash_hex(Box::new(Xxh64::new(0)), b"abc"),
+            "44bc2cf5ad770999"
+        );
+        assert_eq!(
+            hash_hex(Box::new(blake3::Hasher::new()), b""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            hash_hex(Box::new(Sha256::new()), b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
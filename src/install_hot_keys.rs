@@ -21,40 +21,28 @@ use std::{
     path::PathBuf,
 };
 
-use crate::utility::HttmError;
+use crate::forensic::deny_if_forensic;
+use crate::utility::{get_home_dir, HttmError};
 use crate::HttmResult;
 
 pub fn install_hot_keys() -> HttmResult<()> {
+    deny_if_forensic("install hot keys")?;
+
     // get our home directory
-    let home_dir = if let Ok(home) = std::env::var("HOME") {
-        if let Ok(path) = PathBuf::from(&home).canonicalize() {
-            path
-        } else {
-            return Err(HttmError::new(
-                "$HOME, as set in your environment, does not appear to exist",
-            )
-            .into());
-        }
-    } else {
-        return Err(HttmError::new("$HOME does not appear to be set in your environment").into());
-    };
+    let home_dir = get_home_dir()?;
 
     // check whether httm-key-bindings.zsh is already sourced
     // and, if not, open ~/.zshrc append only for sourcing the httm-key-bindings.zsh
     let mut buffer = String::new();
     let zshrc_path: PathBuf = home_dir.join(".zshrc");
-    let mut zshrc_file = if let Ok(file) = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .append(true)
-        .open(zshrc_path)
+    let mut zshrc_file = if let Ok(file) =
+        OpenOptions::new().read(true).append(true).open(zshrc_path)
     {
         file
     } else {
         return Err(HttmError::new(
                 "Either your ~/.zshrc file does not exist or you do not have the permissions to access it.",
-            )
-            .into());
+            ));
     };
     zshrc_file.read_to_string(&mut buffer)?;
 
@@ -77,6 +65,7 @@ pub fn install_hot_keys() -> HttmResult<()> {
             // create_new() will only create if DNE
             // create on a file that exists just opens
             .create(true)
+            .truncate(true)
             .open(zsh_script_path)
         {
             zsh_script_file.write_all(zsh_hot_key_script.as_bytes())?;
@@ -15,19 +15,207 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
-use std::{ffi::OsString, fs::FileType, io::Cursor, path::Path, path::PathBuf, thread, vec};
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::OsString,
+    fs::{read_dir, remove_dir_all, rename, FileType, OpenOptions},
+    io::{Cursor, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::mpsc::{channel, Sender},
+    sync::Mutex,
+    thread,
+    time::{Duration, SystemTime},
+    vec,
+};
 
 use lscolors::Colorable;
+use once_cell::sync::OnceCell;
+use rayon::prelude::*;
 use skim::prelude::*;
 
-use crate::display::display_exec;
-use crate::lookup_versions::versions_lookup_exec;
+use crate::clipboard::copy_to_clipboard;
+use crate::display::{display_exec, display_side_by_side};
+use crate::extract::cat_version;
+use crate::forensic::deny_if_forensic;
+use crate::lookup_versions::{
+    get_all_search_bundles, get_last_snap_relative, get_snap_provenance, select_search_datasets,
+    versions_lookup_exec,
+};
+use crate::open_files::open_files_advisory;
+use crate::parse_snaps::get_snap_creation_times;
+use crate::progress::display_copy_failures;
 use crate::recursive::recursive_exec;
+use crate::restore_attributes::restore_special_attributes;
+use crate::restrict_owner::{deny_unless_restorable, is_owned};
+use crate::sandbox::{diff_temp_copies, edit_temp_copy, exec_on_temp_copy};
+use crate::set_aside::{get_set_aside_path, restore_over_live};
 use crate::utility::{
-    copy_recursive, get_date, paint_string, print_output_buf, BasicDirEntryInfo, DateFormat,
-    HttmError, PathData,
+    create_private_temp_dir, get_date, httm_is_dir, paint_string, print_output_buf,
+    BasicDirEntryInfo, DateFormat, HttmError, PathData, PathMetadata,
+};
+use crate::workspace::materialize_workspace;
+use crate::{
+    Config, DeletedMode, ExecMode, HttmResult, InteractiveMode, PrintSelected, RequestRelative,
+    SnapDatasetType, SnapsAndLiveSet, PICKER_ABORT_EXIT_CODE,
 };
-use crate::{Config, DeletedMode, ExecMode, HttmResult, InteractiveMode, RequestRelative};
+
+// how many previews we keep, as scrolling back and forth through a directory
+// shouldn't mean looking up the same versions again and again
+const PREVIEW_CACHE_CAPACITY: usize = 256;
+
+lazy_static! {
+    static ref PREVIEW_CACHE: Mutex<PreviewCache> =
+        Mutex::new(PreviewCache::new(PREVIEW_CACHE_CAPACITY));
+}
+
+// a preview is still good so long as the live file hasn't been modified since,
+// snapshots, of course, don't change
+type PreviewKey = (PathBuf, Option<SystemTime>);
+
+// a least recently used cache of previews
+struct PreviewCache {
+    capacity: usize,
+    previews: HashMap<PreviewKey, String>,
+    recently_used: VecDeque<PreviewKey>,
+}
+
+impl PreviewCache {
+    fn new(capacity: usize) -> Self {
+        PreviewCache {
+            capacity,
+            previews: HashMap::with_capacity(capacity),
+            recently_used: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, key: &PreviewKey) -> Option<String> {
+        let preview = self.previews.get(key)?.clone();
+        self.touch(key);
+        Some(preview)
+    }
+
+    fn insert(&mut self, key: PreviewKey, preview: String) {
+        if self.previews.insert(key.clone(), preview).is_some() {
+            self.touch(&key);
+            return;
+        }
+
+        if self.recently_used.len() >= self.capacity {
+            if let Some(least_recently_used) = self.recently_used.pop_front() {
+                self.previews.remove(&least_recently_used);
+            }
+        }
+
+        self.recently_used.push_back(key);
+    }
+
+    fn touch(&mut self, key: &PreviewKey) {
+        if let Some(idx) = self.recently_used.iter().position(|used| used == key) {
+            if let Some(used) = self.recently_used.remove(idx) {
+                self.recently_used.push_back(used);
+            }
+        }
+    }
+}
+
+// how long our preview worker waits for the cursor to settle before it starts a lookup
+const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(50);
+
+lazy_static! {
+    static ref PREVIEW_WORKER: Mutex<Sender<PreviewRequest>> = Mutex::new(spawn_preview_worker());
+}
+
+// made on first use, a new 0700 dir of a random name, see create_private_temp_dir, or None if
+// we could not make one, and then each preview is merely empty
+static PREVIEW_RESULTS_DIR: OnceCell<Option<PathBuf>> = OnceCell::new();
+
+static PREVIEW_RESULT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+struct PreviewRequest {
+    config: Arc<Config>,
+    key: PreviewKey,
+    result_path: PathBuf,
+}
+
+// a single worker, so rapid cursor movement can't queue up dozens of lookups: after waiting
+// for the cursor to settle, only the very latest request is looked up, the rest are dropped
+fn spawn_preview_worker() -> Sender<PreviewRequest> {
+    let (tx_request, rx_request) = channel::<PreviewRequest>();
+
+    thread::spawn(move || {
+        while let Ok(mut request) = rx_request.recv() {
+            thread::sleep(PREVIEW_DEBOUNCE);
+
+            while let Ok(newer_request) = rx_request.try_recv() {
+                request = newer_request;
+            }
+
+            let preview = preview_view(&request.config, &request.key.0).unwrap_or_default();
+
+            if let Ok(mut cache) = PREVIEW_CACHE.lock() {
+                cache.insert(request.key, preview.clone());
+            }
+
+            // write then rename, so that the result appears all at once
+            let tmp_path = request.result_path.with_extension("tmp");
+            let _ = write_preview_result(&tmp_path, &preview)
+                .and_then(|_| rename(&tmp_path, &request.result_path));
+        }
+    });
+
+    tx_request
+}
+
+// each result is a new file, never one already there, nor wherever a symlink might point
+fn write_preview_result(path: &Path, preview: &str) -> std::io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(path)?
+        .write_all(preview.as_bytes())
+}
+
+fn request_preview(config: Arc<Config>, key: PreviewKey) -> Option<PathBuf> {
+    let preview_results_dir = PREVIEW_RESULTS_DIR
+        .get_or_init(|| create_private_temp_dir("httm-previews").ok())
+        .as_ref()?;
+
+    let result_path = preview_results_dir.join(
+        PREVIEW_RESULT_COUNT
+            .fetch_add(1, Ordering::Relaxed)
+            .to_string(),
+    );
+
+    let request = PreviewRequest {
+        config,
+        key,
+        result_path: result_path.clone(),
+    };
+
+    PREVIEW_WORKER.lock().ok()?.send(request).ok()?;
+
+    Some(result_path)
+}
+
+// skim runs the preview command with the user's shell, which may not be a POSIX shell,
+// and our result path is ours alone, so it needs no escaping within single quotes
+fn get_wait_command(result_path: &Path) -> String {
+    format!(
+        "sh -c 'while [ ! -e \"$1\" ]; do sleep 0.05; done; cat \"$1\"' sh '{}'",
+        result_path.to_string_lossy()
+    )
+}
+
+fn remove_preview_results() {
+    if let Some(Some(preview_results_dir)) = PREVIEW_RESULTS_DIR.get() {
+        let _ = remove_dir_all(preview_results_dir);
+    }
+}
 
 // these represent to items ready for selection and preview
 // contains everything needs to request preview and paint with
@@ -56,39 +244,40 @@ impl SelectionCandidate {
         }
     }
 
-    fn preview_view(&self) -> HttmResult<String> {
-        let config = &self.config;
-        let path = &self.path;
-        // generate a config for a preview display only
-        let gen_config = Config {
-            paths: vec![PathData::from(path.as_path())],
-            opt_raw: false,
-            opt_zeros: false,
-            opt_no_pretty: false,
-            opt_recursive: false,
-            opt_no_live: false,
-            opt_exact: false,
-            opt_overwrite: false,
-            opt_no_filter: false,
-            opt_no_snap: false,
-            opt_debug: false,
-            requested_utc_offset: config.requested_utc_offset,
-            exec_mode: ExecMode::Display,
-            deleted_mode: DeletedMode::Disabled,
-            dataset_collection: config.dataset_collection.clone(),
-            pwd: config.pwd.clone(),
-            opt_requested_dir: config.opt_requested_dir.clone(),
-        };
-
-        // finally run search on those paths
-        let snaps_and_live_set = versions_lookup_exec(&gen_config, &gen_config.paths)?;
-        // and display
-        let output_buf = display_exec(&gen_config, &snaps_and_live_set)?;
+    fn preview_key(&self) -> PreviewKey {
+        let modify_time = self
+            .path
+            .symlink_metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok();
 
-        Ok(output_buf)
+        (self.path.clone(), modify_time)
     }
 }
 
+fn preview_view(config: &Config, path: &Path) -> HttmResult<String> {
+    // generate a config for a preview display only, always the pretty table, and without the
+    // hash of every version, whatever the user requested for what they select
+    let gen_config = Config {
+        paths: vec![PathData::from(path)],
+        opt_raw: false,
+        opt_zeros: false,
+        opt_no_pretty: false,
+        opt_json: false,
+        opt_chain_of_custody: false,
+        exec_mode: ExecMode::Display,
+        deleted_mode: DeletedMode::Disabled,
+        ..config.clone()
+    };
+
+    // finally run search on those paths
+    let snaps_and_live_set = versions_lookup_exec(&gen_config, &gen_config.paths)?;
+    // and display
+    let output_buf = display_exec(&gen_config, &snaps_and_live_set)?;
+
+    Ok(output_buf)
+}
+
 impl Colorable for &SelectionCandidate {
     fn path(&self) -> PathBuf {
         self.path.clone()
@@ -105,7 +294,7 @@ impl Colorable for &SelectionCandidate {
 }
 
 impl SkimItem for SelectionCandidate {
-    fn text(&self) -> Cow<str> {
+    fn text(&self) -> Cow<'_, str> {
         self.path.to_string_lossy()
     }
     fn display<'a>(&'a self, _context: DisplayContext<'a>) -> AnsiString<'a> {
@@ -125,12 +314,28 @@ impl SkimItem for SelectionCandidate {
                 .to_string_lossy(),
         ))
     }
-    fn output(&self) -> Cow<str> {
+    fn output(&self) -> Cow<'_, str> {
         self.text()
     }
     fn preview(&self, _: PreviewContext<'_>) -> skim::ItemPreview {
-        let preview_output = self.preview_view().unwrap_or_default();
-        skim::ItemPreview::AnsiText(preview_output)
+        let key = self.preview_key();
+
+        // a poisoned cache is merely a cache miss, so we just carry on without it
+        if let Some(preview) = PREVIEW_CACHE
+            .lock()
+            .ok()
+            .and_then(|mut cache| cache.get(&key))
+        {
+            return skim::ItemPreview::AnsiText(preview);
+        }
+
+        // skim calls preview() on its own event loop, so a lookup here would block the whole UI,
+        // instead we ask our worker for the preview, and have skim wait on the result in the background.
+        // skim kills this wait whenever the cursor moves on, and shows the result once it arrives
+        match request_preview(self.config.clone(), key) {
+            Some(result_path) => skim::ItemPreview::Command(get_wait_command(&result_path)),
+            None => skim::ItemPreview::AnsiText(String::new()),
+        }
     }
 }
 
@@ -138,16 +343,37 @@ pub fn interactive_exec(
     config: Arc<Config>,
     interactive_mode: &InteractiveMode,
 ) -> HttmResult<Vec<PathData>> {
-    let paths_selected_in_browse = match &config.opt_requested_dir {
+    let mut config = config;
+
+    let paths_selected_in_browse = match config.opt_requested_dir.clone() {
         // collect string paths from what we get from lookup_view
-        Some(requested_dir) => {
+        Some(mut requested_dir) => {
             // loop until user selects a valid path
             loop {
                 let selected_pathdata =
-                    browse_view(config.clone(), requested_dir, interactive_mode)?
-                        .into_iter()
-                        .map(|path_string| PathData::from(Path::new(&path_string)))
-                        .collect::<Vec<PathData>>();
+                    match browse_view(config.clone(), &requested_dir, interactive_mode)? {
+                        BrowseViewOutput::Selected(selected) => selected
+                            .into_iter()
+                            .map(|path_string| PathData::from(Path::new(&path_string)))
+                            .collect::<Vec<PathData>>(),
+                        // start the browse over, from the top, with the other policy
+                        BrowseViewOutput::ToggleHidden => {
+                            config = Arc::new(Config {
+                                opt_hidden: !config.opt_hidden,
+                                ..config.as_ref().clone()
+                            });
+                            continue;
+                        }
+                        // start the browse over, from the new root
+                        BrowseViewOutput::ChangeRoot(new_root) => {
+                            requested_dir = PathData::from(new_root.as_path());
+                            config = Arc::new(Config {
+                                opt_requested_dir: Some(requested_dir.clone()),
+                                ..config.as_ref().clone()
+                            });
+                            continue;
+                        }
+                    };
                 if !selected_pathdata.is_empty() {
                     break selected_pathdata;
                 }
@@ -157,7 +383,7 @@ pub fn interactive_exec(
             // go to interactive_select early if user has already requested a file
             // and we are in the appropriate mode Select or Restore, see struct Config,
             // and None here is also used for LastSnap to skip browsing for a file/dir
-            match config.paths.get(0) {
+            match config.paths.first() {
                 Some(first_path) => {
                     let selected_file = first_path.clone();
                     interactive_select(config, &[selected_file], interactive_mode)?;
@@ -184,11 +410,110 @@ pub fn interactive_exec(
     }
 }
 
+// listing the requested dir on each snapshot is enough to trigger any automounts,
+// and to bring each dir into cache, errors here are of no interest to anyone
+fn prefetch_snap_mounts(config: &Config, requested_dir: &PathData) {
+    get_all_search_bundles(config, requested_dir)
+        .par_iter()
+        .flat_map(|search_bundle| {
+            search_bundle
+                .snap_mounts
+                .par_iter()
+                .map(|snap_mount| snap_mount.join(&search_bundle.relative_path))
+        })
+        .for_each(|snap_dir| {
+            if let Ok(read_dir) = read_dir(snap_dir) {
+                read_dir.for_each(drop)
+            }
+        });
+}
+
+// whether recovery is even plausible here: the dataset of the dir we browse,
+// how many snapshots it has, and how long ago the newest was taken
+fn get_dataset_header(config: &Config, requested_dir: &PathData) -> String {
+    let proximate_dataset_mount =
+        match select_search_datasets(config, requested_dir, &SnapDatasetType::MostProximate) {
+            Ok(snap_types_of_interest) => snap_types_of_interest.proximate_dataset_mount,
+            Err(_) => {
+                return "DATASET:    none      | (no snapshot versions may be found here)"
+                    .to_owned()
+            }
+        };
+
+    // a user defined alias won't be in our map of datasets, so just use its remote dir
+    let opt_dataset_info = config
+        .dataset_collection
+        .map_of_datasets
+        .get(&proximate_dataset_mount);
+
+    let dataset_name = match opt_dataset_info {
+        Some(dataset_info) => dataset_info.name.clone(),
+        None => proximate_dataset_mount.to_string_lossy().into_owned(),
+    };
+
+    let snap_mounts = match config
+        .dataset_collection
+        .map_of_snaps
+        .get(&proximate_dataset_mount)
+    {
+        Some(snap_mounts) if !snap_mounts.is_empty() => snap_mounts,
+        Some(_) => {
+            return format!(
+                "DATASET:    {} | SNAPSHOTS: 0 | (no snapshot versions may be found here)",
+                dataset_name
+            )
+        }
+        None => {
+            return format!(
+                "DATASET:    {} | SNAPSHOTS: unreadable | (no snapshot versions may be found here)",
+                dataset_name
+            )
+        }
+    };
+
+    let opt_newest = get_snap_creation_times(opt_dataset_info, snap_mounts)
+        .into_values()
+        .max();
+
+    let newest = match opt_newest.and_then(|newest| SystemTime::now().duration_since(newest).ok()) {
+        Some(age) => format!("{} ago", display_age(&age)),
+        None => "unknown".to_owned(),
+    };
+
+    format!(
+        "DATASET:    {} | SNAPSHOTS: {} | NEWEST: {}",
+        dataset_name,
+        snap_mounts.len(),
+        newest
+    )
+}
+
+fn display_age(age: &Duration) -> String {
+    let secs = age.as_secs();
+
+    match secs {
+        secs if secs < 60 => format!("{}s", secs),
+        secs if secs < 60 * 60 => format!("{}m", secs / 60),
+        secs if secs < 60 * 60 * 24 => format!("{}h", secs / (60 * 60)),
+        secs => format!("{}d", secs / (60 * 60 * 24)),
+    }
+}
+
+enum BrowseViewOutput {
+    Selected(Vec<String>),
+    ToggleHidden,
+    ChangeRoot(PathBuf),
+}
+
+const HIDDEN_KEY: &str = "alt-h";
+const ENTER_DIR_KEY: &str = "alt-o";
+const PARENT_DIR_KEY: &str = "alt-u";
+
 fn browse_view(
     config: Arc<Config>,
     requested_dir: &PathData,
     interactive_mode: &InteractiveMode,
-) -> HttmResult<Vec<String>> {
+) -> HttmResult<BrowseViewOutput> {
     // prep thread spawn
     let requested_dir_clone = requested_dir.path_buf.clone();
     let config_clone = config.clone();
@@ -205,33 +530,62 @@ fn browse_view(
         )
     });
 
+    // warm up the snapshot mounts of the requested dir in the background, so the first
+    // preview doesn't pay the cost of automounting every snapshot
+    let requested_dir_clone = requested_dir.clone();
+    let config_clone = config.clone();
+    thread::spawn(move || prefetch_snap_mounts(&config_clone, &requested_dir_clone));
+
     let opt_multi = !matches!(interactive_mode, InteractiveMode::LastSnap(_));
 
+    // the dataset line is made anew each time we change dirs, as each dir may be on another dataset
+    let header = format!(
+        "PREVIEW UP: shift+up | PREVIEW DOWN: shift+down\n\
+        PAGE UP:    page up  | PAGE DOWN:    page down \n\
+        EXIT:       esc      | SELECT:       enter      | SELECT, MULTIPLE: shift+tab\n\
+        HIDDEN:     alt+h    | (toggle showing dotfiles)\n\
+        ENTER DIR:  alt+o    | PARENT DIR:   alt+u    \n\
+        {}\n\
+        ──────────────────────────────────────────────────────────────────────────────",
+        get_dataset_header(config.as_ref(), requested_dir)
+    );
+
     // create the skim component for previews
     let options = SkimOptionsBuilder::default()
         .preview_window(Some("up:50%"))
         .preview(Some(""))
         .exact(config.opt_exact)
-        .header(Some("PREVIEW UP: shift+up | PREVIEW DOWN: shift+down\n\
-                      PAGE UP:    page up  | PAGE DOWN:    page down \n\
-                      EXIT:       esc      | SELECT:       enter      | SELECT, MULTIPLE: shift+tab\n\
-                      ──────────────────────────────────────────────────────────────────────────────",
-        ))
+        .header(Some(&header))
+        .expect(Some(format!(
+            "{},{},{}",
+            HIDDEN_KEY, ENTER_DIR_KEY, PARENT_DIR_KEY
+        )))
         .multi(opt_multi)
         .regex(false)
         .build()
         .expect("Could not initialized skim options for browse_view");
 
     // run_with() reads and shows items from the thread stream created above
-    let selected_items = if let Some(output) = Skim::run_with(&options, Some(rx_item)) {
+    let opt_output = Skim::run_with(&options, Some(rx_item));
+
+    // any previews still waiting were killed along with skim
+    remove_preview_results();
+
+    let (selected_items, opt_key) = if let Some(output) = opt_output {
         if output.is_abort {
             eprintln!("httm interactive file browse session was aborted.  Quitting.");
-            std::process::exit(0)
+            std::process::exit(abort_exit_code(config.as_ref()))
         } else {
-            output.selected_items
+            let opt_key = match output.final_event {
+                Event::EvActAccept(opt_key) => opt_key,
+                _ => None,
+            };
+            (output.selected_items, opt_key)
         }
     } else {
-        return Err(HttmError::new("httm interactive file browse session failed.").into());
+        return Err(HttmError::new(
+            "httm interactive file browse session failed.",
+        ));
     };
     // output() converts the filename/raw path to a absolute path string for use elsewhere
     let output: Vec<String> = selected_items
@@ -239,7 +593,46 @@ fn browse_view(
         .map(|i| i.output().into_owned())
         .collect();
 
-    Ok(output)
+    // for any key below, the enumeration thread for this view is left to finish
+    // on its own, as its sends to our now dropped receiver are simply ignored
+    match opt_key.as_deref() {
+        Some(HIDDEN_KEY) => Ok(BrowseViewOutput::ToggleHidden),
+        Some(ENTER_DIR_KEY) => {
+            // a highlighted file, or a deleted dir, takes us to the dir which contains it
+            let opt_new_root = output.first().and_then(|path_string| {
+                let pathdata = PathData::from(Path::new(path_string));
+
+                if httm_is_dir(&pathdata) {
+                    Some(pathdata)
+                } else {
+                    pathdata.path_buf.parent().map(PathData::from)
+                }
+            });
+
+            Ok(BrowseViewOutput::ChangeRoot(get_browse_root(
+                requested_dir,
+                opt_new_root,
+            )))
+        }
+        Some(PARENT_DIR_KEY) => {
+            let opt_new_root = requested_dir.path_buf.parent().map(PathData::from);
+
+            Ok(BrowseViewOutput::ChangeRoot(get_browse_root(
+                requested_dir,
+                opt_new_root,
+            )))
+        }
+        _ => Ok(BrowseViewOutput::Selected(output)),
+    }
+}
+
+// stay where we are for any root which we may not browse, like the parent of "/",
+// or a dir outside of those owned by the user when restricted to owner
+fn get_browse_root(requested_dir: &PathData, opt_new_root: Option<PathData>) -> PathBuf {
+    opt_new_root
+        .filter(|new_root| httm_is_dir(new_root) && is_owned(&new_root.path_buf))
+        .unwrap_or_else(|| requested_dir.clone())
+        .path_buf
 }
 
 fn interactive_select(
@@ -247,54 +640,98 @@ fn interactive_select(
     paths_selected_in_browse: &[PathData],
     interactive_mode: &InteractiveMode,
 ) -> HttmResult<()> {
-    let snaps_and_live_set = versions_lookup_exec(config.as_ref(), paths_selected_in_browse)?;
-
-    // snap and live set has no snaps
-    if snaps_and_live_set[0].is_empty() {
-        let paths: Vec<String> = paths_selected_in_browse
-            .iter()
-            .map(|path| path.path_buf.to_string_lossy().to_string())
-            .collect();
-        let msg = format!(
-            "{}{:?}",
-            "Cannot select or restore from the following paths as they have no snapshots:\n", paths
-        );
-        return Err(HttmError::new(&msg).into());
-    }
-
     let path_string = match &interactive_mode {
-        InteractiveMode::LastSnap(request_relative) => {
-            // should be good to index into both, there is a known known 2nd vec,
-            let live_version = &paths_selected_in_browse
-                .get(0)
+        InteractiveMode::LastSnap(
+            request_relative @ (RequestRelative::Relative | RequestRelative::RelativeExhaustive),
+        ) => {
+            let live_version = paths_selected_in_browse
+                .first()
                 .expect("ExecMode::LiveSnap should always have exactly one path.");
-            let path_string = snaps_and_live_set[0]
+
+            // no need to lookup every version, just the last which differs from the live version
+            get_last_snap_relative(
+                config.as_ref(),
+                live_version,
+                request_relative == &RequestRelative::RelativeExhaustive,
+            )
+            .ok_or_else(|| HttmError::new("No last snapshot for the requested input file exists."))?
+            .path_buf
+            .to_string_lossy()
+            .into_owned()
+        }
+        InteractiveMode::LastSnap(RequestRelative::Absolute) => {
+            let snaps_and_live_set = get_selected_versions(&config, paths_selected_in_browse)?;
+
+            // versions may be sorted by some other key, but we always want the last in time
+            snaps_and_live_set[0]
                 .iter()
-                .filter(|snap_version| {
-                    if request_relative == &RequestRelative::Relative {
-                        snap_version.md_infallible().modify_time
-                            != live_version.md_infallible().modify_time
-                    } else {
-                        true
-                    }
-                })
-                .last()
+                .max_by_key(|snap_version| snap_version.md_infallible().modify_time)
                 .ok_or_else(|| {
                     HttmError::new("No last snapshot for the requested input file exists.")
                 })?
                 .path_buf
                 .to_string_lossy()
-                .into_owned();
-            path_string
+                .into_owned()
         }
         _ => {
-            // same stuff we do at fn exec, snooze...
-            let selection_buffer = display_exec(config.as_ref(), &snaps_and_live_set)?;
+            let snaps_and_live_set = get_selected_versions(&config, paths_selected_in_browse)?;
+
+            let changes_only_set = [
+                get_changes_only(&snaps_and_live_set[0]),
+                snaps_and_live_set[1].clone(),
+            ];
+            let mut opt_changes_only = false;
 
             // loop until user selects a valid snapshot version
             loop {
-                // get the file name
-                let requested_file_name = select_restore_view(&selection_buffer, false)?;
+                // same stuff we do at fn exec, snooze...
+                let selection_buffer = if opt_changes_only {
+                    display_exec(config.as_ref(), &changes_only_set)?
+                } else {
+                    display_exec(config.as_ref(), &snaps_and_live_set)?
+                };
+
+                // get the file name, and whether the user would rather do something else with it
+                let (requested_file_name, opt_action) =
+                    match select_view(config.as_ref(), &selection_buffer, false, true)? {
+                        SelectViewOutput::Selected(requested_file_name) => {
+                            (requested_file_name, None)
+                        }
+                        SelectViewOutput::Edit(requested_file_name) => {
+                            (requested_file_name, Some(EDIT_KEY))
+                        }
+                        SelectViewOutput::CopyPath(requested_file_name) => {
+                            (requested_file_name, Some(COPY_PATH_KEY))
+                        }
+                        SelectViewOutput::Cat(requested_file_name) => {
+                            (requested_file_name, Some(CAT_KEY))
+                        }
+                        SelectViewOutput::ToggleChangesOnly => {
+                            opt_changes_only = !opt_changes_only;
+                            continue;
+                        }
+                        // once done, the user returns to the select view
+                        SelectViewOutput::Compare(marked_lines) => {
+                            compare_versions(config.as_ref(), &marked_lines)?;
+                            continue;
+                        }
+                        SelectViewOutput::Workspace(marked_lines) => {
+                            let versions: Vec<PathData> =
+                                get_marked_versions(&marked_lines, &snaps_and_live_set[1]);
+
+                            // the user may have only marked live versions, so let them try again
+                            if versions.is_empty() {
+                                continue;
+                            }
+
+                            let workspace_dir = config
+                                .opt_workspace
+                                .as_ref()
+                                .expect("A Workspace output is only possible with a workspace dir");
+                            materialize_workspace(config.as_ref(), &versions, workspace_dir)?;
+                            std::process::exit(0)
+                        }
+                    };
                 // ... we want everything between the quotes
                 let broken_string: Vec<_> = requested_file_name.split_terminator('"').collect();
                 // ... and the file is the 2nd item or the indexed "1" object
@@ -303,6 +740,24 @@ fn interactive_select(
                     if snaps_and_live_set[1].iter().all(|live_version| {
                         Path::new(path_string) != live_version.path_buf.as_path()
                     }) {
+                        // once done, the user returns to the select view
+                        match opt_action {
+                            Some(EDIT_KEY) => {
+                                edit_temp_copy(Path::new(path_string))?;
+                                continue;
+                            }
+                            Some(COPY_PATH_KEY) => {
+                                copy_to_clipboard(path_string)?;
+                                continue;
+                            }
+                            // unlike the others, there's nothing to return to once the version is written
+                            Some(CAT_KEY) => {
+                                cat_version(Path::new(path_string))?;
+                                std::process::exit(0)
+                            }
+                            _ => {}
+                        }
+
                         // return string from the loop
                         break path_string.to_string();
                     }
@@ -321,27 +776,254 @@ fn interactive_select(
             &path_string,
             paths_selected_in_browse,
         )?)
+    } else if let Some(command) = &config.opt_exec {
+        let status = exec_on_temp_copy(command, Path::new(&path_string))?;
+        std::process::exit(status.code().unwrap_or(1))
+    } else if config.opt_edit {
+        let status = edit_temp_copy(Path::new(&path_string))?;
+        std::process::exit(status.code().unwrap_or(1))
+    } else if config.opt_cat {
+        cat_version(Path::new(&path_string))?;
+        std::process::exit(0)
     } else {
-        let output_buf = format!("\"{}\"\n", &path_string);
+        if config.opt_copy_path {
+            copy_to_clipboard(&path_string)?;
+        }
+
+        let output_buf = match config.print_selected {
+            // picker mode prints only the bare path, so a file manager may open it as is
+            PrintSelected::SnapPath if config.opt_picker => format!("{}\n", &path_string),
+            PrintSelected::SnapPath => format!("\"{}\"\n", &path_string),
+            // anything else is for scripts, and is always printed bare
+            _ => {
+                let provenance = get_snap_provenance(
+                    config.as_ref(),
+                    Path::new(&path_string),
+                    paths_selected_in_browse,
+                )
+                .ok_or_else(|| {
+                    HttmError::new("httm could not determine where the selected version came from.")
+                })?;
+
+                match config.print_selected {
+                    PrintSelected::LivePath => {
+                        format!("{}\n", provenance.live_path.to_string_lossy())
+                    }
+                    PrintSelected::RelativePath => {
+                        format!("{}\n", provenance.relative_path.to_string_lossy())
+                    }
+                    PrintSelected::SnapName => format!("{}\n", provenance.snap_name),
+                    PrintSelected::SnapPath => unreachable!(),
+                }
+            }
+        };
         print_output_buf(output_buf)?;
         std::process::exit(0)
     }
 }
 
-fn select_restore_view(preview_buffer: &str, reverse: bool) -> HttmResult<String> {
+fn get_selected_versions(
+    config: &Config,
+    paths_selected_in_browse: &[PathData],
+) -> HttmResult<SnapsAndLiveSet> {
+    let snaps_and_live_set = versions_lookup_exec(config, paths_selected_in_browse)?;
+
+    // snap and live set has no snaps
+    if snaps_and_live_set[0].is_empty() {
+        let paths: Vec<String> = paths_selected_in_browse
+            .iter()
+            .map(|path| path.path_buf.to_string_lossy().to_string())
+            .collect();
+        // a window may well exclude every version, which is not the same as having no snapshots
+        let reason = if config.opt_after.is_some() || config.opt_before.is_some() {
+            "they have no snapshot versions within the window requested"
+        } else {
+            "they have no snapshots"
+        };
+        let msg = format!(
+            "Cannot select or restore from the following paths as {}:\n{:?}",
+            reason, paths
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    Ok(snaps_and_live_set)
+}
+
+// picker mode has a defined exit status for when the user aborts, see PICKER
+fn abort_exit_code(config: &Config) -> i32 {
+    if config.opt_picker {
+        PICKER_ABORT_EXIT_CODE
+    } else {
+        0
+    }
+}
+
+// when hunting for the version in which something went wrong, only the versions in which
+// the contents actually changed are of interest, so hide any version with the same contents
+// as the version before
+fn get_changes_only(snap_versions: &[PathData]) -> Vec<PathData> {
+    snap_versions
+        .iter()
+        .enumerate()
+        .filter(|(idx, version)| match idx.checked_sub(1) {
+            Some(prev_idx) => {
+                let prev_version = &snap_versions[prev_idx];
+                // versions of different files are never the same
+                prev_version.path_buf.file_name() != version.path_buf.file_name()
+                    || !prev_version.is_same_contents(version)
+            }
+            None => true,
+        })
+        .map(|(_idx, version)| version.clone())
+        .collect()
+}
+
+// a select view ends with either a selection, or a request to toggle which versions are shown
+enum SelectViewOutput {
+    Selected(String),
+    Edit(String),
+    CopyPath(String),
+    Cat(String),
+    ToggleChangesOnly,
+    Compare(Vec<String>),
+    Workspace(Vec<String>),
+}
+
+const CHANGES_ONLY_KEY: &str = "ctrl-t";
+const EDIT_KEY: &str = "ctrl-e";
+const COPY_PATH_KEY: &str = "ctrl-y";
+const COMPARE_KEY: &str = "ctrl-d";
+const CAT_KEY: &str = "ctrl-o";
+
+// each snapshot version marked, as a live version can't be copied into a workspace
+fn get_marked_versions(marked_lines: &[String], live_versions: &[PathData]) -> Vec<PathData> {
+    marked_lines
+        .iter()
+        .filter_map(|line| line.split_terminator('"').nth(1))
+        .map(Path::new)
+        .filter(|path| {
+            live_versions
+                .iter()
+                .all(|live_version| *path != live_version.path_buf.as_path())
+        })
+        .map(PathData::from)
+        .collect()
+}
+
+// two versions, either of which may be the live version, are compared older first, and anything
+// other than two, the user may have just marked one too few or too many, returns to the select view
+fn compare_versions(config: &Config, marked_lines: &[String]) -> HttmResult<()> {
+    let mut marked_versions: Vec<PathData> = marked_lines
+        .iter()
+        .filter_map(|line| line.split_terminator('"').nth(1))
+        .map(|path_string| PathData::from(Path::new(path_string)))
+        .collect();
+
+    if marked_versions.len() != 2 {
+        return Ok(());
+    }
+
+    marked_versions.sort_by_key(|pathdata| pathdata.md_infallible().modify_time);
+    let (old_version, new_version) = (&marked_versions[0], &marked_versions[1]);
+
+    match &config.opt_diff_tool {
+        Some(command) => {
+            diff_temp_copies(command, &old_version.path_buf, &new_version.path_buf)?;
+        }
+        None => {
+            // a binary file or a dir can't be diffed, but that's no reason to end the session
+            let compare_buffer = display_side_by_side(config, old_version, new_version)
+                .unwrap_or_else(|err| format!("httm could not compare these versions: {}\n", err));
+            compare_view(&compare_buffer)?;
+        }
+    }
+
+    Ok(())
+}
+
+// just a pager, whether the user accepts or aborts, they return to the select view
+fn compare_view(compare_buffer: &str) -> HttmResult<()> {
+    let header = "PAGE UP:    page up  | PAGE DOWN:  page down\n\
+        EXIT:       esc      | (return to the select view)\n\
+        ─────────────────────────────────────────────";
+
+    let skim_opts = SkimOptionsBuilder::default()
+        .reverse(true)
+        .nosort(true)
+        .tabstop(Some("4"))
+        .exact(true)
+        .multi(false)
+        .regex(false)
+        .header(Some(header))
+        .build()
+        .expect("Could not initialized skim options for compare_view");
+
+    let item_reader_opts = SkimItemReaderOption::default().ansi(true);
+    let item_reader = SkimItemReader::new(item_reader_opts);
+
+    let items = item_reader.of_bufread(Cursor::new(compare_buffer.to_owned()));
+
+    Skim::run_with(&skim_opts, Some(items))
+        .map(|_output| ())
+        .ok_or_else(|| HttmError::new("httm compare session failed."))
+}
+
+pub fn select_restore_view(
+    config: &Config,
+    preview_buffer: &str,
+    reverse: bool,
+) -> HttmResult<String> {
+    match select_view(config, preview_buffer, reverse, false)? {
+        SelectViewOutput::Selected(output) => Ok(output),
+        SelectViewOutput::Edit(_)
+        | SelectViewOutput::CopyPath(_)
+        | SelectViewOutput::Cat(_)
+        | SelectViewOutput::ToggleChangesOnly
+        | SelectViewOutput::Compare(_)
+        | SelectViewOutput::Workspace(_) => {
+            unreachable!("Version action keys are never bound for a select/restore view.")
+        }
+    }
+}
+
+fn select_view(
+    config: &Config,
+    preview_buffer: &str,
+    reverse: bool,
+    opt_version_actions: bool,
+) -> HttmResult<SelectViewOutput> {
+    let header = if opt_version_actions {
+        "PAGE UP:    page up  | PAGE DOWN:  page down\n\
+        EXIT:       esc      | SELECT:     enter    \n\
+        CHANGES:    ctrl+t   | (toggle hiding unchanged versions)\n\
+        EDIT:       ctrl+e   | (view a read-only copy in $EDITOR)\n\
+        COPY PATH:  ctrl+y   | (copy the path to the clipboard)\n\
+        CAT:        ctrl+o   | (write its contents to stdout and exit)\n\
+        MARK:       tab      | COMPARE:    ctrl+d   \n\
+        (compare two marked versions side by side)\n\
+        ─────────────────────────────────────────────"
+    } else {
+        "PAGE UP:    page up  | PAGE DOWN:  page down\n\
+        EXIT:       esc      | SELECT:     enter    \n\
+        ─────────────────────────────────────────────"
+    };
+
     // build our browse view - less to do than before - no previews, looking through one 'lil buffer
     let skim_opts = SkimOptionsBuilder::default()
         .tac(reverse)
         .nosort(reverse)
         .tabstop(Some("4"))
         .exact(true)
-        .multi(false)
+        .multi(opt_version_actions)
         .regex(false)
-        .header(Some(
-            "PAGE UP:    page up  | PAGE DOWN:  page down\n\
-                      EXIT:       esc      | SELECT:     enter    \n\
-                      ─────────────────────────────────────────────",
-        ))
+        .header(Some(header))
+        .expect(opt_version_actions.then(|| {
+            format!(
+                "{},{},{},{},{}",
+                CHANGES_ONLY_KEY, EDIT_KEY, COPY_PATH_KEY, COMPARE_KEY, CAT_KEY
+            )
+        }))
         .build()
         .expect("Could not initialized skim options for select_restore_view");
 
@@ -351,24 +1033,45 @@ fn select_restore_view(preview_buffer: &str, reverse: bool) -> HttmResult<String
     let items = item_reader.of_bufread(Cursor::new(preview_buffer.to_owned()));
 
     // run_with() reads and shows items from the thread stream created above
-    let selected_items = if let Some(output) = Skim::run_with(&skim_opts, Some(items)) {
+    let (selected_items, opt_key) = if let Some(output) = Skim::run_with(&skim_opts, Some(items)) {
         if output.is_abort {
             eprintln!("httm select/restore session was aborted.  Quitting.");
-            std::process::exit(0)
+            std::process::exit(abort_exit_code(config))
         } else {
-            output.selected_items
+            let opt_key = match output.final_event {
+                Event::EvActAccept(opt_key) => opt_key,
+                _ => None,
+            };
+            (output.selected_items, opt_key)
         }
     } else {
-        return Err(HttmError::new("httm select/restore session failed.").into());
+        return Err(HttmError::new("httm select/restore session failed."));
     };
 
     // output() converts the filename/raw path to a absolute path string for use elsewhere
-    let output = selected_items
+    let outputs: Vec<String> = selected_items
         .iter()
         .map(|i| i.output().into_owned())
         .collect();
 
-    Ok(output)
+    if opt_key.as_deref() == Some(COMPARE_KEY) {
+        return Ok(SelectViewOutput::Compare(outputs));
+    }
+
+    if opt_key.is_none() && opt_version_actions && config.opt_workspace.is_some() {
+        return Ok(SelectViewOutput::Workspace(outputs));
+    }
+
+    // only a compare or a workspace wants more than one, any other action is for the first version marked
+    let output = outputs.into_iter().next().unwrap_or_default();
+
+    match opt_key.as_deref() {
+        Some(CHANGES_ONLY_KEY) => Ok(SelectViewOutput::ToggleChangesOnly),
+        Some(EDIT_KEY) => Ok(SelectViewOutput::Edit(output)),
+        Some(COPY_PATH_KEY) => Ok(SelectViewOutput::CopyPath(output)),
+        Some(CAT_KEY) => Ok(SelectViewOutput::Cat(output)),
+        _ => Ok(SelectViewOutput::Selected(output)),
+    }
 }
 
 fn interactive_restore(
@@ -376,6 +1079,8 @@ fn interactive_restore(
     parsed_str: &str,
     paths_selected_in_browse: &[PathData],
 ) -> HttmResult<()> {
+    deny_if_forensic("restore a file")?;
+
     // build pathdata from selection buffer parsed string
     //
     // request is also sanity check for snap path exists below when we check
@@ -384,20 +1089,118 @@ fn interactive_restore(
 
     // sanity check -- snap version has good metadata?
     let snap_path_metadata = snap_pathdata
-        .metadata
+        .metadata()
         .ok_or_else(|| HttmError::new("Source location does not exist on disk. Quitting."))?;
 
     // build new place to send file
-    let new_file_path_buf = if config.opt_overwrite {
+    let new_file_path_buf = get_restore_destination(
+        config.as_ref(),
+        &snap_pathdata,
+        &snap_path_metadata,
+        paths_selected_in_browse,
+        config.opt_overwrite,
+    )?;
+
+    deny_unless_restorable(&snap_pathdata.path_buf, &new_file_path_buf)?;
+
+    // in overwrite mode, the live file is moved out of the way first, rather than lost to the restore
+    let opt_set_aside_path =
+        get_set_aside_path(config.as_ref(), &new_file_path_buf, config.opt_overwrite)?;
+
+    if let Some(set_aside_path) = &opt_set_aside_path {
+        deny_unless_restorable(&new_file_path_buf, set_aside_path)?;
+    }
+
+    // is anything using the file we are about to restore over?  warn, or refuse, before we ask
+    let open_files_warning =
+        open_files_advisory(config.as_ref(), &new_file_path_buf)?.unwrap_or_default();
+
+    let set_aside_line = opt_set_aside_path
+        .as_ref()
+        .map(|set_aside_path| format!("\taside: {:?}\n", set_aside_path))
+        .unwrap_or_default();
+
+    // tell the user what we're up to, and get consent
+    let preview_buffer = format!(
+        "httm will copy a file from a ZFS snapshot:\n\n\
+        \tfrom: {:?}\n\
+        \tto:   {:?}\n\
+        {}\n\
+        {}\
+        Before httm restores this file, it would like your consent. Continue? (YES/NO)\n\
+        ──────────────────────────────────────────────────────────────────────────────\n\
+        YES\n\
+        NO",
+        snap_pathdata.path_buf, new_file_path_buf, set_aside_line, open_files_warning
+    );
+
+    // loop until user consents or doesn't
+    loop {
+        let user_consent =
+            select_restore_view(config.as_ref(), &preview_buffer, true)?.to_ascii_uppercase();
+
+        match user_consent.as_ref() {
+            "YES" | "Y" => {
+                let copy_failures = restore_over_live(
+                    &snap_pathdata.path_buf,
+                    &new_file_path_buf,
+                    opt_set_aside_path.as_deref(),
+                )?;
+                let attribute_notes =
+                    restore_special_attributes(&snap_pathdata.path_buf, &new_file_path_buf);
+
+                let result_buffer = format!(
+                    "httm copied a file from a ZFS snapshot:\n\n\
+                    \tfrom: {:?}\n\
+                    \tto:   {:?}\n\
+                    {}",
+                    snap_pathdata.path_buf, new_file_path_buf, set_aside_line
+                );
+                eprintln!("{}", result_buffer);
+                attribute_notes
+                    .iter()
+                    .for_each(|note| eprintln!("Warning: {}", note));
+
+                // whatever could be restored of a dir was, so we just say what couldn't
+                if !copy_failures.is_empty() {
+                    display_copy_failures(&copy_failures);
+
+                    let msg = format!(
+                        "Restore completed with errors.  httm could not restore the {} file(s) listed above.",
+                        copy_failures.len()
+                    );
+                    return Err(HttmError::new(&msg));
+                }
+
+                eprintln!("Restore completed successfully.");
+                break;
+            }
+            "NO" | "N" => break eprintln!("User declined restore.  No files were restored."),
+            // if not yes or no, then noop and continue to the next iter of loop
+            _ => {}
+        }
+    }
+
+    std::process::exit(0)
+}
+
+pub fn get_restore_destination(
+    config: &Config,
+    snap_pathdata: &PathData,
+    snap_path_metadata: &PathMetadata,
+    live_paths: &[PathData],
+    opt_overwrite: bool,
+) -> HttmResult<PathBuf> {
+    if opt_overwrite {
         // instead of just not naming the new file with extra info (date plus "httm_restored") and shoving that new file
         // into the pwd, here, we actually look for the original location of the file to make sure we overwrite it.
         // so, if you were in /etc and wanted to restore /etc/samba/smb.conf, httm will make certain to overwrite
         // at /etc/samba/smb.conf, not just avoid the rename
-        let opt_original_live_pathdata = paths_selected_in_browse.iter().find_map(|pathdata| {
-            match versions_lookup_exec(config.as_ref(), &[pathdata.clone()]).ok() {
+        let opt_original_live_pathdata = live_paths.iter().find_map(|pathdata| {
+            match versions_lookup_exec(config, std::slice::from_ref(pathdata)).ok() {
                 // safe to index into snaps, known len of 2 for set
                 Some(pathdata_set) => pathdata_set[0].iter().find_map(|pathdata| {
-                    if pathdata == &snap_pathdata {
+                    if pathdata == snap_pathdata {
                         // safe to index into request, known len of 2 for set, known len of 1 for request
                         let original_live_pathdata = pathdata_set[1][0].to_owned();
                         Some(original_live_pathdata)
@@ -410,13 +1213,10 @@ fn interactive_restore(
         });
 
         match opt_original_live_pathdata {
-            Some(pathdata) => pathdata.path_buf,
-            None => {
-                return Err(HttmError::new(
-                    "httm unable to determine original file path in overwrite mode.  Quitting.",
-                )
-                .into())
-            }
+            Some(pathdata) => Ok(pathdata.path_buf),
+            None => Err(HttmError::new(
+                "httm unable to determine original file path in overwrite mode.  Quitting.",
+            )),
         }
     } else {
         let snap_filename = snap_pathdata
@@ -429,7 +1229,7 @@ fn interactive_restore(
         let new_filename = snap_filename
             + ".httm_restored."
             + &get_date(
-                &config,
+                config,
                 &snap_path_metadata.modify_time,
                 DateFormat::Timestamp,
             );
@@ -438,55 +1238,11 @@ fn interactive_restore(
 
         // don't let the user rewrite one restore over another in non-overwrite mode
         if new_file_path_buf.exists() {
-            return Err(
-                HttmError::new("httm will not restore to that file, as a file with the same path name already exists. Quitting.").into(),
-            );
+            Err(
+                HttmError::new("httm will not restore to that file, as a file with the same path name already exists. Quitting."),
+            )
         } else {
-            new_file_path_buf
-        }
-    };
-
-    // tell the user what we're up to, and get consent
-    let preview_buffer = format!(
-        "httm will copy a file from a ZFS snapshot:\n\n\
-        \tfrom: {:?}\n\
-        \tto:   {:?}\n\n\
-        Before httm restores this file, it would like your consent. Continue? (YES/NO)\n\
-        ──────────────────────────────────────────────────────────────────────────────\n\
-        YES\n\
-        NO",
-        snap_pathdata.path_buf, new_file_path_buf
-    );
-
-    // loop until user consents or doesn't
-    loop {
-        let user_consent = select_restore_view(&preview_buffer, true)?.to_ascii_uppercase();
-
-        match user_consent.as_ref() {
-            "YES" | "Y" => match copy_recursive(&snap_pathdata.path_buf, &new_file_path_buf) {
-                Ok(_) => {
-                    let result_buffer = format!(
-                        "httm copied a file from a ZFS snapshot:\n\n\
-                            \tfrom: {:?}\n\
-                            \tto:   {:?}\n\n\
-                            Restore completed successfully.",
-                        snap_pathdata.path_buf, new_file_path_buf
-                    );
-                    break eprintln!("{}", result_buffer);
-                }
-                Err(err) => {
-                    return Err(HttmError::with_context(
-                        "httm restore failed for the following reason",
-                        Box::new(err),
-                    )
-                    .into());
-                }
-            },
-            "NO" | "N" => break eprintln!("User declined restore.  No files were restored."),
-            // if not yes or no, then noop and continue to the next iter of loop
-            _ => {}
+            Ok(new_file_path_buf)
         }
     }
-
-    std::process::exit(0)
 }
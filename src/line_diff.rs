@@ -0,0 +1,268 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{fs::read, path::Path};
+
+use crate::forensic::audit;
+use crate::utility::HttmError;
+use crate::HttmResult;
+
+// a line diff needs a table of the lengths of common subsequences, one cell per pair of lines,
+// past this many cells, the file is too large to diff in any reasonable time or memory
+const MAX_DIFF_CELLS: usize = 64 * 1024 * 1024;
+
+// a row of a side-by-side diff, a line of the old version, of the new version, or of both
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SideBySideRow {
+    pub opt_old_idx: Option<usize>,
+    pub opt_new_idx: Option<usize>,
+    pub is_same: bool,
+}
+
+pub fn read_lines(path: &Path) -> HttmResult<Vec<String>> {
+    audit("read", path);
+    let bytes = read(path)?;
+
+    if bytes.contains(&0u8) {
+        return Err(HttmError::new(
+            "httm can only diff text files, and this file appears to be binary.",
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&bytes)
+        .lines()
+        .map(|line| line.to_owned())
+        .collect())
+}
+
+// the pairs of indices of lines common to both, by way of a longest common subsequence
+pub fn get_matching_lines(old: &[&str], new: &[&str]) -> HttmResult<Vec<(usize, usize)>> {
+    // lines common to the start and end needn't be in our table at all
+    let prefix_len = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(old_line, new_line)| old_line == new_line)
+        .count();
+    let suffix_len = old[prefix_len..]
+        .iter()
+        .rev()
+        .zip(new[prefix_len..].iter().rev())
+        .take_while(|(old_line, new_line)| old_line == new_line)
+        .count();
+
+    let old_mid = &old[prefix_len..old.len() - suffix_len];
+    let new_mid = &new[prefix_len..new.len() - suffix_len];
+
+    let cols = new_mid.len() + 1;
+    let cells = (old_mid.len() + 1) * cols;
+
+    if cells > MAX_DIFF_CELLS {
+        return Err(HttmError::new(
+            "httm can't diff this file, as the differences between its versions are too large.",
+        ));
+    }
+
+    // table[i][j] is the length of the longest common subsequence of old_mid[i..] and new_mid[j..]
+    let mut table = vec![0u32; cells];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            table[i * cols + j] = if old_mid[i] == new_mid[j] {
+                table[(i + 1) * cols + j + 1] + 1
+            } else {
+                table[(i + 1) * cols + j].max(table[i * cols + j + 1])
+            };
+        }
+    }
+
+    let mut matches: Vec<(usize, usize)> = (0..prefix_len).map(|idx| (idx, idx)).collect();
+
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() && j < new_mid.len() {
+        if old_mid[i] == new_mid[j] {
+            matches.push((prefix_len + i, prefix_len + j));
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * cols + j] >= table[i * cols + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    matches.extend(
+        (0..suffix_len).map(|idx| (old.len() - suffix_len + idx, new.len() - suffix_len + idx)),
+    );
+
+    Ok(matches)
+}
+
+// like sdiff, between each pair of matching lines, the lines of old and new which don't match
+// are paired off as changed lines, and any left over were deleted from old, or added to new
+pub fn get_side_by_side(old: &[&str], new: &[&str]) -> HttmResult<Vec<SideBySideRow>> {
+    let matches = get_matching_lines(old, new)?;
+
+    let mut rows: Vec<SideBySideRow> = Vec::with_capacity(old.len().max(new.len()));
+    let (mut old_start, mut new_start) = (0, 0);
+
+    // the ends of both files, as if they were one last matching line
+    for (old_idx, new_idx) in matches
+        .into_iter()
+        .chain(std::iter::once((old.len(), new.len())))
+    {
+        let num_unmatched = (old_idx - old_start).max(new_idx - new_start);
+
+        rows.extend((0..num_unmatched).map(|offset| SideBySideRow {
+            opt_old_idx: Some(old_start + offset).filter(|idx| *idx < old_idx),
+            opt_new_idx: Some(new_start + offset).filter(|idx| *idx < new_idx),
+            is_same: false,
+        }));
+
+        if old_idx < old.len() {
+            rows.push(SideBySideRow {
+                opt_old_idx: Some(old_idx),
+                opt_new_idx: Some(new_idx),
+                is_same: true,
+            });
+        }
+
+        old_start = old_idx + 1;
+        new_start = new_idx + 1;
+    }
+
+    Ok(rows)
+}
+
+// the lines of context around each change, as in "diff -u"
+const UNIFIED_DIFF_CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Same(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+// every line of old and new, in order, as kept, deleted from old, or inserted from new
+fn get_edits(old: &[&str], new: &[&str]) -> HttmResult<Vec<Edit>> {
+    let matches = get_matching_lines(old, new)?;
+
+    let mut edits: Vec<Edit> = Vec::with_capacity(old.len() + new.len());
+    let (mut old_start, mut new_start) = (0, 0);
+
+    // the ends of both files, as if they were one last matching line
+    for (old_idx, new_idx) in matches
+        .into_iter()
+        .chain(std::iter::once((old.len(), new.len())))
+    {
+        edits.extend((old_start..old_idx).map(Edit::Delete));
+        edits.extend((new_start..new_idx).map(Edit::Insert));
+
+        if old_idx < old.len() {
+            edits.push(Edit::Same(old_idx, new_idx));
+        }
+
+        old_start = old_idx + 1;
+        new_start = new_idx + 1;
+    }
+
+    Ok(edits)
+}
+
+// a unified diff, as "diff -u" would write it, and "patch" would read it, empty when there are
+// no differences, both sides named as requested, so a patch may name the file to which it applies
+pub fn get_unified_diff(
+    old_name: &str,
+    new_name: &str,
+    old: &[&str],
+    new: &[&str],
+) -> HttmResult<String> {
+    let edits = get_edits(old, new)?;
+
+    // the position in old and in new before each edit
+    let positions: Vec<(usize, usize)> = edits
+        .iter()
+        .scan((0, 0), |(old_pos, new_pos), edit| {
+            let before = (*old_pos, *new_pos);
+            match edit {
+                Edit::Same(_, _) => {
+                    *old_pos += 1;
+                    *new_pos += 1;
+                }
+                Edit::Delete(_) => *old_pos += 1,
+                Edit::Insert(_) => *new_pos += 1,
+            }
+            Some(before)
+        })
+        .collect();
+
+    // changes closer together than twice the context share a hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+
+    edits
+        .iter()
+        .enumerate()
+        .filter(|(_idx, edit)| !matches!(edit, Edit::Same(_, _)))
+        .for_each(|(idx, _edit)| {
+            let start = idx.saturating_sub(UNIFIED_DIFF_CONTEXT);
+            let end = (idx + UNIFIED_DIFF_CONTEXT + 1).min(edits.len());
+
+            match hunks.last_mut() {
+                Some((_prev_start, prev_end)) if start <= *prev_end => *prev_end = end,
+                _ => hunks.push((start, end)),
+            }
+        });
+
+    if hunks.is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut buffer = format!("--- {}\n+++ {}\n", old_name, new_name);
+
+    hunks.into_iter().for_each(|(start, end)| {
+        let hunk_edits = &edits[start..end];
+        let (old_pos, new_pos) = positions[start];
+
+        let old_count = hunk_edits
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Insert(_)))
+            .count();
+        let new_count = hunk_edits
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Delete(_)))
+            .count();
+
+        // lines are numbered from 1, except that an empty range is numbered by the line before
+        let old_first = if old_count == 0 { old_pos } else { old_pos + 1 };
+        let new_first = if new_count == 0 { new_pos } else { new_pos + 1 };
+
+        buffer += &format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_first, old_count, new_first, new_count
+        );
+
+        hunk_edits.iter().for_each(|edit| {
+            let line = match edit {
+                Edit::Same(old_idx, _new_idx) => format!(" {}\n", old[*old_idx]),
+                Edit::Delete(old_idx) => format!("-{}\n", old[*old_idx]),
+                Edit::Insert(new_idx) => format!("+{}\n", new[*new_idx]),
+            };
+            buffer += &line;
+        });
+    });
+
+    Ok(buffer)
+}
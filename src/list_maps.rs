@@ -0,0 +1,316 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::lookup_versions::{prepare_search_bundles, select_search_datasets};
+use crate::utility::{print_output_buf, PathData};
+use crate::{Config, HttmResult, ListFormat, SnapDatasetType};
+
+#[derive(Debug, Serialize)]
+struct AliasListing {
+    local_dir: String,
+    remote_dir: String,
+    fs_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DatasetListing {
+    mount: String,
+    name: String,
+    fs_type: String,
+    mount_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snap_dir: Option<String>,
+    // none when the snapshots of the dataset could not be read
+    num_snapshots: Option<usize>,
+}
+
+// where httm will look for the snapshot versions of a path, and why
+#[derive(Debug, Serialize)]
+struct PathListing {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mount: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relative_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_snapshots: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct AliasesReport {
+    aliases: Vec<AliasListing>,
+    paths: Vec<PathListing>,
+}
+
+#[derive(Debug, Serialize)]
+struct DatasetsReport {
+    datasets: Vec<DatasetListing>,
+    external_roots: Vec<String>,
+    filter_dirs: Vec<String>,
+    paths: Vec<PathListing>,
+}
+
+pub fn list_aliases_exec(config: &Config, list_format: &ListFormat) -> HttmResult<()> {
+    let aliases: Vec<AliasListing> = config
+        .dataset_collection
+        .opt_map_of_aliases
+        .iter()
+        .flatten()
+        .map(|(local_dir, alias_info)| AliasListing {
+            local_dir: local_dir.to_string_lossy().into_owned(),
+            remote_dir: alias_info.remote_dir.to_string_lossy().into_owned(),
+            fs_type: alias_info.fs_type.to_string(),
+        })
+        .collect();
+
+    let report = AliasesReport {
+        aliases,
+        paths: get_path_listings(config),
+    };
+
+    let output_buf = match list_format {
+        ListFormat::Json => format!("{}\n", serde_json::to_string_pretty(&report)?),
+        ListFormat::Pretty => {
+            let mut buffer = String::new();
+
+            if report.aliases.is_empty() {
+                buffer += "no aliases are in effect\n";
+            } else {
+                let local_width = report
+                    .aliases
+                    .iter()
+                    .map(|alias| alias.local_dir.len())
+                    .max()
+                    .unwrap_or_default();
+
+                buffer += "aliases (local dir -> remote dir):\n";
+                report.aliases.iter().for_each(|alias| {
+                    buffer += &format!(
+                        "  {:<local_width$}  ->  {}  ({})\n",
+                        alias.local_dir, alias.remote_dir, alias.fs_type
+                    );
+                });
+            }
+
+            buffer + &display_path_listings(&report.paths)
+        }
+    };
+
+    print_output_buf(output_buf)
+}
+
+pub fn list_datasets_exec(config: &Config, list_format: &ListFormat) -> HttmResult<()> {
+    let dataset_collection = &config.dataset_collection;
+
+    let datasets: Vec<DatasetListing> = dataset_collection
+        .map_of_datasets
+        .iter()
+        .map(|(mount, dataset_info)| DatasetListing {
+            mount: mount.to_string_lossy().into_owned(),
+            name: dataset_info.name.clone(),
+            fs_type: dataset_info.fs_type.to_string(),
+            mount_type: dataset_info.mount_type.to_string(),
+            snap_dir: dataset_collection
+                .opt_map_of_snap_dirs
+                .as_ref()
+                .and_then(|map_of_snap_dirs| map_of_snap_dirs.get(mount))
+                .map(|snap_dir| snap_dir.to_string_lossy().into_owned()),
+            num_snapshots: dataset_collection
+                .map_of_snaps
+                .get(mount)
+                .map(|snap_mounts| snap_mounts.len()),
+        })
+        .collect();
+
+    let to_strings = |paths: &[std::path::PathBuf]| -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect()
+    };
+
+    let report = DatasetsReport {
+        datasets,
+        external_roots: to_strings(&dataset_collection.vec_of_external_roots),
+        filter_dirs: to_strings(&dataset_collection.vec_of_filter_dirs),
+        paths: get_path_listings(config),
+    };
+
+    let output_buf = match list_format {
+        ListFormat::Json => format!("{}\n", serde_json::to_string_pretty(&report)?),
+        ListFormat::Pretty => {
+            let mut buffer = String::new();
+
+            if report.datasets.is_empty() {
+                buffer += "no supported datasets are mounted\n";
+            } else {
+                let mount_width = report
+                    .datasets
+                    .iter()
+                    .map(|dataset| dataset.mount.len())
+                    .max()
+                    .unwrap_or_default();
+                let name_width = report
+                    .datasets
+                    .iter()
+                    .map(|dataset| dataset.name.len())
+                    .max()
+                    .unwrap_or_default();
+
+                buffer += "datasets (mount, name, filesystem, snapshots):\n";
+                report.datasets.iter().for_each(|dataset| {
+                    let num_snapshots = match dataset.num_snapshots {
+                        Some(num_snapshots) => format!("{} snapshot(s)", num_snapshots),
+                        None => "snapshots not readable".to_owned(),
+                    };
+
+                    buffer += &format!(
+                        "  {:<mount_width$}  {:<name_width$}  {} {}  {}",
+                        dataset.mount,
+                        dataset.name,
+                        dataset.mount_type,
+                        dataset.fs_type,
+                        num_snapshots
+                    );
+
+                    if let Some(snap_dir) = &dataset.snap_dir {
+                        buffer += &format!(", from snap dir {:?}", snap_dir);
+                    }
+
+                    buffer += "\n";
+                });
+            }
+
+            if !report.external_roots.is_empty() {
+                buffer += "external roots:\n";
+                report.external_roots.iter().for_each(|external_root| {
+                    buffer += &format!("  {}\n", external_root);
+                });
+            }
+
+            if !report.filter_dirs.is_empty() {
+                buffer += "filtered dirs, not searched when recursive:\n";
+                report.filter_dirs.iter().for_each(|filter_dir| {
+                    buffer += &format!("  {}\n", filter_dir);
+                });
+            }
+
+            buffer + &display_path_listings(&report.paths)
+        }
+    };
+
+    print_output_buf(output_buf)
+}
+
+fn get_path_listings(config: &Config) -> Vec<PathListing> {
+    config
+        .paths
+        .iter()
+        .map(|pathdata| get_path_listing(config, pathdata))
+        .collect()
+}
+
+// the same two steps any lookup takes: first, the dataset, or alias, which holds the path,
+// then, the snap mounts of that dataset, and the path relative to them
+fn get_path_listing(config: &Config, pathdata: &PathData) -> PathListing {
+    let path = pathdata.path_buf.to_string_lossy().into_owned();
+
+    let res = select_search_datasets(config, pathdata, &SnapDatasetType::MostProximate).and_then(
+        |snap_types_of_interest| {
+            let mount = snap_types_of_interest.proximate_dataset_mount.clone();
+            prepare_search_bundles(config, pathdata, &snap_types_of_interest)
+                .map(|search_bundles| (mount, search_bundles))
+        },
+    );
+
+    match res {
+        Ok((mount, search_bundles)) => {
+            let is_alias = is_alias_remote_dir(config, &mount);
+
+            PathListing {
+                path,
+                source: Some(if is_alias { "alias" } else { "dataset" }),
+                mount: Some(mount.to_string_lossy().into_owned()),
+                relative_path: search_bundles
+                    .first()
+                    .map(|bundle| bundle.relative_path.to_string_lossy().into_owned()),
+                num_snapshots: Some(
+                    search_bundles
+                        .iter()
+                        .map(|bundle| bundle.snap_mounts.len())
+                        .sum(),
+                ),
+                error: None,
+            }
+        }
+        Err(err) => PathListing {
+            path,
+            source: None,
+            mount: None,
+            relative_path: None,
+            num_snapshots: None,
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+fn is_alias_remote_dir(config: &Config, mount: &Path) -> bool {
+    config
+        .dataset_collection
+        .opt_map_of_aliases
+        .iter()
+        .flatten()
+        .any(|(_local_dir, alias_info)| alias_info.remote_dir == mount)
+}
+
+fn display_path_listings(path_listings: &[PathListing]) -> String {
+    if path_listings.is_empty() {
+        return String::new();
+    }
+
+    let mut buffer = "paths:\n".to_owned();
+
+    path_listings.iter().for_each(|path_listing| {
+        buffer += &format!("  {:?}\n", path_listing.path);
+
+        match (&path_listing.mount, &path_listing.error) {
+            (Some(mount), _) => {
+                buffer += &format!(
+                    "    maps to the {} {:?}, as {:?}, with {} snapshot(s)\n",
+                    path_listing.source.unwrap_or_default(),
+                    mount,
+                    path_listing.relative_path.as_deref().unwrap_or_default(),
+                    path_listing.num_snapshots.unwrap_or_default()
+                );
+            }
+            (None, Some(err)) => {
+                buffer += &format!("    maps to no snapshots: {}\n", err);
+            }
+            (None, None) => {}
+        }
+    });
+
+    buffer
+}
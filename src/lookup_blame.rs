@@ -0,0 +1,112 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{collections::BTreeSet, path::Path, time::SystemTime};
+
+use crate::line_diff::{get_matching_lines, read_lines};
+use crate::lookup_versions::get_version_entries;
+use crate::utility::{HttmError, PathData};
+use crate::{Config, HttmResult};
+
+// the version in which a line first appeared, None is the live file
+#[derive(Debug, Clone)]
+pub struct BlameOrigin {
+    pub opt_snap_name: Option<String>,
+    pub modify_time: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+    pub origin_idx: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Blame {
+    pub origins: Vec<BlameOrigin>,
+    pub lines: Vec<BlameLine>,
+}
+
+pub fn get_blame(config: &Config, live_pathdata: &PathData) -> HttmResult<Blame> {
+    let live_metadata = live_pathdata.metadata().ok_or_else(|| {
+        HttmError::new("httm can only blame a file which exists on the live filesystem.")
+    })?;
+
+    // identical versions may exist on multiple datasets, so keep just one of each, in time order
+    let mut version_entries = get_version_entries(config, live_pathdata);
+    version_entries.sort_by_key(|version_entry| {
+        let metadata = version_entry.pathdata.md_infallible();
+        (metadata.modify_time, metadata.size)
+    });
+    let mut seen = BTreeSet::new();
+    version_entries.retain(|version_entry| {
+        let metadata = version_entry.pathdata.md_infallible();
+        seen.insert((metadata.modify_time, metadata.size))
+    });
+
+    // the live file is always the last version of all
+    let versions: Vec<(BlameOrigin, &Path)> = version_entries
+        .iter()
+        .map(|version_entry| {
+            let origin = BlameOrigin {
+                opt_snap_name: Some(version_entry.snap_name.clone()),
+                modify_time: version_entry.pathdata.md_infallible().modify_time,
+            };
+            (origin, version_entry.pathdata.path_buf.as_path())
+        })
+        .chain(std::iter::once((
+            BlameOrigin {
+                opt_snap_name: None,
+                modify_time: live_metadata.modify_time,
+            },
+            live_pathdata.path_buf.as_path(),
+        )))
+        .collect();
+
+    // chain the diffs of each version to the next, a line which survives a diff keeps its
+    // origin, and any line which doesn't is new to that version
+    let mut prev_lines: Vec<BlameLine> = Vec::new();
+
+    for (origin_idx, (_origin, path)) in versions.iter().enumerate() {
+        // a version may have been something other than a file, or unreadable, just skip it
+        let lines = match read_lines(path) {
+            Ok(lines) => lines,
+            Err(_) if origin_idx + 1 < versions.len() => continue,
+            Err(err) => return Err(err),
+        };
+
+        let prev_strs: Vec<&str> = prev_lines.iter().map(|blame| blame.line.as_str()).collect();
+        let line_strs: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let matches = get_matching_lines(&prev_strs, &line_strs)?;
+
+        let mut next_lines: Vec<BlameLine> = lines
+            .into_iter()
+            .map(|line| BlameLine { origin_idx, line })
+            .collect();
+
+        matches.into_iter().for_each(|(prev_idx, idx)| {
+            next_lines[idx].origin_idx = prev_lines[prev_idx].origin_idx
+        });
+
+        prev_lines = next_lines;
+    }
+
+    Ok(Blame {
+        origins: versions.into_iter().map(|(origin, _path)| origin).collect(),
+        lines: prev_lines,
+    })
+}
@@ -16,21 +16,42 @@
 // that was distributed with this source code.
 
 use std::{
-    collections::{BTreeMap, BTreeSet},
-    ffi::OsString,
-    fs::read_dir,
+    collections::BTreeMap,
+    ffi::{CString, OsStr, OsString},
+    fs::{read_dir, symlink_metadata},
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
     path::{Path, PathBuf},
     time::SystemTime,
 };
 
 use itertools::Itertools;
 
+use crate::forensic::audit;
 use crate::lookup_versions::{
     prepare_search_bundles, select_search_datasets, RelativePathAndSnapMounts,
 };
-use crate::utility::{BasicDirEntryInfo, PathData};
+use crate::restrict_owner::{is_owned, is_owned_by};
+use crate::utility::{httm_is_dir, BasicDirEntryInfo, PathData};
 use crate::{Config, HttmResult};
 
+// editor swap and backup files, temporary files, and the droppings of file managers and interpreters,
+// which are deleted all the time, and which no one wishes to recover
+pub const DEFAULT_DELETED_EXCLUDES: &[&str] = &[
+    "*.swp",
+    "*.swo",
+    "*.swx",
+    "*~",
+    ".#*",
+    "#*#",
+    "*.tmp",
+    "*.temp",
+    ".DS_Store",
+    "Thumbs.db",
+    "desktop.ini",
+    "*.pyc",
+    "__pycache__",
+];
+
 pub fn deleted_lookup_exec(
     config: &Config,
     requested_dir: &Path,
@@ -38,7 +59,7 @@ pub fn deleted_lookup_exec(
     // we always need a requesting dir because we are comparing the files in the
     // requesting dir to those of their relative dirs on snapshots
     let requested_dir_pathdata = PathData::from(requested_dir);
-    let vec_requested_dir_pathdata = vec![&requested_dir_pathdata];
+    let vec_requested_dir_pathdata = [&requested_dir_pathdata];
 
     // create vec of all local and replicated backups at once
     //
@@ -60,7 +81,7 @@ pub fn deleted_lookup_exec(
         })
         .flatten()
         .flat_map(|search_bundle| {
-            get_unique_deleted_for_dir(&requested_dir_pathdata.path_buf, &search_bundle)
+            get_unique_deleted_for_dir(config, &requested_dir_pathdata.path_buf, &search_bundle)
         })
         .flatten();
 
@@ -99,39 +120,124 @@ where
 }
 
 fn get_unique_deleted_for_dir(
+    config: &Config,
     requested_dir: &Path,
     search_bundle: &RelativePathAndSnapMounts,
 ) -> HttmResult<Vec<BasicDirEntryInfo>> {
     // get all local entries we need to compare against these to know
     // what is a deleted file
     //
-    // create a collection of local file names
-    let local_filenames_map: BTreeSet<OsString> = read_dir(&requested_dir)?
+    // create a collection of local file names, and their paths
+    audit("list", requested_dir);
+    let local_filenames_map: BTreeMap<OsString, PathBuf> = read_dir(requested_dir)?
         .flatten()
-        .map(|dir_entry| dir_entry.file_name())
+        .map(|dir_entry| (dir_entry.file_name(), dir_entry.path()))
         .collect();
 
     let unique_snap_filenames: BTreeMap<OsString, BasicDirEntryInfo> =
         get_unique_snap_filenames(&search_bundle.snap_mounts, &search_bundle.relative_path)?;
 
     // compare local filenames to all unique snap filenames - none values are unique, here
-    let all_deleted_versions: Vec<BasicDirEntryInfo> = unique_snap_filenames
+    let deleted_versions = unique_snap_filenames
         .into_iter()
-        .filter(|(file_name, _)| !local_filenames_map.contains(file_name))
-        .map(|(_file_name, basic_dir_entry_info)| basic_dir_entry_info)
+        .filter(|(file_name, _)| !local_filenames_map.contains_key(file_name))
+        // a case insensitive, or unicode normalizing, dataset will find a file by another name,
+        // as "README" for "Readme", which is a file renamed, not a file deleted
+        .filter(|(file_name, _)| {
+            let live_path = requested_dir.join(file_name);
+            audit("stat", &live_path);
+            symlink_metadata(&live_path).is_err()
+        })
+        .map(|(_file_name, basic_dir_entry_info)| basic_dir_entry_info);
+
+    let recreated_versions = if config.opt_deleted_recreated {
+        get_recreated_for_dir(&local_filenames_map, search_bundle)
+    } else {
+        Vec::new()
+    };
+
+    let all_deleted_versions: Vec<BasicDirEntryInfo> = deleted_versions
+        .chain(recreated_versions)
+        .filter(|basic_dir_entry_info| {
+            !is_deleted_excluded(config, &basic_dir_entry_info.file_name)
+        })
+        .filter(|basic_dir_entry_info| is_owned(&basic_dir_entry_info.path))
+        // a deleted dir may yet hold files of the selected owner
+        .filter(|basic_dir_entry_info| {
+            httm_is_dir(basic_dir_entry_info)
+                || is_owned_by(&basic_dir_entry_info.path, config.opt_owner)
+        })
         .collect();
 
     Ok(all_deleted_versions)
 }
 
+// a file deleted, and then re-created by the same name, is a file deleted all the same,
+// though the name remains, so find those versions by the same name as a live file, which are
+// of a different object than the live file, and with different contents
+fn get_recreated_for_dir(
+    local_filenames_map: &BTreeMap<OsString, PathBuf>,
+    search_bundle: &RelativePathAndSnapMounts,
+) -> Vec<BasicDirEntryInfo> {
+    let recreated_iter = search_bundle
+        .snap_mounts
+        .iter()
+        .map(|snap_mount| snap_mount.join(&search_bundle.relative_path))
+        .flat_map(|path| {
+            audit("list", &path);
+            read_dir(&path)
+        })
+        .flatten()
+        .flatten()
+        .filter_map(|dir_entry| {
+            local_filenames_map
+                .get(&dir_entry.file_name())
+                .map(|live_path| (live_path, dir_entry))
+        })
+        .filter(|(live_path, dir_entry)| is_recreated(live_path, &dir_entry.path()))
+        .map(|(_live_path, dir_entry)| BasicDirEntryInfo::from(&dir_entry));
+
+    // the version last seen before it was deleted
+    get_latest_in_time_for_filename(recreated_iter)
+        .map(|(_file_name, (_modify_time, basic_dir_entry_info))| basic_dir_entry_info)
+        .collect()
+}
+
+fn is_recreated(live_path: &Path, snap_path: &Path) -> bool {
+    audit("stat", live_path);
+    audit("stat", snap_path);
+    let (live_md, snap_md) = match (symlink_metadata(live_path), symlink_metadata(snap_path)) {
+        (Ok(live_md), Ok(snap_md)) => (live_md, snap_md),
+        _ => return false,
+    };
+
+    // only files may be compared by their contents
+    if !live_md.is_file() || !snap_md.is_file() {
+        return false;
+    }
+
+    // a file modified in place remains the same object, with the same inode number and birth time,
+    // though an inode number may be reused, a birth time, where the filesystem records one, is not
+    let is_other_object = live_md.ino() != snap_md.ino()
+        || matches!(
+            (live_md.created(), snap_md.created()),
+            (Ok(live_birth), Ok(snap_birth)) if live_birth != snap_birth
+        );
+
+    is_other_object && !PathData::from(live_path).is_same_contents(&PathData::from(snap_path))
+}
+
 fn get_unique_snap_filenames(
     mounts: &[PathBuf],
     relative_path: &Path,
 ) -> HttmResult<BTreeMap<OsString, BasicDirEntryInfo>> {
     let basic_dir_entry_info_iter = mounts
         .iter()
-        .map(|path| path.join(&relative_path))
-        .flat_map(|path| read_dir(&path))
+        .map(|path| path.join(relative_path))
+        .flat_map(|path| {
+            audit("list", &path);
+            read_dir(&path)
+        })
         .flatten()
         .flatten()
         .map(|dir_entry| BasicDirEntryInfo::from(&dir_entry));
@@ -144,3 +250,16 @@ fn get_unique_snap_filenames(
         .collect();
     Ok(unique_snap_filenames)
 }
+
+// matched against the file name alone, as by fnmatch(3), as in a shell glob
+fn is_deleted_excluded(config: &Config, file_name: &OsStr) -> bool {
+    let c_file_name = match CString::new(file_name.as_bytes()) {
+        Ok(c_file_name) => c_file_name,
+        Err(_) => return false,
+    };
+
+    config.deleted_excludes.iter().any(|pattern| {
+        // safe, both are valid nul terminated strings which outlive the call
+        unsafe { libc::fnmatch(pattern.as_ptr(), c_file_name.as_ptr(), 0) == 0 }
+    })
+}
@@ -0,0 +1,216 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::read_dir,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use rayon::prelude::*;
+
+use crate::forensic::audit;
+use crate::lookup_versions::{prepare_search_bundles, select_search_datasets};
+use crate::parse_snaps::sort_snaps_by_creation;
+use crate::restrict_owner::is_owned;
+use crate::utility::{httm_is_dir, HttmError, PathData, PathMetadata};
+use crate::{Config, HttmResult};
+
+// the changes to the children of a directory, as found on a single snapshot,
+// relative to the snapshot which came before
+#[derive(Debug, Clone)]
+pub struct SnapDirSummary {
+    pub snap_dir: PathBuf,
+    pub latest_modify_time: SystemTime,
+    pub num_changed: usize,
+    pub changed_bytes: u64,
+    // the oldest snapshot has no snapshot before it, so its children are only counted, not changed
+    pub is_baseline: bool,
+}
+
+pub type DirSummaryForDirs = BTreeMap<PathData, Vec<SnapDirSummary>>;
+
+// key: file name, val: metadata of each file child of a directory
+type ChildrenMetadata = BTreeMap<OsString, PathMetadata>;
+
+pub fn get_dir_summary_for_dirs(config: &Config) -> HttmResult<DirSummaryForDirs> {
+    let (dirs, non_dirs): (Vec<&PathData>, Vec<&PathData>) = config
+        .paths
+        .iter()
+        .partition(|pathdata| httm_is_dir(*pathdata));
+
+    if !non_dirs.is_empty() {
+        eprintln!(
+            "httm can only summarize versions for directories, \
+        so the following paths will be ignored: "
+        );
+
+        non_dirs
+            .iter()
+            .for_each(|pathdata| eprintln!("{}", pathdata.path_buf.to_string_lossy()));
+    }
+
+    if dirs.is_empty() {
+        return Err(HttmError::new(
+            "httm could not find any directories to summarize.",
+        ));
+    }
+
+    let dir_summary_for_dirs: DirSummaryForDirs = dirs
+        .into_iter()
+        .map(|pathdata| {
+            let summaries = get_dir_summary(config, pathdata);
+            (pathdata.clone(), summaries)
+        })
+        .collect();
+
+    Ok(dir_summary_for_dirs)
+}
+
+fn get_dir_summary(config: &Config, requested_dir: &PathData) -> Vec<SnapDirSummary> {
+    config
+        .dataset_collection
+        .snaps_selected_for_search
+        .value()
+        .iter()
+        .flat_map(|dataset_type| select_search_datasets(config, requested_dir, dataset_type))
+        .flat_map(|datasets_of_interest| {
+            prepare_search_bundles(config, requested_dir, &datasets_of_interest)
+        })
+        .flatten()
+        .flat_map(|search_bundle| {
+            let opt_dataset_info = config
+                .dataset_collection
+                .map_of_datasets
+                .get(&search_bundle.dataset_of_interest);
+
+            // collect the children of the requested dir, as they exist on each snapshot, oldest
+            // snapshot first, as each snapshot is the baseline for the snapshot after
+            let children_on_snaps: Vec<(PathBuf, ChildrenMetadata)> =
+                sort_snaps_by_creation(opt_dataset_info, search_bundle.snap_mounts)
+                    .into_par_iter()
+                    .rev()
+                    .map(|snap_mount| {
+                        // join on an empty relative path leaves a trailing slash, collect components to remove it
+                        snap_mount
+                            .join(&search_bundle.relative_path)
+                            .components()
+                            .collect::<PathBuf>()
+                    })
+                    .filter_map(|snap_dir| {
+                        get_children_metadata(&snap_dir)
+                            .ok()
+                            .map(|children| (snap_dir, children))
+                    })
+                    .collect();
+
+            summarize_children_on_snaps(children_on_snaps)
+        })
+        .collect()
+}
+
+// only keep those snapshots for which something actually changed, a child new, modified, or
+// deleted since the snapshot before, after the first snapshot, which is only our baseline
+fn summarize_children_on_snaps(
+    children_on_snaps: Vec<(PathBuf, ChildrenMetadata)>,
+) -> Vec<SnapDirSummary> {
+    let mut iter = children_on_snaps.into_iter();
+
+    let (baseline_dir, mut previous_children) = match iter.next() {
+        Some(baseline) => baseline,
+        None => return Vec::new(),
+    };
+
+    let baseline = SnapDirSummary {
+        latest_modify_time: get_latest_modify_time(&previous_children),
+        num_changed: previous_children.len(),
+        changed_bytes: previous_children
+            .values()
+            .map(|metadata| metadata.size)
+            .sum(),
+        snap_dir: baseline_dir,
+        is_baseline: true,
+    };
+
+    let changes = iter.filter_map(|(snap_dir, children)| {
+        let changed: Vec<&PathMetadata> = children
+            .iter()
+            .filter(|(file_name, metadata)| previous_children.get(*file_name) != Some(metadata))
+            .map(|(_file_name, metadata)| metadata)
+            .collect();
+
+        // a deleted child is a change too, though it has no bytes on this snapshot
+        let num_deleted = previous_children
+            .keys()
+            .filter(|file_name| !children.contains_key(*file_name))
+            .count();
+
+        let summary = if changed.is_empty() && num_deleted == 0 {
+            None
+        } else {
+            // a deletion only leaves its mark upon the modify time of the directory itself
+            let latest_modify_time = if num_deleted == 0 {
+                get_latest_modify_time(&children)
+            } else {
+                PathData::from(snap_dir.as_path())
+                    .metadata()
+                    .map(|md| md.modify_time)
+                    .into_iter()
+                    .chain(std::iter::once(get_latest_modify_time(&children)))
+                    .max()
+                    .unwrap_or(SystemTime::UNIX_EPOCH)
+            };
+
+            Some(SnapDirSummary {
+                latest_modify_time,
+                num_changed: changed.len() + num_deleted,
+                changed_bytes: changed.iter().map(|metadata| metadata.size).sum(),
+                snap_dir,
+                is_baseline: false,
+            })
+        };
+
+        previous_children = children;
+        summary
+    });
+
+    std::iter::once(baseline).chain(changes).collect()
+}
+
+fn get_children_metadata(dir: &Path) -> HttmResult<ChildrenMetadata> {
+    // only files are summarized, a change within a child dir is not a change to our dir
+    audit("list", dir);
+    let children = read_dir(dir)?
+        .flatten()
+        .filter(|dir_entry| !httm_is_dir(dir_entry))
+        .filter(|dir_entry| is_owned(&dir_entry.path()))
+        .map(|dir_entry| (dir_entry.file_name(), PathData::from(&dir_entry)))
+        .filter_map(|(file_name, pathdata)| pathdata.metadata().map(|md| (file_name, md)))
+        .collect();
+
+    Ok(children)
+}
+
+fn get_latest_modify_time(children: &ChildrenMetadata) -> SystemTime {
+    children
+        .values()
+        .map(|metadata| metadata.modify_time)
+        .max()
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
@@ -33,7 +33,7 @@ pub fn get_mounts_for_files(config: &Config) -> HttmResult<MountsForFiles> {
     let (non_phantom_files, phantom_files): (Vec<&PathData>, Vec<&PathData>) = config
         .paths
         .par_iter()
-        .partition(|pathdata| pathdata.metadata.is_some());
+        .partition(|pathdata| pathdata.metadata().is_some());
 
     if !phantom_files.is_empty() {
         eprintln!(
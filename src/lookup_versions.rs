@@ -16,32 +16,168 @@
 // that was distributed with this source code.
 
 use std::{
-    collections::BTreeMap,
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ffi::OsStr,
+    fs::read_dir,
+    io::ErrorKind,
     path::{Path, PathBuf},
     time::SystemTime,
 };
 
 use rayon::prelude::*;
 
-use crate::utility::{HttmError, PathData};
+use crate::parse_snaps::{get_snap_creation_times, sort_snaps_by_creation};
+use crate::progress::{emit_progress, ProgressEvent};
+use crate::restrict_owner::{is_owned, is_owned_by};
+use crate::snapdir::get_snapdir_warning;
+use crate::utility::{DirManifest, HttmError, PathData};
 use crate::{
-    Config, HttmResult, MapOfAliases, MapOfDatasets, MostProximateAndOptAlts, SnapDatasetType,
-    SnapsAndLiveSet, VecOfSnaps,
+    Config, DatasetCollection, DatasetMetadata, DedupVersionsBy, FilesystemType, HttmResult,
+    MapOfAliases, MostProximateAndOptAlts, SnapDatasetType, SnapsAndLiveSet, SortVersionsBy,
+    VecOfSnaps, VersionTimeBy, BTRFS_SNAPPER_HIDDEN_DIRECTORY, BTRFS_SNAPPER_SUFFIX,
+    ZFS_SNAPSHOT_DIRECTORY,
 };
 
 #[derive(Debug, Clone)]
 pub struct RelativePathAndSnapMounts {
     pub relative_path: PathBuf,
+    pub dataset_of_interest: PathBuf,
     pub snap_mounts: VecOfSnaps,
 }
 
+// a snapshot version, and where exactly it was found
+#[derive(Debug, Clone)]
+pub struct VersionEntry {
+    pub pathdata: PathData,
+    // e.g. /srv/.zfs/snapshot/snap_8a86e4fc_prepApt
+    pub snap_mount: PathBuf,
+    // e.g. snap_8a86e4fc_prepApt
+    pub snap_name: String,
+    // e.g. rpool/srv, or, for user defined aliases, the remote dir
+    pub dataset: String,
+}
+
+// where a snapshot version came from
+#[derive(Debug, Clone)]
+pub struct SnapProvenance {
+    pub live_path: PathBuf,
+    // relative to the mount of the dataset
+    pub relative_path: PathBuf,
+    pub snap_name: String,
+}
+
+// what a lookup did, for those who wish to know
+#[derive(Debug, Clone, Default)]
+pub struct LookupStats {
+    pub datasets_searched: usize,
+    pub snapshots_scanned: usize,
+    pub versions_found: usize,
+    pub unique_versions: usize,
+    // for paths with no versions, the snapshot dirs which httm was not permitted to read
+    pub inaccessible_snap_dirs: Vec<PathBuf>,
+    // for paths with no versions, what the snapdir property of each ZFS dataset suggests was amiss
+    pub snapdir_warnings: Vec<String>,
+}
+
 pub fn versions_lookup_exec(config: &Config, path_set: &[PathData]) -> HttmResult<SnapsAndLiveSet> {
+    let (snaps_and_live_set, _lookup_stats) = versions_lookup_exec_with_stats(config, path_set)?;
+
+    Ok(snaps_and_live_set)
+}
+
+pub fn versions_lookup_exec_with_stats(
+    config: &Config,
+    path_set: &[PathData],
+) -> HttmResult<(SnapsAndLiveSet, LookupStats)> {
+    let mut lookup_stats = LookupStats::default();
+
+    emit_progress(&ProgressEvent::ScanStarted {
+        paths: path_set.len(),
+    });
+
+    // identical versions may exist on both the proximate and alt replicated datasets, show each only once
     let snap_versions: Vec<PathData> = if config.opt_no_snap {
         Vec::new()
     } else {
-        get_all_versions_for_path_set(config, path_set)?
+        config
+            .dataset_collection
+            .resolve_proximate_datasets(path_set);
+
+        let search_bundles_per_path: Vec<Vec<RelativePathAndSnapMounts>> = path_set
+            .par_iter()
+            .map(|pathdata| get_all_search_bundles(config, pathdata))
+            .collect();
+
+        let version_entries_per_path: Vec<Vec<VersionEntry>> = search_bundles_per_path
+            .par_iter()
+            .map(|search_bundles| get_version_entries_from_bundles(config, search_bundles))
+            .collect();
+
+        lookup_stats.datasets_searched = search_bundles_per_path
+            .iter()
+            .flatten()
+            .map(|search_bundle| &search_bundle.dataset_of_interest)
+            .collect::<BTreeSet<&PathBuf>>()
+            .len();
+        lookup_stats.snapshots_scanned = search_bundles_per_path
+            .iter()
+            .flatten()
+            .map(|search_bundle| search_bundle.snap_mounts.len())
+            .sum();
+        lookup_stats.versions_found = version_entries_per_path.iter().map(Vec::len).sum();
+        lookup_stats.inaccessible_snap_dirs = path_set
+            .iter()
+            .zip(search_bundles_per_path.iter())
+            .zip(version_entries_per_path.iter())
+            .filter(|(_, version_entries)| version_entries.is_empty())
+            .flat_map(|((pathdata, search_bundles), _)| {
+                get_inaccessible_snap_dirs(config, pathdata, search_bundles)
+            })
+            .collect::<BTreeSet<PathBuf>>()
+            .into_iter()
+            .collect();
+        lookup_stats.snapdir_warnings = path_set
+            .iter()
+            .zip(search_bundles_per_path.iter())
+            .zip(version_entries_per_path.iter())
+            .filter(|(_, version_entries)| version_entries.is_empty())
+            .filter_map(|((pathdata, search_bundles), _)| {
+                get_snapdir_warning(config, pathdata, search_bundles)
+            })
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect();
+
+        version_entries_per_path
+            .into_par_iter()
+            .zip(path_set.par_iter())
+            .map(|(version_entries, pathdata)| {
+                let version_entries = keep_newest_versions(
+                    config,
+                    omit_ditto_versions(
+                        config,
+                        pathdata,
+                        dedup_across_datasets(config, version_entries),
+                    ),
+                );
+                emit_progress(&ProgressEvent::VersionsFound {
+                    path: &pathdata.path_buf,
+                    versions: version_entries.len(),
+                });
+                version_entries
+            })
+            .flat_map(|version_entries| sort_version_entries(config, version_entries))
+            .map(|version_entry| version_entry.pathdata)
+            .collect()
     };
 
+    lookup_stats.unique_versions = snap_versions.len();
+
+    emit_progress(&ProgressEvent::ScanFinished {
+        versions: lookup_stats.unique_versions,
+    });
+
     // create vec of live copies - unless user doesn't want it!
     let live_versions: Vec<PathData> = if config.opt_no_live {
         Vec::new()
@@ -54,43 +190,548 @@ pub fn versions_lookup_exec(config: &Config, path_set: &[PathData]) -> HttmResul
     if snap_versions.is_empty()
         && live_versions
             .iter()
-            .all(|pathdata| pathdata.metadata.is_none())
+            .all(|pathdata| pathdata.metadata().is_none())
         && !config.opt_no_snap
     {
+        // only a single file requested is the path which failed
+        let opt_path = match path_set {
+            [pathdata] => Some(pathdata.path_buf.clone()),
+            _ => None,
+        };
+
+        // not finding any version is not the same as not being permitted to look
+        if !lookup_stats.inaccessible_snap_dirs.is_empty() {
+            return Err(HttmError::PermissionDenied {
+                msg: get_inaccessible_hint(&lookup_stats.inaccessible_snap_dirs),
+                path: opt_path,
+            });
+        }
+
+        if let Some(snapdir_warning) = lookup_stats.snapdir_warnings.first() {
+            return Err(HttmError::NoVersions {
+                msg: format!(
+                    "httm could not find either a live copy or a snapshot copy of any specified file, as {}",
+                    snapdir_warning
+                ),
+                path: opt_path,
+            });
+        }
+
+        return Err(HttmError::NoVersions {
+            msg: "httm could not find either a live copy or a snapshot copy of any specified file, so, umm, 🤷? Please try another file.".to_owned(),
+            path: opt_path,
+        });
+    }
+
+    Ok(([snap_versions, live_versions], lookup_stats))
+}
+
+// a dataset whose snapshot dir could not be listed at all has no snap mounts, and so no search
+// bundle, otherwise a path may be hidden behind a dir we may not search on each snapshot
+fn get_inaccessible_snap_dirs(
+    config: &Config,
+    pathdata: &PathData,
+    search_bundles: &[RelativePathAndSnapMounts],
+) -> Vec<PathBuf> {
+    let is_denied = |result: std::io::Result<()>| match result {
+        Err(err) => err.kind() == ErrorKind::PermissionDenied,
+        Ok(_) => false,
+    };
+
+    let opt_dataset_snap_dir = config
+        .dataset_collection
+        .get_proximate_dataset(pathdata)
+        .ok()
+        .filter(|mount| config.dataset_collection.map_of_snaps.get(mount).is_none())
+        .and_then(|mount| {
+            let dataset_info = config.dataset_collection.map_of_datasets.get(&mount)?;
+
+            let snap_dir = config
+                .dataset_collection
+                .opt_map_of_snap_dirs
+                .as_ref()
+                .and_then(|map_of_snap_dirs| map_of_snap_dirs.get(&mount))
+                .cloned()
+                .unwrap_or_else(|| match dataset_info.fs_type {
+                    FilesystemType::Zfs => PathBuf::from(ZFS_SNAPSHOT_DIRECTORY),
+                    FilesystemType::Btrfs => PathBuf::from(BTRFS_SNAPPER_HIDDEN_DIRECTORY),
+                });
+
+            Some(mount.join(snap_dir))
+        })
+        .filter(|snap_dir| is_denied(read_dir(snap_dir).map(drop)));
+
+    search_bundles
+        .iter()
+        .flat_map(|search_bundle| {
+            search_bundle
+                .snap_mounts
+                .iter()
+                .filter(|snap_mount| {
+                    is_denied(
+                        snap_mount
+                            .join(&search_bundle.relative_path)
+                            .symlink_metadata()
+                            .map(drop),
+                    )
+                })
+                .cloned()
+        })
+        .chain(opt_dataset_snap_dir)
+        .collect()
+}
+
+// the proximate dataset of each path which has no snapshots at all, as then no lookup
+// could find a version, which is not at all the same as finding no versions
+pub fn get_datasets_without_snaps(config: &Config, path_set: &[PathData]) -> Vec<PathBuf> {
+    path_set
+        .iter()
+        .filter_map(|pathdata| {
+            select_search_datasets(config, pathdata, &SnapDatasetType::MostProximate).ok()
+        })
+        .map(|snap_types_of_interest| snap_types_of_interest.proximate_dataset_mount)
+        .filter(|mount| {
+            config
+                .dataset_collection
+                .map_of_snaps
+                .get(mount)
+                .map(|snap_mounts| snap_mounts.is_empty())
+                .unwrap_or(false)
+        })
+        .collect::<BTreeSet<PathBuf>>()
+        .into_iter()
+        .collect()
+}
+
+pub fn get_inaccessible_hint(inaccessible_snap_dirs: &[PathBuf]) -> String {
+    let dirs_msg = match inaccessible_snap_dirs {
+        [snap_dir] => format!("the snapshot dir {:?}", snap_dir),
+        [first, ..] => format!(
+            "{} snapshot dirs, such as {:?}",
+            inaccessible_snap_dirs.len(),
+            first
+        ),
+        [] => "any snapshot dir".to_owned(),
+    };
+
+    // safe, geteuid() cannot fail and has no side effects
+    let euid = unsafe { libc::geteuid() };
+
+    let advice = if euid == 0 {
+        "httm is already running as root, so check the permissions of the snapshot dirs, \
+        and, for a network share, whether the server squashes root."
+    } else {
+        "Try again as a user permitted to read snapshots, for instance, via sudo."
+    };
+
+    format!(
+        "httm was not permitted to read {}, so versions there could not be found.  {}",
+        dirs_msg, advice
+    )
+}
+
+// the path relative to the mount of each dataset, for those datasets upon which the path
+// exists, either live or on any snapshot, so data which has moved between datasets may be found
+pub fn get_paths_on_all_datasets(
+    dataset_collection: &DatasetCollection,
+    relative_path: &Path,
+) -> HttmResult<Vec<PathData>> {
+    if relative_path.is_absolute() {
         return Err(HttmError::new(
-            "httm could not find either a live copy or a snapshot copy of any specified file, so, umm, 🤷? Please try another file.",
-        )
-        .into());
+            "ALL_DATASETS must be a path relative to the mount of each dataset.",
+        ));
     }
 
-    Ok([snap_versions, live_versions])
+    let paths: Vec<PathData> = dataset_collection
+        .map_of_snaps
+        .par_iter()
+        .filter(|(mount, snap_mounts)| {
+            mount.join(relative_path).symlink_metadata().is_ok()
+                || snap_mounts
+                    .iter()
+                    .any(|snap_mount| snap_mount.join(relative_path).symlink_metadata().is_ok())
+        })
+        .map(|(mount, _snap_mounts)| PathData::from(mount.join(relative_path).as_path()))
+        .collect();
+
+    if paths.is_empty() {
+        return Err(HttmError::NoVersions {
+            msg: format!(
+                "httm could not find {:?} on any dataset, either live or on any snapshot.",
+                relative_path
+            ),
+            path: Some(relative_path.to_path_buf()),
+        });
+    }
+
+    Ok(paths)
 }
 
-fn get_all_versions_for_path_set(
+pub fn get_version_entries_for_path_set(
     config: &Config,
     path_set: &[PathData],
-) -> HttmResult<Vec<PathData>> {
+) -> HttmResult<Vec<VersionEntry>> {
+    config
+        .dataset_collection
+        .resolve_proximate_datasets(path_set);
+
     // create vec of all local and replicated backups at once
-    let all_snap_versions: Vec<PathData> = path_set
+    let all_snap_versions: Vec<VersionEntry> = path_set
         .par_iter()
-        .map(|pathdata| {
-            config
+        .flat_map(|pathdata| get_version_entries(config, pathdata))
+        .collect();
+
+    Ok(all_snap_versions)
+}
+
+// every copy of every unique version of a single path, one copy per dataset searched
+pub fn get_version_entries(config: &Config, pathdata: &PathData) -> Vec<VersionEntry> {
+    get_version_entries_from_bundles(config, &get_all_search_bundles(config, pathdata))
+}
+
+fn get_version_entries_from_bundles(
+    config: &Config,
+    search_bundles: &[RelativePathAndSnapMounts],
+) -> Vec<VersionEntry> {
+    search_bundles
+        .par_iter()
+        .flat_map(|search_bundle| get_versions_per_dataset(config, search_bundle))
+        .flatten()
+        .collect()
+}
+
+// for a relative last snap, we only need the newest snapshot upon which our file differed from
+// the live file.  We assume a file which differs from the live file on one snapshot differs on
+// every snapshot older still, so, over snapshots ordered by creation time, we binary search for
+// the boundary, and only stat some log n versions.  A file changed and then changed back breaks
+// that assumption, so, when exhaustive is requested, we search from the newest snapshot back,
+// and never skip over one.  As elsewhere, a version is the same as the live file when both its
+// modify time and its size are, whatever its mode or owner
+pub fn get_last_snap_relative(
+    config: &Config,
+    live_pathdata: &PathData,
+    is_exhaustive: bool,
+) -> Option<PathData> {
+    let opt_live_metadata = live_pathdata.metadata();
+
+    let differs_from_live = |version: &PathData| match (version.metadata(), opt_live_metadata) {
+        (Some(md), Some(live_md)) => {
+            md.modify_time != live_md.modify_time || md.size != live_md.size
+        }
+        _ => true,
+    };
+
+    get_all_search_bundles(config, live_pathdata)
+        .into_par_iter()
+        .filter_map(|search_bundle| {
+            let opt_dataset_info = config
                 .dataset_collection
-                .snaps_selected_for_search
-                .value()
-                .par_iter()
-                .flat_map(|dataset_type| select_search_datasets(config, pathdata, dataset_type))
-                .flat_map(|dataset_for_search| {
-                    prepare_search_bundles(config, pathdata, &dataset_for_search)
+                .map_of_datasets
+                .get(&search_bundle.dataset_of_interest);
+
+            // newest first, and PathData only stats a version when we ask for its metadata
+            let snap_mounts = sort_snaps_by_creation(opt_dataset_info, search_bundle.snap_mounts);
+            let versions: Vec<PathData> = snap_mounts
+                .iter()
+                .map(|snap_mount| {
+                    PathData::from(snap_mount.join(&search_bundle.relative_path).as_path())
                 })
+                .collect();
+
+            let boundary = if is_exhaustive {
+                versions
+                    .iter()
+                    .position(differs_from_live)
+                    .unwrap_or(versions.len())
+            } else {
+                versions.partition_point(|version| !differs_from_live(version))
+            };
+
+            let snap_times = get_window_snap_times(config, opt_dataset_info, &snap_mounts);
+
+            // snapshots taken before the file existed have no version at all, so skip past those,
+            // and past any newer than a requested window
+            versions
+                .into_iter()
+                .zip(snap_mounts.iter())
+                .skip(boundary)
+                .find(|(version, snap_mount)| {
+                    version.metadata().is_some()
+                        && is_within_window(config, version, snap_times.get(*snap_mount))
+                })
+                .map(|(version, _snap_mount)| version)
+        })
+        .max_by_key(|version| version.md_infallible().modify_time)
+}
+
+pub fn get_all_search_bundles(
+    config: &Config,
+    pathdata: &PathData,
+) -> Vec<RelativePathAndSnapMounts> {
+    config
+        .dataset_collection
+        .snaps_selected_for_search
+        .value()
+        .par_iter()
+        .flat_map(|dataset_type| select_search_datasets(config, pathdata, dataset_type))
+        .flat_map(|dataset_for_search| {
+            prepare_search_bundles(config, pathdata, &dataset_for_search)
         })
         .flatten()
-        .flatten()
-        .flat_map(|search_bundle| get_versions(&search_bundle))
-        .flatten()
+        // external versions come last, so any version also found locally is shown as local
+        .chain(get_external_search_bundles(config, pathdata))
+        .collect()
+}
+
+// an external root mirrors the layout of the live filesystem, so our path on the external root
+// is our path joined to the external root, and, from there, we search like any other dataset
+fn get_external_search_bundles(
+    config: &Config,
+    pathdata: &PathData,
+) -> Vec<RelativePathAndSnapMounts> {
+    config
+        .dataset_collection
+        .vec_of_external_roots
+        .par_iter()
+        .filter_map(|external_root| {
+            let external_path = external_root.join(
+                pathdata
+                    .path_buf
+                    .strip_prefix("/")
+                    .unwrap_or(&pathdata.path_buf),
+            );
+
+            let external_dataset_mount = config
+                .dataset_collection
+                .get_proximate_dataset(&PathData::from(external_path.as_path()))
+                .ok()
+                .filter(|mount| mount.starts_with(external_root))?;
+
+            let relative_path = external_path
+                .strip_prefix(&external_dataset_mount)
+                .ok()?
+                .to_path_buf();
+
+            let snap_mounts = config
+                .dataset_collection
+                .map_of_snaps
+                .get(&external_dataset_mount)?
+                .clone();
+
+            Some(RelativePathAndSnapMounts {
+                relative_path,
+                dataset_of_interest: external_dataset_mount,
+                snap_mounts,
+            })
+        })
+        .collect()
+}
+
+// versions of a file are the same when they share a modify time and a size, or such other key as
+// requested, but a directory's own metadata doesn't change when a child is modified, so versions
+// of a directory are the same when they share a manifest of their children.  when permissions
+// are of interest, versions must also share a mode, an owner and a group
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum UniqueVersionKey {
+    ModifyTimeAndSize(SystemTime, u64, Option<PermsKey>),
+    ModifyTime(SystemTime, Option<PermsKey>),
+    Size(u64, Option<PermsKey>),
+    Hash(String, Option<PermsKey>),
+    Dir(DirManifest, Option<PermsKey>),
+    // every copy is its own version
+    Path(PathBuf),
+}
+
+// mode, uid, gid
+type PermsKey = (u32, u32, u32);
+
+fn get_unique_version_key(config: &Config, pathdata: &PathData) -> Option<UniqueVersionKey> {
+    let metadata = pathdata.metadata()?;
+
+    if config.dedup_versions_by == DedupVersionsBy::Nothing {
+        return Some(UniqueVersionKey::Path(pathdata.path_buf.clone()));
+    }
+
+    let opt_perms = config
+        .opt_perms
+        .then_some((metadata.mode, metadata.uid, metadata.gid));
+
+    if let Some(dir_manifest) = pathdata.dir_manifest() {
+        return Some(UniqueVersionKey::Dir(dir_manifest.clone(), opt_perms));
+    }
+
+    let key = match config.dedup_versions_by {
+        DedupVersionsBy::ModifyTimeAndSize => {
+            UniqueVersionKey::ModifyTimeAndSize(metadata.modify_time, metadata.size, opt_perms)
+        }
+        DedupVersionsBy::ModifyTime => {
+            UniqueVersionKey::ModifyTime(metadata.modify_time, opt_perms)
+        }
+        DedupVersionsBy::Size => UniqueVersionKey::Size(metadata.size, opt_perms),
+        // a file we cannot read is a version we cannot hash
+        DedupVersionsBy::Hash => {
+            UniqueVersionKey::Hash(pathdata.comparison_hash()?.to_owned(), opt_perms)
+        }
+        DedupVersionsBy::Nothing => unreachable!(),
+    };
+
+    Some(key)
+}
+
+fn dedup_across_datasets(config: &Config, version_entries: Vec<VersionEntry>) -> Vec<VersionEntry> {
+    // keep the first copy of each version, so our order, alt replicated first, is unchanged
+    let mut seen: BTreeSet<UniqueVersionKey> = BTreeSet::new();
+
+    version_entries
+        .into_iter()
+        .filter(|version_entry| {
+            get_unique_version_key(config, &version_entry.pathdata)
+                .map(|key| seen.insert(key))
+                .unwrap_or(true)
+        })
+        .collect()
+}
+
+// a version the same as the live version, by the key we dedup versions by, is no different
+// from the file at hand, though, when every copy is its own version, we compare modify time and size
+fn omit_ditto_versions(
+    config: &Config,
+    live_pathdata: &PathData,
+    version_entries: Vec<VersionEntry>,
+) -> Vec<VersionEntry> {
+    if !config.opt_omit_ditto {
+        return version_entries;
+    }
+
+    let live_metadata = match live_pathdata.metadata() {
+        Some(live_metadata) => live_metadata,
+        None => return version_entries,
+    };
+
+    let opt_live_key = get_unique_version_key(config, live_pathdata);
+
+    version_entries
+        .into_iter()
+        .filter(|version_entry| {
+            if config.dedup_versions_by == DedupVersionsBy::Nothing {
+                let metadata = version_entry.pathdata.md_infallible();
+                return metadata.modify_time != live_metadata.modify_time
+                    || metadata.size != live_metadata.size;
+            }
+
+            opt_live_key.is_none()
+                || get_unique_version_key(config, &version_entry.pathdata) != opt_live_key
+        })
+        .collect()
+}
+
+// versions may be sorted by some other key, but the newest are always those newest by modify time
+fn keep_newest_versions(
+    config: &Config,
+    mut version_entries: Vec<VersionEntry>,
+) -> Vec<VersionEntry> {
+    if let Some(last_n) = config.opt_last_n {
+        if version_entries.len() > last_n {
+            version_entries.sort_by_key(|version_entry| {
+                Reverse(version_entry.pathdata.md_infallible().modify_time)
+            });
+            version_entries.truncate(last_n);
+        }
+    }
+
+    version_entries
+}
+
+// versions are ordered by the requested key, and ties are broken by the remaining keys,
+// so that the order of versions never differs from run to run
+fn sort_version_entries(
+    config: &Config,
+    mut version_entries: Vec<VersionEntry>,
+) -> Vec<VersionEntry> {
+    let primary_key = config.sort_versions_by;
+
+    let sort_keys: Vec<SortVersionsBy> = std::iter::once(primary_key)
+        .chain(
+            [
+                SortVersionsBy::ModifyTime,
+                SortVersionsBy::Size,
+                SortVersionsBy::SnapCreation,
+                SortVersionsBy::Path,
+            ]
+            .into_iter()
+            .filter(|key| key != &primary_key),
+        )
         .collect();
 
-    Ok(all_snap_versions)
+    // snapshot creation times may require a call out to zfs, so only get them when they can matter,
+    // and they can't matter when no two versions share a modify time and a size
+    let has_ties = {
+        let mut seen: BTreeSet<(SystemTime, u64)> = BTreeSet::new();
+        !version_entries.iter().all(|version_entry| {
+            let metadata = version_entry.pathdata.md_infallible();
+            seen.insert((metadata.modify_time, metadata.size))
+        })
+    };
+
+    let creation_times: BTreeMap<PathBuf, SystemTime> =
+        if primary_key == SortVersionsBy::SnapCreation || has_ties {
+            get_creation_times_for_entries(config, &version_entries)
+        } else {
+            BTreeMap::new()
+        };
+
+    version_entries.sort_by(|a, b| {
+        sort_keys.iter().fold(Ordering::Equal, |ordering, key| {
+            ordering.then_with(|| match key {
+                SortVersionsBy::ModifyTime => a
+                    .pathdata
+                    .md_infallible()
+                    .modify_time
+                    .cmp(&b.pathdata.md_infallible().modify_time),
+                SortVersionsBy::Size => a
+                    .pathdata
+                    .md_infallible()
+                    .size
+                    .cmp(&b.pathdata.md_infallible().size),
+                SortVersionsBy::SnapCreation => creation_times
+                    .get(&a.snap_mount)
+                    .cmp(&creation_times.get(&b.snap_mount)),
+                SortVersionsBy::Path => a.pathdata.path_buf.cmp(&b.pathdata.path_buf),
+            })
+        })
+    });
+
+    version_entries
+}
+
+pub fn get_creation_times_for_entries(
+    config: &Config,
+    version_entries: &[VersionEntry],
+) -> BTreeMap<PathBuf, SystemTime> {
+    // key: dataset name, val: snap mounts of the versions found on that dataset
+    let snap_mounts_per_dataset: BTreeMap<&str, Vec<PathBuf>> =
+        version_entries
+            .iter()
+            .fold(BTreeMap::new(), |mut map, version_entry| {
+                map.entry(version_entry.dataset.as_str())
+                    .or_insert_with(Vec::new)
+                    .push(version_entry.snap_mount.clone());
+                map
+            });
+
+    snap_mounts_per_dataset
+        .into_iter()
+        .flat_map(|(dataset, snap_mounts)| {
+            // a user defined alias won't be in our map of datasets, and that's fine
+            let opt_dataset_info = config
+                .dataset_collection
+                .map_of_datasets
+                .values()
+                .find(|dataset_info| dataset_info.name == dataset);
+
+            get_snap_creation_times(opt_dataset_info, &snap_mounts)
+        })
+        .collect()
 }
 
 pub fn select_search_datasets(
@@ -114,9 +755,9 @@ pub fn select_search_datasets(
     let proximate_dataset_mount = match &config.dataset_collection.opt_map_of_aliases {
         Some(map_of_aliases) => match get_alias_dataset(pathdata, map_of_aliases) {
             Some(alias_snap_dir) => alias_snap_dir,
-            None => get_proximate_dataset(pathdata, &config.dataset_collection.map_of_datasets)?,
+            None => config.dataset_collection.get_proximate_dataset(pathdata)?,
         },
-        None => get_proximate_dataset(pathdata, &config.dataset_collection.map_of_datasets)?,
+        None => config.dataset_collection.get_proximate_dataset(pathdata)?,
     };
 
     let snap_types_for_search: MostProximateAndOptAlts = match requested_dataset_type {
@@ -131,7 +772,7 @@ pub fn select_search_datasets(
             Some(map_of_alts) => match map_of_alts.get(proximate_dataset_mount.as_path()) {
                 Some(snap_types_for_search) => snap_types_for_search.clone(),
                 None => return Err(HttmError::new("If you are here a map of alts is missing for a supplied mount, \
-                this is fine as we should just flatten/ignore this error.").into()),
+                this is fine as we should just flatten/ignore this error.")),
             },
             None => unreachable!("If config option alt-replicated is specified, then a map of alts should have been generated, \
             if you are here such a map is missing."),
@@ -200,6 +841,7 @@ fn get_search_bundle_for_dataset(
 
     Ok(RelativePathAndSnapMounts {
         relative_path,
+        dataset_of_interest: dataset_of_interest.to_path_buf(),
         snap_mounts,
     })
 }
@@ -215,7 +857,7 @@ fn get_relative_path(
     ) -> HttmResult<PathBuf> {
         pathdata
             .path_buf
-            .strip_prefix(&proximate_dataset_mount)
+            .strip_prefix(proximate_dataset_mount)
             .map(|path| path.to_path_buf())
             .map_err(|err| err.into())
     }
@@ -236,7 +878,7 @@ fn get_relative_path(
             // fallback if unable to find an alias or strip a prefix
             // (each an indication we should not be trying aliases)
             match opt_aliased_local_dir {
-                Some(local_dir) => match pathdata.path_buf.strip_prefix(&local_dir) {
+                Some(local_dir) => match pathdata.path_buf.strip_prefix(local_dir) {
                     Ok(alias_stripped_path) => Ok(alias_stripped_path.to_path_buf()),
                     Err(_) => default_path_strip(pathdata, proximate_dataset_mount),
                 },
@@ -257,51 +899,234 @@ fn get_alias_dataset(pathdata: &PathData, map_of_alias: &MapOfAliases) -> Option
     })
 }
 
-fn get_proximate_dataset(
-    pathdata: &PathData,
-    map_of_datasets: &MapOfDatasets,
-) -> HttmResult<PathBuf> {
-    // for /usr/bin, we prefer the most proximate: /usr/bin to /usr and /
-    // ancestors() iterates in this top-down order, when a value: dataset/fstype is available
-    // we map to return the key, instead of the value
-    pathdata
-        .path_buf
-        .ancestors()
-        .find_map(|ancestor| {
-            if map_of_datasets.contains_key(ancestor) {
-                Some(ancestor)
-            } else {
-                None
-            }
-        })
-        .map(|path| path.to_path_buf())
-        .ok_or_else(|| {
-            HttmError::new(
-                "httm could not identify any qualifying dataset.  \
-                Maybe consider specifying manually at SNAP_POINT?",
-            )
-            .into()
-        })
-}
+fn get_versions_per_dataset(
+    config: &Config,
+    search_bundle: &RelativePathAndSnapMounts,
+) -> HttmResult<Vec<VersionEntry>> {
+    let opt_dataset_info = config
+        .dataset_collection
+        .map_of_datasets
+        .get(&search_bundle.dataset_of_interest);
+
+    // a user defined alias won't be in our map of datasets, so just use its remote dir
+    let dataset = match opt_dataset_info {
+        Some(dataset_info) => dataset_info.name.clone(),
+        None => search_bundle
+            .dataset_of_interest
+            .to_string_lossy()
+            .into_owned(),
+    };
+
+    let snap_times = get_window_snap_times(config, opt_dataset_info, &search_bundle.snap_mounts);
 
-fn get_versions(search_bundle: &RelativePathAndSnapMounts) -> HttmResult<Vec<PathData>> {
     // get the DirEntry for our snapshot path which will have all our possible
     // snapshots, like so: .zfs/snapshots/<some snap name>/
     //
-    // BTreeMap will then remove duplicates with the same system modify time and size/file len
-    let unique_versions: BTreeMap<(SystemTime, u64), PathData> = search_bundle
+    // BTreeMap will then remove duplicates with the same unique version key, by default the same
+    // system modify time and size/file len, or, for a directory, with the same manifest
+    let unique_versions: BTreeMap<UniqueVersionKey, VersionEntry> = search_bundle
         .snap_mounts
         .par_iter()
-        .map(|path| path.join(&search_bundle.relative_path))
-        .map(|joined_path| PathData::from(joined_path.as_path()))
-        .filter_map(|pathdata| {
-            pathdata
-                .metadata
-                .map(|metadata| ((metadata.modify_time, metadata.size), pathdata))
+        .map(|snap_mount| {
+            let joined_path = snap_mount.join(&search_bundle.relative_path);
+            (snap_mount, PathData::from(joined_path.as_path()))
+        })
+        .filter(|(_snap_mount, pathdata)| is_owned(&pathdata.path_buf))
+        .filter(|(_snap_mount, pathdata)| is_owned_by(&pathdata.path_buf, config.opt_owner))
+        .filter(|(snap_mount, pathdata)| {
+            is_within_window(config, pathdata, snap_times.get(*snap_mount))
+        })
+        .filter_map(|(snap_mount, pathdata)| {
+            get_unique_version_key(config, &pathdata).map(|key| {
+                let version_entry = VersionEntry {
+                    pathdata,
+                    snap_mount: snap_mount.clone(),
+                    snap_name: get_snap_name(snap_mount),
+                    dataset: dataset.clone(),
+                };
+                (key, version_entry)
+            })
         })
         .collect();
 
-    let sorted_versions: Vec<PathData> = unique_versions.into_values().collect();
+    let sorted_versions: Vec<VersionEntry> = unique_versions.into_values().collect();
+
+    emit_progress(&ProgressEvent::DatasetSearched {
+        dataset: &dataset,
+        relative_path: &search_bundle.relative_path,
+        snapshots: search_bundle.snap_mounts.len(),
+        versions: sorted_versions.len(),
+    });
 
     Ok(sorted_versions)
 }
+
+// like the owner filter, always true when no window is requested
+fn is_within_window(
+    config: &Config,
+    pathdata: &PathData,
+    opt_snap_time: Option<&SystemTime>,
+) -> bool {
+    if config.opt_after.is_none() && config.opt_before.is_none() {
+        return true;
+    }
+
+    let opt_time = match config.version_time_by {
+        VersionTimeBy::FileTime => pathdata.metadata().map(|md| md.modify_time),
+        VersionTimeBy::SnapTime => opt_snap_time.copied(),
+    };
+
+    match opt_time {
+        Some(time) => {
+            !matches!(config.opt_after, Some(after) if time < after)
+                && !matches!(config.opt_before, Some(before) if time >= before)
+        }
+        None => false,
+    }
+}
+
+// creation times may require a call out to zfs, so only get them when a window asks for them
+fn get_window_snap_times(
+    config: &Config,
+    opt_dataset_info: Option<&DatasetMetadata>,
+    snap_mounts: &[PathBuf],
+) -> BTreeMap<PathBuf, SystemTime> {
+    let is_window = config.opt_after.is_some() || config.opt_before.is_some();
+
+    if !is_window || config.version_time_by != VersionTimeBy::SnapTime {
+        return BTreeMap::new();
+    }
+
+    get_snap_creation_times(opt_dataset_info, snap_mounts)
+}
+
+// a snap path is just a snap mount joined to a relative path, so we find which of our live paths,
+// once joined to one of its snap mounts, is the snap path
+pub fn get_snap_provenance(
+    config: &Config,
+    snap_path: &Path,
+    live_paths: &[PathData],
+) -> Option<SnapProvenance> {
+    live_paths.iter().find_map(|live_pathdata| {
+        get_all_search_bundles(config, live_pathdata)
+            .into_iter()
+            .find_map(|search_bundle| {
+                search_bundle
+                    .snap_mounts
+                    .iter()
+                    .find(|snap_mount| snap_mount.join(&search_bundle.relative_path) == snap_path)
+                    .map(|snap_mount| SnapProvenance {
+                        live_path: live_pathdata.path_buf.clone(),
+                        relative_path: search_bundle.relative_path.clone(),
+                        snap_name: get_snap_name(snap_mount),
+                    })
+            })
+    })
+}
+
+// a snap path is one of the snap mounts of the dataset upon which it resides, joined to the
+// relative path of the live file, so we find that snap mount and join the dataset's mount to
+// the rest, None should the path not live upon a snapshot which we know
+pub fn get_live_path(config: &Config, snap_pathdata: &PathData) -> Option<PathBuf> {
+    let proximate_dataset_mount = config
+        .dataset_collection
+        .get_proximate_dataset(snap_pathdata)
+        .ok()?;
+
+    config
+        .dataset_collection
+        .map_of_snaps
+        .get(&proximate_dataset_mount)?
+        .iter()
+        .find_map(|snap_mount| snap_pathdata.path_buf.strip_prefix(snap_mount).ok())
+        .map(|relative_path| proximate_dataset_mount.join(relative_path))
+}
+
+// when every snapshot's copy is its own version, which snapshot holds each copy is the point,
+// so we name the snapshot of each snap path found by searching for our live paths
+pub fn get_snap_names(config: &Config, live_paths: &[PathData]) -> HashMap<PathBuf, String> {
+    live_paths
+        .par_iter()
+        .flat_map(|live_pathdata| get_all_search_bundles(config, live_pathdata))
+        .flat_map(|search_bundle| {
+            search_bundle
+                .snap_mounts
+                .iter()
+                .map(|snap_mount| {
+                    (
+                        snap_mount.join(&search_bundle.relative_path),
+                        get_snap_name(snap_mount),
+                    )
+                })
+                .collect::<Vec<(PathBuf, String)>>()
+        })
+        .collect()
+}
+
+// a version's modify time may be far older than the snapshot which captured it, so, for those
+// who would tell the two apart, the creation time of the snapshot of each snap path
+pub fn get_snap_times(config: &Config, live_paths: &[PathData]) -> HashMap<PathBuf, SystemTime> {
+    live_paths
+        .par_iter()
+        .flat_map(|live_pathdata| get_all_search_bundles(config, live_pathdata))
+        .flat_map(|search_bundle| {
+            let opt_dataset_info = config
+                .dataset_collection
+                .map_of_datasets
+                .get(&search_bundle.dataset_of_interest);
+
+            get_snap_creation_times(opt_dataset_info, &search_bundle.snap_mounts)
+                .into_iter()
+                .map(|(snap_mount, creation_time)| {
+                    (snap_mount.join(&search_bundle.relative_path), creation_time)
+                })
+                .collect::<Vec<(PathBuf, SystemTime)>>()
+        })
+        .collect()
+}
+
+// as get_snap_names, but also with the dataset of each snap path, by the dataset's name, or, for a
+// user defined alias, which won't be in our map of datasets, by its remote dir
+pub fn get_snap_sources(
+    config: &Config,
+    live_paths: &[PathData],
+) -> HashMap<PathBuf, (String, String)> {
+    live_paths
+        .par_iter()
+        .flat_map(|live_pathdata| get_all_search_bundles(config, live_pathdata))
+        .flat_map(|search_bundle| {
+            let dataset = get_dataset_name(config, &search_bundle.dataset_of_interest);
+
+            search_bundle
+                .snap_mounts
+                .iter()
+                .map(|snap_mount| {
+                    (
+                        snap_mount.join(&search_bundle.relative_path),
+                        (get_snap_name(snap_mount), dataset.clone()),
+                    )
+                })
+                .collect::<Vec<(PathBuf, (String, String))>>()
+        })
+        .collect()
+}
+
+pub fn get_dataset_name(config: &Config, dataset_mount: &Path) -> String {
+    match config.dataset_collection.map_of_datasets.get(dataset_mount) {
+        Some(dataset_info) => dataset_info.name.clone(),
+        None => dataset_mount.to_string_lossy().into_owned(),
+    }
+}
+
+fn get_snap_name(snap_mount: &Path) -> String {
+    // btrfs snapper snaps look like .snapshots/<number>/snapshot, so the name is the number
+    let snap_name = if snap_mount.file_name() == Some(OsStr::new(BTRFS_SNAPPER_SUFFIX)) {
+        snap_mount.parent().and_then(|parent| parent.file_name())
+    } else {
+        snap_mount.file_name()
+    };
+
+    snap_name
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| snap_mount.to_string_lossy().into_owned())
+}
@@ -21,49 +21,143 @@ extern crate proc_mounts;
 
 use std::{
     collections::BTreeMap,
+    ffi::CString,
+    fmt,
     fs::canonicalize,
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
 
-// wrap this complex looking error type, which is used everywhere,
-// into something more simple looking. This error, FYI, is really easy to use with rayon.
-pub type HttmResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+// our one error type, used everywhere, see HttmError
+pub type HttmResult<T> = Result<T, HttmError>;
 
 use clap::{crate_name, crate_version, Arg, ArgMatches};
 use indicatif::ProgressBar;
 use rayon::prelude::*;
 use time::UtcOffset;
 
+mod add_alias;
+mod backup_backends;
+mod bisect;
+mod bulk_restore;
+mod check_config;
+mod clipboard;
 mod display;
+mod doctor;
+mod extract;
+mod follow;
+mod forensic;
+mod git_export;
+mod git_xref;
+mod gui_helper;
+mod hash_cache;
+mod hashing;
 mod install_hot_keys;
 mod interactive;
+mod line_diff;
+mod list_maps;
+mod lookup_blame;
 mod lookup_deleted;
+mod lookup_dir_summary;
 mod lookup_file_mounts;
 mod lookup_versions;
+mod open_files;
 mod parse_aliases;
 mod parse_alts;
+mod parse_containers;
 mod parse_mounts;
+mod parse_overlays;
 mod parse_snaps;
+mod patch_export;
+mod pins;
+mod pool_import;
+mod privileged_helper;
+mod progress;
+mod proximate_dataset;
+mod query_hosts;
 mod recursive;
+mod restore_attributes;
+mod restrict_owner;
+mod sandbox;
+mod send_stream;
+mod serve_dbus;
+mod serve_http;
+mod serve_stdio;
+mod set_aside;
+mod shell_helpers;
+mod smb_versions;
+mod snapdir;
 mod snapshot_ops;
+mod triage;
+mod undelete;
 mod utility;
+mod workspace;
 
-use crate::display::{display_exec, display_mounts_for_files};
+use crate::add_alias::add_alias;
+use crate::backup_backends::{backup_exec, BackupQuery};
+use crate::bisect::bisect_exec;
+use crate::check_config::check_config;
+use crate::display::{
+    display_blame, display_custody_header, display_dir_summary, display_exec, display_git_xref,
+    display_mounts_for_files, display_num_versions, display_redundancy, display_stats,
+    display_version_matrix, DEFAULT_DIR_SIZE_MAX_ENTRIES,
+};
+use crate::doctor::doctor;
+use crate::extract::extract_exec;
+use crate::follow::{follow_exec, DEFAULT_FOLLOW_INTERVAL};
+use crate::forensic::{init_forensic, is_forensic};
+use crate::git_export::export_to_git;
+use crate::gui_helper::gui_helper_exec;
+use crate::hash_cache::{disable_hash_cache, exec_hash_cache};
+use crate::hashing::{
+    init_hash_io_budget, init_hash_policy, parse_byte_rate, HashAlgorithm, HashIoBudget, HashPolicy,
+};
 use crate::install_hot_keys::install_hot_keys;
 use crate::interactive::interactive_exec;
-use crate::lookup_versions::versions_lookup_exec;
-use crate::parse_aliases::parse_aliases;
+use crate::list_maps::{list_aliases_exec, list_datasets_exec};
+use crate::lookup_deleted::DEFAULT_DELETED_EXCLUDES;
+use crate::lookup_versions::{
+    get_datasets_without_snaps, get_inaccessible_hint, get_paths_on_all_datasets,
+    versions_lookup_exec_with_stats, LookupStats,
+};
+use crate::parse_aliases::{get_aliases_file_path, parse_aliases, read_aliases_file};
 use crate::parse_alts::precompute_alt_replicated;
+use crate::parse_containers::{get_host_path, parse_container_mounts};
 use crate::parse_mounts::{get_common_snap_dir, parse_mounts_exec};
+use crate::parse_overlays::{get_backing_path, parse_overlay_mounts};
+use crate::parse_snaps::{parse_snap_dirs, parse_snap_dirs_from_env, LazyMapOfSnaps};
+use crate::patch_export::patch_exec;
+use crate::pins::{pin_paths, select_pinned_path, select_root_path};
+use crate::pool_import::import_pool_exec;
+use crate::privileged_helper::privileged_helper_exec;
+use crate::progress::{init_progress_json, DEFAULT_PROGRESS_FD};
+use crate::proximate_dataset::ProximateDatasetCache;
+use crate::query_hosts::{query_hosts_exec, HostsQuery};
 use crate::recursive::display_recursive_wrapper;
+use crate::restrict_owner::{
+    deny_if_restricted_to_owner, deny_unless_owned, get_uid_for_user, init_restrict_to_owner,
+};
+use crate::send_stream::{send_stream_exec, SendStreamQuery};
+use crate::serve_dbus::serve_dbus;
+use crate::serve_http::{serve_http, HttpServeOptions, DEFAULT_HTTP_LISTEN};
+use crate::serve_stdio::serve_stdio;
+use crate::shell_helpers::print_shell_helpers;
+use crate::smb_versions::smb_versions_exec;
+use crate::snapdir::enable_snapdir_exec;
 use crate::snapshot_ops::take_snapshot;
-use crate::utility::{httm_is_dir, print_output_buf, read_stdin, HttmError, PathData};
+use crate::triage::{triage_exec, DEFAULT_SIZE_COLLAPSE_PERCENT};
+use crate::undelete::undelete_exec;
+use crate::utility::{
+    httm_is_dir, parse_date_bound, print_output_buf, read_stdin, HttmError, PathData,
+};
+use crate::workspace::{deny_unless_empty_workspace, WorkspaceDir};
 
 pub const ZFS_HIDDEN_DIRECTORY: &str = ".zfs";
 pub const ZFS_SNAPSHOT_DIRECTORY: &str = ".zfs/snapshot";
 pub const BTRFS_SNAPPER_HIDDEN_DIRECTORY: &str = ".snapshots";
 pub const BTRFS_SNAPPER_SUFFIX: &str = "snapshot";
+pub const PICKER_ABORT_EXIT_CODE: i32 = 2;
 
 #[derive(Debug, Clone)]
 enum ExecMode {
@@ -72,12 +166,60 @@ enum ExecMode {
     Display,
     SnapFileMount,
     MountsForFiles,
+    DirSummary,
+    ServeStdio,
+    ServeDbus(DbusBus),
+    GuiHelper(PathBuf),
+    SmbVersions(PathBuf),
+    ServeHttp(HttpServeOptions),
+    QueryHosts(HostsQuery),
+    SendStream(SendStreamQuery),
+    Backup(BackupQuery),
+    ListAliases(ListFormat),
+    ListDatasets(ListFormat),
+    Bisect(Option<String>),
+    Blame,
+    NumVersions(NumVersionsFormat),
+    ToGit(PathBuf),
+    Follow(Duration),
+    Undelete,
+    Triage(TriageMode),
+    Patch(PathBuf, PathBuf),
+    Extract(ExtractRequest),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumVersionsFormat {
+    Count,
+    WithDates,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbusBus {
+    Session,
+    System,
+}
+
+impl fmt::Display for DbusBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbusBus::Session => write!(f, "session"),
+            DbusBus::System => write!(f, "system"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RequestRelative {
     Absolute,
     Relative,
+    RelativeExhaustive,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -96,18 +238,99 @@ enum DeletedMode {
     Only,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortVersionsBy {
+    ModifyTime,
+    Size,
+    SnapCreation,
+    Path,
+}
+
+// "versions captured last week" and "versions modified last week" are different questions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionTimeBy {
+    FileTime,
+    SnapTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupVersionsBy {
+    ModifyTimeAndSize,
+    ModifyTime,
+    Size,
+    Hash,
+    Nothing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintSelected {
+    SnapPath,
+    LivePath,
+    RelativePath,
+    SnapName,
+}
+
+// only part of a version is wanted, a range of its bytes, or, for an archive, some of its members
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractRequest {
+    ByteRange { start: u64, len: u64 },
+    Members(Vec<String>),
+}
+
+// how a triage scan decides a live file looks to have been damaged since its newest snapshot version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriageMode {
+    // live size is this percent, or less, of the newest snapshot version's size
+    SizeCollapse(u64),
+    // live contents look random, where the newest snapshot version's did not
+    Encryption,
+}
+
+// what becomes of the live file which a restore in place would otherwise overwrite
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClobberedLive {
+    Overwrite,
+    MoveAside,
+    MoveInto(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum OpenFilesPolicy {
+    Warn,
+    Refuse,
+    Ignore,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum FilesystemType {
     Zfs,
     Btrfs,
 }
 
+impl fmt::Display for FilesystemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilesystemType::Zfs => write!(f, "ZFS"),
+            FilesystemType::Btrfs => write!(f, "btrfs"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 enum MountType {
     Local,
     Network,
 }
 
+impl fmt::Display for MountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MountType::Local => write!(f, "local"),
+            MountType::Network => write!(f, "network"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DatasetMetadata {
     name: String,
@@ -168,30 +391,50 @@ pub type MapOfDatasets = BTreeMap<PathBuf, DatasetMetadata>;
 pub type MapOfSnaps = BTreeMap<PathBuf, VecOfSnaps>;
 pub type MapOfAlts = BTreeMap<PathBuf, MostProximateAndOptAlts>;
 pub type MapOfAliases = BTreeMap<PathBuf, RemotePathAndFsType>;
+pub type MapOfSnapDirs = BTreeMap<PathBuf, PathBuf>;
 pub type BtrfsCommonSnapDir = PathBuf;
 pub type VecOfFilterDirs = Vec<PathBuf>;
+pub type VecOfExternalRoots = Vec<PathBuf>;
 pub type VecOfSnaps = Vec<PathBuf>;
 pub type SnapsAndLiveSet = [Vec<PathData>; 2];
 pub type OptMapOfAlts = Option<MapOfAlts>;
 pub type OptMapOfAliases = Option<MapOfAliases>;
+pub type OptMapOfSnapDirs = Option<MapOfSnapDirs>;
 pub type OptBtrfsCommonSnapDir = Option<BtrfsCommonSnapDir>;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct DatasetCollection {
     // key: mount, val: (dataset/subvol, fs_type, mount_type)
     map_of_datasets: MapOfDatasets,
     // key: mount, val: vec snap locations on disk (e.g. /.zfs/snapshot/snap_8a86e4fc_prepApt/home)
-    map_of_snaps: MapOfSnaps,
+    map_of_snaps: LazyMapOfSnaps,
     // key: mount, val: alt dataset
     opt_map_of_alts: OptMapOfAlts,
     // key: local dir, val: (remote dir, fstype)
     opt_map_of_aliases: OptMapOfAliases,
+    // key: mount, val: user defined snap dir, relative to the mount
+    opt_map_of_snap_dirs: OptMapOfSnapDirs,
     // vec dirs to be filtered
     vec_of_filter_dirs: VecOfFilterDirs,
+    // vec of user defined external roots, such as a backup disk, which are presently mounted
+    vec_of_external_roots: VecOfExternalRoots,
     // opt single dir to to be filtered re: btrfs common snap dir
     opt_common_snap_dir: OptBtrfsCommonSnapDir,
     // vec of two enum variants - most proximate and alt replicated, or just most proximate
     snaps_selected_for_search: SnapsSelectedForSearch,
+    // the proximate dataset mount of each parent dir, as already found
+    proximate_dataset_cache: ProximateDatasetCache,
+}
+
+impl DatasetCollection {
+    pub fn get_proximate_dataset(&self, pathdata: &PathData) -> HttmResult<PathBuf> {
+        self.proximate_dataset_cache
+            .get_proximate_dataset(pathdata, &self.map_of_datasets)
+    }
+
+    pub fn resolve_proximate_datasets(&self, path_set: &[PathData]) {
+        self.proximate_dataset_cache.resolve_path_set(path_set)
+    }
 }
 
 fn parse_args() -> ArgMatches {
@@ -231,28 +474,82 @@ fn parse_args() -> ArgMatches {
                 .long("restore")
                 .takes_value(true)
                 .default_missing_value("copy")
-                .possible_values(&["copy", "overwrite", "yolo"])
+                .possible_values(["copy", "overwrite", "yolo"])
                 .min_values(0)
                 .require_equals(true)
                 .help("interactive browse and search a specified directory to display unique file versions.  Continue to another dialog to select a snapshot version to restore.  \
-                Default is a non-destructive \"copy\" to the current working directory with a new name, so as not to overwrite any \"live\" file version.  However, user may specify \"overwrite\" to restore to the same file location.")
+                Default is a non-destructive \"copy\" to the current working directory with a new name, so as not to overwrite any \"live\" file version.  However, user may specify \"overwrite\" to restore to the same file location.  \
+                In \"overwrite\" mode, the live file is first moved aside, beside itself, with a new name, or into the dir given with \"--set-aside-dir\".  \
+                User may specify \"yolo\" to restore to the same file location, and keep nothing of the live file.  \
+                Either way, a restored file keeps the access and modify times and the mode bits of its version, and, when httm is run as root, its owner and group.")
                 .conflicts_with("SELECT")
                 .display_order(4)
         )
+        .arg(
+            Arg::new("SET_ASIDE_DIR")
+                .long("set-aside-dir")
+                .takes_value(true)
+                .value_name("DIR")
+                .allow_invalid_utf8(true)
+                .requires("RESTORE")
+                .help("when restoring with \"--restore=overwrite\", move the live file into DIR, rather than beside itself, before it is restored over.  \
+                The live file is given a new name, with its own modify time, just as a restored copy is given the modify time of its version.")
+                .display_order(4)
+        )
+        .arg(
+            Arg::new("OPEN_FILES")
+                .long("open-files")
+                .takes_value(true)
+                .default_missing_value("refuse")
+                .possible_values(["warn", "refuse", "ignore"])
+                .min_values(0)
+                .require_equals(true)
+                .help("before restoring, check whether the file to be restored over is currently open, or memory mapped, by any process, \
+                such as a database which may be left inconsistent by a restore.  SQLite journal and write ahead log files are checked too.  \
+                Default is to \"warn\" before asking for consent to restore, but user may also \"refuse\" to restore such a file, or \"ignore\" such files entirely.  \
+                Note: httm can only see the open files of other users' processes when run as root.")
+                .display_order(5)
+        )
         .arg(
             Arg::new("DELETED_MODE")
                 .short('d')
                 .long("deleted")
                 .takes_value(true)
                 .default_missing_value("all")
-                .possible_values(&["all", "single", "only"])
+                .possible_values(["all", "single", "only"])
                 .min_values(0)
                 .require_equals(true)
                 .help("show deleted files in interactive modes.  In non-interactive modes, do a search for all files deleted from a specified directory. \
                 If \"--deleted only\" is specified, then, in interactive modes, non-deleted files will be excluded from the search. \
                 If \"--deleted single\" is specified, then, deleted files behind deleted directories, \
                 (files with a depth greater than one) will be ignored.")
-                .display_order(5)
+                .display_order(6)
+        )
+        .arg(
+            Arg::new("DELETED_RECREATED")
+                .long("deleted-recreated")
+                .help("in deleted file searches, also count as deleted a file which was deleted, and then re-created by the same name, with different contents.  \
+                Such a snapshot version is known to be of a different file than the live file, because its inode number or birth time differs.  \
+                Beware, an editor which saves by replacing a file, rather than by rewriting it, makes each edited file appear to have been re-created.")
+                .display_order(7)
+        )
+        .arg(
+            Arg::new("DELETED_EXCLUDE")
+                .long("deleted-exclude")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .value_name("GLOB")
+                .help("omit deleted files, and deleted directories, whose file names match the shell glob specified, as in \"--deleted-exclude='*.bak'\", from deleted file searches.  \
+                May be specified more than once.  Editor swap and backup files, temporary files, and the usual clutter, like \"*.swp\", \"*~\", \"*.tmp\" and \".DS_Store\", \
+                are always omitted, unless \"--no-deleted-defaults\" is specified.")
+                .display_order(8)
+        )
+        .arg(
+            Arg::new("NO_DELETED_DEFAULTS")
+                .long("no-deleted-defaults")
+                .help("do not omit editor swap and backup files, temporary files, and the usual clutter from deleted file searches.  \
+                Only those globs specified via \"--deleted-exclude\", if any, are omitted.")
+                .display_order(9)
         )
         .arg(
             Arg::new("ALT_REPLICATED")
@@ -261,23 +558,35 @@ fn parse_args() -> ArgMatches {
                 .help("automatically discover locally replicated datasets and list their snapshots as well.  \
                 NOTE: Be certain such replicated datasets are mounted before use.  \
                 httm will silently ignore unmounted datasets in the interactive modes.")
-                .conflicts_with_all(&["SNAP_POINT", "LOCAL_DIR"])
-                .display_order(6)
+                .conflicts_with_all(&["REMOTE_DIR", "LOCAL_DIR"])
+                .display_order(10)
+        )
+        .arg(
+            Arg::new("ALL_DATASETS")
+                .long("all-datasets")
+                .takes_value(true)
+                .value_name("RELATIVE_PATH")
+                .allow_invalid_utf8(true)
+                .help("search every dataset detected for the path specified, relative to the mount of each dataset, and display the versions found, live and on snapshots.  \
+                A bare file name is searched for at the mount of each dataset.  Useful for finding data which has moved between datasets over the years.")
+                .conflicts_with_all(&["INPUT_FILES", "PINS", "PICK_ROOT", "INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "RECURSIVE", "DELETED_MODE", "SNAP_FILE_MOUNT", "SERVE_STDIO", "CONTAINER"])
+                .display_order(11)
         )
         .arg(
             Arg::new("RECURSIVE")
                 .short('R')
                 .long("recursive")
                 .conflicts_with_all(&["SNAP_FILE_MOUNT"])
-                .help("recurse into the selected directory to find more files. Only available in interactive and deleted file modes.")
-                .display_order(7)
+                .help("recurse into the selected directory to find more files. Only available in interactive and deleted file modes.  \
+                In the non-interactive deleted file modes, several directories may be specified, and the results for each are grouped beneath that directory.")
+                .display_order(12)
         )
         .arg(
             Arg::new("EXACT")
                 .short('e')
                 .long("exact")
                 .help("use exact pattern matching for searches in the interactive modes (in contrast to the default fuzzy-finder searching).")
-                .display_order(8)
+                .display_order(13)
         )
         .arg(
             Arg::new("SNAP_FILE_MOUNT")
@@ -285,8 +594,8 @@ fn parse_args() -> ArgMatches {
                 .long("snap")
                 .visible_aliases(&["snap-file", "snapshot", "snap-file-mount"])
                 .help("snapshot the mount point/s of the dataset/s which contains the input file/s. Note: This is a ZFS only option.")
-                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "ALT_REPLICATED", "SNAP_POINT", "LOCAL_DIR"])
-                .display_order(9)
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "ALT_REPLICATED", "REMOTE_DIR", "LOCAL_DIR"])
+                .display_order(14)
         )
         .arg(
             Arg::new("MOUNT_FOR_FILE")
@@ -295,7 +604,7 @@ fn parse_args() -> ArgMatches {
                 .visible_alias("mount")
                 .help("display the mount point/s of the dataset/s which contains the input file/s.")
                 .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE"])
-                .display_order(10)
+                .display_order(15)
         )
         .arg(
             Arg::new("LAST_SNAP")
@@ -303,21 +612,97 @@ fn parse_args() -> ArgMatches {
                 .long("last-snap")
                 .takes_value(true)
                 .default_missing_value("abs")
-                .possible_values(&["abs", "absolute", "rel", "relative"])
+                .possible_values(["abs", "absolute", "rel", "relative", "rel-exhaustive", "relative-exhaustive"])
                 .min_values(0)
                 .require_equals(true)
                 .help("automatically select and print the path of last-in-time unique snapshot version for the input file.  \
                 May also be used as a shortcut to restore from such last version when used with the \"--restore\", or \"-r\", flag.  \
-                Default is to return the absolute last-in-time but user may also request the last unique file version relative to the \"live\" version by appending \"relative\" to the flag.")
-                .conflicts_with_all(&["SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "ALT_REPLICATED", "SNAP_POINT", "LOCAL_DIR", "NOT_SO_PRETTY"])
-                .display_order(11)
+                Default is to return the absolute last-in-time but user may also request the last unique file version relative to the \"live\" version by appending \"relative\" to the flag.  \
+                A relative search assumes a file, once it differs from the live version, never changes back, and so binary searches the snapshots, by creation time, for the newest upon which it differed.  \
+                Should a file have changed and then changed back, one may append \"relative-exhaustive\" instead, to check every snapshot, from the newest back, at the cost of a stat of each.")
+                .conflicts_with_all(&["SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "ALT_REPLICATED", "REMOTE_DIR", "LOCAL_DIR", "NOT_SO_PRETTY"])
+                .display_order(16)
         )
         .arg(
             Arg::new("NO_FILTER")
                 .long("no-filter")
                 .help("by default, in the interactive modes, httm will filter out results from non-supported datasets (like ext4, tmpfs, procfs, sysfs, or devtmpfs), and in common snapshot paths.  \
                 Here, one may select to disable such filtering.  httm, however, should always show the input path, and results from behind any input path when that path is searched.")
-                .display_order(12)
+                .display_order(17)
+        )
+        .arg(
+            Arg::new("HIDDEN")
+                .long("hidden")
+                .help("by default, in the interactive modes, httm will not show hidden files and directories, those whose names begin with a dot, when browsing.  \
+                Here, one may select to show such files.  Hidden files may also be shown or hidden, while browsing, by pressing alt+h.")
+                .display_order(18)
+        )
+        .arg(
+            Arg::new("NO_HIDDEN")
+                .long("no-hidden")
+                .help("do not show hidden files and directories, those whose names begin with a dot, when browsing.  \
+                This is the default, but may be used to override a \"--hidden\" given earlier, for instance, in a shell alias.")
+                .display_order(19)
+        )
+        .arg(
+            Arg::new("OWNER")
+                .long("owner")
+                .help("only display files owned by USER, given as either a user name or a uid, when browsing, in the recursive and deleted modes, \
+                and among the snapshot versions of any file.  For instance, to find which files of a departed user still have snapshot versions.  \
+                Directories are always shown and searched, whoever owns them, as they may hold files owned by USER.")
+                .takes_value(true)
+                .value_name("USER")
+                .display_order(20)
+        )
+        .arg(
+            Arg::new("AFTER")
+                .long("after")
+                .help("only display or offer those snapshot versions last modified at or after DATE, or, with \"--by=snap-time\", those captured by a snapshot at or after DATE, \
+                such as when a dataset has hundreds of snapshots, \
+                but only the last week's are of interest.  DATE may be a date, like \"2022-08-01\", a date and a time, like \"2022-08-01 12:00\", \
+                in local time unless \"--utc\" is also specified, or a span before now, like \"12h\", \"7d\" or \"2w\".")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("DATE")
+                .display_order(21)
+        )
+        .arg(
+            Arg::new("BEFORE")
+                .long("before")
+                .help("only display or offer those snapshot versions last modified before DATE.  DATE is given as for \"--after\", and the two may be combined, \
+                as in \"--after=2022-08-01 --before=2022-09-01\" for the versions of August.")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("DATE")
+                .display_order(22)
+        )
+        .arg(
+            Arg::new("LAST_N")
+                .long("last-n")
+                .help("display or offer only the newest N unique versions of each file, by modify time, such as upon systems with thousands of snapshots, \
+                where the full list of versions would be unwieldy, especially within the select view.")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("N")
+                .display_order(23)
+        )
+        .arg(
+            Arg::new("SNAP_TIME")
+                .long("snap-time")
+                .help("display the creation time of the snapshot which holds each version, alongside the version's modify time, as a file's modify time may be far older \
+                than the snapshot which captured it, and it is the age of a snapshot which matters to a retention policy.  \
+                Creation times are as ZFS reports them, or, for btrfs, the birth time of the snapshot subvolume, or as \"btrfs subvolume show\" reports it.")
+                .conflicts_with_all(&["RAW", "ZEROS"])
+                .display_order(24)
+        )
+        .arg(
+            Arg::new("PERMS")
+                .long("perms")
+                .help("display the mode, owner and group of each version, and mark those versions whose mode, owner or group changed from the version before.  \
+                As a chmod or a chown changes neither the modify time nor the size of a file, versions which differ only in their mode, owner or group \
+                would otherwise be shown as one version.  Useful for tracking down, for instance, when a config file lost its 0600 mode.")
+                .conflicts_with_all(&["RAW", "ZEROS"])
+                .display_order(25)
         )
         .arg(
             Arg::new("RAW")
@@ -326,7 +711,7 @@ fn parse_args() -> ArgMatches {
                 .visible_alias("newline")
                 .help("display the snapshot locations only, without extraneous information, delimited by a NEWLINE character.")
                 .conflicts_with_all(&["ZEROS", "NOT_SO_PRETTY"])
-                .display_order(13)
+                .display_order(26)
         )
         .arg(
             Arg::new("ZEROS")
@@ -334,7 +719,7 @@ fn parse_args() -> ArgMatches {
                 .long("zero")
                 .help("display the snapshot locations only, without extraneous information, delimited by a NULL character.")
                 .conflicts_with_all(&["RAW", "NOT_SO_PRETTY"])
-                .display_order(14)
+                .display_order(27)
         )
         .arg(
             Arg::new("NOT_SO_PRETTY")
@@ -342,14 +727,22 @@ fn parse_args() -> ArgMatches {
                 .visible_aliases(&["tabs", "plain-jane"])
                 .help("display the ordinary output, but tab delimited, without any pretty border lines.")
                 .conflicts_with_all(&["RAW", "ZEROS"])
-                .display_order(15)
+                .display_order(28)
         )
         .arg(
             Arg::new("NO_LIVE")
                 .long("no-live")
                 .visible_aliases(&["dead", "disco"])
                 .help("only display information concerning snapshot versions (display no information regarding 'live' versions of files or directories).")
-                .display_order(16)
+                .display_order(29)
+        )
+        .arg(
+            Arg::new("OMIT_DITTO")
+                .long("omit-ditto")
+                .help("omit any snapshot version which is the same as the live version, from every display, select and restore view, as such a version is no different from the file at hand.  \
+                A version is the same as the live version when the two are the same by the same key as \"--dedup-by\" would compare them, by default, their modify times and sizes, \
+                or, with \"--dedup-by=hash\", their contents.")
+                .display_order(30)
         )
         .arg(
             Arg::new("NO_SNAP")
@@ -359,62 +752,857 @@ fn parse_args() -> ArgMatches {
                 Useful for finding only the \"files that once were\" and displaying only those pseudo-live/undead files.")
                 .requires("RECURSIVE")
                 .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "SNAP_FILE_MOUNT", "LAST_SNAP", "NOT_SO_PRETTY"])
-                .display_order(17)
+                .display_order(31)
+        )
+        .arg(
+            Arg::new("MAP_ALIASES")
+                .long("map-aliases")
+                .visible_aliases(&["aliases"])
+                .help("manually map a local directory (eg. \"/Users/<User Name>\") as an alias of a mount point for ZFS or btrfs, \
+                such as the local mount point for a backup on a remote share (eg. \"/Volumes/Home\").  \
+                This option is useful if you wish to view snapshot versions from within the local directory you back up to your remote share.  \
+                Such map is delimited by a colon, ':', and specified as <LOCAL_DIR>:<REMOTE_DIR> (eg. --map-aliases /Users/<User Name>:/Volumes/Home).  \
+                Multiple maps may be specified delimited by a comma, ','.  You may also set via the environment variable HTTM_MAP_ALIASES.  \
+                Any aliases added with \"--add-alias\" are also used, unless an alias given here is for the same local dir.")
+                .use_value_delimiter(true)
+                .takes_value(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(32)
+        )
+        .arg(
+            Arg::new("CONTAINER")
+                .long("container")
+                .help("when run on the host, treat the input files as paths inside the named docker or LXC container.  \
+                httm reads the container's storage configuration to map each such path to the host dir which contains it, \
+                and then displays the snapshot versions available on the host, so you may recover a container's files without entering it.  \
+                Paths inside a container must be absolute.")
+                .takes_value(true)
+                .conflicts_with_all(&["PINS", "PICK_ROOT", "REMOTE_DIR", "LOCAL_DIR"])
+                .display_order(33)
+        )
+        .arg(
+            Arg::new("OVERLAY")
+                .long("overlay")
+                .help("when an input file lies within an overlayfs mount, as is common on container hosts, display the snapshot versions of the file \
+                on the filesystem which backs the overlay.  A file written within the overlay is kept in the overlay's upper dir, so httm searches \
+                the snapshots of the upper dir, or, for a file never written within the overlay, the snapshots of the first lower dir which holds it.")
+                .conflicts_with_all(&["CONTAINER", "ALTROOT", "ALL_DATASETS", "PINS", "PICK_ROOT"])
+                .display_order(34)
+        )
+        .arg(
+            Arg::new("ALTROOT")
+                .long("altroot")
+                .help("point httm at an alternate root, such as a system mounted within a rescue environment, or a pool imported with \"zpool import -R /mnt\".  \
+                httm will then only consider those datasets mounted beneath the alternate root, and will treat any absolute input path as a path within the alternate root \
+                (eg. with --altroot /mnt, \"/home/user/file\" is \"/mnt/home/user/file\").  Relative paths remain relative to the working directory.")
+                .takes_value(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with_all(&["CONTAINER", "REMOTE_DIR", "LOCAL_DIR"])
+                .display_order(35)
+        )
+        .arg(
+            Arg::new("IMPORT_POOL")
+                .long("import-pool")
+                .help("for a ZFS pool which is not normally imported, such as a backup pool, import the named pool read-only beneath an alternate root, \
+                perform the lookups or restores requested, as if with --altroot, and then export the pool once httm is finished.  \
+                httm will ask for your consent at the terminal before it imports the pool.")
+                .takes_value(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with_all(&["ALTROOT", "CONTAINER", "REMOTE_DIR", "LOCAL_DIR", "SNAP_FILE_MOUNT"])
+                .display_order(36)
+        )
+        .arg(
+            Arg::new("ENABLE_SNAPDIR")
+                .long("enable-snapdir")
+                .help("for each mounted ZFS dataset with its snapdir property set to \"disabled\", set the property to \"hidden\", \
+                perform the lookups or restores requested, and then set the property to \"disabled\" again once httm is finished.  \
+                httm will ask for your consent at the terminal before it changes any property.")
+                .conflicts_with_all(&["IMPORT_POOL", "ALTROOT", "CONTAINER", "REMOTE_DIR", "LOCAL_DIR", "SNAP_FILE_MOUNT"])
+                .display_order(37)
+        )
+        .arg(
+            Arg::new("EXTERNAL_ROOTS")
+                .long("external-roots")
+                .help("register the mount point of a \"cold\" backup, such as a USB disk with a replicated pool mounted at \"/mnt/backup\", \
+                which mirrors the layout of the live filesystem (eg. versions of \"/home/user/file\" are found on the datasets mounted beneath \"/mnt/backup/home\").  \
+                When a dataset is mounted beneath an external root, httm will include its unique versions in results, labeled as external, \
+                and, when not, httm will silently ignore the external root.  \
+                Multiple external roots may be specified delimited by a comma, ','.  You may also set via the environment variable HTTM_EXTERNAL_ROOTS.")
+                .use_value_delimiter(true)
+                .takes_value(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(38)
+        )
+        .arg(
+            Arg::new("SNAP_DIRS")
+                .long("snap-dirs")
+                .help("manually specify where the snapshots of a dataset are found, for datasets which do not keep their snapshots in the default location \
+                (\".zfs/snapshot\" for ZFS, and the snapper \".snapshots\" directory for btrfs).  \
+                Such map is delimited by a colon, ':', and specified as <MOUNT>:<SNAP_DIR>, where SNAP_DIR is relative to the MOUNT (eg. --snap-dirs /srv:.zfs/snapshot).  \
+                For btrfs, a SNAP_DIR is expected to have the same layout as a snapper snapshot directory.  \
+                Multiple maps may be specified delimited by a comma, ','.  You may also set via the environment variable HTTM_SNAP_DIRS.")
+                .use_value_delimiter(true)
+                .takes_value(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(39)
+        )
+        .arg(
+            Arg::new("REMOTE_DIR")
+                .long("remote-dir")
+                .visible_aliases(&["remote", "snap-point"])
+                .help("DEPRECATED.  Use MAP_ALIASES. Manually specify that mount point for ZFS (directory which contains a \".zfs\" directory) or btrfs-snapper \
+                (directory which contains a \".snapshots\" directory), such as the local mount point for a remote share.  You may also set via the HTTM_REMOTE_DIR environment variable.")
+                .takes_value(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(40)
+        )
+        .arg(
+            Arg::new("LOCAL_DIR")
+                .long("local-dir")
+                .visible_alias("local")
+                .help("DEPRECATED.  Use MAP_ALIASES.  Used with \"remote-dir\" to determine where the corresponding live root filesystem of the dataset is.  \
+                Put more simply, the \"local-dir\" is likely the directory you backup to your \"remote-dir\".  If not set, httm defaults to your current working directory.  \
+                You may also set via the environment variable HTTM_LOCAL_DIR.")
+                .requires("REMOTE_DIR")
+                .takes_value(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(41)
+        )
+        .arg(
+            Arg::new("MATRIX")
+                .long("matrix")
+                .help("when multiple files are requested, display a matrix of files by day, indicating upon which days a snapshot first captured a unique version of each file.  \
+                Useful for correlating a single change made across several files at once.  Only available in Display and Interactive Browse modes.")
+                .conflicts_with_all(&["RAW", "ZEROS", "SELECT", "RESTORE", "LAST_SNAP", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "NO_SNAP"])
+                .display_order(42)
+        )
+        .arg(
+            Arg::new("GIT_XREF")
+                .long("git-xref")
+                .help("for files inside a git work tree, annotate each version with the commit which was checked out at the time of the version's modify time, \
+                as determined by the reflog, or, if the reflog has expired, by commit dates.  \
+                Versions whose contents were never committed to any branch are flagged, as a safety net for lost uncommitted work.  Only available in Display and Interactive Browse modes.")
+                .conflicts_with_all(&["RAW", "ZEROS", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "NO_SNAP", "MATRIX"])
+                .display_order(43)
+        )
+        .arg(
+            Arg::new("SHOW_REDUNDANCY")
+                .long("show-redundancy")
+                .help("for each unique version of the files specified, display how many independent copies of that version exist, and on which datasets.  \
+                Most useful with \"--alt-replicated\", when auditing whether local snapshots have actually been replicated elsewhere.  Only available in Display and Interactive Browse modes.")
+                .conflicts_with_all(&["RAW", "ZEROS", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "NO_SNAP", "MATRIX", "GIT_XREF"])
+                .display_order(44)
+        )
+        .arg(
+            Arg::new("STATS")
+                .long("stats")
+                .help("after displaying the versions of the files specified, print a summary of the lookup to stderr(3): \
+                the number of datasets searched, snapshots scanned, versions found, unique versions, and the time elapsed.")
+                .conflicts_with_all(&["SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "GIT_XREF", "SHOW_REDUNDANCY", "DELETED_MODE"])
+                .display_order(45)
+        )
+        .arg(
+            Arg::new("SORT_VERSIONS")
+                .long("sort-versions")
+                .takes_value(true)
+                .possible_values(["mtime", "size", "snapshot", "path"])
+                .help("choose the key by which the versions of each file are ordered: modify time, size, snapshot creation time, or path.  \
+                The default is to order by modify time.  Ties are always broken by the remaining keys, in that same order, so the order of versions is the same from run to run.")
+                .display_order(46)
+        )
+        .arg(
+            Arg::new("BY")
+                .long("by")
+                .takes_value(true)
+                .require_equals(true)
+                .possible_values(["file-time", "snap-time"])
+                .help("choose which time of each version both orders the versions of each file and bounds \"--after\" and \"--before\": \
+                the modify time of the file, or the creation time of the snapshot which captured it, as versions captured last week \
+                and versions modified last week may be very different versions.  The default is the file's modify time.  \
+                An order given by \"--sort-versions\" is preferred to either.")
+                .display_order(47)
+        )
+        .arg(
+            Arg::new("DEDUP_BY")
+                .long("dedup-by")
+                .takes_value(true)
+                .possible_values(["mtime-size", "mtime", "size", "hash", "none"])
+                .help("choose the key by which two copies of a file are known to be the same version: modify time and size, modify time alone, size alone, \
+                the SHA-256 of the contents, or nothing, so every snapshot's copy is its own version.  The default is modify time and size.  \
+                Tools which preserve modify times, like \"rsync -a\" or tar, may leave different contents with the same modify time and size, \
+                which only \"hash\" will tell apart, though each copy must then be read in full.  The versions of a directory are always known \
+                by a manifest of their children, except with \"none\".")
+                .display_order(48)
+        )
+        .arg(
+            Arg::new("HASH")
+                .long("hash")
+                .takes_value(true)
+                .multiple_values(true)
+                .use_value_delimiter(true)
+                .require_value_delimiter(true)
+                .possible_values(["sha256", "xxhash", "blake3", "partial"])
+                .value_name("ALGORITHM[,partial]")
+                .help("choose the hash by which the contents of two versions are compared, as with \"--dedup-by=hash\", or wherever httm must \
+                know whether a file has changed: SHA-256, the default, xxHash (XXH64), which is much faster, though not meant to resist a forged collision, or BLAKE3.  \
+                Add \"partial\" to hash only the size and the first and last 1 MiB of any file larger than 2 MiB, which is far faster for enormous files, \
+                though a change only to the middle of such a file will go unnoticed.  A chain of custody is always the SHA-256 of the whole file.")
+                .display_order(49)
+        )
+        .arg(
+            Arg::new("HASH_BANDWIDTH")
+                .long("hash-bandwidth")
+                .takes_value(true)
+                .value_name("RATE")
+                .help("limit the rate at which httm reads versions to hash them, from all threads together, to RATE bytes per second, \
+                like \"50M\" or \"1G\", so comparing the contents of the versions of a whole directory doesn't saturate a pool \
+                that is also in production use.  The default is no limit.")
+                .display_order(50)
+        )
+        .arg(
+            Arg::new("HASH_IOPS")
+                .long("hash-iops")
+                .takes_value(true)
+                .value_name("N")
+                .help("limit the reads httm makes, to hash versions, from all threads together, to N per second.  \
+                Each read is at most 64 KiB.  The default is no limit.")
+                .display_order(51)
+        )
+        .arg(
+            Arg::new("HASH_JOBS_PER_DEVICE")
+                .long("hash-jobs-per-device")
+                .takes_value(true)
+                .value_name("N")
+                .help("hash no more than N files at once on each device, where each ZFS dataset and its snapshots count as one device.  \
+                The default is as many as httm has threads.")
+                .display_order(52)
+        )
+        .arg(
+            Arg::new("NO_HASH_CACHE")
+                .long("no-hash-cache")
+                .help("neither read nor add to the cache of hashes httm keeps of the files on ZFS snapshots.  \
+                As a snapshot never changes, httm caches the hash of each file it hashes on a snapshot, by the snapshot's GUID and the file's inode number, \
+                in the user's home directory at ~/.httm-hash-cache, so comparing the same versions again is nearly free.  \
+                In forensic mode, the cache is never used.")
+                .display_order(52)
+        )
+        .arg(
+            Arg::new("HASH_CACHE")
+                .long("hash-cache")
+                .takes_value(true)
+                .possible_values(["size", "clear"])
+                .value_name("COMMAND")
+                .help("print the number of hashes in the cache of hashes of files on ZFS snapshots, and the size of the cache on disk, \
+                or \"clear\" the cache, and then exit.")
+                .exclusive(true)
+                .display_order(52)
+        )
+        .arg(
+            Arg::new("NO_DEDUP")
+                .long("no-dedup")
+                .help("list every snapshot's copy of a file, one row per snapshot, each with the name of its snapshot, \
+                even when the copies are identical.  Useful for auditing exactly which snapshots hold a file.  Same as \"--dedup-by none\".")
+                .conflicts_with("DEDUP_BY")
+                .display_order(53)
+        )
+        .arg(
+            Arg::new("MAX_SNAPS")
+                .long("max-snaps")
+                .help("only search the newest N snapshots of each dataset, as ordered by snapshot creation time.  \
+                Useful for datasets with many thousands of snapshots, when only recent history is of interest.")
+                .takes_value(true)
+                .value_name("N")
+                .display_order(54)
+        )
+        .arg(
+            Arg::new("DIR_SUMMARY")
+                .long("dir-summary")
+                .help("for each directory specified, summarize the versions of the files it contains.  \
+                For each snapshot upon which a file within the directory changed, or was deleted, display how many files changed, and the sum of the sizes of those changed files.  \
+                The oldest snapshot, having none before it, is shown first as a baseline, with how many files it holds, and the sum of their sizes.  \
+                Useful for locating the snapshot taken just before everything went wrong.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DELETED_MODE", "RECURSIVE", "MATRIX", "RAW", "ZEROS"])
+                .display_order(55)
+        )
+        .arg(
+            Arg::new("DIR_SIZE")
+                .long("dir-size")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("MAX_ENTRIES")
+                .help("when displaying versions of a directory, also display the sum of the sizes of every file beneath the directory, as it was upon each snapshot, \
+                so one may compare how large a directory was over time.  \
+                Each tree is walked in parallel, but no more than 100000 entries are counted per version, unless a different bound is specified, as in \"--dir-size=1000000\", \
+                and a sum which reached the bound is marked as a lower bound.")
+                .conflicts_with_all(&["RAW", "ZEROS", "DIR_SUMMARY"])
+                .display_order(56)
+        )
+        .arg(
+            Arg::new("EXEC")
+                .long("exec")
+                .takes_value(true)
+                .value_name("COMMAND")
+                .help("after selecting a snapshot version, run a command against a temporary copy of that version, rather than printing its path, \
+                as in \"--exec='vim {}'\", where \"{}\" is replaced by the path to the temporary copy.  \
+                The copy is removed once the command exits, and httm exits with the command's exit status.  Implies \"--select\", unless \"--last-snap\" is also specified.")
+                .conflicts_with_all(&["RESTORE", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "BISECT"])
+                .display_order(57)
+        )
+        .arg(
+            Arg::new("EDIT")
+                .long("edit")
+                .help("after selecting a snapshot version, open a read-only temporary copy of that version in the user's editor, \
+                as specified by the VISUAL or EDITOR environment variables, rather than printing its path.  \
+                Within the select view, ctrl+e does the same for the version under the cursor, and then returns to the select view.  \
+                Implies \"--select\", unless \"--last-snap\" is also specified.")
+                .conflicts_with_all(&["RESTORE", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "BISECT", "EXEC"])
+                .display_order(58)
+        )
+        .arg(
+            Arg::new("COPY_PATH")
+                .long("copy-path")
+                .help("after selecting a snapshot version, also copy its path to the system clipboard, by way of the terminal, using an OSC 52 escape sequence, which works over ssh and within tmux.  \
+                Within the select view, ctrl+y does the same for the version under the cursor, and then returns to the select view.  \
+                Implies \"--select\", unless \"--last-snap\" is also specified.")
+                .conflicts_with_all(&["RESTORE", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "BISECT", "EXEC", "EDIT"])
+                .display_order(59)
+        )
+        .arg(
+            Arg::new("CAT")
+                .long("cat")
+                .help("after selecting a snapshot version, write its contents to stdout, rather than printing its path, like \"git show rev:file\", \
+                as in \"httm --cat --last-snap file.conf | diff - file.conf\".  Implies \"--select\", unless \"--last-snap\" is also specified.  \
+                Within the select view, ctrl+o does the same for the highlighted version.")
+                .conflicts_with_all(&["RESTORE", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "BISECT", "EXEC", "EDIT", "COPY_PATH", "PRINT"])
+                .display_order(60)
+        )
+        .arg(
+            Arg::new("WORKSPACE")
+                .long("workspace")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("DIR")
+                .allow_invalid_utf8(true)
+                .help("within the select view, mark one or more snapshot versions with tab, and, upon enter, copy each into a workspace dir, at the path of its live file, \
+                so that, for instance, \"/srv/.zfs/snapshot/snap_8a86e4fc_prepApt/app/config.toml\" becomes \"<DIR>/srv/app/config.toml\", and an old tree may be run in place.  \
+                The workspace may be a dir specified, which must be new or empty, like a tmpfs, or a scratch dataset just created for the purpose, or, by default, a new dir, of a random name, which only its owner may enter, within the system's temporary dir.  \
+                Only the path of the workspace is printed to stdout.  Implies \"--select\".")
+                .conflicts_with_all(&["RESTORE", "PICKER", "LAST_SNAP", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "BISECT", "EXEC", "EDIT", "COPY_PATH", "PRINT", "CAT"])
+                .display_order(61)
+        )
+        .arg(
+            Arg::new("DIFF_TOOL")
+                .long("diff-tool")
+                .takes_value(true)
+                .value_name("COMMAND")
+                .help("within the select view, mark two versions with tab, and ctrl+d compares them, the older on the left and the newer on the right.  \
+                By default, httm shows its own side-by-side diff, but, should a command be specified, httm instead runs that command against read-only temporary copies of both versions, \
+                as in \"--diff-tool=vimdiff\" or \"--diff-tool='meld {}'\", where \"{}\" is replaced by the paths to both copies, older first, or, without a \"{}\", the paths are appended.  \
+                Implies \"--select\", unless \"--last-snap\" is also specified.")
+                .conflicts_with_all(&["RESTORE", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "BISECT", "EXEC", "EDIT"])
+                .display_order(62)
+        )
+        .arg(
+            Arg::new("PRINT")
+                .long("print")
+                .takes_value(true)
+                .possible_values(["snap-path", "live-path", "rel-path", "snapshot-name"])
+                .help("after selecting a snapshot version, choose exactly what is printed: the path of the snapshot version, the path of the live file, \
+                the path relative to the mount of its dataset, or the name of its snapshot.  Values other than \"snap-path\" are printed bare, unquoted and followed by a newline, for use by scripts.  \
+                Implies \"--select\", unless \"--last-snap\" is also specified.")
+                .conflicts_with_all(&["RESTORE", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "BISECT", "EXEC", "EDIT"])
+                .display_order(63)
+        )
+        .arg(
+            Arg::new("BLAME")
+                .long("blame")
+                .help("for each text file specified, attribute each line of the live file to the earliest version in which it appeared, \
+                as determined by chaining a diff of each version to the next, and display each line alongside the date and snapshot name of that version.  \
+                Lines which appear in no snapshot version are attributed to the live file.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE", "NO_SNAP", "BISECT", "RAW", "ZEROS"])
+                .display_order(64)
+        )
+        .arg(
+            Arg::new("NUM_VERSIONS")
+                .long("num-versions")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .possible_values(["count", "dates"])
+                .value_name("FORMAT")
+                .help("display only how many unique snapshot versions each input file has, rather than the versions themselves, such as for a script which would find \
+                those files with no snapshot versions at all.  With \"--num-versions=dates\", also display the modify times of the oldest and newest of those versions.  \
+                With \"--raw\" or \"--zeros\", each is delimited by a tab, for scripts.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE", "NO_SNAP", "BISECT", "BLAME", "JSON"])
+                .display_order(65)
+        )
+        .arg(
+            Arg::new("FORENSIC")
+                .long("forensic")
+                .takes_value(true)
+                .value_name("AUDIT_LOG")
+                .allow_invalid_utf8(true)
+                .help("a strictly read-only mode, for incident responders examining a compromised system.  \
+                Every operation which would change the system, like a restore, a snapshot, or even a temporary copy of a version, is disabled, \
+                and httm refuses to run when any such operation is requested.  To avoid automounting ZFS snapshots, only those snapshots already mounted are searched.  \
+                Each path httm stats, lists or reads is appended to the audit log specified, which is best kept on separate media, and httm refuses to run should that log be unavailable.  \
+                Listings include the SHA-256 of each version, the version of httm, and when the listing was generated, for a chain of custody.")
+                .conflicts_with_all(&["RESTORE", "SNAP_FILE_MOUNT", "IMPORT_POOL", "ENABLE_SNAPDIR", "ZSH_HOT_KEYS", "PIN", "UNDELETE", "TO_GIT", "EXEC", "EDIT", "DIFF_TOOL", "WORKSPACE"])
+                .display_order(66)
+        )
+        .arg(
+            Arg::new("RESTRICT_TO_OWNER")
+                .long("restrict-to-owner")
+                .help("only look up, and only restore, files owned by the invoking user, which, when httm is run via sudo, is the user who ran sudo.  \
+                This mode is meant for a sudo rule which allows users of a shared server to browse and restore their own files from snapshots they could not otherwise read.  \
+                Files, directories and versions owned by another user are silently omitted, except for those paths specified, which must be owned by the invoking user.  \
+                A restore may only replace a file owned by the invoking user, or create a new file within a directory owned by the invoking user.  \
+                When run as root, on Linux, a restore is written as the invoking user, so restored files belong to them, and capabilities and immutable or append-only flags are not restored.  \
+                Any option which would run another program, serve others, or write outside of a restore, such as \"--exec\", \"--edit\", \"--snap\", \"--to-git\", \"--backup-backend\" or \"--serve\", is refused.")
+                .conflicts_with_all(&["EXEC", "EDIT", "DIFF_TOOL", "WORKSPACE", "SNAP_FILE_MOUNT", "IMPORT_POOL", "ENABLE_SNAPDIR", "ZSH_HOT_KEYS", "PIN", "TO_GIT", "GIT_XREF", "CONTAINER", "FORENSIC", "MEMBER", "GUI_HELPER", "BACKUP_BACKEND", "SERVE_HTTP", "SERVE_DBUS", "ADD_ALIAS", "HOSTS", "FROM_SEND_STREAM"])
+                .display_order(67)
+        )
+        .arg(
+            Arg::new("RANSOMWARE")
+                .long("ransomware")
+                .help("scan the directory specified, or the current working directory, for files which appear to have been newly encrypted, as by ransomware.  \
+                httm samples the contents of each live file which differs from its newest snapshot version, and flags those which now look random, \
+                where the newest snapshot version did not, or where the newest snapshot version had a file format signature, like that of a PDF or a ZIP file, which is now lost.  \
+                \"--recursive\" scans the whole tree.  When run from a terminal, httm then offers to restore every file flagged from its newest snapshot version.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "DELETED_MODE", "NO_SNAP", "BISECT", "BLAME", "TO_GIT", "FOLLOW", "UNDELETE", "SIZE_COLLAPSE"])
+                .display_order(68)
+        )
+        .arg(
+            Arg::new("SIZE_COLLAPSE")
+                .long("size-collapse")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("PERCENT")
+                .help("scan the directory specified, or the current working directory, for files whose live size has collapsed, \
+                that is, is now empty, or is 10% or less of the size of their newest snapshot version, a common symptom of ransomware, or of a script gone wrong.  \
+                A different percentage may be specified, as in \"--size-collapse=25\", and \"--recursive\" scans the whole tree.  \
+                When run from a terminal, httm then offers to restore every file flagged from its newest snapshot version.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "DELETED_MODE", "NO_SNAP", "BISECT", "BLAME", "TO_GIT", "FOLLOW", "UNDELETE"])
+                .display_order(69)
+        )
+        .arg(
+            Arg::new("UNDELETE")
+                .long("undelete")
+                .help("a guided recovery of deleted files from the directory specified, or the current working directory.  \
+                httm shows the files deleted from the directory, and when each was last seen on a snapshot, for the user to select one or more to restore.  \
+                httm then asks where to restore those files, and what to do should a file of the same name already exist there, \
+                and shows the plan for the user's consent, before restoring anything.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE", "NO_SNAP", "BISECT", "BLAME", "TO_GIT", "FOLLOW"])
+                .display_order(70)
+        )
+        .arg(
+            Arg::new("FOLLOW")
+                .long("follow")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("SECONDS")
+                .help("like \"tail -f\", print the unique snapshot versions of the files specified, and then continue to watch for new snapshots, \
+                printing each new version as its snapshot appears, until interrupted.  \
+                Snapshots are polled for every 5 seconds, unless a different interval is specified, as in \"--follow=60\".")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE", "NO_SNAP", "BISECT", "BLAME", "TO_GIT"])
+                .display_order(71)
+        )
+        .arg(
+            Arg::new("TO_GIT")
+                .long("to-git")
+                .takes_value(true)
+                .value_name("REPO_DIR")
+                .allow_invalid_utf8(true)
+                .help("export the history of the files specified into a git repository, with one commit per unique snapshot version, dated by the creation time of its snapshot, \
+                so that standard git tools, like \"git log -p\" and \"git blame\", may be used to analyze that history.  \
+                httm will create the repository if it does not yet exist, and, when it does, will only commit those versions not exported before.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE", "NO_SNAP", "BISECT", "BLAME"])
+                .display_order(72)
+        )
+        .arg(
+            Arg::new("PATCH")
+                .long("patch")
+                .takes_value(true)
+                .number_of_values(2)
+                .value_names(&["OLD", "NEW"])
+                .allow_invalid_utf8(true)
+                .help("print a unified diff from the OLD version of a text file to the NEW version, either of which may be a snapshot version or the live file, \
+                as in \"--patch /srv/.zfs/snapshot/snap_8a86e4fc_prepApt/etc/smb.conf /srv/etc/smb.conf\".  \
+                Both sides of the diff are named for the live file, relative to the working directory, so that, from the working directory, \"patch -p0\" applies the diff to the live file.  \
+                To revert a single regression without replacing the whole file, make a patch from the live file to the snapshot version which preceded the regression.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE", "NO_SNAP", "BISECT", "BLAME", "TO_GIT", "RAW", "ZEROS"])
+                .display_order(73)
+        )
+        .arg(
+            Arg::new("BYTE_RANGE")
+                .long("byte-range")
+                .takes_value(true)
+                .value_name("START:LENGTH")
+                .help("write only the range of bytes specified of the version specified to stdout, as in \"--byte-range=1048576:4096\", \
+                rather than copying the whole of a huge version, like a VM image, for a small recovery.  \
+                The range begins START bytes into the version, and is LENGTH bytes long, or shorter, should the version end first.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE", "NO_SNAP", "BISECT", "BLAME", "TO_GIT", "PATCH"])
+                .display_order(74)
+        )
+        .arg(
+            Arg::new("MEMBER")
+                .long("member")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .value_name("MEMBER")
+                .help("write only the member specified of the tar or zip archive version specified to stdout, as in \"--member=etc/fstab\", \
+                rather than copying the whole of a huge archive for a small recovery.  May be specified more than once, and each member is written in turn.  \
+                httm recognizes an archive by its contents, and a compressed tar archive is read by \"tar\" itself, while a zip archive is read by \"unzip\".  A member may not begin with a '-', lest it be read as an option.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE", "NO_SNAP", "BISECT", "BLAME", "TO_GIT", "PATCH", "BYTE_RANGE"])
+                .display_order(75)
+        )
+        .arg(
+            Arg::new("PROGRESS_JSON")
+                .long("progress-json")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("FD")
+                .help("write machine readable progress events, one JSON object per line, to the file descriptor specified, or, by default, to stderr, \
+                so that a GUI frontend may show progress without parsing httm's human readable messages, as in \"httm --progress-json=3 file 3>progress.jsonl\".  \
+                Each object has an \"event\" key, one of \"scan_started\", \"dataset_searched\", \"versions_found\", \"scan_finished\" or \"restore_progress\", \
+                alongside the details of that event, like the number of versions found for a path, or the percent of a restore copied so far.")
+                .display_order(76)
+        )
+        .arg(
+            Arg::new("BISECT")
+                .long("bisect")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("TEST_COMMAND")
+                .help("like \"git bisect\", find the first bad version of the file specified, presuming the live file is bad.  \
+                httm will present versions, one at a time, for the user to judge good or bad, until it has found the first bad version, and prints its path to stdout(3).  \
+                Optionally, a test command may judge each version instead, as in \"--bisect='grep -q setting {}'\", where \"{}\" is replaced by the path to a temporary copy of the version.  \
+                An exit status of 0 means the version is good, any other status means it is bad.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE", "NO_SNAP"])
+                .display_order(77)
+        )
+        .arg(
+            Arg::new("PICKER")
+                .long("picker")
+                .help("interactive browse and search a specified directory, and select a snapshot version, for use as a version picker by file managers such as ranger, lf, or nnn.  \
+                Only the path of the version selected is printed to stdout, unquoted and followed by a newline, and all other output is printed to stderr.  \
+                httm will exit with a status of 0 when a version is selected, and 2 when the user aborts the selection.  \
+                Upon an error, httm exits with a status of 3 when no versions were found, 4 when no dataset was found, 5 when permission was denied, \
+                6 when the snapshots of a dataset could not be scanned, 7 when a command httm needs was not found, and 1 upon any other error.")
+                .conflicts_with_all(&["INTERACTIVE", "RESTORE", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "MATRIX"])
+                .display_order(78)
+        )
+        .arg(
+            Arg::new("PIN")
+                .long("pin")
+                .help("pin the paths specified, so they may be quickly recalled later via \"--pins\", and then exit.  \
+                Pinned paths are stored, one per line, in the user's home directory at ~/.httm-pins.")
+                .takes_value(true)
+                .multiple_values(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .exclusive(true)
+                .display_order(79)
+        )
+        .arg(
+            Arg::new("ADD_ALIAS")
+                .long("add-alias")
+                .value_name("REMOTE_DIR")
+                .help("interactively add an alias for the REMOTE_DIR specified, such as the local mount of a network share, and then exit.  \
+                httm checks the REMOTE_DIR contains a ZFS or btrfs snapper snapshot directory, counts its snapshots, suggests a local dir for it, \
+                and checks how much of the local dir chosen is found on the newest snapshot, before it asks to add the alias.  \
+                Aliases are stored, one per line, as <LOCAL_DIR>:<REMOTE_DIR>, in the user's home directory at ~/.httm-aliases, \
+                and are used whenever httm runs, along with any given via MAP_ALIASES.")
+                .takes_value(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .exclusive(true)
+                .display_order(80)
+        )
+        .arg(
+            Arg::new("PINS")
+                .long("pins")
+                .help("open an interactive menu of pinned paths, and use the path selected as the requested input.  \
+                May be combined with \"--select\" or \"--restore\" to continue straight on to a dialog to select or restore a version of the pinned path.")
+                .conflicts_with_all(&["INPUT_FILES", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "MATRIX"])
+                .display_order(81)
+        )
+        .arg(
+            Arg::new("PICK_ROOT")
+                .long("pick-root")
+                .help("when no input path is given, instead of beginning from the working directory, open an interactive menu of likely paths from which to begin, and use the path selected as the requested input.  \
+                Likely paths are the user's home directory, any pinned directories, and the mount of each detected dataset.")
+                .conflicts_with_all(&["INPUT_FILES", "PINS", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "MATRIX"])
+                .display_order(82)
+        )
+        .arg(
+            Arg::new("SERVE_STDIO")
+                .long("serve-stdio")
+                .help("serve requests, for use by editor plugins and other programs, as line delimited JSON over stdin(3) and stdout(3).  \
+                Each request is a single line JSON object, with an \"id\", a \"method\" and its \"params\".  \
+                Available methods are: \"list_versions\" (params: \"path\"), \"read_version\" (params: \"path\"), and \"restore\" (params: \"version\", \"path\", and optionally \"overwrite\").  \
+                A restore which overwrites first moves the live file aside, beside itself, just as \"--restore=overwrite\".  \
+                Each response is a single line JSON object, which contains the \"id\" of the request and either a \"result\" or an \"error\".")
+                .conflicts_with_all(&["INPUT_FILES", "INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "MATRIX", "RECURSIVE", "DELETED_MODE"])
+                .display_order(83)
+        )
+        .arg(
+            Arg::new("SERVE_DBUS")
+                .long("serve-dbus")
+                .value_name("BUS")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .possible_values(["session", "system"])
+                .default_missing_value("session")
+                .help("serve the methods of \"--serve-stdio\" on D-Bus, so that file manager extensions, such as a \"Restore previous version\" context menu, may call into a running httm.  \
+                httm takes the name \"org.httm.Httm\", and serves the object \"/org/httm/Httm\" with the interface \"org.httm.Httm1\", \
+                which has the methods \"ListVersions\" (args: path), \"ReadVersion\" (args: path), and \"Restore\" (args: version, path, and overwrite).  \
+                Each method returns its result as a string of JSON, just as \"--serve-stdio\", or fails with a D-Bus error.  \
+                Only calls from the user who started httm, or root, are served.  The default BUS is the \"session\" bus.")
+                .conflicts_with_all(&["INPUT_FILES", "INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "MATRIX", "RECURSIVE", "DELETED_MODE"])
+                .display_order(84)
+        )
+        .arg(
+            Arg::new("GUI_HELPER")
+                .long("gui-helper")
+                .value_name("PATH")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .help("list the snapshot versions of PATH in a dialog, and restore the version chosen, either as a copy beside the live file, or over it, \
+                for use as a \"Restore previous version\" action of a file manager's context menu.  \
+                httm uses \"kdialog\" on KDE, otherwise \"zenity\", if either is installed.  \
+                When there is no display, or no dialog program, httm instead prints the result of the \"list_versions\" method of \"--serve-stdio\", as a single line of JSON, \
+                for another program to present, and that program may restore a version with \"--serve-stdio\" or \"--serve-dbus\".")
+                .conflicts_with_all(&["INPUT_FILES", "INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "SERVE_DBUS", "MATRIX", "RECURSIVE", "DELETED_MODE"])
+                .display_order(85)
+        )
+        .arg(
+            Arg::new("SMB_VERSIONS")
+                .long("smb-versions")
+                .value_name("SHARE_ROOT")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .help("answer queries for the previous versions of paths within an SMB share, whose root is SHARE_ROOT, for use by a Samba VFS module, or some wrapper, \
+                which would present httm's versions to SMB \"Previous Versions\" clients.  \
+                Each query is a single line on stdin(3), a path relative to SHARE_ROOT, and each answer is a single line JSON object on stdout(3), \
+                which contains the \"path\" queried, and either its \"versions\" or an \"error\".  \
+                Each version has its \"@GMT\" \"token\", the creation time of its snapshot in UTC, as Samba's shadow_copy2 module and SMB clients name a previous version, \
+                along with its \"snapshot\", \"path\", \"modify_time\", and \"size\".  Versions are listed oldest first.")
+                .conflicts_with_all(&["INPUT_FILES", "INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "SERVE_DBUS", "GUI_HELPER", "MATRIX", "RECURSIVE", "DELETED_MODE"])
+                .display_order(86)
+        )
+        .arg(
+            Arg::new("SERVE_HTTP")
+                .long("serve")
+                .value_name("DIR")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .help("serve DIR, read only, as web pages over HTTP, so that users without a terminal may browse its files, and their snapshot versions, and download whichever version they need.  \
+                Each dir lists its children, each with a link to its versions, and each version may be downloaded, or, for a dir, browsed as it was on that snapshot.  \
+                httm serves only paths within DIR.  See also \"--listen\".")
+                .conflicts_with_all(&["INPUT_FILES", "INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "SERVE_DBUS", "GUI_HELPER", "SMB_VERSIONS", "MATRIX", "RECURSIVE", "DELETED_MODE"])
+                .display_order(87)
+        )
+        .arg(
+            Arg::new("LISTEN")
+                .long("listen")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .requires("SERVE_HTTP")
+                .help("the address and port on which \"--serve\" listens, such as \"192.168.1.2:8080\", or \":8080\" for every interface.  \
+                The default is \"127.0.0.1:8080\", which only serves this machine.")
+                .display_order(88)
+        )
+        .arg(
+            Arg::new("API_TOKEN_FILE")
+                .long("api-token-file")
+                .value_name("FILE")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .help("in addition to its web pages, \"--serve\" will serve a JSON API, for admin portals, runbooks and other programs, which may look up versions and restore them.  \
+                Every API request must carry the token contained in FILE, as the header \"Authorization: Bearer <token>\", or it will be refused.  \
+                The endpoints are \"GET /api/v1/versions?path=<path>\", and \"POST /api/v1/restore\", whose body is a JSON object with a \"version\", a \"path\", and optionally \"overwrite\".  \
+                Each path is relative to the DIR served, and each restore which does not overwrite is restored beside the live file.  \
+                Each response is a JSON object, which contains either a \"result\", just as the same method of \"--serve-stdio\", or an \"error\".  \
+                With \"--hosts\", FILE instead contains the token which httm sends to each host.")
+                .display_order(89)
+        )
+        .arg(
+            Arg::new("HOSTS")
+                .long("hosts")
+                .value_name("HOSTS")
+                .takes_value(true)
+                .use_value_delimiter(true)
+                .requires_all(&["INPUT_FILES", "API_TOKEN_FILE"])
+                .help("query the httm on each of several hosts, each serving with \"--serve\" and \"--api-token-file\", for the versions of the same input paths, \
+                such as for a fleet of replicas, and display every version found, labeled by the host upon which it was found, oldest first.  \
+                Hosts are delimited by a comma, ',', and each may have a port (eg. --hosts host1,host2:8081), the default port is 8080.  \
+                Each input path is relative to the DIR each host serves, and is sent just as given.  \
+                A host which cannot be reached, or which has no versions, is a warning, so long as some host has some version.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "SERVE_DBUS", "GUI_HELPER", "SMB_VERSIONS", "SERVE_HTTP", "MATRIX", "RECURSIVE", "DELETED_MODE"])
+                .display_order(90)
+        )
+        .arg(
+            Arg::new("BACKUP_BACKEND")
+                .long("backup-backend")
+                .value_name("BACKEND")
+                .takes_value(true)
+                .multiple_values(true)
+                .use_value_delimiter(true)
+                .require_value_delimiter(true)
+                .help("display the versions of the input files held within the repository of a backup tool, such as kopia or duplicacy, rather than upon snapshots, \
+                each labeled with the snapshot which holds it, oldest first, followed by the live version.  \
+                For kopia, the versions are found within the snapshots of the most proximate source which contains each file, as \"kopia snapshot list\" lists them.  \
+                For duplicacy, the versions are found within each revision of the repository, the dir initialized with a \".duplicacy\" dir, which contains each file.  \
+                The backup tool must already be configured to reach its repository, as httm runs the tool's own commands.  \
+                Any other BACKEND may be added, without patching httm, as an executable named \"httm-backend-BACKEND\" upon the PATH, \
+                which answers the subcommands \"snapshots LIVE_PATH\", \"resolve SNAPSHOT_ID SNAPSHOT_LOCATOR LIVE_PATH\" and \"read SNAPSHOT_ID VERSION_LOCATOR LIVE_PATH\", \
+                as described in the source of the backup_backends module.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "SERVE_DBUS", "GUI_HELPER", "SMB_VERSIONS", "SERVE_HTTP", "HOSTS", "FROM_SEND_STREAM", "MATRIX", "RECURSIVE", "DELETED_MODE"])
+                .display_order(91)
         )
         .arg(
-            Arg::new("MAP_ALIASES")
-                .long("map-aliases")
-                .visible_aliases(&["aliases"])
-                .help("manually map a local directory (eg. \"/Users/<User Name>\") as an alias of a mount point for ZFS or btrfs, \
-                such as the local mount point for a backup on a remote share (eg. \"/Volumes/Home\").  \
-                This option is useful if you wish to view snapshot versions from within the local directory you back up to your remote share.  \
-                Such map is delimited by a colon, ':', and specified as <LOCAL_DIR>:<REMOTE_DIR> (eg. --map-aliases /Users/<User Name>:/Volumes/Home).  \
-                Multiple maps may be specified delimited by a comma, ','.  You may also set via the environment variable HTTM_MAP_ALIASES.")
-                .use_value_delimiter(true)
+            Arg::new("BACKUP_RESTORE")
+                .long("backup-restore")
+                .value_name("SNAPSHOT")
                 .takes_value(true)
-                .value_parser(clap::builder::ValueParser::os_string())
-                .display_order(18)
+                .requires("BACKUP_BACKEND")
+                .help("with \"--backup-backend\", rather than display the versions of the input files, restore the version of each within SNAPSHOT, \
+                as a copy in the working directory, named just as an interactive restore would name it.  \
+                SNAPSHOT is given as \"--backup-backend\" labels it (eg. kopia:1a2b3c or duplicacy:3), and may be shortened to any prefix which is unique.")
+                .display_order(92)
         )
         .arg(
-            Arg::new("REMOTE_DIR")
-                .long("remote-dir")
-                .visible_aliases(&["remote", "snap-point"])
-                .help("DEPRECATED.  Use MAP_ALIASES. Manually specify that mount point for ZFS (directory which contains a \".zfs\" directory) or btrfs-snapper \
-                (directory which contains a \".snapshots\" directory), such as the local mount point for a remote share.  You may also set via the HTTM_REMOTE_DIR environment variable.")
+            Arg::new("BACKUP_COVERAGE")
+                .long("backup-coverage")
+                .requires("BACKUP_BACKEND")
+                .conflicts_with("BACKUP_RESTORE")
+                .help("with \"--backup-backend\", rather than display the versions of the input files, report which versions are found upon local snapshots only, \
+                within the backends only, or both, as a check that recent versions have reached an offsite backup.  \
+                More than one BACKEND may be given, delimited by a comma, ',' (eg. --backup-backend=kopia,duplicacy --backup-coverage).  \
+                Versions are matched by modify time, to the second, and size.")
+                .display_order(93)
+        )
+        .arg(
+            Arg::new("FROM_SEND_STREAM")
+                .long("from-send-stream")
+                .value_name("STREAM_FILE")
                 .takes_value(true)
+                .multiple_values(true)
+                .use_value_delimiter(true)
                 .value_parser(clap::builder::ValueParser::os_string())
-                .display_order(19)
+                .requires("INPUT_FILES")
+                .help("EXPERIMENTAL: index the versions of the input paths within saved \"zfs send\" stream files, such as archived replication streams, \
+                without receiving them into a pool, for the recovery of versions whose snapshots have since been destroyed.  \
+                Stream files are delimited by a comma, ',', and are read in the order given, so an incremental stream should follow the stream it is relative to.  \
+                Each input path is relative to the root of the dataset sent (eg. httm --from-send-stream=full.zstream,incr.zstream /home/alice/notes.txt).  \
+                Raw (encrypted) and deduplicated streams, and blocks compressed other than with lz4, are not supported.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "SERVE_DBUS", "GUI_HELPER", "SMB_VERSIONS", "SERVE_HTTP", "HOSTS", "MATRIX", "RECURSIVE", "DELETED_MODE"])
+                .display_order(94)
         )
         .arg(
-            Arg::new("LOCAL_DIR")
-                .long("local-dir")
-                .visible_alias("local")
-                .help("DEPRECATED.  Use MAP_ALIASES.  Used with \"remote-dir\" to determine where the corresponding live root filesystem of the dataset is.  \
-                Put more simply, the \"local-dir\" is likely the directory you backup to your \"remote-dir\".  If not set, httm defaults to your current working directory.  \
-                You may also set via the environment variable HTTM_LOCAL_DIR.")
-                .requires("REMOTE_DIR")
+            Arg::new("LIST_ALIASES")
+                .long("list-aliases")
+                .value_name("FORMAT")
                 .takes_value(true)
-                .value_parser(clap::builder::ValueParser::os_string())
-                .display_order(20)
+                .min_values(0)
+                .require_equals(true)
+                .possible_values(["pretty", "json"])
+                .default_missing_value("pretty")
+                .help("print each alias in effect, as resolved from the aliases file, MAP_ALIASES, REMOTE_DIR and LOCAL_DIR, and their environment variables, \
+                that is, each local dir, the remote dir it maps to, and the remote dir's filesystem.  \
+                Then, for each input file, or the working directory when none is given, print the dataset or alias httm would search, \
+                the path relative to its snapshots, and how many snapshots there are, or why there are none, and exit.  \
+                The default FORMAT is \"pretty\", and \"json\" prints the same as a JSON object.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "SERVE_DBUS", "GUI_HELPER", "SMB_VERSIONS", "SERVE_HTTP", "HOSTS", "MATRIX", "RECURSIVE", "DELETED_MODE"])
+                .display_order(95)
+        )
+        .arg(
+            Arg::new("LIST_DATASETS")
+                .long("list-datasets")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .possible_values(["pretty", "json"])
+                .default_missing_value("pretty")
+                .help("print each supported dataset httm detected: its mount, its name, its filesystem, whether it is local or on the network, \
+                how many snapshots of it are visible, and any snap dir given for it with SNAP_DIRS, as well as any external roots and filtered dirs.  \
+                Then, for each input file, or the working directory when none is given, print where httm would search for its snapshot versions, as \"--list-aliases\", and exit.  \
+                The default FORMAT is \"pretty\", and \"json\" prints the same as a JSON object.")
+                .conflicts_with_all(&["INTERACTIVE", "SELECT", "RESTORE", "LAST_SNAP", "PICKER", "PINS", "PICK_ROOT", "SNAP_FILE_MOUNT", "MOUNT_FOR_FILE", "DIR_SUMMARY", "SERVE_STDIO", "SERVE_DBUS", "GUI_HELPER", "SMB_VERSIONS", "SERVE_HTTP", "HOSTS", "MATRIX", "RECURSIVE", "DELETED_MODE", "LIST_ALIASES"])
+                .display_order(96)
+        )
+        .arg(
+            Arg::new("CHECK_CONFIG")
+                .long("check-config")
+                .help("check each setting httm would use, whether given on the command line or in the environment: \
+                MAP_ALIASES and the aliases file, REMOTE_DIR and LOCAL_DIR, SNAP_DIRS, EXTERNAL_ROOTS, the pins file, and HTTM_PRIVILEGED_HELPER.  \
+                Report every problem found, by the setting, entry, or line where it was found, and then exit, before any lookup is made.")
+                .conflicts_with_all(&["DOCTOR", "INPUT_FILES"])
+                .display_order(97)
+        )
+        .arg(
+            Arg::new("JSON")
+                .long("json")
+                .help("print errors to stderr as a single line JSON object, with the error's exit \"code\", its \"kind\", its \"message\", and, where known, the offending \"dataset\" or \"path\", \
+                so that programs which call httm may react to a failure without parsing its prose.  \
+                Listings of versions are then also printed to stdout as a single JSON object, rather than as a table, in which each version has its \"path\", \"size\", \"modify_time\", \"date\", whether it \"is_live\", and its source \"dataset\" and \"snapshot\".  \
+                As with \"--forensic\", listings then also include the SHA-256 of each version, the version of httm, and when the listing was generated.")
+                .display_order(98)
         )
         .arg(
             Arg::new("UTC")
                 .long("utc")
                 .help("use UTC for date display and timestamps")
-                .display_order(21)
+                .display_order(99)
         )
         .arg(
             Arg::new("DEBUG")
                 .long("debug")
                 .help("print configuration and debugging info")
-                .display_order(22)
+                .display_order(100)
+        )
+        .arg(
+            Arg::new("DOCTOR")
+                .long("doctor")
+                .help("check the environment for the usual reasons httm might find nothing: whether any supported datasets are mounted, \
+                whether the snapshots of each dataset are visible, whether the 'zfs' and 'btrfs' commands are available, and with what privileges httm runs.  \
+                Then make a sample lookup of the working directory, print any findings, and exit.")
+                .exclusive(true)
+                .display_order(101)
         )
         .arg(
             Arg::new("ZSH_HOT_KEYS")
                 .long("install-zsh-hot-keys")
                 .help("install zsh hot keys to the users home directory, and then exit")
                 .exclusive(true)
-                .display_order(23)
+                .display_order(102)
+        )
+        .arg(
+            Arg::new("PRIVILEGED_HELPER")
+                .long("privileged-helper")
+                .help("serve, as line delimited JSON over stdin(3) and stdout(3), only those few requests which require privileges: \
+                taking snapshots with \"--snap\", and importing and exporting a pool with \"--import-pool\".  \
+                httm need not otherwise be privileged.  When the environment variable HTTM_PRIVILEGED_HELPER is set to a command which grants privileges, like \"sudo -n\" or \"pkexec\", \
+                and httm runs unprivileged, httm runs itself as the helper via that command, whenever it must take a snapshot, or import or export a pool.  \
+                A sudoers rule, like \"%wheel ALL=(root) NOPASSWD: /usr/bin/httm --privileged-helper\", then grants no more than these requests.  \
+                The helper takes only snapshots of mounted ZFS datasets named as httm would name them, and only imports a pool read-only, and exports only a pool it imported.")
+                .exclusive(true)
+                .display_order(103)
+        )
+        .arg(
+            Arg::new("SHELL_HELPERS")
+                .long("shell-helpers")
+                .help("print shell functions which use httm to navigate snapshots, such as \"cds\", which changes directory to the most recent unique snapshot version of the working directory, and then exit.  \
+                Source the helpers in your shell's rc file by adding: eval \"$(httm --shell-helpers)\"")
+                .exclusive(true)
+                .display_order(104)
         )
         .get_matches()
 }
@@ -426,31 +1614,192 @@ pub struct Config {
     opt_zeros: bool,
     opt_no_pretty: bool,
     opt_no_live: bool,
+    opt_omit_ditto: bool,
     opt_recursive: bool,
     opt_exact: bool,
     opt_overwrite: bool,
+    clobbered_live: ClobberedLive,
     opt_no_filter: bool,
+    opt_hidden: bool,
+    opt_owner: Option<u32>,
+    // a window of modify times, outside of which snapshot versions are ignored
+    opt_after: Option<SystemTime>,
+    opt_before: Option<SystemTime>,
+    // only the newest unique versions of each file
+    opt_last_n: Option<usize>,
+    opt_perms: bool,
+    opt_snap_time: bool,
     opt_no_snap: bool,
+    opt_matrix: bool,
+    opt_picker: bool,
+    opt_git_xref: bool,
+    opt_show_redundancy: bool,
+    opt_stats: bool,
+    opt_exec: Option<String>,
+    opt_edit: bool,
+    opt_copy_path: bool,
+    opt_cat: bool,
+    opt_diff_tool: Option<String>,
+    opt_workspace: Option<WorkspaceDir>,
+    // with a bound on the entries counted per version
+    opt_dir_size: Option<usize>,
+    // evidence-grade listings, with a hash of every version, for forensic and JSON modes
+    opt_chain_of_custody: bool,
+    // listings as a single JSON object, rather than as a table
+    opt_json: bool,
     opt_debug: bool,
     requested_utc_offset: UtcOffset,
     exec_mode: ExecMode,
     dataset_collection: DatasetCollection,
     deleted_mode: DeletedMode,
+    // globs, as C strings, as they are only ever matched by fnmatch(3)
+    deleted_excludes: Vec<CString>,
+    opt_deleted_recreated: bool,
+    open_files_policy: OpenFilesPolicy,
+    sort_versions_by: SortVersionsBy,
+    version_time_by: VersionTimeBy,
+    dedup_versions_by: DedupVersionsBy,
+    print_selected: PrintSelected,
     pwd: PathData,
     opt_requested_dir: Option<PathData>,
 }
 
 impl Config {
-    fn new() -> HttmResult<Self> {
-        let arg_matches = parse_args();
-        Config::from_matches(arg_matches)
-    }
-
     fn from_matches(matches: ArgMatches) -> HttmResult<Self> {
+        // the helper serves privileged requests, and nothing else
+        if matches.is_present("PRIVILEGED_HELPER") {
+            privileged_helper_exec()?
+        }
+
+        // a frontend wants to hear of a scan from its start
+        if matches.is_present("PROGRESS_JSON") {
+            let fd = match matches.value_of("PROGRESS_JSON") {
+                Some(fd) => fd.parse().map_err(|_| {
+                    HttmError::new("PROGRESS_JSON must be the number of an open file descriptor.")
+                })?,
+                None => DEFAULT_PROGRESS_FD,
+            };
+
+            init_progress_json(fd)?;
+        }
+
+        // forensic mode must be in place before we touch anything at all
+        if let Some(audit_log) = matches.value_of_os("FORENSIC") {
+            init_forensic(Path::new(audit_log))?;
+
+            if matches.value_of("BISECT").is_some() {
+                return Err(HttmError::new(
+                    "httm will not run a bisect test command in forensic mode, as each test runs against a temporary copy.",
+                ));
+            }
+        }
+
+        // and the hash policy, before any version is hashed and its hash cached
+        if let Some(values) = matches.values_of("HASH") {
+            let mut opt_algorithm = None;
+            let mut is_partial = false;
+
+            for value in values {
+                let algorithm = match value {
+                    "partial" => {
+                        is_partial = true;
+                        continue;
+                    }
+                    "sha256" => HashAlgorithm::Sha256,
+                    "xxhash" => HashAlgorithm::Xxhash,
+                    "blake3" => HashAlgorithm::Blake3,
+                    _ => unreachable!("clap permits only the possible values of HASH"),
+                };
+
+                if opt_algorithm.replace(algorithm).is_some() {
+                    return Err(HttmError::new(
+                        "HASH may name only one algorithm, optionally followed by \"partial\".",
+                    ));
+                }
+            }
+
+            init_hash_policy(HashPolicy {
+                algorithm: opt_algorithm.unwrap_or(HashAlgorithm::Sha256),
+                is_partial,
+            })?;
+        }
+
+        if matches.is_present("HASH_BANDWIDTH")
+            || matches.is_present("HASH_IOPS")
+            || matches.is_present("HASH_JOBS_PER_DEVICE")
+        {
+            let parse_count = |arg: &str| -> HttmResult<Option<u64>> {
+                match matches.value_of(arg) {
+                    Some(value) => match value.parse::<u64>() {
+                        Ok(num) if num > 0 => Ok(Some(num)),
+                        _ => Err(HttmError::new(&format!(
+                            "{} must be a positive integer.",
+                            arg
+                        ))),
+                    },
+                    None => Ok(None),
+                }
+            };
+
+            init_hash_io_budget(HashIoBudget {
+                opt_bytes_per_sec: matches
+                    .value_of("HASH_BANDWIDTH")
+                    .map(parse_byte_rate)
+                    .transpose()?,
+                opt_reads_per_sec: parse_count("HASH_IOPS")?,
+                opt_files_per_device: parse_count("HASH_JOBS_PER_DEVICE")?.map(|num| num as usize),
+            })?;
+        }
+
+        // likewise, a restriction to the invoking user's files must precede any lookup
+        if matches.is_present("RESTRICT_TO_OWNER") {
+            init_restrict_to_owner()?;
+
+            if matches.value_of("BISECT").is_some() {
+                deny_if_restricted_to_owner("run a bisect test command")?;
+            }
+        }
+
+        if matches.is_present("DOCTOR") {
+            doctor()?
+        }
+
+        if matches.is_present("CHECK_CONFIG") {
+            check_config(&matches)?
+        }
+
+        if let Some(pool_name) = matches.value_of_os("IMPORT_POOL") {
+            import_pool_exec(pool_name)?
+        }
+
+        if matches.is_present("ENABLE_SNAPDIR") {
+            enable_snapdir_exec()?
+        }
+
         if matches.is_present("ZSH_HOT_KEYS") {
             install_hot_keys()?
         }
 
+        if matches.is_present("SHELL_HELPERS") {
+            print_shell_helpers()?
+        }
+
+        if let Some(raw_pins) = matches.values_of_os("PIN") {
+            pin_paths(raw_pins)?
+        }
+
+        if let Some(raw_remote_dir) = matches.value_of_os("ADD_ALIAS") {
+            add_alias(raw_remote_dir)?
+        }
+
+        if let Some(command) = matches.value_of("HASH_CACHE") {
+            exec_hash_cache(command)?
+        }
+
+        if matches.is_present("NO_HASH_CACHE") {
+            disable_hash_cache();
+        }
+
         let requested_utc_offset = if matches.is_present("UTC") {
             UtcOffset::UTC
         } else {
@@ -468,13 +1817,176 @@ impl Config {
         let opt_recursive = matches.is_present("RECURSIVE");
         let opt_exact = matches.is_present("EXACT");
         let opt_no_live = matches.is_present("NO_LIVE");
+        let opt_omit_ditto = matches.is_present("OMIT_DITTO");
         let opt_no_filter = matches.is_present("NO_FILTER");
+        // like fd, the last of --hidden and --no-hidden given wins
+        let opt_hidden = match (matches.index_of("HIDDEN"), matches.index_of("NO_HIDDEN")) {
+            (Some(hidden), Some(no_hidden)) => hidden > no_hidden,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        let opt_owner = matches
+            .value_of("OWNER")
+            .map(get_uid_for_user)
+            .transpose()?;
+        let opt_after = matches
+            .value_of("AFTER")
+            .map(|raw| parse_date_bound(raw, requested_utc_offset))
+            .transpose()?;
+        let opt_before = matches
+            .value_of("BEFORE")
+            .map(|raw| parse_date_bound(raw, requested_utc_offset))
+            .transpose()?;
+        if let (Some(after), Some(before)) = (opt_after, opt_before) {
+            if before <= after {
+                return Err(HttmError::new(
+                    "BEFORE must be later than AFTER, or httm would find no versions between them.",
+                ));
+            }
+        }
+        let opt_last_n = match matches.value_of("LAST_N") {
+            Some(num) => {
+                let num: usize = num
+                    .parse()
+                    .map_err(|_| HttmError::new("LAST_N must be a whole number of versions."))?;
+
+                if num == 0 {
+                    return Err(HttmError::new(
+                        "LAST_N must be at least 1, or there would be no versions to display.",
+                    ));
+                }
+
+                Some(num)
+            }
+            None => None,
+        };
+        let opt_perms = matches.is_present("PERMS");
+        let opt_snap_time = matches.is_present("SNAP_TIME");
+        let opt_matrix = matches.is_present("MATRIX");
+        let opt_picker = matches.is_present("PICKER");
+        let opt_git_xref = matches.is_present("GIT_XREF");
+        let opt_show_redundancy = matches.is_present("SHOW_REDUNDANCY");
+        let opt_stats = matches.is_present("STATS");
+        let opt_exec = matches.value_of("EXEC").map(|command| command.to_owned());
+        let opt_edit = matches.is_present("EDIT");
+        let opt_copy_path = matches.is_present("COPY_PATH");
+        let opt_cat = matches.is_present("CAT");
+        let opt_diff_tool = matches
+            .value_of("DIFF_TOOL")
+            .map(|command| command.to_owned());
+        if matches.is_present("API_TOKEN_FILE")
+            && !matches.is_present("SERVE_HTTP")
+            && !matches.is_present("HOSTS")
+        {
+            return Err(HttmError::new(
+                "API_TOKEN_FILE is only of use along with either SERVE_HTTP or HOSTS.",
+            ));
+        }
+
+        let opt_workspace = if matches.is_present("WORKSPACE") {
+            match matches.value_of_os("WORKSPACE") {
+                Some(workspace_dir) => {
+                    let workspace_dir = PathBuf::from(workspace_dir);
+                    deny_unless_empty_workspace(&workspace_dir)?;
+                    Some(WorkspaceDir::Requested(workspace_dir))
+                }
+                None => Some(WorkspaceDir::Default),
+            }
+        } else {
+            None
+        };
+        let opt_dir_size = if matches.is_present("DIR_SIZE") {
+            match matches.value_of("DIR_SIZE") {
+                Some(max_entries) => {
+                    let max_entries: usize = max_entries.parse().map_err(|_| {
+                        HttmError::new("DIR_SIZE must be a whole number of entries.")
+                    })?;
+
+                    if max_entries == 0 {
+                        return Err(HttmError::new(
+                            "DIR_SIZE must be at least 1, or there would be nothing to count.",
+                        ));
+                    }
+
+                    Some(max_entries)
+                }
+                None => Some(DEFAULT_DIR_SIZE_MAX_ENTRIES),
+            }
+        } else {
+            None
+        };
+        let opt_chain_of_custody = matches.is_present("FORENSIC") || matches.is_present("JSON");
+        let opt_json = matches.is_present("JSON");
         let opt_debug = matches.is_present("DEBUG");
         let opt_overwrite = matches!(
             matches.value_of("RESTORE"),
             Some("overwrite") | Some("yolo")
         );
 
+        let clobbered_live = match matches.value_of_os("SET_ASIDE_DIR") {
+            Some(_) if matches.value_of("RESTORE") == Some("yolo") => {
+                return Err(HttmError::new(
+                    "SET_ASIDE_DIR may not be combined with \"--restore=yolo\", which sets nothing aside.",
+                ))
+            }
+            Some(raw_dir) => {
+                let set_aside_dir = Path::new(raw_dir).canonicalize().map_err(|err| {
+                    HttmError::with_context(
+                        &format!("httm could not find the set aside dir {:?}", raw_dir),
+                        err,
+                    )
+                })?;
+
+                if !set_aside_dir.is_dir() {
+                    let msg = format!(
+                        "The set aside dir {:?} is not a directory.",
+                        set_aside_dir
+                    );
+                    return Err(HttmError::new(&msg));
+                }
+
+                ClobberedLive::MoveInto(set_aside_dir)
+            }
+            None if matches.value_of("RESTORE") == Some("yolo") => ClobberedLive::Overwrite,
+            None => ClobberedLive::MoveAside,
+        };
+
+        let open_files_policy = match matches.value_of("OPEN_FILES") {
+            Some("refuse") => OpenFilesPolicy::Refuse,
+            Some("ignore") => OpenFilesPolicy::Ignore,
+            _ => OpenFilesPolicy::Warn,
+        };
+
+        let print_selected = match matches.value_of("PRINT") {
+            Some("live-path") => PrintSelected::LivePath,
+            Some("rel-path") => PrintSelected::RelativePath,
+            Some("snapshot-name") => PrintSelected::SnapName,
+            _ => PrintSelected::SnapPath,
+        };
+
+        let version_time_by = match matches.value_of("BY") {
+            Some("snap-time") => VersionTimeBy::SnapTime,
+            _ => VersionTimeBy::FileTime,
+        };
+
+        let sort_versions_by = match matches.value_of("SORT_VERSIONS") {
+            Some("size") => SortVersionsBy::Size,
+            Some("snapshot") => SortVersionsBy::SnapCreation,
+            Some("path") => SortVersionsBy::Path,
+            Some(_) => SortVersionsBy::ModifyTime,
+            None if version_time_by == VersionTimeBy::SnapTime => SortVersionsBy::SnapCreation,
+            None => SortVersionsBy::ModifyTime,
+        };
+
+        let dedup_versions_by = match matches.value_of("DEDUP_BY") {
+            _ if matches.is_present("NO_DEDUP") => DedupVersionsBy::Nothing,
+            Some("mtime") => DedupVersionsBy::ModifyTime,
+            Some("size") => DedupVersionsBy::Size,
+            Some("hash") => DedupVersionsBy::Hash,
+            Some("none") => DedupVersionsBy::Nothing,
+            _ => DedupVersionsBy::ModifyTimeAndSize,
+        };
+
         let mut deleted_mode = match matches.value_of("DELETED_MODE") {
             Some("") | Some("all") => DeletedMode::Enabled,
             Some("single") => DeletedMode::DepthOfOne,
@@ -482,19 +1994,48 @@ impl Config {
             _ => DeletedMode::Disabled,
         };
 
+        let mut deleted_exclude_globs: Vec<&str> = matches
+            .values_of("DELETED_EXCLUDE")
+            .map(|globs| globs.collect())
+            .unwrap_or_default();
+
+        if !matches.is_present("NO_DELETED_DEFAULTS") {
+            deleted_exclude_globs.extend_from_slice(DEFAULT_DELETED_EXCLUDES);
+        }
+
+        let opt_deleted_recreated = matches.is_present("DELETED_RECREATED");
+
+        let deleted_excludes: Vec<CString> = deleted_exclude_globs
+            .into_iter()
+            .map(|glob| {
+                CString::new(glob)
+                    .map_err(|_| HttmError::new("DELETED_EXCLUDE may not contain a nul byte."))
+            })
+            .collect::<HttmResult<Vec<CString>>>()?;
+
         let opt_interactive_mode = if matches.is_present("LAST_SNAP") {
-            let request_relative = if matches!(
-                matches.value_of("LAST_SNAP"),
-                Some("rel") | Some("relative")
-            ) {
-                RequestRelative::Relative
-            } else {
-                RequestRelative::Absolute
+            let request_relative = match matches.value_of("LAST_SNAP") {
+                Some("rel") | Some("relative") => RequestRelative::Relative,
+                Some("rel-exhaustive") | Some("relative-exhaustive") => {
+                    RequestRelative::RelativeExhaustive
+                }
+                _ => RequestRelative::Absolute,
             };
             Some(InteractiveMode::LastSnap(request_relative))
         } else if matches.is_present("RESTORE") {
             Some(InteractiveMode::Restore)
-        } else if matches.is_present("SELECT") {
+        } else if matches.is_present("SELECT")
+            || opt_picker
+            || opt_exec.is_some()
+            || opt_edit
+            || opt_copy_path
+            || opt_cat
+            || opt_diff_tool.is_some()
+            || opt_workspace.is_some()
+            || matches.is_present("PRINT")
+        {
+            // picker is just select mode with a stricter output contract, and exec, edit, copy path,
+            // cat, diff tool and workspace are just select mode with something else to do with the selection
             Some(InteractiveMode::Select)
         } else if matches.is_present("INTERACTIVE") {
             Some(InteractiveMode::Browse)
@@ -504,6 +2045,145 @@ impl Config {
 
         let mut exec_mode = if matches.is_present("MOUNT_FOR_FILE") {
             ExecMode::MountsForFiles
+        } else if matches.is_present("SERVE_STDIO") {
+            ExecMode::ServeStdio
+        } else if matches.is_present("SERVE_DBUS") {
+            match matches.value_of("SERVE_DBUS") {
+                Some("system") => ExecMode::ServeDbus(DbusBus::System),
+                _ => ExecMode::ServeDbus(DbusBus::Session),
+            }
+        } else if let Some(requested_path) = matches.value_of_os("GUI_HELPER") {
+            ExecMode::GuiHelper(PathBuf::from(requested_path))
+        } else if let Some(share_root) = matches.value_of_os("SMB_VERSIONS") {
+            ExecMode::SmbVersions(PathBuf::from(share_root))
+        } else if let Some(hosts) = matches.values_of("HOSTS") {
+            ExecMode::QueryHosts(HostsQuery {
+                hosts: hosts.map(|host| host.to_owned()).collect(),
+                paths: matches
+                    .values_of_os("INPUT_FILES")
+                    .map(|input_files| input_files.map(PathBuf::from).collect())
+                    .unwrap_or_default(),
+                // clap requires a token file along with HOSTS
+                api_token_file: matches
+                    .value_of_os("API_TOKEN_FILE")
+                    .map(PathBuf::from)
+                    .unwrap_or_default(),
+            })
+        } else if let Some(stream_files) = matches.values_of_os("FROM_SEND_STREAM") {
+            ExecMode::SendStream(SendStreamQuery {
+                stream_files: stream_files.map(PathBuf::from).collect(),
+                // clap requires input paths along with FROM_SEND_STREAM
+                paths: matches
+                    .values_of_os("INPUT_FILES")
+                    .map(|input_files| input_files.map(PathBuf::from).collect())
+                    .unwrap_or_default(),
+            })
+        } else if let Some(backends) = matches.values_of("BACKUP_BACKEND") {
+            let backends: Vec<String> = backends.map(|backend| backend.to_owned()).collect();
+            let opt_coverage = matches.is_present("BACKUP_COVERAGE");
+
+            if backends.len() > 1 && !opt_coverage {
+                return Err(HttmError::new(
+                    "Only one BACKEND may be given, except along with BACKUP_COVERAGE.",
+                ));
+            }
+
+            ExecMode::Backup(BackupQuery {
+                backends,
+                opt_restore_snapshot: matches
+                    .value_of("BACKUP_RESTORE")
+                    .map(|snapshot| snapshot.to_owned()),
+                opt_coverage,
+            })
+        } else if let Some(root_dir) = matches.value_of_os("SERVE_HTTP") {
+            ExecMode::ServeHttp(HttpServeOptions {
+                root_dir: PathBuf::from(root_dir),
+                listen: matches
+                    .value_of("LISTEN")
+                    .unwrap_or(DEFAULT_HTTP_LISTEN)
+                    .to_owned(),
+                opt_api_token_file: matches.value_of_os("API_TOKEN_FILE").map(PathBuf::from),
+            })
+        } else if let Some(list_format) = matches.value_of("LIST_ALIASES") {
+            ExecMode::ListAliases(get_list_format(list_format))
+        } else if let Some(list_format) = matches.value_of("LIST_DATASETS") {
+            ExecMode::ListDatasets(get_list_format(list_format))
+        } else if matches.is_present("DIR_SUMMARY") {
+            ExecMode::DirSummary
+        } else if matches.is_present("BLAME") {
+            ExecMode::Blame
+        } else if matches.is_present("NUM_VERSIONS") {
+            match matches.value_of("NUM_VERSIONS") {
+                Some("dates") => ExecMode::NumVersions(NumVersionsFormat::WithDates),
+                _ => ExecMode::NumVersions(NumVersionsFormat::Count),
+            }
+        } else if let Some(byte_range) = matches.value_of("BYTE_RANGE") {
+            let (start, len) = byte_range
+                .split_once(':')
+                .and_then(|(start, len)| Some((start.parse().ok()?, len.parse().ok()?)))
+                .ok_or_else(|| {
+                    HttmError::new(
+                        "BYTE_RANGE must be a whole number START and LENGTH, in bytes, separated by a colon.",
+                    )
+                })?;
+
+            ExecMode::Extract(ExtractRequest::ByteRange { start, len })
+        } else if let Some(members) = matches.values_of("MEMBER") {
+            ExecMode::Extract(ExtractRequest::Members(
+                members.map(|member| member.to_owned()).collect(),
+            ))
+        } else if let Some(mut patch_paths) = matches.values_of_os("PATCH") {
+            // clap ensures there are exactly two
+            match (patch_paths.next(), patch_paths.next()) {
+                (Some(old_path), Some(new_path)) => {
+                    ExecMode::Patch(PathBuf::from(old_path), PathBuf::from(new_path))
+                }
+                _ => unreachable!(),
+            }
+        } else if matches.is_present("UNDELETE") {
+            ExecMode::Undelete
+        } else if matches.is_present("SIZE_COLLAPSE") {
+            let percent = match matches.value_of("SIZE_COLLAPSE") {
+                Some(percent) => {
+                    let percent: u64 = percent.parse().map_err(|_| {
+                        HttmError::new("SIZE_COLLAPSE must be a whole number percentage.")
+                    })?;
+
+                    if !(1..100).contains(&percent) {
+                        return Err(HttmError::new(
+                            "SIZE_COLLAPSE must be a percentage of at least 1, and less than 100.",
+                        ));
+                    }
+
+                    percent
+                }
+                None => DEFAULT_SIZE_COLLAPSE_PERCENT,
+            };
+            ExecMode::Triage(TriageMode::SizeCollapse(percent))
+        } else if matches.is_present("RANSOMWARE") {
+            ExecMode::Triage(TriageMode::Encryption)
+        } else if let Some(repo_dir) = matches.value_of_os("TO_GIT") {
+            ExecMode::ToGit(PathBuf::from(repo_dir))
+        } else if matches.is_present("FOLLOW") {
+            let interval = match matches.value_of("FOLLOW") {
+                Some(secs) => {
+                    let secs: u64 = secs
+                        .parse()
+                        .map_err(|_| HttmError::new("FOLLOW must be a whole number of seconds."))?;
+
+                    if secs == 0 {
+                        return Err(HttmError::new(
+                            "FOLLOW must be at least 1 second, or httm would never rest.",
+                        ));
+                    }
+
+                    Duration::from_secs(secs)
+                }
+                None => DEFAULT_FOLLOW_INTERVAL,
+            };
+            ExecMode::Follow(interval)
+        } else if matches.is_present("BISECT") {
+            ExecMode::Bisect(matches.value_of("BISECT").map(|command| command.to_owned()))
         } else if matches.is_present("SNAP_FILE_MOUNT") {
             ExecMode::SnapFileMount
         } else if let Some(interactive_mode) = opt_interactive_mode {
@@ -519,15 +2199,14 @@ impl Config {
 
         if opt_recursive {
             if matches!(exec_mode, ExecMode::Display) {
-                return Err(
-                    HttmError::new("Recursive search not available in Display Mode.").into(),
-                );
+                return Err(HttmError::new(
+                    "Recursive search not available in Display Mode.",
+                ));
             }
         } else if opt_no_filter {
             return Err(HttmError::new(
                 "No filter mode only available when recursive search is enabled.",
-            )
-            .into());
+            ));
         }
 
         // current working directory will be helpful in a number of places
@@ -537,27 +2216,71 @@ impl Config {
             } else {
                 return Err(HttmError::new(
                     "Could not obtain a canonical path for your working directory",
-                )
-                .into());
+                ));
             }
         } else {
             return Err(HttmError::new(
                 "Working directory does not exist or your do not have permissions to access it.",
-            )
-            .into());
+            ));
         };
 
         // paths are immediately converted to our PathData struct
-        let mut paths: Vec<PathData> = if let Some(input_files) =
-            matches.values_of_os("INPUT_FILES")
+        // paths inside a container are mapped to their host dirs, and are never canonicalized,
+        // as a path inside a container means nothing to the host
+        let opt_container_mounts = matches
+            .value_of("CONTAINER")
+            .map(parse_container_mounts)
+            .transpose()?;
+
+        // absolute paths are paths within an alternate root, if one is requested,
+        // and, like paths inside a container, are never canonicalized
+        let opt_altroot = match matches.value_of_os("ALTROOT") {
+            Some(value) => match canonicalize(value) {
+                Ok(altroot) if httm_is_dir(&PathData::from(altroot.as_path())) => Some(altroot),
+                _ => {
+                    return Err(HttmError::new(
+                        "The alternate root requested does not exist or is not a directory.",
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        let get_altroot_path = |path: &Path| -> Option<PathBuf> {
+            match &opt_altroot {
+                Some(altroot) if path.is_absolute() => {
+                    Some(altroot.join(path.strip_prefix("/").unwrap_or(path)))
+                }
+                _ => None,
+            }
+        };
+
+        let mut paths: Vec<PathData> = if matches.is_present("ALL_DATASETS") {
+            // paths on each dataset can only be known once we know the datasets, see below
+            Vec::new()
+        } else if matches.is_present("PINS") {
+            vec![select_pinned_path()?]
+        } else if matches.is_present("PICK_ROOT") {
+            vec![select_root_path()?]
+        } else if let (Some(input_files), Some(container_mounts)) =
+            (matches.values_of_os("INPUT_FILES"), &opt_container_mounts)
         {
+            input_files
+                .map(|path| get_host_path(Path::new(path), container_mounts))
+                .map(|res| res.map(|path| PathData::from(path.as_path())))
+                .collect::<HttmResult<Vec<PathData>>>()?
+        } else if let Some(input_files) = matches.values_of_os("INPUT_FILES") {
             input_files
                 .par_bridge()
                 .map(Path::new)
                 // canonicalize() on a deleted relative path will not exist,
                 // so we have to join with the pwd to make a path that
                 // will exist on a snapshot
-                .map(|path| canonicalize(path).unwrap_or_else(|_| pwd.clone().path_buf.join(path)))
+                .map(|path| {
+                    get_altroot_path(path).unwrap_or_else(|| {
+                        canonicalize(path).unwrap_or_else(|_| pwd.clone().path_buf.join(path))
+                    })
+                })
                 .map(|path| PathData::from(path.as_path()))
                 .collect()
         } else {
@@ -565,18 +2288,65 @@ impl Config {
                 // setting pwd as the path, here, keeps us from waiting on stdin when in certain modes
                 //  is more like Interactive and DisplayRecursive in this respect in requiring only one
                 // input, and waiting on one input from stdin is pretty silly
-                ExecMode::Interactive(_) | ExecMode::DisplayRecursive(_) => {
+                ExecMode::Interactive(_)
+                | ExecMode::DisplayRecursive(_)
+                | ExecMode::DirSummary
+                | ExecMode::ServeStdio
+                | ExecMode::ServeDbus(_)
+                | ExecMode::GuiHelper(_)
+                | ExecMode::SmbVersions(_)
+                | ExecMode::ServeHttp(_)
+                | ExecMode::QueryHosts(_)
+                | ExecMode::SendStream(_)
+                | ExecMode::ListAliases(_)
+                | ExecMode::ListDatasets(_)
+                | ExecMode::Undelete
+                | ExecMode::Triage(_)
+                | ExecMode::Patch(_, _) => {
                     vec![pwd.clone()]
                 }
-                ExecMode::Display | ExecMode::SnapFileMount | ExecMode::MountsForFiles => {
-                    read_stdin()?
+                ExecMode::Display
+                | ExecMode::SnapFileMount
+                | ExecMode::MountsForFiles
+                | ExecMode::Bisect(_)
+                | ExecMode::Blame
+                | ExecMode::NumVersions(_)
+                | ExecMode::ToGit(_)
+                | ExecMode::Follow(_)
+                | ExecMode::Extract(_)
+                | ExecMode::Backup(_) => match &opt_container_mounts {
+                    Some(container_mounts) => read_stdin()?
+                        .iter()
+                        .map(|string| get_host_path(Path::new(&string), container_mounts))
+                        .map(|res| res.map(|path| PathData::from(path.as_path())))
+                        .collect::<HttmResult<Vec<PathData>>>()?,
+                    None => read_stdin()?
                         .par_iter()
-                        .map(|string| PathData::from(Path::new(&string)))
-                        .collect()
-                }
+                        .map(Path::new)
+                        .map(|path| get_altroot_path(path).unwrap_or_else(|| path.to_path_buf()))
+                        .map(|path| PathData::from(path.as_path()))
+                        .collect(),
+                },
             }
         };
 
+        // paths within an overlay are mapped to the dirs which back the overlay
+        if matches.is_present("OVERLAY") {
+            let overlay_mounts = parse_overlay_mounts()?;
+
+            paths = paths
+                .iter()
+                .map(|pathdata| get_backing_path(&pathdata.path_buf, &overlay_mounts))
+                .map(|path| PathData::from(path.as_path()))
+                .collect();
+        }
+
+        // paths which don't exist may be deleted files, and only their owned versions will be found
+        paths
+            .iter()
+            .filter(|pathdata| pathdata.metadata().is_some())
+            .try_for_each(|pathdata| deny_unless_owned("look up", &pathdata.path_buf))?;
+
         // deduplicate pathdata and sort if in display mode --
         // so input of ./.z* and ./.zshrc will only print ./.zshrc once
         paths = if paths.len() > 1 {
@@ -591,7 +2361,7 @@ impl Config {
 
         // for exec_modes in which we can only take a single directory, process how we handle those here
         let opt_requested_dir: Option<PathData> = match exec_mode {
-            ExecMode::Interactive(_) | ExecMode::DisplayRecursive(_) => {
+            ExecMode::Interactive(_) | ExecMode::DisplayRecursive(_) | ExecMode::Triage(_) => {
                 match paths.len() {
                     0 => Some(pwd.clone()),
                     1 => {
@@ -610,8 +2380,7 @@ impl Config {
                                             // doesn't make sense to have a non-dir in these modes
                                             return Err(HttmError::new(
                                                         "Path specified is not a directory, and therefore not suitable for browsing.",
-                                                    )
-                                                    .into());
+                                                    ));
                                         }
                                         InteractiveMode::LastSnap(_)
                                         | InteractiveMode::Restore
@@ -628,20 +2397,59 @@ impl Config {
                                     deleted_mode = DeletedMode::Disabled;
                                     None
                                 }
+                                ExecMode::Triage(_) => {
+                                    return Err(HttmError::new(
+                                        "Path specified is not a directory, and therefore not suitable for triage.",
+                                    ));
+                                }
                                 _ => unreachable!(),
                             }
                         }
                     }
-                    n if n > 1 => return Err(HttmError::new(
-                        "May only specify one path in the display recursive or interactive modes.",
-                    )
-                    .into()),
+                    // display recursive may search several roots, each in turn, see display_recursive_wrapper
+                    n if n > 1 && matches!(exec_mode, ExecMode::DisplayRecursive(_)) => {
+                        if let Some(non_dir) = paths.iter().find(|pathdata| !httm_is_dir(*pathdata))
+                        {
+                            return Err(HttmError::new(&format!(
+                                "Path specified is not a directory, and therefore not suitable as a root for a recursive search: {:?}",
+                                non_dir.path_buf
+                            )));
+                        }
+
+                        Some(paths[0].clone())
+                    }
+                    n if n > 1 => {
+                        return Err(HttmError::new(
+                            "May only specify one path in the interactive modes.",
+                        ))
+                    }
                     _ => {
                         unreachable!()
                     }
                 }
             }
-            ExecMode::Display | ExecMode::SnapFileMount | ExecMode::MountsForFiles => {
+            ExecMode::Display
+            | ExecMode::SnapFileMount
+            | ExecMode::MountsForFiles
+            | ExecMode::DirSummary
+            | ExecMode::ServeStdio
+            | ExecMode::ServeDbus(_)
+            | ExecMode::GuiHelper(_)
+            | ExecMode::SmbVersions(_)
+            | ExecMode::ServeHttp(_)
+            | ExecMode::QueryHosts(_)
+            | ExecMode::SendStream(_)
+            | ExecMode::ListAliases(_)
+            | ExecMode::ListDatasets(_)
+            | ExecMode::Bisect(_)
+            | ExecMode::Blame
+            | ExecMode::NumVersions(_)
+            | ExecMode::ToGit(_)
+            | ExecMode::Follow(_)
+            | ExecMode::Undelete
+            | ExecMode::Patch(_, _)
+            | ExecMode::Extract(_)
+            | ExecMode::Backup(_) => {
                 // in non-interactive mode / display mode, requested dir is just a file
                 // like every other file and pwd must be the requested working dir.
                 None
@@ -651,7 +2459,78 @@ impl Config {
         // obtain a map of datasets, a map of snapshot directories, and possibly a map of
         // alternate filesystems and map of aliases if the user requests
         let dataset_collection = {
-            let (map_of_datasets, map_of_snaps, vec_of_filter_dirs) = parse_mounts_exec()?;
+            // user defined snap dirs must be known before we search for snapshots
+            let opt_map_of_snap_dirs = match matches.values_of_os("SNAP_DIRS") {
+                Some(cmd_snap_dirs) => {
+                    let snap_dir_values: Vec<String> = cmd_snap_dirs
+                        .into_iter()
+                        .map(|os_str| os_str.to_string_lossy().to_string())
+                        .collect();
+                    Some(parse_snap_dirs(&snap_dir_values)?)
+                }
+                None => parse_snap_dirs_from_env()?,
+            };
+
+            let (map_of_datasets, vec_of_filter_dirs) =
+                match parse_mounts_exec(&opt_map_of_snap_dirs, &opt_altroot) {
+                    // neither a stream file nor a backup repository needs a dataset, and either may
+                    // well be read on a system without one
+                    Err(HttmError::NoDatasetFound(_))
+                        if matches!(exec_mode, ExecMode::SendStream(_) | ExecMode::Backup(_)) =>
+                    {
+                        (MapOfDatasets::new(), VecOfFilterDirs::new())
+                    }
+                    res => res?,
+                };
+
+            let opt_max_snaps = match matches.value_of("MAX_SNAPS") {
+                Some(max_snaps) => {
+                    let max_snaps: usize = max_snaps.parse().map_err(|_| {
+                        HttmError::new("MAX_SNAPS must be a whole number of snapshots.")
+                    })?;
+
+                    if max_snaps == 0 {
+                        return Err(HttmError::new(
+                            "MAX_SNAPS must be at least 1, or there would be nothing to search.",
+                        ));
+                    }
+
+                    Some(max_snaps)
+                }
+                None => None,
+            };
+
+            // snap mounts are only found for a dataset once it is searched
+            let map_of_snaps = LazyMapOfSnaps::new(
+                &map_of_datasets,
+                &opt_map_of_snap_dirs,
+                opt_max_snaps,
+                is_forensic(),
+            )?;
+
+            // external roots are only of interest when a dataset is mounted beneath them
+            let external_root_values: Vec<PathBuf> = match matches.values_of_os("EXTERNAL_ROOTS") {
+                Some(cmd_external_roots) => cmd_external_roots.map(PathBuf::from).collect(),
+                None => std::env::var_os("HTTM_EXTERNAL_ROOTS")
+                    .map(|env_external_roots| {
+                        env_external_roots
+                            .to_string_lossy()
+                            .split_terminator(',')
+                            .map(PathBuf::from)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            };
+
+            let vec_of_external_roots: VecOfExternalRoots = external_root_values
+                .into_iter()
+                .map(|external_root| canonicalize(&external_root).unwrap_or(external_root))
+                .filter(|external_root| {
+                    map_of_datasets
+                        .keys()
+                        .any(|mount| mount.starts_with(external_root))
+                })
+                .collect();
 
             // for a collection of btrfs mounts, indicates a common snapshot directory to ignore
             let opt_common_snap_dir = get_common_snap_dir(&map_of_datasets, &map_of_snaps);
@@ -663,7 +2542,7 @@ impl Config {
                 None
             };
 
-            let alias_values: Option<Vec<String>> =
+            let explicit_alias_values: Option<Vec<String>> =
                 if let Some(env_map_aliases) = std::env::var_os("HTTM_MAP_ALIASES") {
                     Some(
                         env_map_aliases
@@ -681,6 +2560,27 @@ impl Config {
                     })
                 };
 
+            // aliases given explicitly are added last, so they win over those of the aliases file,
+            // and, without a home dir, there is no aliases file
+            let file_alias_values: Vec<String> = match get_aliases_file_path() {
+                Ok(aliases_file_path) => read_aliases_file(&aliases_file_path)?
+                    .into_iter()
+                    .map(|(_line_num, alias)| alias)
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+
+            let alias_values: Option<Vec<String>> =
+                match (file_alias_values.is_empty(), explicit_alias_values) {
+                    (true, opt_explicit_alias_values) => opt_explicit_alias_values,
+                    (false, opt_explicit_alias_values) => Some(
+                        file_alias_values
+                            .into_iter()
+                            .chain(opt_explicit_alias_values.into_iter().flatten())
+                            .collect(),
+                    ),
+                };
+
             let raw_snap_dir = if let Some(value) = matches.value_of_os("REMOTE_DIR") {
                 Some(value.to_os_string())
             } else if std::env::var_os("HTTM_REMOTE_DIR").is_some() {
@@ -718,33 +2618,72 @@ impl Config {
                 SnapsSelectedForSearch::MostProximateOnly
             };
 
+            let proximate_dataset_cache = ProximateDatasetCache::new(&map_of_datasets);
+
             DatasetCollection {
                 map_of_datasets,
                 map_of_snaps,
                 opt_map_of_alts,
                 vec_of_filter_dirs,
+                vec_of_external_roots,
                 opt_common_snap_dir,
                 opt_map_of_aliases,
+                opt_map_of_snap_dirs,
                 snaps_selected_for_search,
+                proximate_dataset_cache,
             }
         };
 
+        if let Some(relative_path) = matches.value_of_os("ALL_DATASETS") {
+            paths = get_paths_on_all_datasets(&dataset_collection, Path::new(relative_path))?;
+        }
+
         let config = Config {
             paths,
             opt_raw,
             opt_zeros,
             opt_no_pretty,
             opt_no_live,
+            opt_omit_ditto,
             opt_recursive,
             opt_exact,
             opt_overwrite,
+            clobbered_live,
             opt_no_filter,
+            opt_hidden,
+            opt_owner,
+            opt_after,
+            opt_before,
+            opt_last_n,
+            opt_snap_time,
+            opt_perms,
             opt_no_snap,
+            opt_matrix,
+            opt_picker,
+            opt_git_xref,
+            opt_show_redundancy,
+            opt_stats,
+            opt_exec,
+            opt_edit,
+            opt_copy_path,
+            opt_cat,
+            opt_diff_tool,
+            opt_workspace,
+            opt_dir_size,
+            opt_chain_of_custody,
+            opt_json,
             opt_debug,
             requested_utc_offset,
             dataset_collection,
             exec_mode,
             deleted_mode,
+            deleted_excludes,
+            opt_deleted_recreated,
+            open_files_policy,
+            sort_versions_by,
+            version_time_by,
+            dedup_versions_by,
+            print_selected,
             pwd,
             opt_requested_dir,
         };
@@ -753,20 +2692,35 @@ impl Config {
     }
 }
 
+fn get_list_format(list_format: &str) -> ListFormat {
+    match list_format {
+        "json" => ListFormat::Json,
+        _ => ListFormat::Pretty,
+    }
+}
+
 fn main() {
-    match exec() {
+    // parse our args here, before anything else, so that we know how to print
+    // an error which may occur while generating our config
+    let arg_matches = parse_args();
+    let opt_json = arg_matches.is_present("JSON");
+
+    match exec(arg_matches) {
         Ok(_) => std::process::exit(0),
         Err(error) => {
-            eprintln!("Error: {}", error);
-            std::process::exit(1)
+            if opt_json {
+                eprintln!("{}", error.to_json());
+            } else {
+                eprintln!("Error: {}", error);
+            }
+            std::process::exit(error.exit_code())
         }
     }
 }
 
-fn exec() -> HttmResult<()> {
-    // get our program args and generate a config for use
-    // everywhere else
-    let config = Arc::new(Config::new()?);
+fn exec(arg_matches: ArgMatches) -> HttmResult<()> {
+    // generate a config for use everywhere else
+    let config = Arc::new(Config::from_matches(arg_matches)?);
 
     if config.opt_debug {
         eprintln!("{:#?}", config);
@@ -781,29 +2735,118 @@ fn exec() -> HttmResult<()> {
         // ExecMode::LastSnap will never return back, its a shortcut to select and restore themselves
         ExecMode::Interactive(interactive_mode) => {
             let browse_result = &interactive_exec(config.clone(), interactive_mode)?;
-            let snaps_and_live_set = versions_lookup_exec(config.as_ref(), browse_result)?;
-            print_snaps_and_live_set(&config, &snaps_and_live_set)?
+            print_versions(&config, browse_result)?
         }
         // ExecMode::Display will be just printed, we already know the paths
-        ExecMode::Display => {
-            let snaps_and_live_set = versions_lookup_exec(config.as_ref(), &config.paths)?;
-            print_snaps_and_live_set(&config, &snaps_and_live_set)?
-        }
-        // ExecMode::DisplayRecursive, ExecMode::SnapFileMount, and ExecMode::MountsForFiles will print their
+        ExecMode::Display => print_versions(&config, &config.paths)?,
+        // ExecMode::DisplayRecursive, ExecMode::SnapFileMount, ExecMode::MountsForFiles,
+        // ExecMode::DirSummary, ExecMode::ServeStdio, and ExecMode::ServeDbus will print their
         // output elsewhere
         ExecMode::DisplayRecursive(_) => display_recursive_wrapper(config.clone())?,
         ExecMode::SnapFileMount => take_snapshot(config.clone())?,
         ExecMode::MountsForFiles => display_mounts_for_files(config.as_ref())?,
+        ExecMode::DirSummary => display_dir_summary(config.as_ref())?,
+        ExecMode::ServeStdio => serve_stdio(config.as_ref())?,
+        ExecMode::ServeDbus(bus) => serve_dbus(config.as_ref(), bus)?,
+        ExecMode::GuiHelper(requested_path) => gui_helper_exec(config.as_ref(), requested_path)?,
+        ExecMode::SmbVersions(share_root) => smb_versions_exec(config.as_ref(), share_root)?,
+        ExecMode::ServeHttp(options) => serve_http(config.as_ref(), options)?,
+        ExecMode::QueryHosts(hosts_query) => query_hosts_exec(config.as_ref(), hosts_query)?,
+        ExecMode::SendStream(query) => send_stream_exec(config.as_ref(), query)?,
+        ExecMode::Backup(query) => backup_exec(config.as_ref(), query)?,
+        ExecMode::ListAliases(list_format) => list_aliases_exec(config.as_ref(), list_format)?,
+        ExecMode::ListDatasets(list_format) => list_datasets_exec(config.as_ref(), list_format)?,
+        ExecMode::Bisect(opt_test_command) => bisect_exec(config.as_ref(), opt_test_command)?,
+        ExecMode::Blame => display_blame(config.as_ref())?,
+        ExecMode::NumVersions(format) => display_num_versions(config.as_ref(), format)?,
+        ExecMode::ToGit(repo_dir) => export_to_git(config.as_ref(), repo_dir)?,
+        ExecMode::Follow(interval) => follow_exec(config.as_ref(), interval)?,
+        ExecMode::Undelete => undelete_exec(config.as_ref())?,
+        ExecMode::Triage(triage_mode) => triage_exec(config.as_ref(), triage_mode)?,
+        ExecMode::Patch(old_path, new_path) => patch_exec(config.as_ref(), old_path, new_path)?,
+        ExecMode::Extract(extract_request) => extract_exec(config.as_ref(), extract_request)?,
     }
 
     Ok(())
 }
 
-fn print_snaps_and_live_set(
-    config: &Config,
-    snaps_and_live_set: &SnapsAndLiveSet,
-) -> HttmResult<()> {
-    let output_buf = display_exec(config, snaps_and_live_set)?;
+// no versions found, when httm was not permitted to look, or could not look, is not the same as no versions
+fn warn_if_inaccessible(lookup_stats: &LookupStats) {
+    if !lookup_stats.inaccessible_snap_dirs.is_empty() {
+        eprintln!(
+            "Warning: {}",
+            get_inaccessible_hint(&lookup_stats.inaccessible_snap_dirs)
+        );
+    }
+
+    lookup_stats
+        .snapdir_warnings
+        .iter()
+        .for_each(|warning| eprintln!("Warning: {}", warning));
+}
+
+// warned of before any lookup, so an empty listing which follows doesn't look like a bug
+fn warn_if_no_snaps(config: &Config, path_set: &[PathData]) {
+    if config.opt_no_snap {
+        return;
+    }
+
+    get_datasets_without_snaps(config, path_set)
+        .iter()
+        .for_each(|mount| {
+            let opt_dataset_info = config.dataset_collection.map_of_datasets.get(mount);
+
+            let dataset_name = match opt_dataset_info {
+                Some(dataset_info) => dataset_info.name.clone(),
+                None => mount.to_string_lossy().into_owned(),
+            };
+
+            // "--snap" only snapshots local ZFS datasets
+            let advice = match opt_dataset_info {
+                Some(dataset_info)
+                    if dataset_info.fs_type == FilesystemType::Zfs
+                        && dataset_info.mount_type == MountType::Local =>
+                {
+                    "httm can take one with \"httm --snap\" and the path of any file on the dataset."
+                }
+                _ => "Take a snapshot of the dataset, and httm will find versions of its files from then on.",
+            };
+
+            eprintln!(
+                "Warning: the dataset {:?} mounted at {:?} has no snapshots, so httm can find no snapshot versions of its files.  {}",
+                dataset_name, mount, advice
+            );
+        });
+}
+
+fn print_versions(config: &Config, path_set: &[PathData]) -> HttmResult<()> {
+    warn_if_no_snaps(config, path_set);
+
+    // the header says which version of httm produced the listing, and when, for the record
+    // a JSON listing has the same within its JSON
+    if config.opt_chain_of_custody && !config.opt_raw && !config.opt_zeros && !config.opt_json {
+        print_output_buf(display_custody_header(config))?;
+    }
+
+    let output_buf = if config.opt_matrix {
+        display_version_matrix(config, path_set)?
+    } else if config.opt_git_xref {
+        display_git_xref(config, path_set)?
+    } else if config.opt_show_redundancy {
+        display_redundancy(config, path_set)?
+    } else if config.opt_stats {
+        let start_time = Instant::now();
+        let (snaps_and_live_set, lookup_stats) = versions_lookup_exec_with_stats(config, path_set)?;
+        warn_if_inaccessible(&lookup_stats);
+        let output_buf = display_exec(config, &snaps_and_live_set)?;
+        print_output_buf(output_buf)?;
+        eprint!("{}", display_stats(&lookup_stats, start_time.elapsed()));
+        return Ok(());
+    } else {
+        let (snaps_and_live_set, lookup_stats) = versions_lookup_exec_with_stats(config, path_set)?;
+        warn_if_inaccessible(&lookup_stats);
+        display_exec(config, &snaps_and_live_set)?
+    };
     print_output_buf(output_buf)?;
     Ok(())
 }
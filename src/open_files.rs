@@ -0,0 +1,135 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::{read_dir, read_link, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+
+use crate::utility::HttmError;
+use crate::{Config, HttmResult, OpenFilesPolicy};
+
+// SQLite keeps its journal and write ahead log beside the database, and a process
+// which has only these open is still very much in the middle of a write
+const SQLITE_SIDECAR_SUFFIXES: &[&str] = &["-journal", "-wal", "-shm"];
+
+// key: pid, val: process name
+type OpenFileProcesses = BTreeMap<u32, String>;
+
+// check whether anything is using the path we are about to restore over, and warn or refuse per the user's policy
+//
+// returns a warning for display, if one is necessary
+pub fn open_files_advisory(config: &Config, restore_path: &Path) -> HttmResult<Option<String>> {
+    if matches!(config.open_files_policy, OpenFilesPolicy::Ignore) {
+        return Ok(None);
+    }
+
+    let processes = get_processes_with_open_file(restore_path);
+
+    if processes.is_empty() {
+        return Ok(None);
+    }
+
+    let processes_list: String = processes
+        .iter()
+        .map(|(pid, name)| format!("\t{} (pid {})\n", name, pid))
+        .collect();
+
+    match config.open_files_policy {
+        OpenFilesPolicy::Refuse => {
+            let msg = format!(
+                "httm will not restore to {:?}, as it is currently open in the following processes:\n{}\
+                Stop these processes and try again.",
+                restore_path, processes_list
+            );
+            Err(HttmError::new(&msg))
+        }
+        OpenFilesPolicy::Warn => {
+            let msg = format!(
+                "WARNING: {:?} is currently open in the following processes:\n{}\
+                Restoring a file which is in use, such as a database, may leave it inconsistent.\n",
+                restore_path, processes_list
+            );
+            Ok(Some(msg))
+        }
+        OpenFilesPolicy::Ignore => unreachable!(),
+    }
+}
+
+fn get_processes_with_open_file(restore_path: &Path) -> OpenFileProcesses {
+    // our paths of interest: the path itself, or anything beneath it, if a dir, and any SQLite sidecar files
+    let sidecar_paths: Vec<PathBuf> = SQLITE_SIDECAR_SUFFIXES
+        .iter()
+        .map(|suffix| {
+            let mut sidecar: OsString = restore_path.as_os_str().to_owned();
+            sidecar.push(suffix);
+            PathBuf::from(sidecar)
+        })
+        .collect();
+
+    let is_of_interest = |open_path: &Path| -> bool {
+        open_path.starts_with(restore_path) || sidecar_paths.iter().any(|path| path == open_path)
+    };
+
+    // no /proc, say on macOS, just means we can't see any open files, and,
+    // if we are not root, we also can't see the open files of other users
+    let proc_entries = match read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return OpenFileProcesses::new(),
+    };
+
+    proc_entries
+        .flatten()
+        .par_bridge()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<u32>().ok())
+                .map(|pid| (pid, entry.path()))
+        })
+        .filter(|(_pid, proc_path)| {
+            // check open file descriptors, and memory mapped files, which is how SQLite, etc., often access a database
+            let has_open_fd = read_dir(proc_path.join("fd"))
+                .map(|fds| {
+                    fds.flatten()
+                        .filter_map(|fd| read_link(fd.path()).ok())
+                        .any(|open_path| is_of_interest(&open_path))
+                })
+                .unwrap_or(false);
+
+            has_open_fd
+                || read_to_string(proc_path.join("maps"))
+                    .map(|maps| {
+                        maps.lines()
+                            .filter_map(|line| line.find('/').map(|idx| &line[idx..]))
+                            .any(|open_path| is_of_interest(Path::new(open_path)))
+                    })
+                    .unwrap_or(false)
+        })
+        .map(|(pid, proc_path)| {
+            let name = read_to_string(proc_path.join("comm"))
+                .map(|comm| comm.trim().to_owned())
+                .unwrap_or_else(|_| "<unknown>".to_owned());
+            (pid, name)
+        })
+        .collect()
+}
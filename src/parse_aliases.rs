@@ -15,11 +15,44 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
-use std::{ffi::OsString, path::Path, path::PathBuf};
+use std::{ffi::OsString, fs::read_to_string, path::Path, path::PathBuf};
 
-use crate::utility::{get_fs_type_from_hidden_dir, HttmError};
+use crate::utility::{get_fs_type_from_hidden_dir, get_home_dir, HttmError};
 use crate::{HttmResult, MapOfAliases, RemotePathAndFsType};
 
+// aliases added with "--add-alias" are stored one per line, as <LOCAL_DIR>:<REMOTE_DIR>,
+// just as each is given to MAP_ALIASES
+pub const ALIASES_FILE_NAME: &str = ".httm-aliases";
+
+pub fn get_aliases_file_path() -> HttmResult<PathBuf> {
+    let home_dir = get_home_dir()?;
+    Ok(home_dir.join(ALIASES_FILE_NAME))
+}
+
+// each line of the aliases file, with its line number, counted from one
+pub fn read_aliases_file(aliases_file_path: &Path) -> HttmResult<Vec<(usize, String)>> {
+    // no aliases file is the same as no aliases
+    if !aliases_file_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let buffer = read_to_string(aliases_file_path).map_err(|err| {
+        HttmError::with_context(
+            "httm could not read the aliases file in the user's home directory",
+            err,
+        )
+    })?;
+
+    let aliases = buffer
+        .lines()
+        .enumerate()
+        .filter(|(_idx, line)| !line.is_empty())
+        .map(|(idx, line)| (idx + 1, line.to_owned()))
+        .collect();
+
+    Ok(aliases)
+}
+
 pub fn parse_aliases(
     raw_local_dir: &Option<OsString>,
     raw_snap_dir: &Option<OsString>,
@@ -51,14 +84,11 @@ pub fn parse_aliases(
                 })
                 .collect();
 
-            match res.ok_or_else(|| {
+            res.ok_or_else(|| {
                 HttmError::new(
                     "Must use specified delimiter (':') between aliases for MAP_ALIASES.",
                 )
-            }) {
-                Ok(res) => res,
-                Err(err) => return Err(err.into()),
-            }
+            })?
         }
         None => Vec::new(),
     };
@@ -40,7 +40,7 @@ fn get_alt_replicated_datasets(
     let proximate_dataset_fs_name = match &map_of_datasets.get(proximate_dataset_mount) {
         Some(dataset_info) => dataset_info.name.clone(),
         None => {
-            return Err(HttmError::new("httm was unable to detect an alternate replicated mount point.  Perhaps the replicated filesystem is not mounted?").into());
+            return Err(HttmError::new("httm was unable to detect an alternate replicated mount point.  Perhaps the replicated filesystem is not mounted?"));
         }
     };
 
@@ -61,7 +61,7 @@ fn get_alt_replicated_datasets(
 
     if alt_replicated_mounts.is_empty() {
         // could not find the any replicated mounts
-        Err(HttmError::new("httm was unable to detect an alternate replicated mount point.  Perhaps the replicated filesystem is not mounted?").into())
+        Err(HttmError::new("httm was unable to detect an alternate replicated mount point.  Perhaps the replicated filesystem is not mounted?"))
     } else {
         alt_replicated_mounts.sort_unstable_by_key(|path| path.as_os_str().len());
         Ok(MostProximateAndOptAlts {
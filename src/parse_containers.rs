@@ -0,0 +1,169 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    process::Command as ExecProcess,
+};
+
+use serde::Deserialize;
+use which::which;
+
+use crate::utility::HttmError;
+use crate::HttmResult;
+
+const LXC_CONFIG_DIR: &str = "/var/lib/lxc";
+
+// a dir on the host, and where that dir appears inside a container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerMount {
+    pub host_dir: PathBuf,
+    pub container_dir: PathBuf,
+}
+
+pub type VecOfContainerMounts = Vec<ContainerMount>;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerMount {
+    source: PathBuf,
+    destination: PathBuf,
+}
+
+// we read the container's storage configuration from the host, first asking docker,
+// and then looking for an LXC container of the same name
+pub fn parse_container_mounts(container_name: &str) -> HttmResult<VecOfContainerMounts> {
+    let mut container_mounts = match parse_from_docker(container_name) {
+        Some(container_mounts) => container_mounts,
+        None => parse_from_lxc_config(container_name)?,
+    };
+
+    if container_mounts.is_empty() {
+        return Err(HttmError::new(
+            "httm found the container requested, but could not find any host directories mounted within it.",
+        ));
+    }
+
+    // most proximate container dir first, so /srv/data is preferred to /srv and /
+    container_mounts.sort_by(|a, b| {
+        b.container_dir
+            .components()
+            .count()
+            .cmp(&a.container_dir.components().count())
+    });
+
+    Ok(container_mounts)
+}
+
+fn parse_from_docker(container_name: &str) -> Option<VecOfContainerMounts> {
+    let docker_command = which("docker").ok()?;
+
+    let process_output = ExecProcess::new(docker_command)
+        .args(["inspect", "--format", "{{json .Mounts}}", container_name])
+        .output()
+        .ok()?;
+
+    // docker will exit with an error when no such container exists
+    if !process_output.status.success() {
+        return None;
+    }
+
+    let docker_mounts: Vec<DockerMount> = serde_json::from_slice(&process_output.stdout).ok()?;
+
+    let container_mounts = docker_mounts
+        .into_iter()
+        .map(|docker_mount| ContainerMount {
+            host_dir: docker_mount.source,
+            container_dir: docker_mount.destination,
+        })
+        .collect();
+
+    Some(container_mounts)
+}
+
+fn parse_from_lxc_config(container_name: &str) -> HttmResult<VecOfContainerMounts> {
+    let config_path = Path::new(LXC_CONFIG_DIR)
+        .join(container_name)
+        .join("config");
+
+    let config_contents = read_to_string(&config_path).map_err(|_| {
+        HttmError::new(&format!(
+            "httm could not find a docker or an LXC container named {:?}.",
+            container_name
+        ))
+    })?;
+
+    let container_mounts = config_contents
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .filter_map(|(key, value)| match key {
+            // rootfs may be prefixed with its backing store type, eg. "zfs:" or "dir:"
+            "lxc.rootfs.path" | "lxc.rootfs" => {
+                let rootfs = value
+                    .split_once(':')
+                    .map(|(_backing_store, path)| path)
+                    .unwrap_or(value);
+
+                Some(ContainerMount {
+                    host_dir: PathBuf::from(rootfs),
+                    container_dir: PathBuf::from("/"),
+                })
+            }
+            // fstab format, the container dir is relative to the container's root
+            "lxc.mount.entry" => {
+                let mut fields = value.split_whitespace();
+                let host_dir = fields.next()?;
+                let container_dir = fields.next()?;
+
+                Some(ContainerMount {
+                    host_dir: PathBuf::from(host_dir),
+                    container_dir: Path::new("/").join(container_dir),
+                })
+            }
+            _ => None,
+        })
+        // mount entries may also be for proc, sysfs, etc., we only want dirs on the host
+        .filter(|container_mount| container_mount.host_dir.is_absolute())
+        .collect();
+
+    Ok(container_mounts)
+}
+
+// container paths must be absolute, as the working directory is on the host
+pub fn get_host_path(
+    container_path: &Path,
+    container_mounts: &[ContainerMount],
+) -> HttmResult<PathBuf> {
+    let container_path = Path::new("/").join(container_path);
+
+    container_mounts
+        .iter()
+        .find_map(|container_mount| {
+            container_path
+                .strip_prefix(&container_mount.container_dir)
+                .ok()
+                .map(|relative_path| container_mount.host_dir.join(relative_path))
+        })
+        .ok_or_else(|| {
+            HttmError::new(&format!(
+                "httm could not find a host directory for the container path {:?}.",
+                container_path
+            ))
+        })
+}
@@ -15,122 +15,106 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
-use std::{collections::BTreeMap, path::Path, path::PathBuf, process::Command as ExecProcess};
+use std::{path::Path, path::PathBuf, process::Command as ExecProcess};
 
 use proc_mounts::MountIter;
 use rayon::iter::Either;
 use rayon::prelude::*;
 use which::which;
 
-use crate::parse_snaps::precompute_snap_mounts;
+use crate::parse_snaps::LazyMapOfSnaps;
 use crate::utility::{get_common_path, get_fs_type_from_hidden_dir, HttmError};
 use crate::{
-    DatasetMetadata, FilesystemType, HttmResult, MapOfDatasets, MapOfSnaps, MountType,
-    OptBtrfsCommonSnapDir, VecOfFilterDirs, ZFS_SNAPSHOT_DIRECTORY,
+    DatasetMetadata, FilesystemType, HttmResult, MapOfDatasets, MountType, OptBtrfsCommonSnapDir,
+    OptMapOfSnapDirs, VecOfFilterDirs, ZFS_SNAPSHOT_DIRECTORY,
 };
 
 pub const ZFS_FSTYPE: &str = "zfs";
 pub const BTRFS_FSTYPE: &str = "btrfs";
 pub const SMB_FSTYPE: &str = "smbfs";
 pub const NFS_FSTYPE: &str = "nfs";
+pub const NFS4_FSTYPE: &str = "nfs4";
+pub const CIFS_FSTYPE: &str = "cifs";
 pub const AFP_FSTYPE: &str = "afpfs";
 
 // divide by the type of system we are on
 // Linux allows us the read proc mounts
 #[allow(clippy::type_complexity)]
-pub fn parse_mounts_exec() -> HttmResult<(MapOfDatasets, MapOfSnaps, VecOfFilterDirs)> {
-    let (map_of_datasets, vec_filter_dirs) = if cfg!(target_os = "linux") {
-        parse_from_proc_mounts()?
+pub fn parse_mounts_exec(
+    opt_map_of_snap_dirs: &OptMapOfSnapDirs,
+    opt_altroot: &Option<PathBuf>,
+) -> HttmResult<(MapOfDatasets, VecOfFilterDirs)> {
+    let (mut map_of_datasets, vec_filter_dirs) = parse_datasets()?;
+
+    // with an alternate root, the datasets of the running system are of no interest
+    if let Some(altroot) = opt_altroot {
+        map_of_datasets.retain(|mount, _dataset_info| mount.starts_with(altroot));
+
+        if map_of_datasets.is_empty() {
+            return Err(HttmError::NoDatasetFound(format!(
+                "httm could not find any valid datasets mounted beneath the alternate root {:?}.",
+                altroot
+            )));
+        }
+    }
+
+    // snapshots mounted beneath a user defined snap dir are not raw filesystems either
+    if let Some(map_of_snap_dirs) = opt_map_of_snap_dirs {
+        let user_snap_dirs: Vec<PathBuf> = map_of_snap_dirs
+            .iter()
+            .map(|(mount, snap_dir)| mount.join(snap_dir))
+            .collect();
+
+        map_of_datasets.retain(|mount, _dataset_info| {
+            !user_snap_dirs
+                .iter()
+                .any(|user_snap_dir| mount.starts_with(user_snap_dir))
+        });
+    }
+
+    Ok((map_of_datasets, vec_filter_dirs))
+}
+
+#[allow(clippy::type_complexity)]
+pub fn parse_datasets() -> HttmResult<(MapOfDatasets, VecOfFilterDirs)> {
+    if cfg!(target_os = "linux") {
+        parse_from_proc_mounts()
     } else {
-        parse_from_mount_cmd()?
-    };
+        parse_from_mount_cmd()
+    }
+}
 
-    let map_of_snaps = precompute_snap_mounts(&map_of_datasets)?;
+// a mount, as read from the mount table, before we know whether it is a dataset
+struct MountEntry {
+    source: String,
+    dest: PathBuf,
+    fstype: String,
+    options: Vec<String>,
+}
 
-    Ok((map_of_datasets, map_of_snaps, vec_filter_dirs))
+// what the fstype of a mount tells us about it, before any look inside
+enum MountClass {
+    Dataset(DatasetMetadata),
+    // a network share might be backed by any filesystem, it needs a look inside
+    Network,
+    Unsupported,
 }
 
 // parsing from proc mounts is both faster and necessary for certain btrfs features
 // for instance, allows us to read subvolumes mounts, like "/@" or "/@home"
 #[allow(clippy::type_complexity)]
 fn parse_from_proc_mounts() -> HttmResult<(MapOfDatasets, VecOfFilterDirs)> {
-    let (map_of_datasets, filter_dirs): (MapOfDatasets, Vec<PathBuf>) = MountIter::new()?
-        .par_bridge()
+    let mount_entries: Vec<MountEntry> = MountIter::new()?
         .flatten()
-        // but exclude snapshot mounts.  we want only the raw filesystems
-        .filter(|mount_info| {
-            !mount_info
-                .dest
-                .to_string_lossy()
-                .contains(ZFS_SNAPSHOT_DIRECTORY)
+        .map(|mount_info| MountEntry {
+            source: mount_info.source.to_string_lossy().into_owned(),
+            dest: mount_info.dest,
+            fstype: mount_info.fstype,
+            options: mount_info.options,
         })
-        .partition_map(|mount_info| match &mount_info.fstype.as_str() {
-            &ZFS_FSTYPE => Either::Left((
-                mount_info.dest,
-                DatasetMetadata {
-                    name: mount_info.source.to_string_lossy().into_owned(),
-                    fs_type: FilesystemType::Zfs,
-                    mount_type: MountType::Local,
-                },
-            )),
-            &SMB_FSTYPE | &AFP_FSTYPE | &NFS_FSTYPE => {
-                match get_fs_type_from_hidden_dir(&mount_info.dest) {
-                    Ok(FilesystemType::Zfs) => Either::Left((
-                        mount_info.dest,
-                        DatasetMetadata {
-                            name: mount_info.source.to_string_lossy().into_owned(),
-                            fs_type: FilesystemType::Zfs,
-                            mount_type: MountType::Network,
-                        },
-                    )),
-                    Ok(FilesystemType::Btrfs) => Either::Left((
-                        mount_info.dest,
-                        DatasetMetadata {
-                            name: mount_info.source.to_string_lossy().into_owned(),
-                            fs_type: FilesystemType::Btrfs,
-                            mount_type: MountType::Network,
-                        },
-                    )),
-                    Err(_) => Either::Right(mount_info.dest),
-                }
-            }
-            &BTRFS_FSTYPE => {
-                let keyed_options: BTreeMap<String, String> = mount_info
-                    .options
-                    .par_iter()
-                    .filter(|line| line.contains('='))
-                    .filter_map(|line| {
-                        line.split_once(&"=")
-                            .map(|(key, value)| (key.to_owned(), value.to_owned()))
-                    })
-                    .collect();
-
-                let name = match keyed_options.get("subvol") {
-                    Some(subvol) => subvol.clone(),
-                    None => mount_info.source.to_string_lossy().into_owned(),
-                };
-
-                let fs_type = FilesystemType::Btrfs;
-
-                let mount_type = MountType::Local;
-
-                Either::Left((
-                    mount_info.dest,
-                    DatasetMetadata {
-                        name,
-                        fs_type,
-                        mount_type,
-                    },
-                ))
-            }
-            _ => Either::Right(mount_info.dest),
-        });
+        .collect();
 
-    if map_of_datasets.is_empty() {
-        Err(HttmError::new("httm could not find any valid datasets on the system.").into())
-    } else {
-        Ok((map_of_datasets, filter_dirs))
-    }
+    classify_mounts(mount_entries)
 }
 
 // old fashioned parsing for non-Linux systems, nearly as fast, works everywhere with a mount command
@@ -141,54 +125,13 @@ fn parse_from_mount_cmd() -> HttmResult<(MapOfDatasets, VecOfFilterDirs)> {
         let command_output =
             std::str::from_utf8(&ExecProcess::new(mount_command).output()?.stdout)?.to_owned();
 
-        // parse "mount" for filesystems and mountpoints
-        let (map_of_datasets, filter_dirs): (MapOfDatasets, Vec<PathBuf>) = command_output
-            .par_lines()
-            // but exclude snapshot mounts.  we want the raw filesystem names.
-            .filter(|line| !line.contains(ZFS_SNAPSHOT_DIRECTORY))
-            // where to split, to just have the src and dest of mounts
-            .filter_map(|line|
-                // GNU Linux mount output
-                if line.contains("type") {
-                    line.split_once(&" type")
-                // Busybox and BSD mount output
-                } else {
-                    line.split_once(&" (")
-                }
-            )
-            .map(|(filesystem_and_mount,_)| filesystem_and_mount )
-            // mount cmd includes and " on " between src and dest of mount
-            .filter_map(|filesystem_and_mount| filesystem_and_mount.split_once(&" on "))
-            .map(|(filesystem, mount)| (filesystem.to_owned(), PathBuf::from(mount)))
-            // sanity check: does the filesystem exist and have a ZFS hidden dir? if not, filter it out
-            // and flip around, mount should key of key/value
-            .partition_map(|(filesystem, mount)| {
-                match get_fs_type_from_hidden_dir(&mount) {
-                    Ok(FilesystemType::Zfs) => {
-                        Either::Left((mount, DatasetMetadata {
-                            name: filesystem,
-                            fs_type: FilesystemType::Zfs,
-                            mount_type: MountType::Local
-                        }))
-                    },
-                    Ok(FilesystemType::Btrfs) => {
-                        Either::Left((mount, DatasetMetadata{
-                            name: filesystem,
-                            fs_type: FilesystemType::Btrfs,
-                            mount_type: MountType::Local
-                        }))
-                    },
-                    Err(_) => {
-                        Either::Right(mount)
-                    }
-                }
-            });
+        // parse "mount" for filesystems, mountpoints, fstypes and options
+        let mount_entries: Vec<MountEntry> = command_output
+            .lines()
+            .filter_map(parse_mount_line)
+            .collect();
 
-        if map_of_datasets.is_empty() {
-            Err(HttmError::new("httm could not find any valid datasets on the system.").into())
-        } else {
-            Ok((map_of_datasets, filter_dirs))
-        }
+        classify_mounts(mount_entries)
     }
 
     // do we have the necessary commands for search if user has not defined a snap point?
@@ -198,8 +141,127 @@ fn parse_from_mount_cmd() -> HttmResult<(MapOfDatasets, VecOfFilterDirs)> {
     } else {
         Err(HttmError::new(
             "'mount' command not be found. Make sure the command 'mount' is in your path.",
-        )
-        .into())
+        ))
+    }
+}
+
+// GNU Linux mount output: "rpool/ROOT on / type zfs (rw,xattr,noacl)"
+// Busybox and BSD mount output: "rpool/ROOT on / (zfs, local, noatime, nfsv4acls)"
+fn parse_mount_line(line: &str) -> Option<MountEntry> {
+    // mount cmd includes and " on " between src and dest of mount
+    let (source, rest) = line.split_once(" on ")?;
+
+    let (dest, fstype, options) = match rest.split_once(" type ") {
+        Some((dest, type_and_options)) => {
+            let (fstype, options) = type_and_options
+                .split_once(" (")
+                .unwrap_or((type_and_options, ""));
+            (dest, fstype, options.trim_end_matches(')'))
+        }
+        None => {
+            let (dest, fstype_and_options) = rest.split_once(" (")?;
+            let fstype_and_options = fstype_and_options.trim_end_matches(')');
+            let (fstype, options) = fstype_and_options
+                .split_once(", ")
+                .unwrap_or((fstype_and_options, ""));
+            (dest, fstype, options)
+        }
+    };
+
+    Some(MountEntry {
+        source: source.to_owned(),
+        dest: PathBuf::from(dest),
+        fstype: fstype.trim().to_owned(),
+        options: options
+            .split(',')
+            .map(|option| option.trim().to_owned())
+            .filter(|option| !option.is_empty())
+            .collect(),
+    })
+}
+
+#[allow(clippy::type_complexity)]
+fn classify_mounts(mount_entries: Vec<MountEntry>) -> HttmResult<(MapOfDatasets, VecOfFilterDirs)> {
+    let mut map_of_datasets = MapOfDatasets::new();
+    let mut filter_dirs: VecOfFilterDirs = Vec::new();
+    let mut network_mounts: Vec<MountEntry> = Vec::new();
+
+    mount_entries
+        .into_iter()
+        // but exclude snapshot mounts.  we want only the raw filesystems
+        .filter(|mount_entry| {
+            !mount_entry
+                .dest
+                .to_string_lossy()
+                .contains(ZFS_SNAPSHOT_DIRECTORY)
+        })
+        .for_each(|mount_entry| match classify_by_fstype(&mount_entry) {
+            MountClass::Dataset(dataset_info) => {
+                map_of_datasets.insert(mount_entry.dest, dataset_info);
+            }
+            MountClass::Network => network_mounts.push(mount_entry),
+            MountClass::Unsupported => filter_dirs.push(mount_entry.dest),
+        });
+
+    // only a network share needs a look inside, and as each look may wait on the network,
+    // we look at every share at once
+    let (network_datasets, network_filter_dirs): (Vec<(PathBuf, DatasetMetadata)>, Vec<PathBuf>) =
+        network_mounts.into_par_iter().partition_map(
+            |mount_entry| match get_fs_type_from_hidden_dir(&mount_entry.dest) {
+                Ok(fs_type) => Either::Left((
+                    mount_entry.dest,
+                    DatasetMetadata {
+                        name: mount_entry.source,
+                        fs_type,
+                        mount_type: MountType::Network,
+                    },
+                )),
+                Err(_) => Either::Right(mount_entry.dest),
+            },
+        );
+
+    map_of_datasets.extend(network_datasets);
+    filter_dirs.extend(network_filter_dirs);
+
+    if map_of_datasets.is_empty() {
+        Err(HttmError::NoDatasetFound(
+            "httm could not find any valid datasets on the system.".to_owned(),
+        ))
+    } else {
+        Ok((map_of_datasets, filter_dirs))
+    }
+}
+
+fn classify_by_fstype(mount_entry: &MountEntry) -> MountClass {
+    match mount_entry.fstype.as_str() {
+        // some tools, like zfsbootmenu or zrepl, mount a snapshot outside of the snapshot dir,
+        // perhaps beneath /run or /tmp.  a snapshot is never a live dataset, and would only
+        // confuse which dataset is proximate to a path, so treat it like any other unsupported mount.
+        // ZFS names a snapshot "dataset@snap", and never names a dataset so
+        ZFS_FSTYPE if mount_entry.source.contains('@') => MountClass::Unsupported,
+        ZFS_FSTYPE => MountClass::Dataset(DatasetMetadata {
+            name: mount_entry.source.clone(),
+            fs_type: FilesystemType::Zfs,
+            mount_type: MountType::Local,
+        }),
+        BTRFS_FSTYPE => {
+            // the subvol option names the subvolume mounted, like "/@home"
+            let name = mount_entry
+                .options
+                .iter()
+                .find_map(|option| option.strip_prefix("subvol="))
+                .unwrap_or(&mount_entry.source)
+                .to_owned();
+
+            MountClass::Dataset(DatasetMetadata {
+                name,
+                fs_type: FilesystemType::Btrfs,
+                mount_type: MountType::Local,
+            })
+        }
+        SMB_FSTYPE | AFP_FSTYPE | NFS_FSTYPE | NFS4_FSTYPE | CIFS_FSTYPE => MountClass::Network,
+        // overlayfs, tmpfs, and the rest, have no snapshots of their own
+        _ => MountClass::Unsupported,
     }
 }
 
@@ -207,7 +269,7 @@ fn parse_from_mount_cmd() -> HttmResult<(MapOfDatasets, VecOfFilterDirs)> {
 // so we can hide that common path from searches later
 pub fn get_common_snap_dir(
     map_of_datasets: &MapOfDatasets,
-    map_of_snaps: &MapOfSnaps,
+    map_of_snaps: &LazyMapOfSnaps,
 ) -> OptBtrfsCommonSnapDir {
     let btrfs_datasets: Vec<&PathBuf> = map_of_datasets
         .par_iter()
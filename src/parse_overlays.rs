@@ -0,0 +1,126 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::path::{Path, PathBuf};
+
+use proc_mounts::MountIter;
+
+use crate::utility::HttmError;
+use crate::HttmResult;
+
+pub const OVERLAY_FSTYPE: &str = "overlay";
+
+// an overlayfs mount, and the dirs which together make it.  the upper dir holds every file
+// written within the overlay, and each lower dir, in order, the files beneath
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlayMount {
+    pub merged_dir: PathBuf,
+    pub opt_upper_dir: Option<PathBuf>,
+    pub lower_dirs: Vec<PathBuf>,
+}
+
+pub type VecOfOverlayMounts = Vec<OverlayMount>;
+
+pub fn parse_overlay_mounts() -> HttmResult<VecOfOverlayMounts> {
+    if !cfg!(target_os = "linux") {
+        return Err(HttmError::new(
+            "httm can only find the dirs which make an overlayfs mount on Linux.",
+        ));
+    }
+
+    let mut overlay_mounts: VecOfOverlayMounts = MountIter::new()?
+        .flatten()
+        .filter(|mount_info| mount_info.fstype == OVERLAY_FSTYPE)
+        .map(|mount_info| {
+            let opt_upper_dir = mount_info
+                .options
+                .iter()
+                .find_map(|option| option.strip_prefix("upperdir="))
+                .map(PathBuf::from);
+
+            let lower_dirs = mount_info
+                .options
+                .iter()
+                .find_map(|option| option.strip_prefix("lowerdir="))
+                .map(split_lower_dirs)
+                .unwrap_or_default();
+
+            OverlayMount {
+                merged_dir: mount_info.dest,
+                opt_upper_dir,
+                lower_dirs,
+            }
+        })
+        .collect();
+
+    // most proximate merged dir first, so an overlay mounted within another is preferred
+    overlay_mounts.sort_by(|a, b| {
+        b.merged_dir
+            .components()
+            .count()
+            .cmp(&a.merged_dir.components().count())
+    });
+
+    Ok(overlay_mounts)
+}
+
+// lower dirs are delimited by a colon, and a colon within a dir is escaped with a backslash
+fn split_lower_dirs(lower_dirs: &str) -> Vec<PathBuf> {
+    let mut split = Vec::new();
+    let mut current = String::new();
+    let mut chars = lower_dirs.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            ':' => split.push(PathBuf::from(std::mem::take(&mut current))),
+            _ => current.push(c),
+        }
+    }
+
+    split.push(PathBuf::from(current));
+
+    split
+        .into_iter()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect()
+}
+
+// a path within an overlay is found on the upper dir, if it was ever written within the overlay,
+// or else on the first lower dir which holds it.  a path which exists on neither, perhaps because
+// it was deleted, may yet be found on a snapshot of the upper dir.  a path outside any overlay
+// remains as it is
+pub fn get_backing_path(path: &Path, overlay_mounts: &[OverlayMount]) -> PathBuf {
+    let opt_backing_path = overlay_mounts.iter().find_map(|overlay_mount| {
+        let relative_path = path.strip_prefix(&overlay_mount.merged_dir).ok()?;
+
+        let backing_paths: Vec<PathBuf> = overlay_mount
+            .opt_upper_dir
+            .iter()
+            .chain(overlay_mount.lower_dirs.iter())
+            .map(|dir| dir.join(relative_path))
+            .collect();
+
+        backing_paths
+            .iter()
+            .find(|backing_path| backing_path.symlink_metadata().is_ok())
+            .or_else(|| backing_paths.first())
+            .cloned()
+    });
+
+    opt_backing_path.unwrap_or_else(|| path.to_path_buf())
+}
@@ -15,57 +15,169 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
-use std::{fs::read_dir, path::Path, path::PathBuf, process::Command as ExecProcess};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::read_dir,
+    path::Path,
+    path::PathBuf,
+    process::Command as ExecProcess,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
+use proc_mounts::MountIter;
 use rayon::prelude::*;
+use time::UtcOffset;
 use which::which;
 
-use crate::utility::HttmError;
+use crate::forensic::audit;
+use crate::utility::{parse_date_time, HttmError};
 use crate::{
-    FilesystemType, HttmResult, MapOfDatasets, MapOfSnaps, MountType, VecOfSnaps,
-    BTRFS_SNAPPER_HIDDEN_DIRECTORY, BTRFS_SNAPPER_SUFFIX, ZFS_SNAPSHOT_DIRECTORY,
+    DatasetMetadata, FilesystemType, HttmResult, MapOfDatasets, MapOfSnapDirs, MountType,
+    OptMapOfSnapDirs, VecOfSnaps, BTRFS_SNAPPER_HIDDEN_DIRECTORY, BTRFS_SNAPPER_SUFFIX,
+    ZFS_SNAPSHOT_DIRECTORY,
 };
 
 // fans out precompute of snap mounts to the appropriate function based on fstype
-pub fn precompute_snap_mounts(map_of_datasets: &MapOfDatasets) -> HttmResult<MapOfSnaps> {
-    let opt_root_mount_path: Option<&PathBuf> =
-        map_of_datasets
+// the snap mounts of each dataset, found only once a dataset is first searched, because on a system
+// with many datasets, most datasets are never searched at all
+#[derive(Debug, Clone)]
+pub struct LazyMapOfSnaps {
+    map_of_datasets: MapOfDatasets,
+    opt_map_of_snap_dirs: OptMapOfSnapDirs,
+    opt_root_mount_path: Option<PathBuf>,
+    opt_max_snaps: Option<usize>,
+    // the snapshots already mounted, when we must not mount any others
+    opt_mounted_snaps: Option<BTreeSet<PathBuf>>,
+    // key: mount, val: snap mounts, or none when the snapshots of the dataset could not be read
+    map_of_cells: BTreeMap<PathBuf, OnceCell<Option<VecOfSnaps>>>,
+}
+
+impl LazyMapOfSnaps {
+    pub fn new(
+        map_of_datasets: &MapOfDatasets,
+        opt_map_of_snap_dirs: &OptMapOfSnapDirs,
+        opt_max_snaps: Option<usize>,
+        only_mounted: bool,
+    ) -> HttmResult<Self> {
+        let opt_mounted_snaps = if only_mounted {
+            Some(get_mounted_snaps()?)
+        } else {
+            None
+        };
+
+        Ok(LazyMapOfSnaps {
+            map_of_datasets: map_of_datasets.clone(),
+            opt_map_of_snap_dirs: opt_map_of_snap_dirs.clone(),
+            opt_root_mount_path: get_btrfs_root_mount_path(map_of_datasets).cloned(),
+            opt_max_snaps,
+            opt_mounted_snaps,
+            map_of_cells: map_of_datasets
+                .keys()
+                .map(|mount| (mount.clone(), OnceCell::new()))
+                .collect(),
+        })
+    }
+
+    pub fn get(&self, mount: &Path) -> Option<&VecOfSnaps> {
+        self.map_of_cells
+            .get(mount)?
+            .get_or_init(|| self.find_snap_mounts(mount))
+            .as_ref()
+    }
+
+    // every dataset whose snapshots could be read, which means reading them all
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (&PathBuf, &VecOfSnaps)> + '_ {
+        self.map_of_cells
             .par_iter()
-            .find_map_first(|(mount, dataset_info)| match dataset_info.fs_type {
-                FilesystemType::Btrfs => {
-                    if dataset_info.name.as_str() == "/" {
-                        Some(mount)
-                    } else {
-                        None
-                    }
-                }
-                FilesystemType::Zfs => None,
-            });
+            .filter_map(|(mount, _cell)| self.get(mount).map(|snap_mounts| (mount, snap_mounts)))
+    }
 
-    let map_of_snaps: MapOfSnaps = map_of_datasets
-        .par_iter()
-        .flat_map(|(mount, dataset_info)| {
-            let snap_mounts = match dataset_info.fs_type {
-                FilesystemType::Zfs => precompute_from_defined_mounts(mount, &dataset_info.fs_type),
-                FilesystemType::Btrfs => match opt_root_mount_path {
-                    Some(root_mount_path) => match dataset_info.mount_type {
-                        MountType::Local => precompute_from_btrfs_cmd(mount, root_mount_path),
-                        MountType::Network => {
-                            precompute_from_defined_mounts(mount, &dataset_info.fs_type)
-                        }
-                    },
-                    None => precompute_from_defined_mounts(mount, &dataset_info.fs_type),
-                },
-            };
+    // forget every snap mount found so far, so any new snapshot will be found when next searched
+    pub fn clear(&mut self) {
+        self.map_of_cells
+            .values_mut()
+            .for_each(|cell| *cell = OnceCell::new());
+    }
+
+    fn find_snap_mounts(&self, mount: &Path) -> Option<VecOfSnaps> {
+        let dataset_info = self.map_of_datasets.get(mount)?;
+
+        let opt_snap_dir = self
+            .opt_map_of_snap_dirs
+            .as_ref()
+            .and_then(|map_of_snap_dirs| map_of_snap_dirs.get(mount));
+
+        let snap_mounts = precompute_snap_mounts_for_dataset(
+            mount,
+            dataset_info,
+            self.opt_root_mount_path.as_ref(),
+            opt_snap_dir,
+        )
+        .ok()?;
+
+        let snap_mounts = match self.opt_max_snaps {
+            Some(max_snaps) => limit_snap_mounts(dataset_info, snap_mounts, max_snaps),
+            None => snap_mounts,
+        };
+
+        let snap_mounts = match &self.opt_mounted_snaps {
+            Some(mounted_snaps) => {
+                retain_mounted_snap_mounts(mount, dataset_info, snap_mounts, mounted_snaps)
+            }
+            None => snap_mounts,
+        };
 
-            snap_mounts.map(|snap_mounts| (mount.clone(), snap_mounts))
+        Some(snap_mounts)
+    }
+}
+
+pub fn get_btrfs_root_mount_path(map_of_datasets: &MapOfDatasets) -> Option<&PathBuf> {
+    map_of_datasets
+        .par_iter()
+        .find_map_first(|(mount, dataset_info)| match dataset_info.fs_type {
+            FilesystemType::Btrfs => {
+                if dataset_info.name.as_str() == "/" {
+                    Some(mount)
+                } else {
+                    None
+                }
+            }
+            FilesystemType::Zfs => None,
         })
-        .collect();
+}
 
-    if map_of_snaps.is_empty() {
-        Err(HttmError::new("httm could not find any valid datasets on the system.").into())
-    } else {
-        Ok(map_of_snaps)
+pub fn precompute_snap_mounts_for_dataset(
+    mount: &Path,
+    dataset_info: &DatasetMetadata,
+    opt_root_mount_path: Option<&PathBuf>,
+    opt_snap_dir: Option<&PathBuf>,
+) -> HttmResult<VecOfSnaps> {
+    // a user defined snap dir always wins, and, for btrfs, implies a snapper layout
+    if let Some(snap_dir) = opt_snap_dir {
+        return precompute_from_defined_mounts(mount, &dataset_info.fs_type, snap_dir);
+    }
+
+    let default_snap_dir = match dataset_info.fs_type {
+        FilesystemType::Zfs => Path::new(ZFS_SNAPSHOT_DIRECTORY),
+        FilesystemType::Btrfs => Path::new(BTRFS_SNAPPER_HIDDEN_DIRECTORY),
+    };
+
+    match dataset_info.fs_type {
+        FilesystemType::Zfs => {
+            precompute_from_defined_mounts(mount, &dataset_info.fs_type, default_snap_dir)
+        }
+        FilesystemType::Btrfs => match opt_root_mount_path {
+            Some(root_mount_path) => match dataset_info.mount_type {
+                MountType::Local => precompute_from_btrfs_cmd(mount, root_mount_path),
+                MountType::Network => {
+                    precompute_from_defined_mounts(mount, &dataset_info.fs_type, default_snap_dir)
+                }
+            },
+            None => precompute_from_defined_mounts(mount, &dataset_info.fs_type, default_snap_dir),
+        },
     }
 }
 
@@ -90,7 +202,7 @@ fn precompute_from_btrfs_cmd(
 
         let snaps = command_output
             .par_lines()
-            .filter_map(|line| line.split_once(&"path "))
+            .filter_map(|line| line.split_once("path "))
             .map(
                 |(_first, snap_path)| match snap_path.strip_prefix("<FS_TREE>/") {
                     Some(fs_tree_path) => {
@@ -117,10 +229,7 @@ fn precompute_from_btrfs_cmd(
         let snapshot_locations = parse(mount_point_path, root_mount_path, &btrfs_command)?;
         Ok(snapshot_locations)
     } else {
-        Err(HttmError::new(
-            "'btrfs' command not found. Make sure the command 'btrfs' is in your path.",
-        )
-        .into())
+        Err(HttmError::CommandNotFound("btrfs".to_owned()))
     }
 }
 
@@ -128,14 +237,24 @@ fn precompute_from_btrfs_cmd(
 fn precompute_from_defined_mounts(
     mount_point_path: &Path,
     fs_type: &FilesystemType,
+    snap_dir: &Path,
 ) -> HttmResult<VecOfSnaps> {
+    audit("list", &mount_point_path.join(snap_dir));
     let snaps = match fs_type {
-        FilesystemType::Btrfs => read_dir(mount_point_path.join(BTRFS_SNAPPER_HIDDEN_DIRECTORY))?
+        FilesystemType::Btrfs => read_dir(mount_point_path.join(snap_dir))
+            .map_err(|source| HttmError::SnapshotScanFailed {
+                dataset: mount_point_path.to_path_buf(),
+                source,
+            })?
             .flatten()
             .par_bridge()
             .map(|entry| entry.path().join(BTRFS_SNAPPER_SUFFIX))
             .collect(),
-        FilesystemType::Zfs => read_dir(mount_point_path.join(ZFS_SNAPSHOT_DIRECTORY))?
+        FilesystemType::Zfs => read_dir(mount_point_path.join(snap_dir))
+            .map_err(|source| HttmError::SnapshotScanFailed {
+                dataset: mount_point_path.to_path_buf(),
+                source,
+            })?
             .flatten()
             .par_bridge()
             .map(|entry| entry.path())
@@ -144,3 +263,285 @@ fn precompute_from_defined_mounts(
 
     Ok(snaps)
 }
+
+// user defined snap dirs, for datasets which keep their snapshots somewhere other than the default,
+// each delimited by a colon, as <MOUNT>:<SNAP_DIR>, where SNAP_DIR is relative to the MOUNT
+pub fn parse_snap_dirs(input_snap_dirs: &[String]) -> HttmResult<MapOfSnapDirs> {
+    input_snap_dirs
+        .iter()
+        .map(|snap_dir| {
+            let (mount, snap_dir) = snap_dir.split_once(':').ok_or_else(|| {
+                HttmError::new("Must use specified delimiter (':') between the mount and the snap dir for SNAP_DIRS.")
+            })?;
+
+            let snap_dir = PathBuf::from(snap_dir);
+
+            if snap_dir.is_absolute() {
+                return Err(HttmError::new(
+                    "A snap dir specified for SNAP_DIRS must be relative to its mount.",
+                ));
+            }
+
+            Ok((PathBuf::from(mount), snap_dir))
+        })
+        .collect()
+}
+
+pub fn parse_snap_dirs_from_env() -> HttmResult<OptMapOfSnapDirs> {
+    std::env::var_os("HTTM_SNAP_DIRS")
+        .map(|env_snap_dirs| {
+            let snap_dir_values: Vec<String> = env_snap_dirs
+                .to_string_lossy()
+                .split_terminator(',')
+                .map(|str| str.to_owned())
+                .collect();
+
+            parse_snap_dirs(&snap_dir_values)
+        })
+        .transpose()
+}
+
+fn get_mounted_snaps() -> HttmResult<BTreeSet<PathBuf>> {
+    let mounted_snaps = MountIter::new()?
+        .flatten()
+        .map(|mount_info| mount_info.dest)
+        .filter(|dest| dest.to_string_lossy().contains(ZFS_SNAPSHOT_DIRECTORY))
+        .collect();
+
+    Ok(mounted_snaps)
+}
+
+// merely looking inside a ZFS snapshot mounts it, which, for forensic use, is a change to the system
+// under examination, so keep only those snapshots which are already mounted.  We can't know what
+// a network share will do, so snapshots of network datasets are kept as is.
+fn retain_mounted_snap_mounts(
+    mount: &Path,
+    dataset_info: &DatasetMetadata,
+    snap_mounts: VecOfSnaps,
+    mounted_snaps: &BTreeSet<PathBuf>,
+) -> VecOfSnaps {
+    if dataset_info.fs_type != FilesystemType::Zfs || dataset_info.mount_type != MountType::Local {
+        return snap_mounts;
+    }
+
+    let num_snaps = snap_mounts.len();
+
+    let snap_mounts: VecOfSnaps = snap_mounts
+        .into_iter()
+        .filter(|snap_mount| mounted_snaps.contains(snap_mount))
+        .collect();
+
+    let num_skipped = num_snaps - snap_mounts.len();
+
+    if num_skipped > 0 {
+        eprintln!(
+            "WARNING: httm will not search {} snapshot(s) of {:?} which are not yet mounted, as mounting them would change the system.",
+            num_skipped, mount
+        );
+    }
+
+    snap_mounts
+}
+
+// keep only the newest max_snaps snap mounts for each dataset, so datasets with many thousands
+// of snapshots needn't be scanned in full, when only recent history is of interest
+fn limit_snap_mounts(
+    dataset_info: &DatasetMetadata,
+    snap_mounts: VecOfSnaps,
+    max_snaps: usize,
+) -> VecOfSnaps {
+    if snap_mounts.len() <= max_snaps {
+        return snap_mounts;
+    }
+
+    sort_snaps_by_creation(Some(dataset_info), snap_mounts)
+        .into_iter()
+        .take(max_snaps)
+        .collect()
+}
+
+// sorts snap mounts newest first, then by path to make ties deterministic
+pub fn sort_snaps_by_creation(
+    opt_dataset_info: Option<&DatasetMetadata>,
+    snap_mounts: VecOfSnaps,
+) -> VecOfSnaps {
+    let mut snaps_by_creation: Vec<(SystemTime, PathBuf)> =
+        get_snap_creation_times(opt_dataset_info, &snap_mounts)
+            .into_iter()
+            .map(|(snap_mount, creation_time)| (creation_time, snap_mount))
+            .collect();
+
+    snaps_by_creation.sort_unstable_by(|a, b| b.cmp(a));
+
+    snaps_by_creation
+        .into_iter()
+        .map(|(_creation_time, snap_mount)| snap_mount)
+        .collect()
+}
+
+lazy_static! {
+    // a snapshot's creation time never changes, and finding it may mean a call out to zfs or
+    // btrfs, so each is found only once. key: snap mount, val: creation time of the snapshot
+    static ref CREATION_TIMES_CACHE: Mutex<HashMap<PathBuf, SystemTime>> =
+        Mutex::new(HashMap::new());
+}
+
+// key: snap mount, val: creation time of the snapshot
+pub fn get_snap_creation_times(
+    opt_dataset_info: Option<&DatasetMetadata>,
+    snap_mounts: &[PathBuf],
+) -> BTreeMap<PathBuf, SystemTime> {
+    let mut creation_times: BTreeMap<PathBuf, SystemTime> = BTreeMap::new();
+
+    let uncached: Vec<&PathBuf> = {
+        let cache = CREATION_TIMES_CACHE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        snap_mounts
+            .iter()
+            .filter(|snap_mount| match cache.get(*snap_mount) {
+                Some(creation_time) => {
+                    creation_times.insert(snap_mount.to_path_buf(), *creation_time);
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    };
+
+    if uncached.is_empty() {
+        return creation_times;
+    }
+
+    // prefer the creation times zfs reports, as the times of a snap mount, once mounted,
+    // are the times of the dataset's root dir, and not of the snapshot itself
+    let opt_creation_times = opt_dataset_info
+        .filter(|dataset_info| {
+            dataset_info.fs_type == FilesystemType::Zfs
+                && dataset_info.mount_type == MountType::Local
+        })
+        .and_then(|dataset_info| get_zfs_creation_times(&dataset_info.name).ok());
+
+    let is_btrfs = opt_dataset_info
+        .map(|dataset_info| dataset_info.fs_type == FilesystemType::Btrfs)
+        .unwrap_or(false);
+
+    let found: Vec<(PathBuf, SystemTime)> = uncached
+        .par_iter()
+        .map(|snap_mount| {
+            let creation_time = get_snap_creation_time(snap_mount, &opt_creation_times, is_btrfs);
+            (snap_mount.to_path_buf(), creation_time)
+        })
+        .collect();
+
+    CREATION_TIMES_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .extend(found.iter().cloned());
+
+    creation_times.extend(found);
+
+    creation_times
+}
+
+// key: snap name, val: creation time, as reported by "zfs list"
+fn get_zfs_creation_times(dataset_name: &str) -> HttmResult<BTreeMap<String, SystemTime>> {
+    let zfs_command = which("zfs").map_err(|_| HttmError::CommandNotFound("zfs".to_owned()))?;
+    let args = vec![
+        "list",
+        "-H",
+        "-p",
+        "-t",
+        "snapshot",
+        "-d",
+        "1",
+        "-o",
+        "name,creation",
+        dataset_name,
+    ];
+
+    let command_output =
+        std::str::from_utf8(&ExecProcess::new(zfs_command).args(&args).output()?.stdout)?
+            .to_owned();
+
+    let creation_times = command_output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(full_name, creation)| {
+            let (_dataset, snap_name) = full_name.split_once('@')?;
+            let secs: u64 = creation.trim().parse().ok()?;
+            Some((snap_name.to_owned(), UNIX_EPOCH + Duration::from_secs(secs)))
+        })
+        .collect();
+
+    Ok(creation_times)
+}
+
+fn get_snap_creation_time(
+    snap_mount: &Path,
+    opt_creation_times: &Option<BTreeMap<String, SystemTime>>,
+    is_btrfs: bool,
+) -> SystemTime {
+    let opt_zfs_creation_time = opt_creation_times
+        .as_ref()
+        .zip(snap_mount.file_name())
+        .and_then(|(creation_times, snap_name)| {
+            creation_times.get(snap_name.to_string_lossy().as_ref())
+        })
+        .copied();
+
+    // otherwise, a btrfs snapshot is a subvolume created at the time of the snapshot, so its
+    // birth time is the creation time, or, where there is no birth time, btrfs will say, and,
+    // failing that, the modify time will have to do
+    opt_zfs_creation_time
+        .or_else(|| {
+            snap_mount
+                .symlink_metadata()
+                .ok()
+                .and_then(|metadata| metadata.created().ok())
+        })
+        .or_else(|| {
+            is_btrfs
+                .then(|| get_btrfs_creation_time(snap_mount))
+                .flatten()
+        })
+        .or_else(|| {
+            snap_mount
+                .symlink_metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .unwrap_or(UNIX_EPOCH)
+}
+
+// as "btrfs subvolume show" reports it, like "Creation time:  2022-08-01 12:00:00 +0200"
+fn get_btrfs_creation_time(snap_mount: &Path) -> Option<SystemTime> {
+    let btrfs_command = which("btrfs").ok()?;
+
+    let command_output = ExecProcess::new(btrfs_command)
+        .arg("subvolume")
+        .arg("show")
+        .arg(snap_mount)
+        .output()
+        .ok()?;
+
+    let stdout = std::str::from_utf8(&command_output.stdout).ok()?;
+
+    let creation = stdout
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Creation time:"))?;
+
+    let mut parts = creation.split_whitespace();
+    let date = parts.next()?;
+    let time = parts.next()?;
+    let offset = parts.next().unwrap_or("+0000");
+
+    let sign: i8 = if offset.starts_with('-') { -1 } else { 1 };
+    let digits = offset.trim_start_matches(['+', '-']);
+    let hours: i8 = digits.get(0..2)?.parse().ok()?;
+    let minutes: i8 = digits.get(2..4).unwrap_or("00").parse().ok()?;
+    let utc_offset = UtcOffset::from_hms(sign * hours, sign * minutes, 0).ok()?;
+
+    parse_date_time(date, time, utc_offset)
+}
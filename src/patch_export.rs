@@ -0,0 +1,84 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::path::{Path, PathBuf};
+
+use crate::line_diff::{get_unified_diff, read_lines};
+use crate::lookup_versions::get_live_path;
+use crate::utility::{httm_is_dir, print_output_buf, HttmError, PathData};
+use crate::{Config, HttmResult};
+
+// a patch from one version of a file to another, either of which may be the live file,
+// so that a single regression may be reverted with "patch -p0", rather than by a restore
+pub fn patch_exec(config: &Config, old_path: &Path, new_path: &Path) -> HttmResult<()> {
+    let old_pathdata = PathData::from(old_path);
+    let new_pathdata = PathData::from(new_path);
+
+    [&old_pathdata, &new_pathdata]
+        .iter()
+        .try_for_each(|pathdata| {
+            if pathdata.metadata().is_none() {
+                return Err(HttmError::new(&format!(
+                    "httm could not find the version specified: {:?}",
+                    pathdata.path_buf
+                )));
+            }
+
+            if httm_is_dir(*pathdata) {
+                return Err(HttmError::new(&format!(
+                    "httm can only make a patch between versions of a file, and this is a directory: {:?}",
+                    pathdata.path_buf
+                )));
+            }
+
+            Ok(())
+        })?;
+
+    let patch_name = get_patch_name(config, &old_pathdata, &new_pathdata);
+
+    let old_lines = read_lines(&old_pathdata.path_buf)?;
+    let new_lines = read_lines(&new_pathdata.path_buf)?;
+
+    let old_strs: Vec<&str> = old_lines.iter().map(String::as_str).collect();
+    let new_strs: Vec<&str> = new_lines.iter().map(String::as_str).collect();
+
+    let patch = get_unified_diff(&patch_name, &patch_name, &old_strs, &new_strs)?;
+
+    if patch.is_empty() {
+        eprintln!("httm found no differences between the versions specified.");
+        return Ok(());
+    }
+
+    print_output_buf(patch)?;
+
+    Ok(())
+}
+
+// a patch applies to the live file, so both sides are named for the live file, as a path
+// relative to the working directory, which is what "patch -p0" expects, when it can be
+fn get_patch_name(config: &Config, old_pathdata: &PathData, new_pathdata: &PathData) -> String {
+    // a version which is not on a snapshot we know of is presumed to be the live file
+    let live_path: PathBuf = get_live_path(config, new_pathdata)
+        .or_else(|| get_live_path(config, old_pathdata))
+        .unwrap_or_else(|| new_pathdata.path_buf.clone());
+
+    live_path
+        .strip_prefix(&config.pwd.path_buf)
+        .unwrap_or(&live_path)
+        .to_string_lossy()
+        .into_owned()
+}
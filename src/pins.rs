@@ -0,0 +1,193 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    ffi::OsStr,
+    fs::OpenOptions,
+    io::{Cursor, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use skim::prelude::*;
+
+use crate::forensic::deny_if_forensic;
+use crate::parse_mounts::parse_datasets;
+use crate::utility::{get_home_dir, HttmError, PathData};
+use crate::HttmResult;
+
+// pins are stored one path per line, in the order in which they were pinned
+pub const PINS_FILE_NAME: &str = ".httm-pins";
+
+pub fn get_pins_file_path() -> HttmResult<PathBuf> {
+    let home_dir = get_home_dir()?;
+    Ok(home_dir.join(PINS_FILE_NAME))
+}
+
+fn read_pins(pins_file_path: &Path) -> HttmResult<Vec<PathBuf>> {
+    // no pins file is the same as no pins
+    if !pins_file_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut buffer = String::new();
+    OpenOptions::new()
+        .read(true)
+        .open(pins_file_path)?
+        .read_to_string(&mut buffer)?;
+
+    let pins = buffer
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect();
+
+    Ok(pins)
+}
+
+pub fn pin_paths<'a, I>(raw_paths: I) -> HttmResult<()>
+where
+    I: Iterator<Item = &'a OsStr>,
+{
+    deny_if_forensic("pin a path")?;
+
+    let pwd = std::env::current_dir()?;
+    let pins_file_path = get_pins_file_path()?;
+    let mut pins = read_pins(&pins_file_path)?;
+
+    // canonicalize() on a deleted path will fail, but a deleted file is
+    // exactly the sort of thing one might want to recover again and again,
+    // so we take the path relative to the working dir, just as with INPUT_FILES
+    let new_pins: Vec<PathBuf> = raw_paths
+        .map(Path::new)
+        .map(|path| path.canonicalize().unwrap_or_else(|_| pwd.join(path)))
+        .collect();
+
+    new_pins.into_iter().for_each(|pin| {
+        if pins.contains(&pin) {
+            eprintln!("httm: path is already pinned: {:?}", pin);
+        } else {
+            eprintln!("httm: pinned path: {:?}", pin);
+            pins.push(pin);
+        }
+    });
+
+    let pins_buffer: String = pins
+        .iter()
+        .map(|pin| format!("{}\n", pin.to_string_lossy()))
+        .collect();
+
+    // should overwrite the file always, we just read in all the old pins
+    let mut pins_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&pins_file_path)
+        .map_err(|err| {
+            HttmError::with_context(
+                "httm could not write to the pins file in the user's home directory",
+                err,
+            )
+        })?;
+    pins_file.write_all(pins_buffer.as_bytes())?;
+
+    std::process::exit(0)
+}
+
+pub fn select_pinned_path() -> HttmResult<PathData> {
+    let pins_file_path = get_pins_file_path()?;
+    let pins = read_pins(&pins_file_path)?;
+
+    if pins.is_empty() {
+        return Err(HttmError::new(
+            "httm could not find any pinned paths.  Pin a path with \"httm --pin <path>\" first.",
+        ));
+    }
+
+    select_path(&pins, "pins")
+}
+
+// likely places to start, for one who has not yet given httm a path: the home dir,
+// any pinned dirs, and then the mount of each dataset httm knows about
+pub fn select_root_path() -> HttmResult<PathData> {
+    let home_dir = get_home_dir().ok();
+
+    let pinned_dirs = get_pins_file_path()
+        .and_then(|pins_file_path| read_pins(&pins_file_path))
+        .unwrap_or_default();
+
+    let (map_of_datasets, _vec_of_filter_dirs) = parse_datasets()?;
+
+    let roots: Vec<PathBuf> = home_dir
+        .into_iter()
+        .chain(pinned_dirs.into_iter().filter(|pin| pin.is_dir()))
+        .chain(map_of_datasets.into_keys())
+        .unique()
+        .collect();
+
+    if roots.is_empty() {
+        return Err(HttmError::new(
+            "httm could not find any likely roots from which to begin.",
+        ));
+    }
+
+    select_path(&roots, "roots")
+}
+
+fn select_path(paths: &[PathBuf], session_name: &str) -> HttmResult<PathData> {
+    let paths_buffer: String = paths
+        .iter()
+        .map(|path| format!("{}\n", path.to_string_lossy()))
+        .collect();
+
+    let skim_opts = SkimOptionsBuilder::default()
+        .exact(true)
+        .multi(false)
+        .regex(false)
+        .header(Some(
+            "PAGE UP:    page up  | PAGE DOWN:  page down\n\
+                      EXIT:       esc      | SELECT:     enter    \n\
+                      ─────────────────────────────────────────────",
+        ))
+        .build()
+        .expect("Could not initialized skim options for select_path");
+
+    let item_reader = SkimItemReader::new(SkimItemReaderOption::default());
+    let items = item_reader.of_bufread(Cursor::new(paths_buffer));
+
+    let selected_items = if let Some(output) = Skim::run_with(&skim_opts, Some(items)) {
+        if output.is_abort {
+            eprintln!("httm {} session was aborted.  Quitting.", session_name);
+            std::process::exit(0)
+        } else {
+            output.selected_items
+        }
+    } else {
+        return Err(HttmError::new(&format!(
+            "httm {} session failed.",
+            session_name
+        )));
+    };
+
+    match selected_items.first() {
+        Some(item) => Ok(PathData::from(Path::new(item.output().as_ref()))),
+        None => Err(HttmError::new(&format!(
+            "httm {} session returned no selection.",
+            session_name
+        ))),
+    }
+}
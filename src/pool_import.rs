@@ -0,0 +1,119 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    ffi::{OsStr, OsString},
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::Command as ExecProcess,
+};
+
+use crate::forensic::deny_if_forensic;
+use crate::privileged_helper::{
+    exec_privileged, get_import_altroot, validate_pool_name, PrivilegedRequest,
+};
+use crate::utility::HttmError;
+use crate::HttmResult;
+
+// import a pool which isn't normally imported, read-only and beneath an alternate root,
+// run httm again, as requested, but within that alternate root, and then export the pool.
+// a child process does the work, so that we export the pool however it exits
+pub fn import_pool_exec(pool_name: &OsStr) -> HttmResult<()> {
+    deny_if_forensic("import a pool")?;
+
+    let pool_name = pool_name
+        .to_str()
+        .ok_or_else(|| HttmError::new("The pool name requested is not a valid pool name."))?;
+
+    validate_pool_name(pool_name)?;
+
+    let altroot = get_import_altroot(pool_name);
+
+    if !get_consent(pool_name, &altroot)? {
+        eprintln!("User declined import.  No pools were imported.");
+        std::process::exit(0)
+    }
+
+    // importing and exporting are the only privileged operations here, the child need not be privileged
+    let altroot = PathBuf::from(exec_privileged(&PrivilegedRequest::ImportPool {
+        pool_name: pool_name.to_owned(),
+    })?);
+
+    let child_status = ExecProcess::new(std::env::current_exe()?)
+        .args(get_child_args())
+        .arg("--altroot")
+        .arg(&altroot)
+        .status();
+
+    if let Err(err) = exec_privileged(&PrivilegedRequest::ExportPool {
+        pool_name: pool_name.to_owned(),
+    }) {
+        eprintln!(
+            "Warning: httm was unable to export the pool {:?}: {}",
+            pool_name, err
+        );
+    }
+
+    // exit with the child's status, as the child has already printed any error
+    std::process::exit(child_status?.code().unwrap_or(1))
+}
+
+// stdin may well be our input, so we ask at the terminal
+fn get_consent(pool_name: &str, altroot: &Path) -> HttmResult<bool> {
+    let mut tty = File::options()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|_| {
+            HttmError::new("httm must be run from a terminal to ask consent to import a pool.")
+        })?;
+
+    write!(
+        tty,
+        "httm will import the pool {:?} read-only at {:?}, perform the requested lookups or restores, \
+        and then export the pool.\n\
+        Before httm imports this pool, it would like your consent. Continue? (YES/NO) ",
+        pool_name, altroot
+    )?;
+    tty.flush()?;
+
+    let mut user_consent = String::new();
+    BufReader::new(tty).read_line(&mut user_consent)?;
+
+    Ok(matches!(
+        user_consent.trim().to_ascii_uppercase().as_ref(),
+        "YES" | "Y"
+    ))
+}
+
+// our own args, less the request to import a pool
+fn get_child_args() -> Vec<OsString> {
+    let mut child_args = Vec::new();
+    let mut args = std::env::args_os().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--import-pool" {
+            // skip its value as well
+            let _ = args.next();
+        } else if !arg.to_string_lossy().starts_with("--import-pool=") {
+            child_args.push(arg);
+        }
+    }
+
+    child_args
+}
@@ -0,0 +1,426 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    ffi::OsStr,
+    fs::{remove_dir, symlink_metadata, DirBuilder},
+    io::{BufRead, ErrorKind, Write},
+    os::unix::fs::{DirBuilderExt, MetadataExt},
+    path::{Path, PathBuf},
+    process::{Command as ExecProcess, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+use which::which;
+
+use crate::parse_mounts::parse_datasets;
+use crate::snapdir::{get_snapdir_property, SnapdirProperty};
+use crate::utility::HttmError;
+use crate::{FilesystemType, HttmResult};
+
+// the only operations which require privileges.  a request names what to act upon, never how,
+// so the helper resolves its own commands, and checks each request against the system as it finds it
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PrivilegedRequest {
+    Snapshot { snapshot_names: Vec<String> },
+    ImportPool { pool_name: String },
+    ExportPool { pool_name: String },
+    EnableSnapdir { dataset_name: String },
+    RestoreSnapdir { dataset_name: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PrivilegedResponse {
+    Ok(String),
+    Error(String),
+}
+
+// when unprivileged, and a means of elevation is given, like "sudo -n" or "pkexec",
+// a request is sent to another httm, run as the helper, otherwise we just do the work ourselves
+pub fn exec_privileged(request: &PrivilegedRequest) -> HttmResult<String> {
+    // safe, geteuid() cannot fail and has no side effects
+    let euid = unsafe { libc::geteuid() };
+
+    match std::env::var("HTTM_PRIVILEGED_HELPER") {
+        Ok(elevate_command) if euid != 0 && !elevate_command.trim().is_empty() => {
+            exec_helper(&elevate_command, request)
+        }
+        _ => handle_request(request),
+    }
+}
+
+fn exec_helper(elevate_command: &str, request: &PrivilegedRequest) -> HttmResult<String> {
+    let mut elevate_args = elevate_command.split_whitespace();
+    // safe to unwrap, as we know the command is not empty
+    let elevate_program = elevate_args.next().unwrap();
+
+    let mut child = ExecProcess::new(elevate_program)
+        .args(elevate_args)
+        .arg(std::env::current_exe()?)
+        .arg("--privileged-helper")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| {
+            let msg = format!(
+                "httm could not run its privileged helper via {:?}: {}",
+                elevate_command, err
+            );
+            HttmError::new(&msg)
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{}", serde_json::to_string(request)?)?;
+    }
+
+    let output = child.wait_with_output()?;
+
+    let response: PrivilegedResponse = std::str::from_utf8(&output.stdout)?
+        .lines()
+        .next()
+        .and_then(|line| serde_json::from_str(line).ok())
+        .ok_or_else(|| {
+            let msg = format!(
+                "httm's privileged helper, run via {:?}, did not respond.  Is httm permitted to run as the helper?",
+                elevate_command
+            );
+            HttmError::new(&msg)
+        })?;
+
+    match response {
+        PrivilegedResponse::Ok(output) => Ok(output),
+        PrivilegedResponse::Error(msg) => Err(HttmError::new(&msg)),
+    }
+}
+
+// run as the helper, we serve one request per line of stdin, and respond with one line on stdout
+pub fn privileged_helper_exec() -> HttmResult<()> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<PrivilegedRequest>(&line) {
+            Ok(request) => match handle_request(&request) {
+                Ok(output) => PrivilegedResponse::Ok(output),
+                Err(err) => PrivilegedResponse::Error(err.to_string()),
+            },
+            Err(err) => PrivilegedResponse::Error(format!("Invalid request: {}", err)),
+        };
+
+        let mut out = stdout.lock();
+        writeln!(out, "{}", serde_json::to_string(&response)?)?;
+        out.flush()?;
+    }
+
+    std::process::exit(0)
+}
+
+fn handle_request(request: &PrivilegedRequest) -> HttmResult<String> {
+    match request {
+        PrivilegedRequest::Snapshot { snapshot_names } => snapshot(snapshot_names),
+        PrivilegedRequest::ImportPool { pool_name } => import_pool(pool_name),
+        PrivilegedRequest::ExportPool { pool_name } => export_pool(pool_name),
+        PrivilegedRequest::EnableSnapdir { dataset_name } => enable_snapdir(dataset_name),
+        PrivilegedRequest::RestoreSnapdir { dataset_name } => restore_snapdir(dataset_name),
+    }
+}
+
+fn snapshot(snapshot_names: &[String]) -> HttmResult<String> {
+    let zfs_command = which("zfs").map_err(|_| HttmError::CommandNotFound("zfs".to_owned()))?;
+
+    // only snapshots of mounted ZFS datasets, and only snapshots named as httm would name them
+    let (map_of_datasets, _vec_of_filter_dirs) = parse_datasets()?;
+
+    snapshot_names.iter().try_for_each(|snapshot_name| {
+        let is_valid = match snapshot_name.split_once('@') {
+            Some((dataset, snap_name)) => {
+                map_of_datasets.values().any(|dataset_info| {
+                    matches!(dataset_info.fs_type, FilesystemType::Zfs)
+                        && dataset_info.name == dataset
+                }) && snap_name.starts_with("snap_")
+                    && snap_name.ends_with("_httmSnapFileMount")
+                    && !snap_name.contains(char::is_whitespace)
+            }
+            None => false,
+        };
+
+        if is_valid {
+            Ok(())
+        } else {
+            let msg = format!(
+                "httm will not take the snapshot {:?}, as it is not a snapshot httm would take of a mounted ZFS dataset.",
+                snapshot_name
+            );
+            Err(HttmError::new(&msg))
+        }
+    })?;
+
+    let process_output = ExecProcess::new(zfs_command)
+        .arg("snapshot")
+        .args(snapshot_names)
+        .output()?;
+    let stderr_string = std::str::from_utf8(&process_output.stderr)?.trim();
+
+    // stderr_string is a string not an error, so here we build an err or output
+    if !stderr_string.is_empty() {
+        if stderr_string.contains("cannot create snapshots : permission denied") {
+            Err(HttmError::PermissionDenied {
+                msg: "httm must have root privileges to snapshot a filesystem".to_owned(),
+                path: None,
+            })
+        } else {
+            let msg =
+                "httm was unable to take snapshots. The 'zfs' command issued the following error: "
+                    .to_owned()
+                    + stderr_string;
+            Err(HttmError::new(&msg))
+        }
+    } else {
+        Ok(String::new())
+    }
+}
+
+// the helper decides where a pool is imported, so a pool may only ever be imported read-only,
+// beneath a directory of httm's naming, and only a pool imported so may be exported.  Unlike a
+// temp dir, which anyone may write to, only root may write here, though anyone may look within,
+// as the lookups and restores on behalf of the user who asked are not privileged
+const IMPORT_ALTROOT_PARENT: &str = "/run/httm";
+
+pub fn get_import_altroot(pool_name: &str) -> PathBuf {
+    Path::new(IMPORT_ALTROOT_PARENT).join(format!("import-{}", pool_name))
+}
+
+// a dir which another user could write to, and so swap for a symlink, is no place for a pool
+fn create_root_only_dir(path: &Path) -> HttmResult<()> {
+    match DirBuilder::new().mode(0o755).create(path) {
+        Ok(_) => {}
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+        Err(err) => {
+            let msg = format!("httm could not create the directory {:?}", path);
+            return Err(HttmError::with_context(&msg, err));
+        }
+    }
+
+    let md = symlink_metadata(path)?;
+
+    if !md.is_dir() || md.uid() != 0 || md.mode() & 0o022 != 0 {
+        let msg = format!(
+            "httm will not import a pool at {:?}, as it is not a directory which only root may write to.",
+            path
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    Ok(())
+}
+
+pub fn validate_pool_name(pool_name: &str) -> HttmResult<()> {
+    let is_valid = pool_name
+        .chars()
+        .next()
+        .map(|first| first.is_ascii_alphabetic())
+        .unwrap_or(false)
+        && pool_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'));
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(HttmError::new(
+            "The pool name requested is not a valid pool name.",
+        ))
+    }
+}
+
+fn import_pool(pool_name: &str) -> HttmResult<String> {
+    validate_pool_name(pool_name)?;
+
+    let zpool_command =
+        which("zpool").map_err(|_| HttmError::CommandNotFound("zpool".to_owned()))?;
+
+    let altroot = get_import_altroot(pool_name);
+
+    create_root_only_dir(Path::new(IMPORT_ALTROOT_PARENT))?;
+    create_root_only_dir(&altroot)?;
+
+    let import_output = ExecProcess::new(&zpool_command)
+        .args([
+            OsStr::new("import"),
+            OsStr::new("-o"),
+            OsStr::new("readonly=on"),
+        ])
+        .arg("-R")
+        .arg(&altroot)
+        .arg(pool_name)
+        .output()?;
+
+    if !import_output.status.success() {
+        let _ = remove_dir(&altroot);
+        let stderr_string = std::str::from_utf8(&import_output.stderr)?.trim();
+        return Err(HttmError::new(
+            &("httm was unable to import the pool. The 'zpool' command issued the following error: "
+                .to_owned() + stderr_string),
+        ));
+    }
+
+    Ok(altroot.to_string_lossy().into_owned())
+}
+
+fn export_pool(pool_name: &str) -> HttmResult<String> {
+    validate_pool_name(pool_name)?;
+
+    let zpool_command =
+        which("zpool").map_err(|_| HttmError::CommandNotFound("zpool".to_owned()))?;
+
+    let altroot = get_import_altroot(pool_name);
+
+    let get_output = ExecProcess::new(&zpool_command)
+        .args(["get", "-H", "-o", "value", "altroot", pool_name])
+        .output()?;
+
+    if Path::new(std::str::from_utf8(&get_output.stdout)?.trim()) != altroot {
+        let msg = format!(
+            "httm will not export the pool {:?}, as httm did not import it.",
+            pool_name
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    let export_output = ExecProcess::new(&zpool_command)
+        .arg("export")
+        .arg(pool_name)
+        .output()?;
+
+    if !export_output.status.success() {
+        let stderr_string = std::str::from_utf8(&export_output.stderr)?.trim();
+        return Err(HttmError::new(
+            &("httm was unable to export the pool. The 'zpool' command issued the following error: "
+                .to_owned() + stderr_string),
+        ));
+    }
+
+    let _ = remove_dir(&altroot);
+
+    Ok(String::new())
+}
+
+// a snapdir may only ever be changed from disabled to hidden, and, so we know which we changed,
+// we mark the dataset with a user property, and only a snapdir so marked may be disabled again
+const SNAPDIR_RESTORE_PROPERTY: &str = "org.httm:snapdir_restore";
+
+fn validate_zfs_dataset(dataset_name: &str) -> HttmResult<()> {
+    let (map_of_datasets, _vec_of_filter_dirs) = parse_datasets()?;
+
+    let is_valid = map_of_datasets.values().any(|dataset_info| {
+        matches!(dataset_info.fs_type, FilesystemType::Zfs) && dataset_info.name == dataset_name
+    });
+
+    if is_valid {
+        Ok(())
+    } else {
+        let msg = format!(
+            "httm will not change the snapdir of {:?}, as it is not a mounted ZFS dataset.",
+            dataset_name
+        );
+        Err(HttmError::new(&msg))
+    }
+}
+
+fn exec_zfs_set(zfs_command: &Path, args: &[&str]) -> HttmResult<()> {
+    let process_output = ExecProcess::new(zfs_command).args(args).output()?;
+
+    if !process_output.status.success() {
+        let stderr_string = std::str::from_utf8(&process_output.stderr)?.trim();
+        return Err(HttmError::new(
+            &("httm was unable to change the snapdir property. The 'zfs' command issued the following error: "
+                .to_owned() + stderr_string),
+        ));
+    }
+
+    Ok(())
+}
+
+fn enable_snapdir(dataset_name: &str) -> HttmResult<String> {
+    validate_zfs_dataset(dataset_name)?;
+
+    let zfs_command = which("zfs").map_err(|_| HttmError::CommandNotFound("zfs".to_owned()))?;
+
+    if get_snapdir_property(dataset_name) != Some(SnapdirProperty::Disabled) {
+        let msg = format!(
+            "httm will not change the snapdir of {:?}, as it is not disabled.",
+            dataset_name
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    exec_zfs_set(
+        &zfs_command,
+        &[
+            "set",
+            "snapdir=hidden",
+            &format!("{}=disabled", SNAPDIR_RESTORE_PROPERTY),
+            dataset_name,
+        ],
+    )?;
+
+    Ok(String::new())
+}
+
+fn restore_snapdir(dataset_name: &str) -> HttmResult<String> {
+    validate_zfs_dataset(dataset_name)?;
+
+    let zfs_command = which("zfs").map_err(|_| HttmError::CommandNotFound("zfs".to_owned()))?;
+
+    // "-s local" so a mark inherited from a parent dataset is not mistaken for our own
+    let get_output = ExecProcess::new(&zfs_command)
+        .args([
+            "get",
+            "-H",
+            "-s",
+            "local",
+            "-o",
+            "value",
+            SNAPDIR_RESTORE_PROPERTY,
+            dataset_name,
+        ])
+        .output()?;
+
+    if std::str::from_utf8(&get_output.stdout)?.trim() != "disabled" {
+        let msg = format!(
+            "httm will not disable the snapdir of {:?}, as httm did not enable it.",
+            dataset_name
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    exec_zfs_set(&zfs_command, &["set", "snapdir=disabled", dataset_name])?;
+    exec_zfs_set(
+        &zfs_command,
+        &["inherit", SNAPDIR_RESTORE_PROPERTY, dataset_name],
+    )?;
+
+    Ok(String::new())
+}
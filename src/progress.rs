@@ -0,0 +1,400 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    fs::{create_dir_all, read_dir, symlink_metadata, File},
+    io::{self, Write},
+    os::unix::io::FromRawFd,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    sync::Mutex,
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use once_cell::sync::OnceCell;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::restrict_owner::{as_invoking_user, is_owned, open_if_owned};
+use crate::utility::{copy_symlink, preserve_metadata, HttmError};
+use crate::HttmResult;
+
+// by default, progress events share stderr with any human readable messages
+pub const DEFAULT_PROGRESS_FD: i32 = 2;
+
+// once set, every progress event is written here, one JSON object per line
+static PROGRESS_SINK: OnceCell<Mutex<File>> = OnceCell::new();
+
+// what a GUI needs to know to draw a progress bar, without parsing our human readable text
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    ScanStarted {
+        paths: usize,
+    },
+    DatasetSearched {
+        dataset: &'a str,
+        relative_path: &'a Path,
+        snapshots: usize,
+        versions: usize,
+    },
+    VersionsFound {
+        path: &'a Path,
+        versions: usize,
+    },
+    ScanFinished {
+        versions: usize,
+    },
+    RestoreProgress {
+        from: &'a Path,
+        to: &'a Path,
+        bytes_copied: u64,
+        total_bytes: u64,
+        percent: u64,
+    },
+}
+
+pub fn init_progress_json(fd: i32) -> HttmResult<()> {
+    // safe, F_GETFD only asks whether the fd is open, and has no side effects
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(HttmError::new(&format!(
+            "httm could not write progress events to file descriptor {}, as it is not open.",
+            fd
+        )));
+    }
+
+    // safe, as we just checked the fd is open, and it is never closed, as the sink is never dropped
+    let sink = unsafe { File::from_raw_fd(fd) };
+
+    PROGRESS_SINK
+        .set(Mutex::new(sink))
+        .map_err(|_| HttmError::new("httm progress events may only be initialized once."))
+}
+
+pub fn is_progress_json() -> bool {
+    PROGRESS_SINK.get().is_some()
+}
+
+// a frontend which has gone away is no reason to stop a restore, so errors are ignored
+pub fn emit_progress(event: &ProgressEvent) {
+    if let Some(sink) = PROGRESS_SINK.get() {
+        if let Ok(mut sink) = sink.lock() {
+            if let Ok(line) = serde_json::to_string(event) {
+                let _ = writeln!(sink, "{}", line);
+            }
+        }
+    }
+}
+
+// one file, or dir, within a dir restored, which could not be restored
+#[derive(Debug)]
+pub struct CopyFailure {
+    pub path: PathBuf,
+    pub err: io::Error,
+}
+
+// a restore reports its progress after each file is copied, to a frontend, should one listen,
+// and, for a dir, to the terminal.  A dir is copied file by file, in parallel, and each file which
+// fails is returned, so one unreadable file doesn't leave the rest of a tree unrestored, and only
+// a failure to restore the top of the tree is itself an error
+pub fn copy_recursive_with_progress(src: &Path, dst: &Path) -> io::Result<Vec<CopyFailure>> {
+    let src_md = symlink_metadata(src)?;
+
+    if src_md.file_type().is_symlink() {
+        let progress = CopyProgress::new(src, dst, 0, 1, false);
+        progress.report(0, false);
+
+        as_invoking_user(|| copy_symlink(src, dst).and_then(|_| preserve_metadata(src, dst)))?;
+        progress.report(0, true);
+
+        return Ok(Vec::new());
+    }
+
+    if !src_md.is_dir() {
+        let progress = CopyProgress::new(src, dst, src_md.len(), 1, false);
+        progress.report(0, false);
+
+        let bytes = copy_file(src, dst)?;
+        progress.report(bytes, true);
+
+        return Ok(Vec::new());
+    }
+
+    let CopyTree {
+        dirs,
+        files,
+        symlinks,
+        mut failures,
+    } = walk_copy_tree(src);
+
+    let total_bytes = files.iter().map(|(_relative_path, len)| len).sum();
+    let total_files = (files.len() + symlinks.len()) as u64;
+    let progress = CopyProgress::new(src, dst, total_bytes, total_files, true);
+    progress.report(0, false);
+
+    as_invoking_user(|| create_dir_all(dst))?;
+
+    // the walk finds each dir before any of its children, and, once a dir fails, we needn't try,
+    // and fail, again for each of its children
+    let mut failed_dirs: Vec<&Path> = Vec::new();
+
+    dirs.iter().for_each(|relative_path| {
+        if is_within_any(relative_path, &failed_dirs) {
+            return;
+        }
+
+        if let Err(err) = as_invoking_user(|| create_dir_all(dst.join(relative_path))) {
+            failed_dirs.push(relative_path);
+            failures.push(CopyFailure {
+                path: src.join(relative_path),
+                err,
+            });
+        }
+    });
+
+    let file_failures: Vec<CopyFailure> = files
+        .par_iter()
+        .filter(|(relative_path, _len)| !is_within_any(relative_path, &failed_dirs))
+        .filter_map(|(relative_path, _len)| {
+            let from = src.join(relative_path);
+            let to = dst.join(relative_path);
+
+            match copy_file(&from, &to) {
+                Ok(bytes) => {
+                    progress.report(bytes, true);
+                    None
+                }
+                Err(err) => {
+                    progress.report(0, true);
+                    Some(CopyFailure { path: from, err })
+                }
+            }
+        })
+        .collect();
+
+    failures.extend(file_failures);
+
+    symlinks
+        .iter()
+        .filter(|relative_path| !is_within_any(relative_path, &failed_dirs))
+        .for_each(|relative_path| {
+            let from = src.join(relative_path);
+            let to = dst.join(relative_path);
+
+            if let Err(err) = as_invoking_user(|| {
+                copy_symlink(&from, &to).and_then(|_| preserve_metadata(&from, &to))
+            }) {
+                failures.push(CopyFailure { path: from, err });
+            }
+
+            progress.report(0, true);
+        });
+
+    // children before parents, as copying a child changes the modify time of its parent
+    dirs.iter()
+        .rev()
+        .map(|relative_path| relative_path.as_path())
+        .chain(std::iter::once(Path::new("")))
+        .filter(|relative_path| !is_within_any(relative_path, &failed_dirs))
+        .for_each(|relative_path| {
+            let from = src.join(relative_path);
+
+            if let Err(err) =
+                as_invoking_user(|| preserve_metadata(&from, &dst.join(relative_path)))
+            {
+                failures.push(CopyFailure { path: from, err });
+            }
+        });
+
+    progress.finish();
+
+    Ok(failures)
+}
+
+// restricted to the invoking user, we read, as root, only what they own, see open_if_owned, and
+// write only as them, see as_invoking_user
+fn copy_file(src: &Path, dst: &Path) -> io::Result<u64> {
+    let mut src_file = open_if_owned(src)?;
+
+    as_invoking_user(|| {
+        let mut dst_file = File::create(dst)?;
+        let bytes = io::copy(&mut src_file, &mut dst_file)?;
+        preserve_metadata(src, dst)?;
+        Ok(bytes)
+    })
+}
+
+pub fn display_copy_failures(failures: &[CopyFailure]) {
+    failures
+        .iter()
+        .for_each(|failure| eprintln!("Failed: {:?}: {}", failure.path, failure.err));
+}
+
+fn is_within_any(relative_path: &Path, dirs: &[&Path]) -> bool {
+    dirs.iter().any(|dir| relative_path.starts_with(dir))
+}
+
+struct CopyTree {
+    // relative to the top of the tree, each dir is found before any of its children
+    dirs: Vec<PathBuf>,
+    // relative to the top of the tree, with its len
+    files: Vec<(PathBuf, u64)>,
+    // relative to the top of the tree, each is restored as a symlink, not as what it points to
+    symlinks: Vec<PathBuf>,
+    failures: Vec<CopyFailure>,
+}
+
+// a stack, rather than recursion, so a tree of any depth may be walked, and we never follow a
+// symlink, lest a restore copy in whatever a symlink happens to point to
+fn walk_copy_tree(src: &Path) -> CopyTree {
+    let mut copy_tree = CopyTree {
+        dirs: Vec::new(),
+        files: Vec::new(),
+        symlinks: Vec::new(),
+        failures: Vec::new(),
+    };
+
+    let mut stack: Vec<PathBuf> = vec![PathBuf::new()];
+
+    while let Some(relative_dir) = stack.pop() {
+        let entries = match read_dir(src.join(&relative_dir)) {
+            Ok(entries) => entries,
+            Err(err) => {
+                copy_tree.failures.push(CopyFailure {
+                    path: src.join(&relative_dir),
+                    err,
+                });
+                continue;
+            }
+        };
+
+        entries.for_each(|entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    return copy_tree.failures.push(CopyFailure {
+                        path: src.join(&relative_dir),
+                        err,
+                    })
+                }
+            };
+
+            let relative_path = relative_dir.join(entry.file_name());
+
+            // nor, restricted to the invoking user, do we restore what another owns, or what is within it
+            if !is_owned(&entry.path()) {
+                return copy_tree.failures.push(CopyFailure {
+                    path: entry.path(),
+                    err: io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "not owned by the invoking user",
+                    ),
+                });
+            }
+
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => {
+                    copy_tree.dirs.push(relative_path.clone());
+                    stack.push(relative_path);
+                }
+                Ok(file_type) if file_type.is_symlink() => copy_tree.symlinks.push(relative_path),
+                Ok(_) => {
+                    let len = entry.metadata().map(|md| md.len()).unwrap_or_default();
+                    copy_tree.files.push((relative_path, len));
+                }
+                Err(err) => copy_tree.failures.push(CopyFailure {
+                    path: entry.path(),
+                    err,
+                }),
+            }
+        });
+    }
+
+    copy_tree
+}
+
+struct CopyProgress<'a> {
+    src: &'a Path,
+    dst: &'a Path,
+    total_bytes: u64,
+    total_files: u64,
+    bytes_copied: AtomicU64,
+    files_copied: AtomicU64,
+    opt_progress_bar: Option<ProgressBar>,
+}
+
+impl<'a> CopyProgress<'a> {
+    fn new(src: &'a Path, dst: &'a Path, total_bytes: u64, total_files: u64, is_dir: bool) -> Self {
+        // progress events may well be written to stderr too, so, then, there's no progress bar
+        let opt_progress_bar = (is_dir && !is_progress_json()).then(|| {
+            let progress_bar = ProgressBar::new(total_bytes);
+
+            if let Ok(style) =
+                ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} ({percent}%), {msg}")
+            {
+                progress_bar.set_style(style);
+            }
+
+            progress_bar.set_message(format!("0/{} files", total_files));
+            progress_bar
+        });
+
+        CopyProgress {
+            src,
+            dst,
+            total_bytes,
+            total_files,
+            bytes_copied: AtomicU64::new(0),
+            files_copied: AtomicU64::new(0),
+            opt_progress_bar,
+        }
+    }
+
+    // a file which fails still counts as done, though none of its bytes were copied
+    fn report(&self, bytes: u64, is_file_done: bool) {
+        let bytes_copied = self.bytes_copied.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let files_copied = self
+            .files_copied
+            .fetch_add(is_file_done as u64, Ordering::Relaxed)
+            + is_file_done as u64;
+
+        if let Some(progress_bar) = &self.opt_progress_bar {
+            progress_bar.set_position(bytes_copied);
+            progress_bar.set_message(format!("{}/{} files", files_copied, self.total_files));
+        }
+
+        // nothing to copy is as good as done
+        let percent = bytes_copied
+            .saturating_mul(100)
+            .checked_div(self.total_bytes)
+            .unwrap_or(100);
+
+        emit_progress(&ProgressEvent::RestoreProgress {
+            from: self.src,
+            to: self.dst,
+            bytes_copied,
+            total_bytes: self.total_bytes,
+            percent,
+        })
+    }
+
+    fn finish(&self) {
+        if let Some(progress_bar) = &self.opt_progress_bar {
+            progress_bar.finish_and_clear();
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use crate::utility::{HttmError, PathData};
+use crate::{HttmResult, MapOfDatasets};
+
+pub fn get_proximate_dataset(
+    pathdata: &PathData,
+    map_of_datasets: &MapOfDatasets,
+) -> HttmResult<PathBuf> {
+    // for /usr/bin, we prefer the most proximate: /usr/bin to /usr and /
+    // ancestors() iterates in this top-down order, when a value: dataset/fstype is available
+    // we map to return the key, instead of the value
+    pathdata
+        .path_buf
+        .ancestors()
+        .find(|ancestor| map_of_datasets.contains_key(*ancestor))
+        .map(|ancestor| ancestor.to_path_buf())
+        .ok_or_else(no_dataset_found)
+}
+
+fn no_dataset_found() -> HttmError {
+    HttmError::NoDatasetFound(
+        "httm could not identify any qualifying dataset.  \
+        Maybe consider specifying manually at SNAP_POINT?"
+            .to_owned(),
+    )
+}
+
+// every dataset mount, one path component per level, so the proximate dataset of a path is found
+// in a single walk down its components, rather than a lookup of each of its ancestors in turn
+#[derive(Debug, Clone, Default)]
+struct DatasetTrie {
+    opt_mount: Option<PathBuf>,
+    children: HashMap<OsString, DatasetTrie>,
+}
+
+impl DatasetTrie {
+    fn new(map_of_datasets: &MapOfDatasets) -> Self {
+        let mut root = DatasetTrie::default();
+
+        map_of_datasets.keys().for_each(|mount| {
+            let node = mount.components().fold(&mut root, |node, component| {
+                node.children
+                    .entry(component.as_os_str().to_owned())
+                    .or_default()
+            });
+
+            node.opt_mount = Some(mount.clone());
+        });
+
+        root
+    }
+
+    // the deepest mount along the way is the most proximate
+    fn find(&self, path: &Path) -> Option<&PathBuf> {
+        let mut node = self;
+        let mut opt_mount = node.opt_mount.as_ref();
+
+        for component in path.components() {
+            match node.children.get(component.as_os_str()) {
+                Some(child) => node = child,
+                None => break,
+            }
+
+            if node.opt_mount.is_some() {
+                opt_mount = node.opt_mount.as_ref();
+            }
+        }
+
+        opt_mount
+    }
+}
+
+// how many parent dirs we remember, before we forget them all and begin again
+const PROXIMATE_DATASET_CACHE_CAPACITY: usize = 65_536;
+
+// the proximate dataset of each parent dir already seen, so thousands of files within the same dir
+// needn't each walk their ancestors.  a cache belongs to the map of datasets of a single dataset
+// collection, which is only ever read whole from the mount table, so when the mount table is read
+// again, the new dataset collection begins with a new cache, and nothing stale survives
+#[derive(Debug)]
+pub struct ProximateDatasetCache {
+    dataset_trie: DatasetTrie,
+    mounts_for_dirs: RwLock<HashMap<PathBuf, Option<PathBuf>>>,
+}
+
+impl ProximateDatasetCache {
+    pub fn new(map_of_datasets: &MapOfDatasets) -> Self {
+        ProximateDatasetCache {
+            dataset_trie: DatasetTrie::new(map_of_datasets),
+            mounts_for_dirs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // map_of_datasets must be the same map from which this cache was built
+    pub fn get_proximate_dataset(
+        &self,
+        pathdata: &PathData,
+        map_of_datasets: &MapOfDatasets,
+    ) -> HttmResult<PathBuf> {
+        // a dataset mount is its own proximate dataset, whatever is proximate to its parent
+        if map_of_datasets.contains_key(&pathdata.path_buf) {
+            return Ok(pathdata.path_buf.clone());
+        }
+
+        let parent = match pathdata.path_buf.parent() {
+            Some(parent) => parent,
+            None => return get_proximate_dataset(pathdata, map_of_datasets),
+        };
+
+        let opt_cached = self
+            .mounts_for_dirs
+            .read()
+            .ok()
+            .and_then(|mounts_for_dirs| mounts_for_dirs.get(parent).cloned());
+
+        let opt_mount = match opt_cached {
+            Some(opt_mount) => opt_mount,
+            None => {
+                let opt_mount = self.dataset_trie.find(parent).cloned();
+
+                if let Ok(mut mounts_for_dirs) = self.mounts_for_dirs.write() {
+                    if mounts_for_dirs.len() >= PROXIMATE_DATASET_CACHE_CAPACITY {
+                        mounts_for_dirs.clear();
+                    }
+
+                    mounts_for_dirs.insert(parent.to_path_buf(), opt_mount.clone());
+                }
+
+                opt_mount
+            }
+        };
+
+        opt_mount.ok_or_else(no_dataset_found)
+    }
+
+    // when many paths are looked up at once, resolve each of their parent dirs only once, and up
+    // front, so that the lookups which follow, in parallel, needn't each contend to write the cache
+    pub fn resolve_path_set(&self, path_set: &[PathData]) {
+        if path_set.len() < 2 {
+            return;
+        }
+
+        let parents: BTreeSet<&Path> = path_set
+            .iter()
+            .filter_map(|pathdata| pathdata.path_buf.parent())
+            .collect();
+
+        if let Ok(mut mounts_for_dirs) = self.mounts_for_dirs.write() {
+            let unresolved: Vec<&Path> = parents
+                .into_iter()
+                .filter(|parent| !mounts_for_dirs.contains_key(*parent))
+                .collect();
+
+            if mounts_for_dirs.len() + unresolved.len() > PROXIMATE_DATASET_CACHE_CAPACITY {
+                mounts_for_dirs.clear();
+            }
+
+            unresolved
+                .into_iter()
+                .take(PROXIMATE_DATASET_CACHE_CAPACITY)
+                .for_each(|parent| {
+                    let opt_mount = self.dataset_trie.find(parent).cloned();
+                    mounts_for_dirs.insert(parent.to_path_buf(), opt_mount);
+                });
+        }
+    }
+}
+
+// a copy of a dataset collection is a copy of its map of datasets, so what we know holds for the copy
+impl Clone for ProximateDatasetCache {
+    fn clone(&self) -> Self {
+        let mounts_for_dirs = self
+            .mounts_for_dirs
+            .read()
+            .map(|mounts_for_dirs| mounts_for_dirs.clone())
+            .unwrap_or_default();
+
+        ProximateDatasetCache {
+            dataset_trie: self.dataset_trie.clone(),
+            mounts_for_dirs: RwLock::new(mounts_for_dirs),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use rayon::prelude::*;
+use serde_json::Value;
+
+use crate::display::{display_human_size, get_fancy_border_string};
+use crate::serve_http::{percent_encode, read_api_token, API_PREFIX};
+use crate::utility::{print_output_buf, HttmError};
+use crate::{Config, HttmResult};
+
+// the default port of "--serve", see DEFAULT_HTTP_LISTEN
+const DEFAULT_HOST_PORT: u16 = 8080;
+const HOST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// the paths to look up on each host, relative to the dir each host serves
+#[derive(Debug, Clone)]
+pub struct HostsQuery {
+    pub hosts: Vec<String>,
+    pub paths: Vec<PathBuf>,
+    pub api_token_file: PathBuf,
+}
+
+// a single version, as one host reported it
+struct HostVersion {
+    host: String,
+    date: String,
+    modify_time: u64,
+    size: u64,
+    path: String,
+    is_live: bool,
+}
+
+pub fn query_hosts_exec(config: &Config, hosts_query: &HostsQuery) -> HttmResult<()> {
+    let api_token = read_api_token(&hosts_query.api_token_file)?;
+
+    let mut out_buffer = String::new();
+
+    hosts_query.paths.iter().try_for_each(|path| {
+        let results: Vec<(&String, HttmResult<Vec<HostVersion>>)> = hosts_query
+            .hosts
+            .par_iter()
+            .map(|host| (host, query_host(host, &api_token, path)))
+            .collect();
+
+        // one unreachable replica is exactly why one asks several
+        let mut host_versions: Vec<HostVersion> = results
+            .into_iter()
+            .filter_map(|(host, res)| match res {
+                Ok(versions) => Some(versions),
+                Err(err) => {
+                    eprintln!(
+                        "Warning: httm could not query {:?} for {:?}: {}",
+                        host, path, err
+                    );
+                    None
+                }
+            })
+            .flatten()
+            .collect();
+
+        if host_versions.is_empty() {
+            return Err(HttmError::NoVersions {
+                msg: format!(
+                    "httm could not find any versions of {:?} on any host queried.",
+                    path
+                ),
+                path: Some(path.to_path_buf()),
+            });
+        }
+
+        // snapshot versions, oldest first, then each live version, as httm displays them locally
+        host_versions.sort_by(|a, b| {
+            a.is_live
+                .cmp(&b.is_live)
+                .then_with(|| a.modify_time.cmp(&b.modify_time))
+                .then_with(|| a.host.cmp(&b.host))
+        });
+
+        out_buffer.push_str(&format_host_versions(config, &host_versions));
+        Ok(())
+    })?;
+
+    print_output_buf(out_buffer)
+}
+
+fn format_host_versions(config: &Config, host_versions: &[HostVersion]) -> String {
+    if config.opt_raw || config.opt_zeros {
+        let delimiter = if config.opt_zeros { '\0' } else { '\n' };
+
+        return host_versions
+            .iter()
+            .map(|version| format!("{}\t{}{}", version.host, version.path, delimiter))
+            .collect();
+    }
+
+    let host_width = host_versions
+        .iter()
+        .map(|version| version.host.len())
+        .max()
+        .unwrap_or_default();
+    let date_width = host_versions
+        .iter()
+        .map(|version| version.date.len())
+        .max()
+        .unwrap_or_default();
+    let size_width = host_versions
+        .iter()
+        .map(|version| display_human_size(&version.size).len())
+        .max()
+        .unwrap_or_default();
+
+    let lines: Vec<(bool, String)> = host_versions
+        .iter()
+        .map(|version| {
+            let line = format!(
+                "{:<host_width$}  {:<date_width$}  {:>size_width$}  \"{}\"\n",
+                version.host,
+                version.date,
+                display_human_size(&version.size),
+                version.path,
+            );
+            (version.is_live, line)
+        })
+        .collect();
+
+    let border_len = lines
+        .iter()
+        .map(|(_is_live, line)| line.trim_end().chars().count())
+        .max()
+        .unwrap_or_default();
+    let fancy_border_string = get_fancy_border_string(border_len);
+
+    // a border before, between the snapshot and live versions, and after, as httm displays versions locally
+    let mut buffer = fancy_border_string.clone();
+    let mut is_live_section = false;
+
+    lines.iter().for_each(|(is_live, line)| {
+        if *is_live && !is_live_section {
+            if buffer.len() > fancy_border_string.len() {
+                buffer.push_str(&fancy_border_string);
+            }
+            is_live_section = true;
+        }
+        buffer.push_str(line);
+    });
+
+    buffer.push_str(&fancy_border_string);
+
+    buffer
+}
+
+fn query_host(host: &str, api_token: &str, path: &Path) -> HttmResult<Vec<HostVersion>> {
+    let host_and_port = get_host_and_port(host);
+
+    let socket_addr = host_and_port
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| HttmError::new("the host's name did not resolve to any address"))?;
+
+    let mut stream = TcpStream::connect_timeout(&socket_addr, HOST_TIMEOUT)?;
+    stream.set_read_timeout(Some(HOST_TIMEOUT))?;
+    stream.set_write_timeout(Some(HOST_TIMEOUT))?;
+
+    write!(
+        stream,
+        "GET {}versions?path={} HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+        API_PREFIX,
+        percent_encode(path.as_os_str().as_bytes(), true),
+        host_and_port,
+        api_token
+    )?;
+    stream.flush()?;
+
+    // "--serve" closes every connection once it has answered
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+
+    let header_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| HttmError::new("the host's response was not HTTP"))?;
+
+    let body: Value = serde_json::from_slice(&response[header_end + 4..])?;
+
+    if let Some(err_msg) = body["error"].as_str() {
+        return Err(HttmError::new(err_msg));
+    }
+
+    let result = &body["result"];
+
+    let versions = result["versions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|version| (version, false))
+        .chain(
+            result
+                .get("live")
+                .filter(|live| !live.is_null())
+                .map(|live| (live, true)),
+        )
+        .map(|(version, is_live)| HostVersion {
+            host: host.to_owned(),
+            date: version["date"].as_str().unwrap_or_default().to_owned(),
+            modify_time: version["modify_time"].as_u64().unwrap_or_default(),
+            size: version["size"].as_u64().unwrap_or_default(),
+            path: version["path"].as_str().unwrap_or_default().to_owned(),
+            is_live,
+        })
+        .collect();
+
+    Ok(versions)
+}
+
+// a host may be given with or without its port, and an IPv6 address must be bracketed to have one
+fn get_host_and_port(host: &str) -> String {
+    let has_port = match host.rsplit_once(':') {
+        Some((name, port)) => {
+            port.parse::<u16>().is_ok() && (!name.contains(':') || name.ends_with(']'))
+        }
+        None => false,
+    };
+
+    if has_port {
+        host.to_owned()
+    } else if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, DEFAULT_HOST_PORT)
+    } else {
+        format!("{}:{}", host, DEFAULT_HOST_PORT)
+    }
+}
@@ -16,16 +16,19 @@
 // that was distributed with this source code.
 
 use std::fs::DirEntry;
-use std::{fs::read_dir, path::Path, sync::Arc};
+use std::os::unix::ffi::OsStrExt;
+use std::{ffi::OsStr, fs::read_dir, path::Path, sync::Arc};
 
 use once_cell::unsync::OnceCell;
 use rayon::{prelude::*, Scope, ThreadPool};
 use skim::prelude::*;
 
 use crate::display::display_exec;
+use crate::forensic::audit;
 use crate::interactive::SelectionCandidate;
 use crate::lookup_deleted::deleted_lookup_exec;
 use crate::lookup_versions::versions_lookup_exec;
+use crate::restrict_owner::{is_owned, is_owned_by};
 use crate::utility::{httm_is_dir, print_output_buf, BasicDirEntryInfo, HttmError, PathData};
 use crate::{
     Config, DeletedMode, ExecMode, HttmResult, BTRFS_SNAPPER_HIDDEN_DIRECTORY, ZFS_HIDDEN_DIRECTORY,
@@ -34,6 +37,26 @@ use crate::{
 pub fn display_recursive_wrapper(config: Arc<Config>) -> HttmResult<()> {
     // won't be sending anything anywhere, this just allows us to reuse enumerate_directory
     let (dummy_tx_item, _): (SkimItemSender, SkimItemReceiver) = unbounded();
+
+    // several roots are searched each in turn, and the results for each root
+    // are grouped beneath it, as all the threads for one root finish before the next
+    if config.paths.len() > 1 {
+        config.paths.iter().try_for_each(|root| {
+            if !config.opt_raw && !config.opt_zeros {
+                print_output_buf(format!("{}:\n", root.path_buf.to_string_lossy()))?;
+            }
+
+            let config_for_root = Arc::new(Config {
+                opt_requested_dir: Some(root.clone()),
+                ..config.as_ref().clone()
+            });
+
+            recursive_exec(config_for_root, &root.path_buf, dummy_tx_item.clone())
+        })?;
+
+        return Ok(());
+    }
+
     let config_clone = config.clone();
 
     match &config.opt_requested_dir {
@@ -43,8 +66,7 @@ pub fn display_recursive_wrapper(config: Arc<Config>) -> HttmResult<()> {
         None => {
             return Err(HttmError::new(
                 "requested_dir should never be None in Display Recursive mode",
-            )
-            .into())
+            ))
         }
     }
 
@@ -97,7 +119,29 @@ fn enumerate_live_files(
 
     // check exec mode and deleted mode, we do something different for each
     match config.exec_mode {
-        ExecMode::Display | ExecMode::SnapFileMount | ExecMode::MountsForFiles => unreachable!(),
+        ExecMode::Display
+        | ExecMode::SnapFileMount
+        | ExecMode::MountsForFiles
+        | ExecMode::DirSummary
+        | ExecMode::ServeStdio
+        | ExecMode::ServeDbus(_)
+        | ExecMode::GuiHelper(_)
+        | ExecMode::SmbVersions(_)
+        | ExecMode::ServeHttp(_)
+        | ExecMode::QueryHosts(_)
+        | ExecMode::SendStream(_)
+        | ExecMode::ListAliases(_)
+        | ExecMode::ListDatasets(_)
+        | ExecMode::Bisect(_)
+        | ExecMode::Blame
+        | ExecMode::NumVersions(_)
+        | ExecMode::ToGit(_)
+        | ExecMode::Follow(_)
+        | ExecMode::Undelete
+        | ExecMode::Triage(_)
+        | ExecMode::Patch(_, _)
+        | ExecMode::Extract(_)
+        | ExecMode::Backup(_) => unreachable!(),
         ExecMode::DisplayRecursive(_) => {
             match config.deleted_mode {
                 // display recursive in DeletedMode::Disabled may be
@@ -178,12 +222,13 @@ fn enumerate_live_files(
     Ok(())
 }
 
-fn get_entries_partitioned(
+pub fn get_entries_partitioned(
     config: &Config,
     requested_dir: &Path,
 ) -> HttmResult<(Vec<BasicDirEntryInfo>, Vec<BasicDirEntryInfo>)> {
     //separates entries into dirs and files
-    let (vec_dirs, vec_files) = read_dir(&requested_dir)?
+    audit("list", requested_dir);
+    let (vec_dirs, vec_files) = read_dir(requested_dir)?
         .flatten()
         .par_bridge()
         // checking file_type on dir entries is always preferable
@@ -195,12 +240,26 @@ fn get_entries_partitioned(
                 !is_filter_dir(config, dir_entry)
             }
         })
+        .filter(|dir_entry| !is_hidden_filtered(config, &dir_entry.file_name()))
+        .filter(|dir_entry| is_owned(&dir_entry.path()))
+        // a dir may yet hold files of the selected owner
+        .filter(|dir_entry| {
+            httm_is_dir(dir_entry) || is_owned_by(&dir_entry.path(), config.opt_owner)
+        })
         .map(|dir_entry| BasicDirEntryInfo::from(&dir_entry))
-        .partition(|entry| httm_is_dir(entry));
+        .partition(httm_is_dir);
 
     Ok((vec_dirs, vec_files))
 }
 
+// dotfiles are only hidden while browsing, never from display recursive,
+// and the requested dir itself is never hidden, as only its children are checked
+fn is_hidden_filtered(config: &Config, file_name: &OsStr) -> bool {
+    matches!(config.exec_mode, ExecMode::Interactive(_))
+        && !config.opt_hidden
+        && file_name.as_bytes().starts_with(b".")
+}
+
 fn is_filter_dir(config: &Config, dir_entry: &DirEntry) -> bool {
     // FYI path is always a relative path, but no need to canonicalize as
     // partial eq for paths is comparison of components iter
@@ -213,6 +272,16 @@ fn is_filter_dir(config: &Config, dir_entry: &DirEntry) -> bool {
         return true;
     }
 
+    // nor any user defined snap dir
+    if let Some(map_of_snap_dirs) = &config.dataset_collection.opt_map_of_snap_dirs {
+        if map_of_snap_dirs
+            .iter()
+            .any(|(mount, snap_dir)| path == mount.join(snap_dir))
+        {
+            return true;
+        }
+    }
+
     // is a common path for btrfs or is a non-supported dataset?
 
     // is a common btrfs snapshot dir?
@@ -264,12 +333,14 @@ fn enumerate_deleted_per_dir(
     tx_item: SkimItemSender,
 ) -> HttmResult<()> {
     // obtain all unique deleted, policy is one version for each file, latest in time
-    let deleted = deleted_lookup_exec(config.as_ref(), requested_dir)?;
+    let mut deleted = deleted_lookup_exec(config.as_ref(), requested_dir)?;
+    deleted.retain(|basic_dir_entry_info| {
+        !is_hidden_filtered(config.as_ref(), &basic_dir_entry_info.file_name)
+    });
 
     // combined entries will be sent or printed, but we need the vec_dirs to recurse
-    let (vec_dirs, vec_files): (Vec<BasicDirEntryInfo>, Vec<BasicDirEntryInfo>) = deleted
-        .into_iter()
-        .partition(|basic_dir_entry_info| httm_is_dir(basic_dir_entry_info));
+    let (vec_dirs, vec_files): (Vec<BasicDirEntryInfo>, Vec<BasicDirEntryInfo>) =
+        deleted.into_iter().partition(httm_is_dir);
 
     // partition above is needed as vec_files will be used later
     // to determine dirs to recurse, here, we recombine to obtain
@@ -324,8 +395,8 @@ fn get_entries_behind_deleted_dir(
     ) -> HttmResult<()> {
         // deleted_dir_on_snap is the path from the deleted dir on the snapshot
         // pseudo_live_dir is the path from the fake, deleted directory that once was
-        let deleted_dir_on_snap = &from_deleted_dir.to_path_buf().join(&dir_name);
-        let pseudo_live_dir = &from_requested_dir.to_path_buf().join(&dir_name);
+        let deleted_dir_on_snap = &from_deleted_dir.to_path_buf().join(dir_name);
+        let pseudo_live_dir = &from_requested_dir.to_path_buf().join(dir_name);
 
         let (vec_dirs, vec_files): (Vec<BasicDirEntryInfo>, Vec<BasicDirEntryInfo>) =
             get_entries_partitioned(config.as_ref(), deleted_dir_on_snap)?;
@@ -364,7 +435,7 @@ fn get_entries_behind_deleted_dir(
             requested_dir,
             tx_item,
         )?,
-        None => return Err(HttmError::new("Not a valid file!").into()),
+        None => return Err(HttmError::new("Not a valid file!")),
     }
 
     Ok(())
@@ -0,0 +1,184 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    ffi::{CString, OsStr},
+    fs::{read_dir, symlink_metadata, File, OpenOptions},
+    io,
+    os::unix::{ffi::OsStrExt, fs::OpenOptionsExt, io::AsRawFd},
+    path::Path,
+};
+
+use crate::restrict_owner::is_restricted_to_owner;
+use crate::utility::PathData;
+
+const CAPABILITY_XATTR: &str = "security.capability";
+// from linux/fs.h, what chattr(1) calls 'i' and 'a'
+const FS_IMMUTABLE_FL: libc::c_int = 0x00000010;
+const FS_APPEND_FL: libc::c_int = 0x00000020;
+
+// a plain copy loses a file's capabilities and its immutable and append-only flags, so, once a
+// restore is done, we reapply each, and describe any we could not, rather than fail the restore.
+// call after any chown, as a chown clears capabilities, and an immutable file may not be chown-ed
+pub fn restore_special_attributes(src: &Path, dst: &Path) -> Vec<String> {
+    let mut notes = Vec::new();
+
+    // only root may set either, and, restricted to the invoking user, neither is theirs to restore
+    if is_restricted_to_owner() {
+        return notes;
+    }
+
+    let src_md = match symlink_metadata(src) {
+        Ok(md) => md,
+        Err(_) => return notes,
+    };
+
+    // symlinks have neither capabilities nor flags of their own
+    if src_md.file_type().is_symlink() {
+        return notes;
+    }
+
+    // children first, as an immutable dir's children may not be changed afterwards
+    if src_md.is_dir() {
+        if let Ok(entries) = read_dir(src) {
+            entries.flatten().for_each(|entry| {
+                notes.extend(restore_special_attributes(
+                    &entry.path(),
+                    &dst.join(entry.file_name()),
+                ))
+            });
+        }
+    }
+
+    if let Err(err) = restore_capabilities(src, dst) {
+        notes.push(format!(
+            "httm could not reapply the file capabilities of {:?}: {}{}",
+            dst,
+            err,
+            get_privilege_hint(&err, "CAP_SETFCAP")
+        ));
+    }
+
+    // flags last, because once immutable, nothing else may change
+    match restore_flags(src, dst) {
+        Ok(()) => (),
+        Err((names, err)) => notes.push(format!(
+            "httm could not reapply the {} flag(s) of {:?}: {}{}",
+            names,
+            dst,
+            err,
+            get_privilege_hint(&err, "CAP_LINUX_IMMUTABLE")
+        )),
+    }
+
+    notes
+}
+
+fn get_privilege_hint(err: &io::Error, capability: &str) -> String {
+    if err.kind() == io::ErrorKind::PermissionDenied {
+        format!(" (reapplying requires root, or {})", capability)
+    } else {
+        String::new()
+    }
+}
+
+fn restore_capabilities(src: &Path, dst: &Path) -> io::Result<()> {
+    let src_pathdata = PathData::from(src);
+
+    // no capabilities, or no way to have them, means nothing to do
+    let value_buf = match src_pathdata
+        .xattrs()
+        .and_then(|xattrs| xattrs.get(OsStr::new(CAPABILITY_XATTR)))
+    {
+        Some(value_buf) => value_buf,
+        None => return Ok(()),
+    };
+
+    let c_dst = CString::new(dst.as_os_str().as_bytes())?;
+    let c_name = CString::new(CAPABILITY_XATTR)?;
+
+    let res = unsafe {
+        libc::lsetxattr(
+            c_dst.as_ptr(),
+            c_name.as_ptr(),
+            value_buf.as_ptr() as *const libc::c_void,
+            value_buf.len(),
+            0,
+        )
+    };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+fn restore_flags(src: &Path, dst: &Path) -> Result<(), (String, io::Error)> {
+    let src_flags = match open_for_flags(src).and_then(|file| get_flags(&file)) {
+        Ok(flags) => flags & (FS_IMMUTABLE_FL | FS_APPEND_FL),
+        // a filesystem without flags has none to lose
+        Err(_) => return Ok(()),
+    };
+
+    if src_flags == 0 {
+        return Ok(());
+    }
+
+    let names = [
+        (FS_IMMUTABLE_FL, "immutable"),
+        (FS_APPEND_FL, "append-only"),
+    ]
+    .iter()
+    .filter(|(flag, _name)| src_flags & flag != 0)
+    .map(|(_flag, name)| *name)
+    .collect::<Vec<&str>>()
+    .join(" and ");
+
+    open_for_flags(dst)
+        .and_then(|file| {
+            let dst_flags = get_flags(&file)?;
+            set_flags(&file, dst_flags | src_flags)
+        })
+        .map_err(|err| (names, err))
+}
+
+fn open_for_flags(path: &Path) -> io::Result<File> {
+    // never follow a symlink, and never block on, say, a fifo
+    OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(path)
+}
+
+fn get_flags(file: &File) -> io::Result<libc::c_int> {
+    let mut flags: libc::c_int = 0;
+
+    // the kernel reads and writes an int here, whatever the ioctl's declared type
+    if unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(flags)
+}
+
+fn set_flags(file: &File, flags: libc::c_int) -> io::Result<()> {
+    if unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_SETFLAGS, &flags) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,321 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    ffi::CString,
+    fs::{symlink_metadata, File, OpenOptions},
+    io,
+    os::unix::fs::{MetadataExt, OpenOptionsExt},
+    path::Path,
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::utility::HttmError;
+use crate::HttmResult;
+
+#[derive(Debug, Clone, Copy)]
+struct InvokingUser {
+    uid: u32,
+    gid: u32,
+}
+
+// once set, httm only looks up and restores files owned by the invoking user
+static INVOKING_USER: OnceCell<InvokingUser> = OnceCell::new();
+
+pub fn init_restrict_to_owner() -> HttmResult<()> {
+    // safe, getuid() and getgid() cannot fail and have no side effects
+    let (real_uid, real_gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+    // when run via sudo, the invoking user is whoever sudo says ran us, not root
+    let invoking_user = if real_uid == 0 {
+        let get_sudo_id = |var: &str| -> HttmResult<Option<u32>> {
+            std::env::var(var)
+                .ok()
+                .map(|id| {
+                    id.parse::<u32>().map_err(|_| {
+                        let msg = format!("httm could not parse the {} of the invoking user.", var);
+                        HttmError::new(&msg)
+                    })
+                })
+                .transpose()
+        };
+
+        InvokingUser {
+            uid: get_sudo_id("SUDO_UID")?.unwrap_or(real_uid),
+            gid: get_sudo_id("SUDO_GID")?.unwrap_or(real_gid),
+        }
+    } else {
+        InvokingUser {
+            uid: real_uid,
+            gid: real_gid,
+        }
+    };
+
+    INVOKING_USER
+        .set(invoking_user)
+        .map_err(|_| HttmError::new("httm owner restriction may only be initialized once."))
+}
+
+pub fn is_restricted_to_owner() -> bool {
+    INVOKING_USER.get().is_some()
+}
+
+// what is run on behalf of the invoking user would run with our privileges, not theirs
+pub fn deny_if_restricted_to_owner(operation: &str) -> HttmResult<()> {
+    if is_restricted_to_owner() {
+        let msg = format!(
+            "httm will not {} when restricted to the invoking user's files.",
+            operation
+        );
+        return Err(HttmError::PermissionDenied { msg, path: None });
+    }
+
+    Ok(())
+}
+
+// always true when unrestricted, and never true for a path which does not exist
+pub fn is_owned(path: &Path) -> bool {
+    match INVOKING_USER.get() {
+        Some(invoking_user) => symlink_metadata(path)
+            .map(|md| md.uid() == invoking_user.uid)
+            .unwrap_or(false),
+        None => true,
+    }
+}
+
+// unlike the restriction to the invoking user, merely a filter, always true when no owner is selected
+pub fn is_owned_by(path: &Path, opt_uid: Option<u32>) -> bool {
+    match opt_uid {
+        Some(uid) => symlink_metadata(path)
+            .map(|md| md.uid() == uid)
+            .unwrap_or(false),
+        None => true,
+    }
+}
+
+// a user may be given by name, or by uid, as a departed user may no longer have a name
+pub fn get_uid_for_user(user: &str) -> HttmResult<u32> {
+    let c_user = CString::new(user)
+        .map_err(|_| HttmError::new("httm could not look up a user name with a nul byte."))?;
+
+    // safe, c_user is a valid nul terminated string which outlives the call, and we copy
+    // the uid out of the static passwd struct returned before any other call could change it
+    let passwd = unsafe { libc::getpwnam(c_user.as_ptr()) };
+
+    if !passwd.is_null() {
+        return Ok(unsafe { (*passwd).pw_uid });
+    }
+
+    user.parse::<u32>().map_err(|_| {
+        let msg = format!("httm could not find a user named {:?}.", user);
+        HttmError::new(&msg)
+    })
+}
+
+pub fn deny_unless_owned(operation: &str, path: &Path) -> HttmResult<()> {
+    if !is_owned(path) {
+        let msg = format!(
+            "httm will not {} {:?}, as it is not owned by the invoking user.",
+            operation, path
+        );
+        return Err(HttmError::PermissionDenied {
+            msg,
+            path: Some(path.to_path_buf()),
+        });
+    }
+
+    Ok(())
+}
+
+// a path's owner, looked up before we read it, need not be the owner of what we read, once a link,
+// or the path itself, is swapped out from under us, so what we check is the file we actually opened
+pub fn open_owned(operation: &str, path: &Path) -> HttmResult<File> {
+    let invoking_user = match INVOKING_USER.get() {
+        Some(invoking_user) => invoking_user,
+        None => {
+            return File::open(path).map_err(|source| HttmError::OpenFailed {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    };
+
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(path)
+        .map_err(|source| HttmError::OpenFailed {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    if file.metadata()?.uid() != invoking_user.uid {
+        let msg = format!(
+            "httm will not {} {:?}, as it is not owned by the invoking user.",
+            operation, path
+        );
+        return Err(HttmError::PermissionDenied {
+            msg,
+            path: Some(path.to_path_buf()),
+        });
+    }
+
+    Ok(file)
+}
+
+// as open_owned, for a copy, whose every failure is an io::Error
+pub fn open_if_owned(path: &Path) -> io::Result<File> {
+    open_owned("restore from", path).map_err(|err| match err {
+        HttmError::Io(err) | HttmError::OpenFailed { source: err, .. } => err,
+        err => io::Error::new(io::ErrorKind::PermissionDenied, err.to_string()),
+    })
+}
+
+// what we write on behalf of the invoking user, we write as them, so the kernel, not a check of
+// ours, which a swapped path or link could slip past, decides where we may write, and what we
+// create is theirs.  Only this thread's filesystem ids and groups change, see setfsuid(2), so we
+// keep our own privileges to read, and other threads keep theirs
+pub fn as_invoking_user<T, E: From<io::Error>>(f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    match INVOKING_USER.get() {
+        // safe, geteuid() cannot fail and has no side effects
+        Some(invoking_user) if unsafe { libc::geteuid() } == 0 => {
+            let _assumed = AssumedIds::new(invoking_user)?;
+            f()
+        }
+        _ => f(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct AssumedIds {
+    groups: Vec<libc::gid_t>,
+}
+
+#[cfg(target_os = "linux")]
+impl AssumedIds {
+    fn new(invoking_user: &InvokingUser) -> io::Result<Self> {
+        // safe, a size of 0 only asks how many groups there are
+        let len = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut groups: Vec<libc::gid_t> = vec![0; len as usize];
+        // safe, groups has room for len groups
+        let len = unsafe { libc::getgroups(len, groups.as_mut_ptr()) };
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        groups.truncate(len as usize);
+
+        // the raw syscall, as glibc's setgroups() changes the groups of every thread, not just this one,
+        // safe, as the list is one gid long, which outlives the call
+        if unsafe {
+            libc::syscall(
+                libc::SYS_setgroups,
+                1,
+                &invoking_user.gid as *const libc::gid_t,
+            )
+        } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        // from here on, should anything fail, drop puts back whatever was changed
+        let assumed = AssumedIds { groups };
+
+        // safe, setfsgid() and setfsuid() cannot fail, and report the ids they replaced, so,
+        // to tell whether each took, we ask for each again, with an id which changes nothing
+        let is_assumed = unsafe {
+            libc::setfsgid(invoking_user.gid);
+            libc::setfsuid(invoking_user.uid);
+
+            libc::setfsgid(u32::MAX) as u32 == invoking_user.gid
+                && libc::setfsuid(u32::MAX) as u32 == invoking_user.uid
+        };
+
+        if !is_assumed {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "httm could not assume the identity of the invoking user",
+            ));
+        }
+
+        Ok(assumed)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for AssumedIds {
+    fn drop(&mut self) {
+        // safe, as above, and the groups are those we read, which outlive the call
+        unsafe {
+            libc::setfsuid(libc::geteuid());
+            libc::setfsgid(libc::getegid());
+            libc::syscall(libc::SYS_setgroups, self.groups.len(), self.groups.as_ptr());
+        }
+    }
+}
+
+// elsewhere, a process has only one set of ids, which each of our threads would share
+#[cfg(not(target_os = "linux"))]
+struct AssumedIds;
+
+#[cfg(not(target_os = "linux"))]
+impl AssumedIds {
+    fn new(_invoking_user: &InvokingUser) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "httm can only restore as root on behalf of the invoking user on Linux",
+        ))
+    }
+}
+
+// a restore may only replace a file the invoking user owns, or create a new file within
+// a directory the invoking user owns, and never follows a symlink to somewhere else
+pub fn deny_unless_restorable(snap_path: &Path, new_file_path: &Path) -> HttmResult<()> {
+    if !is_restricted_to_owner() {
+        return Ok(());
+    }
+
+    deny_unless_owned("restore from", snap_path)?;
+
+    match symlink_metadata(new_file_path) {
+        Ok(md) if md.file_type().is_symlink() => {
+            let msg = format!(
+                "httm will not restore to {:?}, as it is a symlink.",
+                new_file_path
+            );
+            Err(HttmError::PermissionDenied {
+                msg,
+                path: Some(new_file_path.to_path_buf()),
+            })
+        }
+        Ok(_) => deny_unless_owned("restore over", new_file_path),
+        Err(_) => {
+            let parent = new_file_path
+                .parent()
+                .and_then(|parent| parent.canonicalize().ok())
+                .ok_or_else(|| {
+                    HttmError::new("httm could not determine the directory to restore to.")
+                })?;
+
+            deny_unless_owned("restore into", &parent)
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    fs::{metadata, read_dir, remove_dir_all, set_permissions, DirBuilder, File, OpenOptions},
+    io,
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    process::{Command as ExecProcess, ExitStatus},
+};
+
+use crate::forensic::deny_if_forensic;
+use crate::restrict_owner::deny_if_restricted_to_owner;
+use crate::utility::{create_private_temp_dir, HttmError};
+use crate::HttmResult;
+
+// run a user's command against a temporary copy of a version, rather than the version itself,
+// so the command may do as it pleases with the copy, the copy is removed once the command exits
+pub fn exec_on_temp_copy(command: &str, version_path: &Path) -> HttmResult<ExitStatus> {
+    exec_on_copies(command, &[version_path], false)
+}
+
+// open a read-only temporary copy of a version in the user's editor, read-only so no one
+// mistakes the copy for the live file, and saves their changes to a file about to be removed
+pub fn edit_temp_copy(version_path: &Path) -> HttmResult<ExitStatus> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_owned());
+
+    exec_on_copies(&editor, &[version_path], true)
+}
+
+// run the user's diff tool against read-only temporary copies of two versions, read-only as
+// many diff tools will happily merge changes into either side
+pub fn diff_temp_copies(
+    command: &str,
+    old_version_path: &Path,
+    new_version_path: &Path,
+) -> HttmResult<ExitStatus> {
+    exec_on_copies(command, &[old_version_path, new_version_path], true)
+}
+
+fn exec_on_copies(
+    command: &str,
+    version_paths: &[&Path],
+    read_only: bool,
+) -> HttmResult<ExitStatus> {
+    deny_if_forensic("make a temporary copy of a version")?;
+    deny_if_restricted_to_owner("run another program")?;
+
+    let sandbox_dir = create_private_temp_dir("httm-sandbox")?;
+
+    // versions of a file usually share a file name, so each copy gets a dir of its own
+    let res_status = version_paths
+        .iter()
+        .enumerate()
+        .map(|(idx, version_path)| {
+            let file_name = version_path.file_name().ok_or_else(|| {
+                HttmError::new("Could not determine a file name for the version.")
+            })?;
+
+            let sandbox_path = if version_paths.len() == 1 {
+                sandbox_dir.join(file_name)
+            } else {
+                sandbox_dir.join(idx.to_string()).join(file_name)
+            };
+
+            copy_to_sandbox(version_path, &sandbox_path, read_only)?;
+
+            Ok(sandbox_path)
+        })
+        .collect::<HttmResult<Vec<PathBuf>>>()
+        .and_then(|sandbox_paths| exec_command(command, &sandbox_paths));
+    let _ = remove_dir_all(&sandbox_dir);
+
+    res_status
+}
+
+fn copy_to_sandbox(version_path: &Path, sandbox_path: &Path, read_only: bool) -> io::Result<()> {
+    if let Some(parent) = sandbox_path.parent() {
+        if !parent.exists() {
+            DirBuilder::new().mode(0o700).create(parent)?;
+        }
+    }
+
+    copy_new(version_path, sandbox_path)?;
+
+    if read_only {
+        set_read_only(sandbox_path)?;
+    }
+
+    Ok(())
+}
+
+// the sandbox dir is ours alone, still, each copy is created new, and never written through
+// anything already there, a symlink within the version is copied as what it points to
+fn copy_new(src: &Path, dst: &Path) -> io::Result<()> {
+    let src_md = metadata(src)?;
+
+    if src_md.is_dir() {
+        DirBuilder::new().mode(0o700).create(dst)?;
+
+        return read_dir(src)?.try_for_each(|entry| {
+            let entry = entry?;
+            copy_new(&entry.path(), &dst.join(entry.file_name()))
+        });
+    }
+
+    let mut src_file = File::open(src)?;
+    let mut dst_file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(src_md.permissions().mode() & 0o777)
+        .open(dst)?;
+
+    io::copy(&mut src_file, &mut dst_file)?;
+
+    Ok(())
+}
+
+fn set_read_only(path: &Path) -> io::Result<()> {
+    let mut permissions = metadata(path)?.permissions();
+    permissions.set_readonly(true);
+    set_permissions(path, permissions)
+}
+
+fn exec_command(command: &str, sandbox_paths: &[PathBuf]) -> HttmResult<ExitStatus> {
+    // the paths are passed as positional parameters, so they need no quoting of their own,
+    // "{}" marks where the paths go, and, without one, the paths are appended
+    let script = if command.contains("{}") {
+        command.replace("{}", "\"$@\"")
+    } else {
+        format!("{} \"$@\"", command)
+    };
+
+    let status = ExecProcess::new("sh")
+        .arg("-c")
+        .arg(&script)
+        .arg("sh")
+        .args(sandbox_paths)
+        .status()?;
+
+    Ok(status)
+}
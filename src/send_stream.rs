@@ -0,0 +1,1096 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+// EXPERIMENTAL: index the versions of paths within saved "zfs send" streams, such as archived
+// replication streams of snapshots long since destroyed, without receiving them into a pool.
+//
+// A stream is a sequence of 312 byte records, some followed by a payload.  Each substream,
+// from a BEGIN record to its END record, is one snapshot of one dataset, and an incremental
+// substream only holds what changed from the snapshot before, so we replay each object of the
+// dataset, in the order given, and, at the END of each substream, take a version of each path.
+// We keep the contents of ZAP objects only, that is, directories and the few objects which
+// name the root directory and describe the layout of each file's attributes, and, of each
+// plain file, just the length of its data.
+//
+// Not supported: raw (encrypted) streams, deduplicated streams, and blocks compressed with
+// anything other than lz4.  Spill blocks are skipped.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufReader, Read},
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::display::{display_human_size, get_fancy_border_string};
+use crate::utility::{get_date, print_output_buf, DateFormat, HttmError};
+use crate::{Config, HttmResult};
+
+const DRR_RECORD_SIZE: usize = 312;
+const DMU_BACKUP_MAGIC: u64 = 0x2F5BACBAC;
+
+const DRR_BEGIN: u32 = 0;
+const DRR_OBJECT: u32 = 1;
+const DRR_FREEOBJECTS: u32 = 2;
+const DRR_WRITE: u32 = 3;
+const DRR_FREE: u32 = 4;
+const DRR_END: u32 = 5;
+const DRR_WRITE_BYREF: u32 = 6;
+const DRR_SPILL: u32 = 7;
+const DRR_WRITE_EMBEDDED: u32 = 8;
+
+const DMU_COMPOUNDSTREAM: u64 = 2;
+const DMU_BACKUP_FEATURE_RAW: u64 = 1 << 24;
+
+const DMU_OT_ZNODE: u32 = 17;
+const DMU_OT_PLAIN_FILE_CONTENTS: u32 = 19;
+const DMU_OT_DIRECTORY_CONTENTS: u32 = 20;
+const DMU_OT_MASTER_NODE: u32 = 21;
+const DMU_OT_SA: u32 = 44;
+const DMU_OT_SA_MASTER_NODE: u32 = 45;
+const DMU_OT_SA_ATTR_REGISTRATION: u32 = 46;
+const DMU_OT_SA_ATTR_LAYOUTS: u32 = 47;
+
+const ZIO_COMPRESS_OFF: u8 = 2;
+const ZIO_COMPRESS_LZ4: u8 = 15;
+
+// the largest sizes zfs itself will write, so that a corrupt or hostile record header
+// cannot have us allocate whatever it claims
+const SPA_MAXBLOCKSIZE: u64 = 16 * 1024 * 1024;
+const BPE_PAYLOAD_SIZE: u64 = 112;
+const DNODE_MAX_SLOTS: usize = 32;
+
+const MASTER_NODE_OBJ: u64 = 1;
+const ZBT_LEAF: u64 = 1 << 63;
+const ZBT_HEADER: u64 = (1 << 63) + 1;
+const ZBT_MICRO: u64 = (1 << 63) + 3;
+const ZAP_LEAF_MAGIC: u32 = 0x2AB1EAF;
+const ZAP_CHUNK_ARRAY: u8 = 251;
+const ZAP_CHUNK_ENTRY: u8 = 252;
+const ZAP_CHAIN_END: u16 = 0xffff;
+const SA_MAGIC: u32 = 0x2F505A;
+const ZFS_DIRENT_OBJ_MASK: u64 = (1 << 48) - 1;
+
+// the stream files to read, oldest first, and the paths to look up, relative to the root of
+// the dataset sent
+#[derive(Debug, Clone)]
+pub struct SendStreamQuery {
+    pub stream_files: Vec<PathBuf>,
+    pub paths: Vec<PathBuf>,
+}
+
+// a single version, as found at the end of one substream
+struct StreamVersion {
+    snap_name: String,
+    snap_time: SystemTime,
+    object: u64,
+    size: u64,
+    opt_modify_time: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    fn u16(self, buf: &[u8], offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = buf.get(offset..offset + 2)?.try_into().ok()?;
+        match self {
+            ByteOrder::Little => Some(u16::from_le_bytes(bytes)),
+            ByteOrder::Big => Some(u16::from_be_bytes(bytes)),
+        }
+    }
+
+    fn u32(self, buf: &[u8], offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = buf.get(offset..offset + 4)?.try_into().ok()?;
+        match self {
+            ByteOrder::Little => Some(u32::from_le_bytes(bytes)),
+            ByteOrder::Big => Some(u32::from_be_bytes(bytes)),
+        }
+    }
+
+    fn u64(self, buf: &[u8], offset: usize) -> Option<u64> {
+        let bytes: [u8; 8] = buf.get(offset..offset + 8)?.try_into().ok()?;
+        match self {
+            ByteOrder::Little => Some(u64::from_le_bytes(bytes)),
+            ByteOrder::Big => Some(u64::from_be_bytes(bytes)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ObjectState {
+    dmu_type: u32,
+    bonus_type: u32,
+    blksz: u32,
+    bonus: Vec<u8>,
+    // only kept for ZAP objects, keyed by offset
+    zap_blocks: BTreeMap<u64, Vec<u8>>,
+    // only kept for plain files, the end of the last data written
+    data_len: u64,
+}
+
+// every object of one dataset, as replayed so far
+#[derive(Debug, Default)]
+struct ObjsetState {
+    objects: HashMap<u64, ObjectState>,
+}
+
+// the layout of the system attributes within each file's bonus buffer
+struct SaTables {
+    // attr num -> (name, fixed length, or zero if variable)
+    registry: HashMap<u16, (String, u16)>,
+    // layout num -> attr nums, in order
+    layouts: HashMap<u16, Vec<u16>>,
+}
+
+#[derive(Default)]
+struct StreamWarnings {
+    skipped_blocks: usize,
+    byref_records: usize,
+    spill_records: usize,
+    incomplete_substreams: usize,
+}
+
+pub fn send_stream_exec(config: &Config, query: &SendStreamQuery) -> HttmResult<()> {
+    let relative_paths: Vec<Vec<String>> = query
+        .paths
+        .iter()
+        .map(|path| get_path_components(path))
+        .collect::<HttmResult<_>>()?;
+
+    let mut map_of_versions: Vec<Vec<StreamVersion>> =
+        query.paths.iter().map(|_| Vec::new()).collect();
+    let mut map_of_objsets: HashMap<String, ObjsetState> = HashMap::new();
+    let mut warnings = StreamWarnings::default();
+
+    query.stream_files.iter().try_for_each(|stream_file| {
+        let file = File::open(stream_file).map_err(|err| {
+            HttmError::with_context(
+                &format!("httm could not open the stream file {:?}", stream_file),
+                err,
+            )
+        })?;
+
+        let mut reader = BufReader::with_capacity(1024 * 1024, file);
+
+        read_stream(
+            &mut reader,
+            &mut map_of_objsets,
+            &mut warnings,
+            |snap, objset| {
+                relative_paths
+                    .iter()
+                    .zip(map_of_versions.iter_mut())
+                    .for_each(|(components, versions)| {
+                        if let Some(version) = get_version(snap, objset, components) {
+                            versions.push(version);
+                        }
+                    });
+            },
+        )
+        .map_err(|err| {
+            HttmError::new(&format!(
+                "httm could not read the stream file {:?}: {}",
+                stream_file, err
+            ))
+        })
+    })?;
+
+    display_warnings(&warnings);
+
+    let mut out_buffer = String::new();
+
+    query
+        .paths
+        .iter()
+        .zip(map_of_versions.iter_mut())
+        .try_for_each(|(path, versions)| {
+            // like any other lookup, only those versions which differ from the version before
+            versions.dedup_by(|later, earlier| {
+                later.object == earlier.object
+                    && later.size == earlier.size
+                    && later.opt_modify_time == earlier.opt_modify_time
+            });
+
+            if versions.is_empty() {
+                return Err(HttmError::NoVersions {
+                    msg: format!(
+                        "httm could not find any versions of {:?} within the stream file(s) given.",
+                        path
+                    ),
+                    path: Some(path.to_path_buf()),
+                });
+            }
+
+            out_buffer.push_str(&format_stream_versions(config, path, versions));
+            Ok(())
+        })?;
+
+    print_output_buf(out_buffer)
+}
+
+// the path is relative to the root of the dataset, whether or not it has a leading '/'
+fn get_path_components(path: &Path) -> HttmResult<Vec<String>> {
+    path.components()
+        .filter(|component| !matches!(component, Component::RootDir | Component::CurDir))
+        .map(|component| match component {
+            Component::Normal(name) => Ok(name.to_string_lossy().into_owned()),
+            _ => Err(HttmError::new(&format!(
+                "The path {:?} must be relative to the root of the dataset sent, and without any \"..\".",
+                path
+            ))),
+        })
+        .collect()
+}
+
+fn format_stream_versions(config: &Config, path: &Path, versions: &[StreamVersion]) -> String {
+    let display_path = Path::new("/").join(path.strip_prefix("/").unwrap_or(path));
+
+    if config.opt_raw || config.opt_zeros {
+        let delimiter = if config.opt_zeros { '\0' } else { '\n' };
+
+        return versions
+            .iter()
+            .map(|version| {
+                format!(
+                    "{}\t{}{}",
+                    version.snap_name,
+                    display_path.to_string_lossy(),
+                    delimiter
+                )
+            })
+            .collect();
+    }
+
+    // the modify time of a version, or, if its attributes could not be read, its snapshot's creation
+    let rows: Vec<(String, String, &str)> = versions
+        .iter()
+        .map(|version| {
+            (
+                get_date(
+                    config,
+                    &version.opt_modify_time.unwrap_or(version.snap_time),
+                    DateFormat::Display,
+                ),
+                display_human_size(&version.size),
+                version.snap_name.as_str(),
+            )
+        })
+        .collect();
+
+    let date_width = rows
+        .iter()
+        .map(|(date, _size, _snap_name)| date.len())
+        .max()
+        .unwrap_or_default();
+    let size_width = rows
+        .iter()
+        .map(|(_date, size, _snap_name)| size.len())
+        .max()
+        .unwrap_or_default();
+
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|(date, size, snap_name)| {
+            format!(
+                "{:<date_width$}  {:>size_width$}  \"{}\"  {}\n",
+                date,
+                size,
+                display_path.to_string_lossy(),
+                snap_name
+            )
+        })
+        .collect();
+
+    let border_len = lines
+        .iter()
+        .map(|line| line.trim_end().chars().count())
+        .max()
+        .unwrap_or_default();
+    let fancy_border_string = get_fancy_border_string(border_len);
+
+    let mut buffer = fancy_border_string.clone();
+    lines.iter().for_each(|line| buffer.push_str(line));
+    buffer.push_str(&fancy_border_string);
+
+    buffer
+}
+
+fn display_warnings(warnings: &StreamWarnings) {
+    if warnings.skipped_blocks > 0 {
+        eprintln!(
+            "Warning: httm skipped {} block(s) of directories compressed other than with lz4, some entries may be missing.",
+            warnings.skipped_blocks
+        );
+    }
+    if warnings.byref_records > 0 {
+        eprintln!(
+            "Warning: httm skipped {} deduplicated record(s), as deduplicated streams are not supported, some entries may be missing.",
+            warnings.byref_records
+        );
+    }
+    if warnings.spill_records > 0 {
+        eprintln!(
+            "Warning: httm skipped {} spill block(s), the attributes of some files may be missing.",
+            warnings.spill_records
+        );
+    }
+    if warnings.incomplete_substreams > 0 {
+        eprintln!(
+            "Warning: {} snapshot(s) ended before their stream's end record, and so were not indexed.",
+            warnings.incomplete_substreams
+        );
+    }
+}
+
+// the name and creation time of the snapshot of the substream being read
+struct SubstreamInfo {
+    snap_name: String,
+    dataset: String,
+    snap_time: SystemTime,
+    // the sender writes its records and its data alike in its own byte order
+    order: ByteOrder,
+}
+
+fn read_stream<R, F>(
+    reader: &mut R,
+    map_of_objsets: &mut HashMap<String, ObjsetState>,
+    warnings: &mut StreamWarnings,
+    mut on_snapshot: F,
+) -> HttmResult<()>
+where
+    R: Read,
+    F: FnMut(&SubstreamInfo, &ObjsetState),
+{
+    let mut record = [0u8; DRR_RECORD_SIZE];
+
+    if !read_record(reader, &mut record)? {
+        return Err(HttmError::new("the file is empty"));
+    }
+
+    let order = if ByteOrder::Little.u64(&record, 8) == Some(DMU_BACKUP_MAGIC) {
+        ByteOrder::Little
+    } else if ByteOrder::Big.u64(&record, 8) == Some(DMU_BACKUP_MAGIC) {
+        ByteOrder::Big
+    } else {
+        return Err(HttmError::new("the file is not a zfs send stream"));
+    };
+
+    let field_u32 = |record: &[u8], offset| order.u32(record, offset).unwrap_or_default();
+    let field_u64 = |record: &[u8], offset| order.u64(record, offset).unwrap_or_default();
+
+    let mut opt_substream: Option<SubstreamInfo> = None;
+
+    loop {
+        match field_u32(&record, 0) {
+            DRR_BEGIN => {
+                if field_u64(&record, 8) != DMU_BACKUP_MAGIC {
+                    return Err(HttmError::new("a begin record has a bad magic number"));
+                }
+
+                let version_info = field_u64(&record, 16);
+                let feature_flags = (version_info >> 2) & ((1 << 30) - 1);
+
+                if feature_flags & DMU_BACKUP_FEATURE_RAW != 0 {
+                    return Err(HttmError::new("raw (encrypted) streams are not supported"));
+                }
+
+                // a nvlist, of resume state or, for a compound stream, of the datasets within
+                skip_bytes(reader, field_u32(&record, 4) as u64)?;
+
+                if opt_substream.take().is_some() {
+                    warnings.incomplete_substreams += 1;
+                }
+
+                // a compound stream ("zfs send -R") is only a wrapper of further substreams
+                if version_info & 0x3 != DMU_COMPOUNDSTREAM {
+                    let name_bytes = &record[56..DRR_RECORD_SIZE];
+                    let name_len = name_bytes
+                        .iter()
+                        .position(|byte| *byte == 0)
+                        .unwrap_or(name_bytes.len());
+                    let snap_name = String::from_utf8_lossy(&name_bytes[..name_len]).into_owned();
+                    let dataset = snap_name
+                        .split_once('@')
+                        .map(|(dataset, _snap)| dataset.to_owned())
+                        .unwrap_or_else(|| snap_name.clone());
+
+                    // a full stream replaces whatever we knew of the dataset
+                    if field_u64(&record, 48) == 0 {
+                        map_of_objsets.insert(dataset.clone(), ObjsetState::default());
+                    }
+
+                    opt_substream = Some(SubstreamInfo {
+                        snap_name,
+                        dataset,
+                        snap_time: SystemTime::UNIX_EPOCH
+                            + Duration::from_secs(field_u64(&record, 24)),
+                        order,
+                    });
+                }
+            }
+            DRR_END => {
+                // the end of a compound stream follows the end of its last substream
+                if let Some(substream) = opt_substream.take() {
+                    if let Some(objset) = map_of_objsets.get(&substream.dataset) {
+                        on_snapshot(&substream, objset);
+                    }
+                }
+            }
+            DRR_OBJECT => {
+                let bonus_len = field_u32(&record, 28) as usize;
+                let max_bonus_len = get_max_bonus_len(record[34]);
+
+                if bonus_len > max_bonus_len {
+                    return Err(HttmError::new(&format!(
+                        "an object record has a bonus of {} bytes, more than the {} bytes its dnode may hold",
+                        bonus_len, max_bonus_len
+                    )));
+                }
+
+                let mut bonus = vec![0u8; round_up_8(bonus_len)];
+                reader.read_exact(&mut bonus)?;
+                bonus.truncate(bonus_len);
+
+                if let Some(objset) = get_objset(map_of_objsets, &opt_substream) {
+                    let object = field_u64(&record, 8);
+                    let dmu_type = field_u32(&record, 16);
+                    let blksz = field_u32(&record, 24);
+
+                    let object_state = objset.objects.entry(object).or_default();
+
+                    // an object reallocated as another type, or with another block size, starts over
+                    if object_state.dmu_type != dmu_type || object_state.blksz != blksz {
+                        *object_state = ObjectState {
+                            dmu_type,
+                            blksz,
+                            ..Default::default()
+                        };
+                    }
+
+                    object_state.bonus_type = field_u32(&record, 20);
+                    object_state.bonus = bonus;
+                }
+            }
+            DRR_FREEOBJECTS => {
+                if let Some(objset) = get_objset(map_of_objsets, &opt_substream) {
+                    let first_obj = field_u64(&record, 8);
+                    let last_obj = first_obj.saturating_add(field_u64(&record, 16));
+
+                    objset
+                        .objects
+                        .retain(|object, _state| *object < first_obj || *object >= last_obj);
+                }
+            }
+            DRR_WRITE => {
+                let compression = record[50];
+                let logical_size = field_u64(&record, 32);
+                let payload_len = if compression != 0 {
+                    field_u64(&record, 96)
+                } else {
+                    logical_size
+                };
+
+                let write = BlockWrite {
+                    object: field_u64(&record, 8),
+                    dmu_type: field_u32(&record, 16),
+                    offset: field_u64(&record, 24),
+                    logical_size,
+                    compression,
+                    payload_len,
+                };
+
+                read_write(reader, map_of_objsets, &opt_substream, warnings, write)?;
+            }
+            DRR_WRITE_EMBEDDED => {
+                let object = field_u64(&record, 8);
+                let dmu_type = get_objset(map_of_objsets, &opt_substream)
+                    .and_then(|objset| objset.objects.get(&object))
+                    .map(|object_state| object_state.dmu_type)
+                    .unwrap_or_default();
+
+                let write = BlockWrite {
+                    object,
+                    dmu_type,
+                    offset: field_u64(&record, 16),
+                    logical_size: field_u32(&record, 48) as u64,
+                    compression: record[40],
+                    payload_len: round_up_8(field_u32(&record, 52) as usize) as u64,
+                };
+
+                if write.payload_len > BPE_PAYLOAD_SIZE {
+                    return Err(HttmError::new(&format!(
+                        "an embedded write record has a payload of {} bytes, more than the {} bytes a block pointer may hold",
+                        write.payload_len, BPE_PAYLOAD_SIZE
+                    )));
+                }
+
+                read_write(reader, map_of_objsets, &opt_substream, warnings, write)?;
+            }
+            DRR_FREE => {
+                if let Some(object_state) = get_objset(map_of_objsets, &opt_substream)
+                    .and_then(|objset| objset.objects.get_mut(&field_u64(&record, 8)))
+                {
+                    let offset = field_u64(&record, 16);
+                    let end = offset.saturating_add(field_u64(&record, 24));
+
+                    object_state.zap_blocks.retain(|block_offset, _block| {
+                        *block_offset < offset || *block_offset >= end
+                    });
+
+                    if end >= object_state.data_len {
+                        object_state.data_len = object_state.data_len.min(offset);
+                    }
+                }
+            }
+            DRR_SPILL => {
+                warnings.spill_records += 1;
+
+                let payload_len = if record[33] != 0 {
+                    field_u64(&record, 40)
+                } else {
+                    field_u64(&record, 16)
+                };
+                skip_bytes(reader, payload_len)?;
+            }
+            DRR_WRITE_BYREF => {
+                warnings.byref_records += 1;
+            }
+            // an object range, a redaction, or a record type unknown to us, none have a payload
+            _ => {}
+        }
+
+        if !read_record(reader, &mut record)? {
+            break;
+        }
+    }
+
+    if opt_substream.is_some() {
+        warnings.incomplete_substreams += 1;
+    }
+
+    Ok(())
+}
+
+fn get_objset<'a>(
+    map_of_objsets: &'a mut HashMap<String, ObjsetState>,
+    opt_substream: &Option<SubstreamInfo>,
+) -> Option<&'a mut ObjsetState> {
+    let substream = opt_substream.as_ref()?;
+    Some(map_of_objsets.entry(substream.dataset.clone()).or_default())
+}
+
+struct BlockWrite {
+    object: u64,
+    dmu_type: u32,
+    offset: u64,
+    logical_size: u64,
+    compression: u8,
+    payload_len: u64,
+}
+
+fn read_write<R: Read>(
+    reader: &mut R,
+    map_of_objsets: &mut HashMap<String, ObjsetState>,
+    opt_substream: &Option<SubstreamInfo>,
+    warnings: &mut StreamWarnings,
+    write: BlockWrite,
+) -> HttmResult<()> {
+    if write.logical_size > SPA_MAXBLOCKSIZE || write.payload_len > SPA_MAXBLOCKSIZE {
+        return Err(HttmError::new(&format!(
+            "a write record has a block of {} bytes, and a payload of {} bytes, but no block is larger than {} bytes",
+            write.logical_size, write.payload_len, SPA_MAXBLOCKSIZE
+        )));
+    }
+
+    let opt_object_state = get_objset(map_of_objsets, opt_substream)
+        .and_then(|objset| objset.objects.get_mut(&write.object));
+
+    let object_state = match opt_object_state {
+        Some(object_state) if is_zap_type(write.dmu_type) => object_state,
+        Some(object_state) => {
+            if write.dmu_type == DMU_OT_PLAIN_FILE_CONTENTS {
+                object_state.data_len = object_state
+                    .data_len
+                    .max(write.offset.saturating_add(write.logical_size));
+            }
+            return skip_bytes(reader, write.payload_len);
+        }
+        None => return skip_bytes(reader, write.payload_len),
+    };
+
+    let mut payload = vec![0u8; write.payload_len as usize];
+    reader.read_exact(&mut payload)?;
+
+    let opt_block = match write.compression {
+        0 | ZIO_COMPRESS_OFF => Some(payload),
+        ZIO_COMPRESS_LZ4 => decompress_lz4(&payload, write.logical_size as usize),
+        _ => None,
+    };
+
+    match opt_block {
+        Some(mut block) => {
+            block.resize(write.logical_size as usize, 0);
+            object_state.zap_blocks.insert(write.offset, block);
+        }
+        None => warnings.skipped_blocks += 1,
+    }
+
+    Ok(())
+}
+
+fn is_zap_type(dmu_type: u32) -> bool {
+    matches!(
+        dmu_type,
+        DMU_OT_DIRECTORY_CONTENTS
+            | DMU_OT_MASTER_NODE
+            | DMU_OT_SA_MASTER_NODE
+            | DMU_OT_SA_ATTR_REGISTRATION
+            | DMU_OT_SA_ATTR_LAYOUTS
+    )
+}
+
+// false at the end of the stream
+fn read_record<R: Read>(reader: &mut R, record: &mut [u8; DRR_RECORD_SIZE]) -> HttmResult<bool> {
+    let mut filled = 0usize;
+
+    while filled < DRR_RECORD_SIZE {
+        match reader.read(&mut record[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(HttmError::new("the stream ends within a record")),
+            Ok(num_read) => filled += num_read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(true)
+}
+
+fn skip_bytes<R: Read>(reader: &mut R, num_bytes: u64) -> HttmResult<()> {
+    let num_skipped = io::copy(&mut reader.take(num_bytes), &mut io::sink())?;
+
+    if num_skipped < num_bytes {
+        return Err(HttmError::new("the stream ends within a record's payload"));
+    }
+
+    Ok(())
+}
+
+// a dnode of one 512 byte slot holds a bonus of 320 bytes, and each further slot ("dnodesize")
+// another 512 bytes, less the core of the dnode and its one block pointer
+fn get_max_bonus_len(dn_slots: u8) -> usize {
+    let dn_slots = (dn_slots as usize).clamp(1, DNODE_MAX_SLOTS);
+
+    dn_slots * 512 - 64 - 128
+}
+
+fn round_up_8(len: usize) -> usize {
+    (len + 7) & !7
+}
+
+// walk from the root directory to the path, at the end of one substream
+fn get_version(
+    snap: &SubstreamInfo,
+    objset: &ObjsetState,
+    components: &[String],
+) -> Option<StreamVersion> {
+    let order = snap.order;
+    let master_node = read_zap(objset.objects.get(&MASTER_NODE_OBJ)?, order)?;
+
+    let root_obj = *master_node.get("ROOT")?.first()?;
+
+    let object = components.iter().try_fold(root_obj, |dir_obj, name| {
+        let dir_state = objset.objects.get(&dir_obj)?;
+        if dir_state.dmu_type != DMU_OT_DIRECTORY_CONTENTS {
+            return None;
+        }
+        let entries = read_zap(dir_state, order)?;
+        entries
+            .get(name)?
+            .first()
+            .map(|entry| entry & ZFS_DIRENT_OBJ_MASK)
+    })?;
+
+    let object_state = objset.objects.get(&object)?;
+
+    let opt_sa_tables = master_node
+        .get("SA_ATTRS")
+        .and_then(|values| values.first())
+        .and_then(|sa_obj| read_sa_tables(objset, *sa_obj, order));
+
+    let (opt_size, opt_modify_time) = match object_state.bonus_type {
+        DMU_OT_SA => opt_sa_tables
+            .as_ref()
+            .and_then(|sa_tables| read_sa_bonus(&object_state.bonus, sa_tables, order))
+            .unwrap_or((None, None)),
+        DMU_OT_ZNODE => read_znode_bonus(&object_state.bonus, order),
+        _ => (None, None),
+    };
+
+    Some(StreamVersion {
+        snap_name: snap.snap_name.clone(),
+        snap_time: snap.snap_time,
+        object,
+        size: opt_size.unwrap_or(object_state.data_len),
+        opt_modify_time,
+    })
+}
+
+fn read_sa_tables(objset: &ObjsetState, sa_obj: u64, order: ByteOrder) -> Option<SaTables> {
+    let sa_master = read_zap(objset.objects.get(&sa_obj)?, order)?;
+
+    let registry_obj = *sa_master.get("REGISTRY")?.first()?;
+    let layouts_obj = *sa_master.get("LAYOUTS")?.first()?;
+
+    // each registered attr is its num, its byteswap function, and its length, packed together
+    let registry = read_zap(objset.objects.get(&registry_obj)?, order)?
+        .into_iter()
+        .filter_map(|(name, values)| {
+            let value = *values.first()?;
+            Some((
+                (value & 0xffff) as u16,
+                (name, ((value >> 24) & 0xffff) as u16),
+            ))
+        })
+        .collect();
+
+    let layouts = read_zap(objset.objects.get(&layouts_obj)?, order)?
+        .into_iter()
+        .filter_map(|(name, values)| {
+            Some((
+                name.parse::<u16>().ok()?,
+                values.into_iter().map(|value| value as u16).collect(),
+            ))
+        })
+        .collect();
+
+    Some(SaTables { registry, layouts })
+}
+
+// the size and modify time of a file, from its system attributes
+fn read_sa_bonus(
+    bonus: &[u8],
+    sa_tables: &SaTables,
+    order: ByteOrder,
+) -> Option<(Option<u64>, Option<SystemTime>)> {
+    if order.u32(bonus, 0)? != SA_MAGIC {
+        return None;
+    }
+
+    let layout_info = order.u16(bonus, 4)?;
+    let layout_num = layout_info & 0x3ff;
+    let header_size = (((layout_info >> 10) & 0x3f) as usize) * 8;
+
+    let layout = sa_tables.layouts.get(&layout_num)?;
+
+    let mut offset = header_size;
+    let mut var_idx = 0usize;
+    let mut opt_size = None;
+    let mut opt_modify_time = None;
+
+    for attr_num in layout {
+        let (name, registered_len) = sa_tables.registry.get(attr_num)?;
+
+        // a variable length attr has its length in the header, in the order of such attrs
+        let len = if *registered_len == 0 {
+            let len = order.u16(bonus, 6 + 2 * var_idx)? as usize;
+            var_idx += 1;
+            len
+        } else {
+            *registered_len as usize
+        };
+
+        match name.as_str() {
+            "ZPL_SIZE" => opt_size = order.u64(bonus, offset),
+            "ZPL_MTIME" => opt_modify_time = get_time(bonus, offset, order),
+            _ => {}
+        }
+
+        offset = round_up_8(offset + len);
+    }
+
+    Some((opt_size, opt_modify_time))
+}
+
+// before system attributes, a file's attributes were a fixed struct
+fn read_znode_bonus(bonus: &[u8], order: ByteOrder) -> (Option<u64>, Option<SystemTime>) {
+    (order.u64(bonus, 80), get_time(bonus, 16, order))
+}
+
+fn get_time(buf: &[u8], offset: usize, order: ByteOrder) -> Option<SystemTime> {
+    let secs = order.u64(buf, offset)?;
+    let nanos = order.u64(buf, offset + 8)?;
+
+    SystemTime::UNIX_EPOCH.checked_add(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+// the entries of a microzap, or of the leaves of a fatzap, as names and integer values
+fn read_zap(object_state: &ObjectState, order: ByteOrder) -> Option<HashMap<String, Vec<u64>>> {
+    let first_block = object_state.zap_blocks.get(&0)?;
+
+    match order.u64(first_block, 0)? {
+        ZBT_MICRO => Some(read_microzap(first_block, order)),
+        // the header block of a fatzap, its entries are within its leaf blocks
+        ZBT_HEADER => {
+            let entries = object_state
+                .zap_blocks
+                .values()
+                .filter(|block| order.u64(block, 0) == Some(ZBT_LEAF))
+                .flat_map(|block| read_zap_leaf(block, order))
+                .collect();
+            Some(entries)
+        }
+        _ => None,
+    }
+}
+
+fn read_microzap(block: &[u8], order: ByteOrder) -> HashMap<String, Vec<u64>> {
+    const MZAP_ENT_LEN: usize = 64;
+
+    block
+        .chunks_exact(MZAP_ENT_LEN)
+        .skip(1)
+        .filter_map(|entry| {
+            let value = order.u64(entry, 0)?;
+            let name_bytes = &entry[14..];
+            let name_len = name_bytes.iter().position(|byte| *byte == 0)?;
+            if name_len == 0 {
+                return None;
+            }
+            Some((
+                String::from_utf8_lossy(&name_bytes[..name_len]).into_owned(),
+                vec![value],
+            ))
+        })
+        .collect()
+}
+
+fn read_zap_leaf(block: &[u8], order: ByteOrder) -> Vec<(String, Vec<u64>)> {
+    const LEAF_HEADER_LEN: usize = 48;
+    const CHUNK_LEN: usize = 24;
+
+    if order.u32(block, 24) != Some(ZAP_LEAF_MAGIC) || !block.len().is_power_of_two() {
+        return Vec::new();
+    }
+
+    let block_shift = block.len().trailing_zeros();
+    if block_shift < 9 {
+        return Vec::new();
+    }
+
+    let num_hash_entries = 1usize << (block_shift - 5);
+    let chunks_start = LEAF_HEADER_LEN + 2 * num_hash_entries;
+    let num_chunks = ((block.len() - 2 * num_hash_entries) / CHUNK_LEN).saturating_sub(2);
+
+    let get_chunk = |idx: u16| -> Option<&[u8]> {
+        let start = chunks_start + (idx as usize) * CHUNK_LEN;
+        if (idx as usize) >= num_chunks {
+            return None;
+        }
+        block.get(start..start + CHUNK_LEN)
+    };
+
+    // a name, or a value, is the bytes of a chain of array chunks
+    let read_array = |first_chunk: u16, num_bytes: usize| -> Option<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(num_bytes);
+        let mut idx = first_chunk;
+
+        while bytes.len() < num_bytes {
+            let chunk = get_chunk(idx)?;
+            if chunk[0] != ZAP_CHUNK_ARRAY {
+                return None;
+            }
+            let wanted = (num_bytes - bytes.len()).min(21);
+            bytes.extend_from_slice(&chunk[1..1 + wanted]);
+            idx = order.u16(chunk, 22)?;
+            if idx == ZAP_CHAIN_END && bytes.len() < num_bytes {
+                return None;
+            }
+        }
+
+        Some(bytes)
+    };
+
+    (0..num_chunks as u16)
+        .filter_map(|idx| {
+            let chunk = get_chunk(idx)?;
+            if chunk[0] != ZAP_CHUNK_ENTRY {
+                return None;
+            }
+
+            let value_intlen = chunk[1] as usize;
+            let name_chunk = order.u16(chunk, 4)?;
+            let name_numints = order.u16(chunk, 6)? as usize;
+            let value_chunk = order.u16(chunk, 8)?;
+            let value_numints = order.u16(chunk, 10)? as usize;
+
+            if !matches!(value_intlen, 1 | 2 | 4 | 8) {
+                return None;
+            }
+
+            let mut name_bytes = read_array(name_chunk, name_numints)?;
+            if name_bytes.last() == Some(&0) {
+                name_bytes.pop();
+            }
+
+            // the integers of a fatzap's values are always big endian
+            let values = read_array(value_chunk, value_numints * value_intlen)?
+                .chunks_exact(value_intlen)
+                .map(|int_bytes| {
+                    int_bytes
+                        .iter()
+                        .fold(0u64, |acc, byte| (acc << 8) | *byte as u64)
+                })
+                .collect();
+
+            Some((String::from_utf8_lossy(&name_bytes).into_owned(), values))
+        })
+        .collect()
+}
+
+// zfs prefixes a lz4 block with its compressed length, as a big endian u32
+fn decompress_lz4(payload: &[u8], logical_size: usize) -> Option<Vec<u8>> {
+    let compressed_len = ByteOrder::Big.u32(payload, 0)? as usize;
+    let src = payload.get(4..4 + compressed_len)?;
+
+    let mut out: Vec<u8> = Vec::with_capacity(logical_size);
+    let mut idx = 0usize;
+
+    let read_len = |idx: &mut usize, mut len: usize| -> Option<usize> {
+        if len == 15 {
+            loop {
+                let byte = *src.get(*idx)?;
+                *idx += 1;
+                len += byte as usize;
+                if byte != 255 {
+                    break;
+                }
+            }
+        }
+        Some(len)
+    };
+
+    while idx < src.len() {
+        let token = src[idx];
+        idx += 1;
+
+        let literal_len = read_len(&mut idx, (token >> 4) as usize)?;
+        out.extend_from_slice(src.get(idx..idx + literal_len)?);
+        idx += literal_len;
+
+        // the last sequence is only literals
+        if idx >= src.len() || out.len() >= logical_size {
+            break;
+        }
+
+        let match_offset = ByteOrder::Little.u16(src, idx)? as usize;
+        idx += 2;
+        if match_offset == 0 || match_offset > out.len() {
+            return None;
+        }
+
+        let match_len = read_len(&mut idx, (token & 0xf) as usize)? + 4;
+        let match_start = out.len() - match_offset;
+        (0..match_len).for_each(|pos| {
+            let byte = out[match_start + pos];
+            out.push(byte);
+        });
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(record_type: u32) -> [u8; DRR_RECORD_SIZE] {
+        let mut record = [0u8; DRR_RECORD_SIZE];
+        record[0..4].copy_from_slice(&record_type.to_le_bytes());
+        record
+    }
+
+    // a BEGIN record of a full substream of "pool/ds@snap", followed by the given records
+    fn stream_with(records: &[[u8; DRR_RECORD_SIZE]]) -> Vec<u8> {
+        let mut begin = record(DRR_BEGIN);
+        begin[8..16].copy_from_slice(&DMU_BACKUP_MAGIC.to_le_bytes());
+        begin[16..24].copy_from_slice(&1u64.to_le_bytes());
+        begin[56..68].copy_from_slice(b"pool/ds@snap");
+
+        std::iter::once(&begin)
+            .chain(records.iter())
+            .chain(std::iter::once(&record(DRR_END)))
+            .flat_map(|record| record.iter().copied())
+            .collect()
+    }
+
+    fn read(stream: &[u8]) -> HttmResult<usize> {
+        let mut map_of_objsets = HashMap::new();
+        let mut warnings = StreamWarnings::default();
+        let mut num_snapshots = 0usize;
+
+        read_stream(
+            &mut &stream[..],
+            &mut map_of_objsets,
+            &mut warnings,
+            |_substream, _objset| num_snapshots += 1,
+        )?;
+
+        Ok(num_snapshots)
+    }
+
+    #[test]
+    fn reads_an_empty_substream() {
+        assert_eq!(read(&stream_with(&[])).unwrap(), 1);
+    }
+
+    #[test]
+    fn refuses_an_oversized_bonus() {
+        let mut object = record(DRR_OBJECT);
+        object[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        assert!(read(&stream_with(&[object])).is_err());
+    }
+
+    #[test]
+    fn refuses_an_oversized_write() {
+        let mut write = record(DRR_WRITE);
+        write[32..40].copy_from_slice(&(SPA_MAXBLOCKSIZE + 1).to_le_bytes());
+
+        assert!(read(&stream_with(&[write])).is_err());
+
+        let mut compressed_write = record(DRR_WRITE);
+        compressed_write[32..40].copy_from_slice(&4096u64.to_le_bytes());
+        compressed_write[50] = ZIO_COMPRESS_LZ4;
+        compressed_write[96..104].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        assert!(read(&stream_with(&[compressed_write])).is_err());
+    }
+
+    #[test]
+    fn refuses_an_oversized_embedded_write() {
+        let mut embedded = record(DRR_WRITE_EMBEDDED);
+        embedded[52..56].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        assert!(read(&stream_with(&[embedded])).is_err());
+    }
+}
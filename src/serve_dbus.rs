@@ -0,0 +1,175 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{path::PathBuf, sync::Arc};
+
+use zbus::{
+    blocking,
+    fdo::{self, RequestNameFlags, RequestNameReply},
+    interface,
+    message::Header,
+    names::BusName,
+    Connection,
+};
+
+use crate::serve_stdio::{exec_method, Method};
+use crate::utility::HttmError;
+use crate::{Config, DbusBus, HttmResult};
+
+// the methods served are those of --serve-stdio, each of which takes its params as
+// arguments and returns its result as a string of JSON
+pub const DBUS_BUS_NAME: &str = "org.httm.Httm";
+const DBUS_OBJECT_PATH: &str = "/org/httm/Httm";
+
+pub fn serve_dbus(config: &Config, bus: &DbusBus) -> HttmResult<()> {
+    let service = HttmService {
+        config: Arc::new(config.clone()),
+    };
+
+    let connection = match bus {
+        DbusBus::Session => blocking::connection::Builder::session(),
+        DbusBus::System => blocking::connection::Builder::system(),
+    }
+    .and_then(|builder| builder.serve_at(DBUS_OBJECT_PATH, service))
+    .and_then(|builder| builder.build())
+    .map_err(|err| {
+        HttmError::with_context(
+            &format!(
+                "httm could not serve {:?} on the {} bus",
+                DBUS_BUS_NAME, bus
+            ),
+            err,
+        )
+    })?;
+
+    // never queue for, nor take, the name from another httm already serving on this bus
+    match connection.request_name_with_flags(DBUS_BUS_NAME, RequestNameFlags::DoNotQueue.into()) {
+        Ok(RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner) => {}
+        _ => {
+            return Err(HttmError::new(
+                "httm could not acquire its D-Bus name.  Perhaps another httm is already serving on this bus.",
+            ))
+        }
+    }
+
+    eprintln!(
+        "httm is serving \"org.httm.Httm1\" as {:?} on the {} bus.",
+        DBUS_BUS_NAME, bus
+    );
+
+    // the object server answers each call on its own, we only wait here until the bus hangs up
+    blocking::MessageIterator::from(&connection).for_each(drop);
+
+    Ok(())
+}
+
+struct HttmService {
+    config: Arc<Config>,
+}
+
+#[interface(name = "org.httm.Httm1")]
+impl HttmService {
+    #[zbus(out_args("result"))]
+    async fn list_versions(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+        path: String,
+    ) -> fdo::Result<String> {
+        let method = Method::ListVersions {
+            path: PathBuf::from(path),
+        };
+
+        self.exec(&header, connection, method).await
+    }
+
+    #[zbus(out_args("result"))]
+    async fn read_version(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+        path: String,
+    ) -> fdo::Result<String> {
+        let method = Method::ReadVersion {
+            path: PathBuf::from(path),
+        };
+
+        self.exec(&header, connection, method).await
+    }
+
+    #[zbus(out_args("result"))]
+    async fn restore(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+        version: String,
+        path: String,
+        overwrite: bool,
+    ) -> fdo::Result<String> {
+        let method = Method::Restore {
+            version: PathBuf::from(version),
+            path: PathBuf::from(path),
+            overwrite,
+        };
+
+        self.exec(&header, connection, method).await
+    }
+}
+
+impl HttmService {
+    async fn exec(
+        &self,
+        header: &Header<'_>,
+        connection: &Connection,
+        method: Method,
+    ) -> fdo::Result<String> {
+        // anyone on the bus may call us, but we only act on behalf of our own user, or root
+        deny_unless_caller_permitted(header, connection).await?;
+
+        exec_method(&self.config, method)
+            .map(|value| value.to_string())
+            .map_err(|err| match err {
+                HttmError::PermissionDenied { msg, .. } => fdo::Error::AccessDenied(msg),
+                err => fdo::Error::Failed(err.to_string()),
+            })
+    }
+}
+
+async fn deny_unless_caller_permitted(
+    header: &Header<'_>,
+    connection: &Connection,
+) -> fdo::Result<()> {
+    let sender = header.sender().ok_or_else(|| {
+        fdo::Error::AccessDenied("httm cannot identify a caller which has no sender.".to_owned())
+    })?;
+
+    let caller_uid = fdo::DBusProxy::new(connection)
+        .await?
+        .get_connection_unix_user(BusName::from(sender.to_owned()))
+        .await?;
+
+    // safe, getuid() cannot fail and has no side effects
+    let real_uid = unsafe { libc::getuid() };
+
+    if caller_uid == real_uid || caller_uid == 0 {
+        return Ok(());
+    }
+
+    Err(fdo::Error::AccessDenied(
+        "httm only serves requests from the user which started it, or root.".to_owned(),
+    ))
+}
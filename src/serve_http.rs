@@ -0,0 +1,947 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    ffi::{OsStr, OsString},
+    fs::{read_dir, read_to_string, File},
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Component, Path, PathBuf},
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::display::display_human_size;
+use crate::forensic::audit;
+use crate::lookup_versions::{get_live_path, versions_lookup_exec};
+use crate::restrict_owner::{deny_unless_owned, is_owned, open_owned};
+use crate::serve_stdio::{exec_method, Method};
+use crate::utility::{get_date, httm_is_dir, DateFormat, HttmError, PathData};
+use crate::{Config, HttmResult};
+
+pub const DEFAULT_HTTP_LISTEN: &str = "127.0.0.1:8080";
+
+// a client should never need more than this to ask for a path, and a slow client, even one
+// which sends its request a byte at a time, should not hold on to one of our threads for longer
+const MAX_REQUEST_HEAD_LEN: usize = 64 * 1024;
+const MAX_REQUEST_HEADERS: usize = 100;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+// beyond this many clients at once, the next waits its turn, rather than each get a thread
+const MAX_CONNECTIONS: usize = 64;
+
+// the query by which a page asks for the versions of a path, and for a single version
+const VERSIONS_QUERY: &str = "versions";
+const VERSION_QUERY: &str = "version";
+
+pub const API_PREFIX: &str = "/api/v1/";
+const MAX_REQUEST_BODY_LEN: usize = 1024 * 1024;
+
+// how, and what, to serve, as requested on the command line
+#[derive(Debug, Clone)]
+pub struct HttpServeOptions {
+    pub root_dir: PathBuf,
+    pub listen: String,
+    pub opt_api_token_file: Option<PathBuf>,
+}
+
+struct HttpServer {
+    root_dir: PathBuf,
+    // without a token, there is no API
+    opt_api_token: Option<String>,
+}
+
+// each connection holds a slot until it is done, see MAX_CONNECTIONS
+struct ConnectionLimit {
+    in_use: Mutex<usize>,
+    condvar: Condvar,
+}
+
+struct ConnectionSlot<'a> {
+    limit: &'a ConnectionLimit,
+}
+
+impl ConnectionLimit {
+    fn new() -> Self {
+        ConnectionLimit {
+            in_use: Mutex::new(0),
+            condvar: Condvar::new(),
+        }
+    }
+
+    fn acquire(&self) -> ConnectionSlot<'_> {
+        let mut in_use = self
+            .in_use
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        while *in_use >= MAX_CONNECTIONS {
+            in_use = self
+                .condvar
+                .wait(in_use)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+
+        *in_use += 1;
+
+        ConnectionSlot { limit: self }
+    }
+}
+
+impl Drop for ConnectionSlot<'_> {
+    fn drop(&mut self) {
+        let mut in_use = self
+            .limit
+            .in_use
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        *in_use = in_use.saturating_sub(1);
+
+        drop(in_use);
+        self.limit.condvar.notify_one();
+    }
+}
+
+// a read timeout bounds each read, and this, however slowly it trickles in, the whole request
+struct DeadlineReader {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                "The request took too long to arrive.",
+            ));
+        }
+
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+struct HttpRequest {
+    method: String,
+    // the path requested, relative to the root dir served, still percent encoded
+    raw_path: String,
+    query: Vec<(String, OsString)>,
+    // header names are lowercase
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+struct HttpResponse {
+    status: u16,
+    reason: &'static str,
+    content_type: &'static str,
+    extra_headers: Vec<(&'static str, String)>,
+    body: HttpBody,
+}
+
+enum HttpBody {
+    Bytes(Vec<u8>),
+    File(File, u64),
+}
+
+pub fn serve_http(config: &Config, options: &HttpServeOptions) -> HttmResult<()> {
+    let root_dir = options.root_dir.canonicalize().map_err(|err| {
+        HttmError::with_context(
+            &format!(
+                "httm could not find the dir to serve {:?}",
+                options.root_dir
+            ),
+            err,
+        )
+    })?;
+
+    if !root_dir.is_dir() {
+        return Err(HttmError::new(&format!(
+            "httm can only serve a directory, and {:?} is not a directory.",
+            root_dir
+        )));
+    }
+
+    let opt_api_token = options
+        .opt_api_token_file
+        .as_deref()
+        .map(read_api_token)
+        .transpose()?;
+
+    // like other tools, ":8080" means every interface
+    let listen_addr = match options.listen.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => options.listen.clone(),
+    };
+
+    let listener = TcpListener::bind(&listen_addr).map_err(|err| {
+        HttmError::with_context(&format!("httm could not listen on {:?}", listen_addr), err)
+    })?;
+
+    eprintln!(
+        "httm is serving the versions of {:?}, read only, at http://{}/",
+        root_dir,
+        listener.local_addr()?
+    );
+
+    if opt_api_token.is_some() {
+        eprintln!(
+            "httm is serving its API, which may restore, at http://{}{}",
+            listener.local_addr()?,
+            API_PREFIX
+        );
+    }
+
+    let server = HttpServer {
+        root_dir,
+        opt_api_token,
+    };
+
+    let connection_limit = ConnectionLimit::new();
+
+    std::thread::scope(|scope| {
+        listener.incoming().flatten().for_each(|stream| {
+            let server = &server;
+            // once every slot is taken, we stop accepting, until a connection is done
+            let slot = connection_limit.acquire();
+            scope.spawn(move || {
+                let _slot = slot;
+                // a client which hangs up on us is no reason to stop serving others
+                let _ = handle_connection(config, server, stream);
+            });
+        });
+    });
+
+    Ok(())
+}
+
+pub fn read_api_token(token_file: &Path) -> HttmResult<String> {
+    let token = read_to_string(token_file).map_err(|err| {
+        HttmError::with_context(
+            &format!("httm could not read the API token file {:?}", token_file),
+            err,
+        )
+    })?;
+
+    let token = token.trim();
+
+    if token.is_empty() {
+        return Err(HttmError::new(&format!(
+            "The API token file {:?} is empty.",
+            token_file
+        )));
+    }
+
+    Ok(token.to_owned())
+}
+
+fn handle_connection(config: &Config, server: &HttpServer, stream: TcpStream) -> HttmResult<()> {
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+
+    let mut reader = BufReader::new(DeadlineReader {
+        stream: stream.try_clone()?,
+        deadline: Instant::now() + HTTP_TIMEOUT,
+    });
+
+    let response = match read_request(&mut reader) {
+        Ok(request) if request.raw_path.starts_with(API_PREFIX) => {
+            handle_api_request(config, server, &request)
+        }
+        Ok(request) if request.method != "GET" && request.method != "HEAD" => error_response(
+            405,
+            "Method Not Allowed",
+            "httm only serves its versions, read only.",
+        ),
+        Ok(request) => {
+            let is_head = request.method == "HEAD";
+            let response = handle_request(config, &server.root_dir, &request)
+                .unwrap_or_else(|err| error_response(404, "Not Found", &err.to_string()));
+            return write_response(stream, response, is_head);
+        }
+        Err(err) => error_response(400, "Bad Request", &err.to_string()),
+    };
+
+    write_response(stream, response, false)
+}
+
+fn read_request(reader: &mut impl BufRead) -> HttmResult<HttpRequest> {
+    let mut request_line = String::new();
+    reader
+        .by_ref()
+        .take(MAX_REQUEST_HEAD_LEN as u64)
+        .read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_owned(), target.to_owned()),
+        _ => return Err(HttmError::new("httm could not parse the request line.")),
+    };
+
+    // we must read every header before we reply, even those of no use to us
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut head_len = request_line.len();
+    loop {
+        let mut header_line = String::new();
+        let len = reader
+            .by_ref()
+            .take((MAX_REQUEST_HEAD_LEN - head_len.min(MAX_REQUEST_HEAD_LEN)) as u64)
+            .read_line(&mut header_line)?;
+        head_len += len;
+
+        if len == 0 || header_line.trim().is_empty() {
+            break;
+        }
+
+        if head_len >= MAX_REQUEST_HEAD_LEN {
+            return Err(HttmError::new("The request headers are too large."));
+        }
+
+        if headers.len() >= MAX_REQUEST_HEADERS {
+            return Err(HttmError::new("The request has too many headers."));
+        }
+
+        if let Some((name, value)) = header_line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+    }
+
+    let content_len = headers
+        .iter()
+        .find(|(name, _value)| name == "content-length")
+        .map(|(_name, value)| {
+            value
+                .parse::<usize>()
+                .map_err(|_| HttmError::new("The request's Content-Length is not a number."))
+        })
+        .transpose()?
+        .unwrap_or(0);
+
+    if content_len > MAX_REQUEST_BODY_LEN {
+        return Err(HttmError::new("The request body is too large."));
+    }
+
+    let mut body = vec![0u8; content_len];
+    reader.read_exact(&mut body)?;
+
+    let (raw_path, raw_query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+
+    let query = raw_query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                OsString::from_vec(percent_decode(key, true))
+                    .to_string_lossy()
+                    .into_owned(),
+                OsString::from_vec(percent_decode(value, true)),
+            )
+        })
+        .collect();
+
+    Ok(HttpRequest {
+        method,
+        raw_path: raw_path.to_owned(),
+        query,
+        headers,
+        body,
+    })
+}
+
+// the API answers in JSON, with either a "result" or an "error", such as:
+// GET /api/v1/versions?path=docs/report.odt
+// POST /api/v1/restore {"version": "/srv/.zfs/snapshot/snap_1/docs/report.odt", "path": "docs/report.odt"}
+//
+// each path is relative to the dir served, just as with the web pages, and each request
+// must carry the token, as "Authorization: Bearer <token>"
+#[derive(Debug, Deserialize)]
+struct ApiRestoreRequest {
+    version: PathBuf,
+    path: PathBuf,
+    #[serde(default)]
+    overwrite: bool,
+}
+
+fn handle_api_request(config: &Config, server: &HttpServer, request: &HttpRequest) -> HttpResponse {
+    let api_token = match &server.opt_api_token {
+        Some(api_token) => api_token,
+        None => {
+            return api_error_response(
+                404,
+                "Not Found",
+                "httm serves no API, as no API token file was given.",
+            )
+        }
+    };
+
+    let opt_bearer = request
+        .headers
+        .iter()
+        .find(|(name, _value)| name == "authorization")
+        .and_then(|(_name, value)| value.strip_prefix("Bearer "));
+
+    match opt_bearer {
+        Some(bearer) if is_same_token(bearer.trim(), api_token) => {}
+        _ => {
+            let mut response = api_error_response(
+                401,
+                "Unauthorized",
+                "The request does not carry the API token.",
+            );
+            response
+                .extra_headers
+                .push(("WWW-Authenticate", "Bearer".to_owned()));
+            return response;
+        }
+    }
+
+    let endpoint = &request.raw_path[API_PREFIX.len()..];
+
+    let res = match (request.method.as_str(), endpoint) {
+        ("GET", "versions") => api_versions(config, server, request),
+        ("POST", "restore") => api_restore(config, server, request),
+        (_, "versions" | "restore") => {
+            return api_error_response(
+                405,
+                "Method Not Allowed",
+                "\"versions\" must be a GET, and \"restore\" must be a POST.",
+            )
+        }
+        _ => {
+            return api_error_response(
+                404,
+                "Not Found",
+                "The API has only the endpoints \"versions\" and \"restore\".",
+            )
+        }
+    };
+
+    match res {
+        Ok(result) => json_response(200, "OK", &json!({ "result": result })),
+        Err(err) => {
+            let (status, reason) = match err {
+                HttmError::NoVersions { .. } => (404, "Not Found"),
+                HttmError::PermissionDenied { .. } => (403, "Forbidden"),
+                HttmError::Io(ref io_err) if io_err.kind() == ErrorKind::PermissionDenied => {
+                    (403, "Forbidden")
+                }
+                HttmError::OpenFailed { ref source, .. }
+                    if source.kind() == ErrorKind::PermissionDenied =>
+                {
+                    (403, "Forbidden")
+                }
+                HttmError::OpenFailed { ref source, .. }
+                    if source.kind() == ErrorKind::NotFound =>
+                {
+                    (404, "Not Found")
+                }
+                _ => (400, "Bad Request"),
+            };
+            api_error_response(status, reason, &err.to_string())
+        }
+    }
+}
+
+fn api_versions(config: &Config, server: &HttpServer, request: &HttpRequest) -> HttmResult<Value> {
+    let path = request
+        .query
+        .iter()
+        .find(|(key, _value)| key == "path")
+        .map(|(_key, value)| Path::new(value))
+        .ok_or_else(|| HttmError::new("The request has no \"path\" to look up."))?;
+
+    let live_path = get_api_live_path(server, path)?;
+
+    exec_method(config, Method::ListVersions { path: live_path })
+}
+
+fn api_restore(config: &Config, server: &HttpServer, request: &HttpRequest) -> HttmResult<Value> {
+    let restore_request: ApiRestoreRequest = serde_json::from_slice(&request.body)?;
+
+    let live_path = get_api_live_path(server, &restore_request.path)?;
+
+    // our pwd is no place for a client's restored copy, so a copy is restored beside the live file
+    let live_dir = live_path
+        .parent()
+        .map(|parent| parent.to_path_buf())
+        .unwrap_or_else(|| server.root_dir.clone());
+
+    let restore_config = Config {
+        pwd: PathData::from(live_dir.as_path()),
+        ..config.clone()
+    };
+
+    exec_method(
+        &restore_config,
+        Method::Restore {
+            version: restore_request.version,
+            path: live_path,
+            overwrite: restore_request.overwrite,
+        },
+    )
+}
+
+fn get_api_live_path(server: &HttpServer, path: &Path) -> HttmResult<PathBuf> {
+    let relative_path = get_relative_path(path)?;
+    let live_path = server.root_dir.join(relative_path);
+
+    if let Ok(canonical_path) = live_path.canonicalize() {
+        if !canonical_path.starts_with(&server.root_dir) {
+            return Err(HttmError::PermissionDenied {
+                msg: "httm will only serve paths within the dir served.".to_owned(),
+                path: Some(path.to_path_buf()),
+            });
+        }
+    }
+
+    Ok(live_path)
+}
+
+// compare every byte, so that how long a comparison takes says nothing of the token
+fn is_same_token(given: &str, api_token: &str) -> bool {
+    given.len() == api_token.len()
+        && given
+            .bytes()
+            .zip(api_token.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn json_response(status: u16, reason: &'static str, value: &Value) -> HttpResponse {
+    HttpResponse {
+        status,
+        reason,
+        content_type: "application/json",
+        extra_headers: Vec::new(),
+        body: HttpBody::Bytes(format!("{}\n", value).into_bytes()),
+    }
+}
+
+fn api_error_response(status: u16, reason: &'static str, msg: &str) -> HttpResponse {
+    json_response(status, reason, &json!({ "error": msg }))
+}
+
+fn handle_request(
+    config: &Config,
+    root_dir: &Path,
+    request: &HttpRequest,
+) -> HttmResult<HttpResponse> {
+    let decoded_path = OsString::from_vec(percent_decode(&request.raw_path, false));
+    let relative_path = get_relative_path(Path::new(&decoded_path))?;
+    let live_path = root_dir.join(&relative_path);
+
+    // a symlink may point anywhere, so what we serve must remain within the dir served
+    if let Ok(canonical_path) = live_path.canonicalize() {
+        if !canonical_path.starts_with(root_dir) {
+            return Err(HttmError::new(
+                "httm will only serve paths within the dir served.",
+            ));
+        }
+    }
+
+    if live_path.symlink_metadata().is_ok() && !is_owned(&live_path) {
+        return Err(HttmError::new(
+            "httm will not serve a path which is not owned by the invoking user.",
+        ));
+    }
+
+    let opt_version = request
+        .query
+        .iter()
+        .find(|(key, _value)| key == VERSION_QUERY)
+        .map(|(_key, value)| PathBuf::from(value));
+
+    if let Some(version) = opt_version {
+        let snap_path = deny_unless_version_of(config, &version, &live_path)?;
+        return serve_path(config, &relative_path, &snap_path, Some(&snap_path));
+    }
+
+    if request
+        .query
+        .iter()
+        .any(|(key, _value)| key == VERSIONS_QUERY)
+    {
+        return versions_page(config, &relative_path, &live_path);
+    }
+
+    serve_path(config, &relative_path, &live_path, None)
+}
+
+// refuse anything which might leave the dir served
+fn get_relative_path(path: &Path) -> HttmResult<PathBuf> {
+    let relative_path: PathBuf = path
+        .components()
+        .filter(|component| !matches!(component, Component::RootDir | Component::CurDir))
+        .map(|component| match component {
+            Component::Normal(normal) => Ok(normal),
+            _ => Err(HttmError::new(
+                "httm will only serve paths within the dir served.",
+            )),
+        })
+        .collect::<HttmResult<PathBuf>>()?;
+
+    Ok(relative_path)
+}
+
+// a client may ask for any path as if it were a version, so only serve a path which
+// lives upon a snapshot we know, and which is a version of the live path requested
+fn deny_unless_version_of(
+    config: &Config,
+    version: &Path,
+    live_path: &Path,
+) -> HttmResult<PathBuf> {
+    let not_a_version =
+        || HttmError::new("The version requested is not a snapshot version of the path given.");
+
+    let snap_path = version.canonicalize().map_err(|_| not_a_version())?;
+
+    match get_live_path(config, &PathData::from(snap_path.as_path())) {
+        Some(snap_live_path) if snap_live_path == live_path => Ok(snap_path),
+        _ => Err(not_a_version()),
+    }
+}
+
+fn serve_path(
+    config: &Config,
+    relative_path: &Path,
+    path: &Path,
+    opt_snap_path: Option<&Path>,
+) -> HttmResult<HttpResponse> {
+    let pathdata = PathData::from(path);
+
+    if pathdata.metadata().is_none() {
+        return Err(HttmError::new("The path requested does not exist."));
+    }
+
+    // a version need not be owned by whoever owns the live path, if there still is one
+    if httm_is_dir(&pathdata) {
+        deny_unless_owned("serve", path)?;
+        return dir_page(config, relative_path, path, opt_snap_path);
+    }
+
+    audit("read", path);
+    let file = open_owned("serve", path)?;
+    let len = file.metadata()?.len();
+
+    let file_name = relative_path
+        .file_name()
+        .unwrap_or_else(|| OsStr::new("download"));
+
+    Ok(HttpResponse {
+        status: 200,
+        reason: "OK",
+        content_type: "application/octet-stream",
+        extra_headers: vec![(
+            "Content-Disposition",
+            format!(
+                "attachment; filename*=UTF-8''{}",
+                percent_encode(file_name.as_bytes(), false)
+            ),
+        )],
+        body: HttpBody::File(file, len),
+    })
+}
+
+// the children of a live dir link to their versions, the children of a snapshot dir
+// link to themselves, as versions of the live children they once were
+fn dir_page(
+    config: &Config,
+    relative_path: &Path,
+    dir: &Path,
+    opt_snap_path: Option<&Path>,
+) -> HttmResult<HttpResponse> {
+    audit("list", dir);
+    let mut children: Vec<PathData> = read_dir(dir)?
+        .flatten()
+        .filter(|dir_entry| is_owned(&dir_entry.path()))
+        .map(|dir_entry| PathData::from(&dir_entry))
+        .collect();
+    children.sort_by(|a, b| a.path_buf.cmp(&b.path_buf));
+
+    let mut rows = String::new();
+
+    if let Some(parent) = relative_path.parent() {
+        let parent_href = get_href(parent);
+        rows.push_str(&format!(
+            "<tr><td><a href=\"{}\">../</a></td><td></td><td></td><td></td></tr>\n",
+            parent_href
+        ));
+    }
+
+    children.iter().for_each(|child| {
+        let file_name = child.path_buf.file_name().unwrap_or_default();
+        let child_relative_path = relative_path.join(file_name);
+        let is_dir = httm_is_dir(child);
+
+        let mut display_name = html_escape(&file_name.to_string_lossy());
+        if is_dir {
+            display_name.push('/');
+        }
+
+        let (href, versions_link) = match opt_snap_path {
+            Some(_) => (
+                format!(
+                    "{}?{}={}",
+                    get_href(&child_relative_path),
+                    VERSION_QUERY,
+                    percent_encode(child.path_buf.as_os_str().as_bytes(), true)
+                ),
+                String::new(),
+            ),
+            None => (
+                get_href(&child_relative_path),
+                format!(
+                    "<a href=\"{}?{}\">versions</a>",
+                    get_href(&child_relative_path),
+                    VERSIONS_QUERY
+                ),
+            ),
+        };
+
+        let (size, modified) = match child.metadata() {
+            Some(path_metadata) if !is_dir => (
+                display_human_size(&path_metadata.size),
+                get_date(config, &path_metadata.modify_time, DateFormat::Display),
+            ),
+            Some(path_metadata) => (
+                String::new(),
+                get_date(config, &path_metadata.modify_time, DateFormat::Display),
+            ),
+            None => (String::new(), String::new()),
+        };
+
+        rows.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            href,
+            display_name,
+            html_escape(&size),
+            html_escape(&modified),
+            versions_link
+        ));
+    });
+
+    let heading = match opt_snap_path {
+        Some(snap_path) => format!(
+            "/{} as of {}",
+            relative_path.to_string_lossy(),
+            snap_path.to_string_lossy()
+        ),
+        None => format!("/{}", relative_path.to_string_lossy()),
+    };
+
+    let body = format!(
+        "<table>\n<tr><th>Name</th><th>Size</th><th>Modified</th><th></th></tr>\n{}</table>\n",
+        rows
+    );
+
+    Ok(html_response(&heading, &body))
+}
+
+fn versions_page(
+    config: &Config,
+    relative_path: &Path,
+    live_path: &Path,
+) -> HttmResult<HttpResponse> {
+    let live_pathdata = PathData::from(live_path);
+    let snaps_and_live_set = versions_lookup_exec(config, std::slice::from_ref(&live_pathdata))?;
+
+    let href = get_href(relative_path);
+    let mut rows = String::new();
+
+    // safe to index into snaps, known len of 2 for set
+    snaps_and_live_set[0]
+        .iter()
+        .filter(|snap_pathdata| is_owned(&snap_pathdata.path_buf))
+        .filter_map(|snap_pathdata| snap_pathdata.metadata().map(|md| (snap_pathdata, md)))
+        .for_each(|(snap_pathdata, path_metadata)| {
+            rows.push_str(&format!(
+                "<tr><td><a href=\"{}?{}={}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+                href,
+                VERSION_QUERY,
+                percent_encode(snap_pathdata.path_buf.as_os_str().as_bytes(), true),
+                html_escape(&get_date(
+                    config,
+                    &path_metadata.modify_time,
+                    DateFormat::Display
+                )),
+                html_escape(&display_human_size(&path_metadata.size)),
+                html_escape(&snap_pathdata.path_buf.to_string_lossy())
+            ));
+        });
+
+    if rows.is_empty() {
+        rows.push_str(
+            "<tr><td colspan=\"3\">httm found no snapshot versions of this path.</td></tr>\n",
+        );
+    }
+
+    // and, should the path still exist, the live version, last
+    if let Some(path_metadata) = live_pathdata.metadata() {
+        rows.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>live</td></tr>\n",
+            href,
+            html_escape(&get_date(
+                config,
+                &path_metadata.modify_time,
+                DateFormat::Display
+            )),
+            html_escape(&display_human_size(&path_metadata.size)),
+        ));
+    }
+
+    let heading = format!("Versions of /{}", relative_path.to_string_lossy());
+    let body = format!(
+        "<table>\n<tr><th>Modified</th><th>Size</th><th>Version</th></tr>\n{}</table>\n",
+        rows
+    );
+
+    Ok(html_response(&heading, &body))
+}
+
+fn get_href(relative_path: &Path) -> String {
+    format!(
+        "/{}",
+        percent_encode(relative_path.as_os_str().as_bytes(), false)
+    )
+}
+
+fn html_response(heading: &str, body: &str) -> HttpResponse {
+    let heading = html_escape(heading);
+    let page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>httm: {}</title>\n</head>\n<body>\n<h1>{}</h1>\n{}</body>\n</html>\n",
+        heading, heading, body
+    );
+
+    HttpResponse {
+        status: 200,
+        reason: "OK",
+        content_type: "text/html; charset=utf-8",
+        extra_headers: Vec::new(),
+        body: HttpBody::Bytes(page.into_bytes()),
+    }
+}
+
+fn error_response(status: u16, reason: &'static str, msg: &str) -> HttpResponse {
+    HttpResponse {
+        status,
+        reason,
+        content_type: "text/plain; charset=utf-8",
+        extra_headers: Vec::new(),
+        body: HttpBody::Bytes(format!("{}\n", msg).into_bytes()),
+    }
+}
+
+fn write_response(stream: TcpStream, response: HttpResponse, is_head: bool) -> HttmResult<()> {
+    let mut writer = std::io::BufWriter::new(stream);
+
+    let content_len = match &response.body {
+        HttpBody::Bytes(bytes) => bytes.len() as u64,
+        HttpBody::File(_file, len) => *len,
+    };
+
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status, response.reason, response.content_type, content_len
+    )?;
+
+    response
+        .extra_headers
+        .iter()
+        .try_for_each(|(name, value)| write!(writer, "{}: {}\r\n", name, value))?;
+
+    writer.write_all(b"\r\n")?;
+
+    if !is_head {
+        match response.body {
+            HttpBody::Bytes(bytes) => writer.write_all(&bytes)?,
+            HttpBody::File(mut file, _len) => {
+                std::io::copy(&mut file, &mut writer)?;
+            }
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+// only a query value may encode a space as a plus
+fn percent_decode(value: &str, is_query_value: bool) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let opt_escaped = (bytes[idx] == b'%')
+            .then(|| value.get(idx + 1..idx + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (opt_escaped, bytes[idx]) {
+            (Some(byte), _) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            (None, b'+') if is_query_value => {
+                decoded.push(b' ');
+                idx += 1;
+            }
+            (None, byte) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+
+    decoded
+}
+
+// a path may keep its slashes, but a query value may not
+pub fn percent_encode(bytes: &[u8], is_query_value: bool) -> String {
+    bytes
+        .iter()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (*byte as char).to_string()
+            }
+            b'/' if !is_query_value => "/".to_owned(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_owned(),
+            '<' => "&lt;".to_owned(),
+            '>' => "&gt;".to_owned(),
+            '"' => "&quot;".to_owned(),
+            '\'' => "&#39;".to_owned(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,367 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    io::{BufRead, Read, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use clap::crate_version;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::forensic::{audit, deny_if_forensic};
+use crate::interactive::get_restore_destination;
+use crate::lookup_versions::{
+    get_live_path, get_version_entries_for_path_set, versions_lookup_exec,
+};
+use crate::open_files::open_files_advisory;
+use crate::restore_attributes::restore_special_attributes;
+use crate::restrict_owner::{deny_unless_owned, deny_unless_restorable, is_owned, open_owned};
+use crate::set_aside::{get_set_aside_path, restore_over_live};
+use crate::utility::{get_date, DateFormat, HttmError, PathData};
+use crate::{Config, HttmResult};
+
+// each request is a single line of JSON, such as:
+// {"id": 1, "method": "list_versions", "params": {"path": "/etc/samba/smb.conf"}}
+//
+// and each response is a single line of JSON, which echoes the request's id, and
+// contains either a "result" or an "error"
+#[derive(Debug, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Method {
+    ListVersions {
+        path: PathBuf,
+    },
+    ReadVersion {
+        path: PathBuf,
+    },
+    Restore {
+        version: PathBuf,
+        path: PathBuf,
+        #[serde(default)]
+        overwrite: bool,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct VersionInfo {
+    path: String,
+    date: String,
+    modify_time: u64,
+    size: u64,
+    // only snapshot versions have a snapshot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snapshot: Option<SnapshotInfo>,
+    // only in forensic and JSON modes, and only for files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SnapshotInfo {
+    mount: String,
+    name: String,
+    dataset: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ListVersionsResult {
+    versions: Vec<VersionInfo>,
+    live: Option<VersionInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    custody: Option<CustodyInfo>,
+}
+
+// which version of httm produced a listing, and when, so a listing may stand on its own as a record
+#[derive(Debug, Serialize)]
+struct CustodyInfo {
+    httm_version: String,
+    generated_at: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ReadVersionResult {
+    path: String,
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct RestoreResult {
+    from: String,
+    to: String,
+    // where the live file was moved, before it was restored over
+    #[serde(skip_serializing_if = "Option::is_none")]
+    set_aside: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warning: Option<String>,
+    // capabilities and immutable or append-only flags which could not be reapplied
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attributes_not_restored: Vec<String>,
+    // the files, within a dir restored, which could not be restored, and why
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    files_not_restored: Vec<String>,
+}
+
+pub fn serve_stdio(config: &Config) -> HttmResult<()> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+
+    // serve until our client hangs up
+    for line in stdin.lock().lines() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = handle_request(config, &line);
+
+        let mut out_locked = stdout.lock();
+        serde_json::to_writer(&mut out_locked, &response)?;
+        out_locked.write_all(b"\n")?;
+        out_locked.flush()?;
+    }
+
+    Ok(())
+}
+
+fn handle_request(config: &Config, line: &str) -> Response {
+    // parse as a value first, so that we may still echo the id of a malformed request
+    let (id, res) = match serde_json::from_str::<Value>(line) {
+        Ok(value) => {
+            let id = value.get("id").cloned().unwrap_or(Value::Null);
+            let res = serde_json::from_value::<Method>(value)
+                .map_err(|err| err.into())
+                .and_then(|method| exec_method(config, method));
+            (id, res)
+        }
+        Err(err) => (Value::Null, Err(err.into())),
+    };
+
+    match res {
+        Ok(result) => Response {
+            id,
+            result: Some(result),
+            error: None,
+        },
+        Err(err) => Response {
+            id,
+            result: None,
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+pub fn exec_method(config: &Config, method: Method) -> HttmResult<Value> {
+    let result = match method {
+        Method::ListVersions { path } => {
+            serde_json::to_value(list_versions(config, &absolute_path(config, &path))?)?
+        }
+        Method::ReadVersion { path } => {
+            serde_json::to_value(read_version(config, &absolute_path(config, &path))?)?
+        }
+        Method::Restore {
+            version,
+            path,
+            overwrite,
+        } => serde_json::to_value(restore(
+            config,
+            &absolute_path(config, &version),
+            &absolute_path(config, &path),
+            overwrite,
+        )?)?,
+    };
+
+    Ok(result)
+}
+
+// canonicalize() on a deleted path will not exist, so, just as with INPUT_FILES,
+// we join with the pwd to make a path that will exist on a snapshot
+fn absolute_path(config: &Config, path: &Path) -> PathData {
+    let path_buf = path
+        .canonicalize()
+        .unwrap_or_else(|_| config.pwd.path_buf.join(path));
+    PathData::from(path_buf.as_path())
+}
+
+fn version_info(config: &Config, pathdata: &PathData) -> VersionInfo {
+    let path_metadata = pathdata.md_infallible();
+
+    VersionInfo {
+        path: pathdata.path_buf.to_string_lossy().into_owned(),
+        date: get_date(config, &path_metadata.modify_time, DateFormat::Display),
+        modify_time: path_metadata
+            .modify_time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default(),
+        size: path_metadata.size,
+        snapshot: None,
+        sha256: config
+            .opt_chain_of_custody
+            .then(|| pathdata.content_hash().map(|hash| hash.to_owned()))
+            .flatten(),
+    }
+}
+
+fn list_versions(config: &Config, pathdata: &PathData) -> HttmResult<ListVersionsResult> {
+    // as with paths given on the command line, a path which doesn't exist may be a deleted file
+    if pathdata.metadata().is_some() {
+        deny_unless_owned("look up", &pathdata.path_buf)?;
+    }
+
+    // lookup the version entries directly, as we want to know from which snapshot each version came
+    let versions: Vec<VersionInfo> =
+        get_version_entries_for_path_set(config, std::slice::from_ref(pathdata))?
+            .iter()
+            .filter(|version_entry| is_owned(&version_entry.pathdata.path_buf))
+            .map(|version_entry| VersionInfo {
+                snapshot: Some(SnapshotInfo {
+                    mount: version_entry.snap_mount.to_string_lossy().into_owned(),
+                    name: version_entry.snap_name.clone(),
+                    dataset: version_entry.dataset.clone(),
+                }),
+                ..version_info(config, &version_entry.pathdata)
+            })
+            .collect();
+
+    // a live version which does not exist, here, has been deleted
+    let live = pathdata
+        .metadata()
+        .map(|_metadata| version_info(config, pathdata));
+
+    if versions.is_empty() && live.is_none() {
+        return Err(HttmError::NoVersions {
+            msg: "httm could not find either a live copy or a snapshot copy of the specified file."
+                .to_owned(),
+            path: Some(pathdata.path_buf.clone()),
+        });
+    }
+
+    let custody = config.opt_chain_of_custody.then(|| CustodyInfo {
+        httm_version: crate_version!().to_owned(),
+        generated_at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default(),
+    });
+
+    Ok(ListVersionsResult {
+        versions,
+        live,
+        custody,
+    })
+}
+
+fn read_version(config: &Config, snap_pathdata: &PathData) -> HttmResult<ReadVersionResult> {
+    if snap_pathdata.metadata().is_none() {
+        return Err(HttmError::new("Version requested does not exist on disk."));
+    }
+
+    // a client may ask for any path as if it were a version, so only read a path which
+    // lives upon a snapshot we know
+    if get_live_path(config, snap_pathdata).is_none() {
+        return Err(HttmError::new(
+            "Version requested is not a version upon any known snapshot.",
+        ));
+    }
+
+    audit("read", &snap_pathdata.path_buf);
+    let mut bytes = Vec::new();
+    open_owned("read", &snap_pathdata.path_buf)?.read_to_end(&mut bytes)?;
+    let content = String::from_utf8(bytes).map_err(|_| {
+        HttmError::new("Version requested is not valid UTF-8, and cannot be read as text.")
+    })?;
+
+    Ok(ReadVersionResult {
+        path: snap_pathdata.path_buf.to_string_lossy().into_owned(),
+        content,
+    })
+}
+
+fn restore(
+    config: &Config,
+    snap_pathdata: &PathData,
+    live_pathdata: &PathData,
+    opt_overwrite: bool,
+) -> HttmResult<RestoreResult> {
+    deny_if_forensic("restore a file")?;
+
+    let snap_path_metadata = snap_pathdata
+        .metadata()
+        .ok_or_else(|| HttmError::new("Source location does not exist on disk."))?;
+
+    // we have no dialog here to confirm a user's intent, so
+    // refuse anything which is not a version of the path given
+    let snaps_and_live_set = versions_lookup_exec(config, std::slice::from_ref(live_pathdata))?;
+    if !snaps_and_live_set[0].contains(snap_pathdata) {
+        return Err(HttmError::new(
+            "Source location is not a snapshot version of the path given.",
+        ));
+    }
+
+    let new_file_path_buf = get_restore_destination(
+        config,
+        snap_pathdata,
+        &snap_path_metadata,
+        std::slice::from_ref(live_pathdata),
+        opt_overwrite,
+    )?;
+
+    deny_unless_restorable(&snap_pathdata.path_buf, &new_file_path_buf)?;
+
+    // as with an interactive restore, the live file is moved out of the way first
+    let opt_set_aside_path = get_set_aside_path(config, &new_file_path_buf, opt_overwrite)?;
+
+    if let Some(set_aside_path) = &opt_set_aside_path {
+        deny_unless_restorable(&new_file_path_buf, set_aside_path)?;
+    }
+
+    let warning = open_files_advisory(config, &new_file_path_buf)?;
+
+    let copy_failures = restore_over_live(
+        &snap_pathdata.path_buf,
+        &new_file_path_buf,
+        opt_set_aside_path.as_deref(),
+    )?;
+    let attributes_not_restored =
+        restore_special_attributes(&snap_pathdata.path_buf, &new_file_path_buf);
+
+    Ok(RestoreResult {
+        from: snap_pathdata.path_buf.to_string_lossy().into_owned(),
+        to: new_file_path_buf.to_string_lossy().into_owned(),
+        set_aside: opt_set_aside_path
+            .map(|set_aside_path| set_aside_path.to_string_lossy().into_owned()),
+        warning,
+        attributes_not_restored,
+        files_not_restored: copy_failures
+            .iter()
+            .map(|failure| format!("{}: {}", failure.path.to_string_lossy(), failure.err))
+            .collect(),
+    })
+}
@@ -0,0 +1,143 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    fs::{remove_dir_all, remove_file, rename},
+    path::{Path, PathBuf},
+};
+
+use crate::progress::{copy_recursive_with_progress, CopyFailure};
+use crate::restrict_owner::as_invoking_user;
+use crate::utility::{copy_recursive, get_date, DateFormat, HttmError, PathData};
+use crate::{ClobberedLive, Config, HttmResult};
+
+// every restore which may replace a live file comes through here, so that, however the restore
+// is asked for, the live file is first set aside, see get_set_aside_path, and, should the
+// restore fail, put back
+pub fn restore_over_live(
+    snap_path: &Path,
+    new_file_path: &Path,
+    opt_set_aside_path: Option<&Path>,
+) -> HttmResult<Vec<CopyFailure>> {
+    if let Some(set_aside_path) = opt_set_aside_path {
+        set_aside(new_file_path, set_aside_path)?;
+    }
+
+    copy_recursive_with_progress(snap_path, new_file_path).map_err(|err| {
+        let msg = match opt_set_aside_path {
+            Some(set_aside_path) => match put_back(new_file_path, set_aside_path) {
+                Ok(_) => "httm restore failed, and the live file was put back, for the following reason".to_owned(),
+                Err(_) => format!(
+                    "httm restore failed, and the live file remains at {:?}, for the following reason",
+                    set_aside_path
+                ),
+            },
+            None => "httm restore failed for the following reason".to_owned(),
+        };
+
+        HttmError::with_context(&msg, Box::new(err))
+    })
+}
+
+// where the live file is set aside before a restore in place, or nothing, should there be no live
+// file, or should the user wish it overwritten, named for the live file's own modify time
+pub fn get_set_aside_path(
+    config: &Config,
+    live_path: &Path,
+    opt_overwrite: bool,
+) -> HttmResult<Option<PathBuf>> {
+    if !opt_overwrite {
+        return Ok(None);
+    }
+
+    let live_pathdata = PathData::from(live_path);
+
+    let live_metadata = match live_pathdata.metadata() {
+        Some(live_metadata) => live_metadata,
+        None => return Ok(None),
+    };
+
+    let set_aside_dir = match &config.clobbered_live {
+        ClobberedLive::Overwrite => return Ok(None),
+        ClobberedLive::MoveAside => live_path
+            .parent()
+            .map(|parent| parent.to_path_buf())
+            .ok_or_else(|| {
+                HttmError::new("httm could not determine the directory of the live file.")
+            })?,
+        ClobberedLive::MoveInto(set_aside_dir) => set_aside_dir.clone(),
+    };
+
+    let live_filename = live_path
+        .file_name()
+        .ok_or_else(|| HttmError::new("httm could not determine the file name of the live file."))?
+        .to_string_lossy()
+        .into_owned();
+
+    let set_aside_filename = live_filename
+        + ".httm_set_aside."
+        + &get_date(config, &live_metadata.modify_time, DateFormat::Timestamp);
+    let set_aside_path = set_aside_dir.join(set_aside_filename);
+
+    // just as with a restore, we never write one over another
+    if set_aside_path.symlink_metadata().is_ok() {
+        let msg = format!(
+            "httm will not set the live file aside at {:?}, as a file with the same path name already exists. Quitting.",
+            set_aside_path
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    Ok(Some(set_aside_path))
+}
+
+// restricted to the invoking user, we move, copy and remove only as them, see as_invoking_user
+fn set_aside(live_path: &Path, set_aside_path: &Path) -> HttmResult<()> {
+    as_invoking_user(|| match rename(live_path, set_aside_path) {
+        Ok(_) => Ok(()),
+        // a set aside dir on another filesystem means a copy, which, like a rename, keeps each symlink a
+        // symlink, and only then may we remove the live file
+        Err(err) if err.raw_os_error() == Some(libc::EXDEV) => {
+            copy_recursive(live_path, set_aside_path, true)?;
+
+            if live_path.symlink_metadata()?.is_dir() {
+                remove_dir_all(live_path)?;
+            } else {
+                remove_file(live_path)?;
+            }
+
+            Ok(())
+        }
+        Err(err) => Err(HttmError::with_context(
+            &format!(
+                "httm could not set the live file aside at {:?}",
+                set_aside_path
+            ),
+            err,
+        )),
+    })
+}
+
+// after a failed restore, whatever was partially restored gives way to the live file once more
+fn put_back(live_path: &Path, set_aside_path: &Path) -> HttmResult<()> {
+    as_invoking_user(|| match live_path.symlink_metadata() {
+        Ok(md) if md.is_dir() => remove_dir_all(live_path),
+        Ok(_) => remove_file(live_path),
+        Err(_) => Ok(()),
+    })?;
+
+    set_aside(set_aside_path, live_path)
+}
@@ -0,0 +1,163 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    io::{BufRead, Write},
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::Serialize;
+
+use crate::lookup_versions::{get_creation_times_for_entries, get_version_entries_for_path_set};
+use crate::restrict_owner::deny_unless_owned;
+use crate::utility::{get_date, DateFormat, HttmError, PathData};
+use crate::{Config, HttmResult};
+
+// each query is a single line, a path relative to the root of the share, such as:
+// docs/report.odt
+//
+// and each answer is a single line of JSON, which lists the previous versions of that
+// path by their "@GMT" token, as an SMB client would ask for them
+#[derive(Debug, Serialize)]
+struct SmbAnswer {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    versions: Option<Vec<SmbVersion>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SmbVersion {
+    // e.g. @GMT-2022.08.01-12.00.00, the creation time of the snapshot, in UTC
+    token: String,
+    snapshot: String,
+    path: String,
+    modify_time: u64,
+    size: u64,
+}
+
+pub fn smb_versions_exec(config: &Config, share_root: &Path) -> HttmResult<()> {
+    let share_root = share_root.canonicalize().map_err(|err| {
+        HttmError::with_context(
+            &format!("httm could not find the share root {:?}", share_root),
+            err,
+        )
+    })?;
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+
+    // answer until our client hangs up
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let share_relative_path = line.trim_end_matches('\r');
+
+        if share_relative_path.trim().is_empty() {
+            continue;
+        }
+
+        let answer = match get_smb_versions(config, &share_root, share_relative_path) {
+            Ok(versions) => SmbAnswer {
+                path: share_relative_path.to_owned(),
+                versions: Some(versions),
+                error: None,
+            },
+            Err(err) => SmbAnswer {
+                path: share_relative_path.to_owned(),
+                versions: None,
+                error: Some(err.to_string()),
+            },
+        };
+
+        let mut out_locked = stdout.lock();
+        serde_json::to_writer(&mut out_locked, &answer)?;
+        out_locked.write_all(b"\n")?;
+        out_locked.flush()?;
+    }
+
+    Ok(())
+}
+
+fn get_smb_versions(
+    config: &Config,
+    share_root: &Path,
+    share_relative_path: &str,
+) -> HttmResult<Vec<SmbVersion>> {
+    let relative_path = Path::new(share_relative_path.trim_start_matches('/'));
+
+    // a query may only ask after paths within the share
+    if relative_path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(HttmError::new(
+            "httm will only look up paths relative to, and within, the root of the share.",
+        ));
+    }
+
+    let live_path: PathBuf = share_root.join(relative_path);
+    let live_pathdata = PathData::from(live_path.as_path());
+
+    // as with --serve-stdio, a path which doesn't exist may be a deleted file
+    if live_pathdata.metadata().is_some() {
+        deny_unless_owned("look up", &live_pathdata.path_buf)?;
+    }
+
+    let version_entries =
+        get_version_entries_for_path_set(config, std::slice::from_ref(&live_pathdata))?;
+
+    let creation_times = get_creation_times_for_entries(config, &version_entries);
+
+    let mut versions: Vec<(SystemTime, SmbVersion)> = version_entries
+        .iter()
+        .map(|version_entry| {
+            let creation_time = creation_times
+                .get(&version_entry.snap_mount)
+                .copied()
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            let path_metadata = version_entry.pathdata.md_infallible();
+
+            let version = SmbVersion {
+                token: get_date(config, &creation_time, DateFormat::SmbGmt),
+                snapshot: version_entry.snap_name.clone(),
+                path: version_entry
+                    .pathdata
+                    .path_buf
+                    .to_string_lossy()
+                    .into_owned(),
+                modify_time: path_metadata
+                    .modify_time
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or_default(),
+                size: path_metadata.size,
+            };
+
+            (creation_time, version)
+        })
+        .collect();
+
+    // oldest first, as an SMB client lists them
+    versions.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.path.cmp(&b.1.path)));
+
+    Ok(versions
+        .into_iter()
+        .map(|(_time, version)| version)
+        .collect())
+}
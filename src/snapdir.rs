@@ -0,0 +1,216 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    ffi::OsString,
+    fs::{read_dir, File},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    process::Command as ExecProcess,
+};
+
+use which::which;
+
+use crate::forensic::deny_if_forensic;
+use crate::lookup_versions::RelativePathAndSnapMounts;
+use crate::parse_mounts::parse_datasets;
+use crate::privileged_helper::{exec_privileged, PrivilegedRequest};
+use crate::utility::{HttmError, PathData};
+use crate::{Config, FilesystemType, HttmResult};
+
+// the ZFS "snapdir" property of a dataset, "disabled" is only available on more recent versions of ZFS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapdirProperty {
+    Visible,
+    Hidden,
+    Disabled,
+}
+
+pub fn get_snapdir_property(dataset_name: &str) -> Option<SnapdirProperty> {
+    let zfs_command = which("zfs").ok()?;
+
+    let process_output = ExecProcess::new(zfs_command)
+        .args(["get", "-H", "-o", "value", "snapdir", dataset_name])
+        .output()
+        .ok()?;
+
+    match std::str::from_utf8(&process_output.stdout).ok()?.trim() {
+        "visible" => Some(SnapdirProperty::Visible),
+        "hidden" => Some(SnapdirProperty::Hidden),
+        "disabled" => Some(SnapdirProperty::Disabled),
+        _ => None,
+    }
+}
+
+// when a ZFS path has no versions, the snapdir property may explain why, either there is no
+// snapshot dir at all, or the snapshots on a hidden snapshot dir were never automounted
+pub fn get_snapdir_warning(
+    config: &Config,
+    pathdata: &PathData,
+    search_bundles: &[RelativePathAndSnapMounts],
+) -> Option<String> {
+    let map_of_datasets = &config.dataset_collection.map_of_datasets;
+    let mount = config
+        .dataset_collection
+        .get_proximate_dataset(pathdata)
+        .ok()?;
+    let dataset_info = map_of_datasets.get(&mount)?;
+
+    if !matches!(dataset_info.fs_type, FilesystemType::Zfs) {
+        return None;
+    }
+
+    let opt_snap_mounts = search_bundles
+        .iter()
+        .find(|search_bundle| search_bundle.dataset_of_interest == mount)
+        .map(|search_bundle| &search_bundle.snap_mounts);
+
+    match opt_snap_mounts {
+        // no search bundle means no snapshot dir could be listed at all
+        None => match get_snapdir_property(&dataset_info.name)? {
+            SnapdirProperty::Disabled => Some(format!(
+                "the ZFS dataset {:?} has its snapdir property set to \"disabled\", so httm cannot see its snapshots.  \
+                Try again with \"--enable-snapdir\", which sets it to \"hidden\" only until httm is finished, \
+                or set it yourself with \"zfs set snapdir=hidden {}\".",
+                dataset_info.name, dataset_info.name
+            )),
+            SnapdirProperty::Visible | SnapdirProperty::Hidden => None,
+        },
+        // a snapshot which was never automounted is just an empty dir
+        Some(snap_mounts) if !snap_mounts.is_empty() => {
+            let is_never_mounted = snap_mounts
+                .iter()
+                .all(|snap_mount| match read_dir(snap_mount) {
+                    Ok(mut read_dir) => read_dir.next().is_none(),
+                    // permissions are another matter, see get_inaccessible_snap_dirs
+                    Err(err) => err.kind() != ErrorKind::PermissionDenied,
+                });
+
+            if is_never_mounted
+                && get_snapdir_property(&dataset_info.name)? == SnapdirProperty::Hidden
+            {
+                Some(format!(
+                    "the ZFS dataset {:?} has a hidden snapdir, and none of its snapshots appear to be mounted, \
+                    so httm could not search them.  ZFS should mount each snapshot automatically, when it is accessed.  \
+                    Perhaps automounts are not permitted here, as within some containers, or the snapshots must be mounted by hand.",
+                    dataset_info.name
+                ))
+            } else {
+                None
+            }
+        }
+        Some(_) => None,
+    }
+}
+
+// sets the snapdir of each mounted ZFS dataset with a disabled snapdir to hidden, runs httm again
+// as requested, and then disables each snapdir again.  a child process does the work, so that
+// we disable each snapdir again however it exits
+pub fn enable_snapdir_exec() -> HttmResult<()> {
+    deny_if_forensic("change the snapdir property of a dataset")?;
+
+    let (map_of_datasets, _vec_of_filter_dirs) = parse_datasets()?;
+
+    let disabled_datasets: Vec<String> = map_of_datasets
+        .values()
+        .filter(|dataset_info| matches!(dataset_info.fs_type, FilesystemType::Zfs))
+        .filter(|dataset_info| {
+            get_snapdir_property(&dataset_info.name) == Some(SnapdirProperty::Disabled)
+        })
+        .map(|dataset_info| dataset_info.name.clone())
+        .collect();
+
+    if disabled_datasets.is_empty() {
+        eprintln!("httm found no mounted ZFS datasets with a disabled snapdir.");
+    } else if !get_consent(&disabled_datasets)? {
+        eprintln!("User declined.  No snapdir properties were changed.");
+        std::process::exit(0)
+    }
+
+    let enabled_datasets: Vec<&String> = disabled_datasets
+        .iter()
+        .filter(|dataset_name| {
+            match exec_privileged(&PrivilegedRequest::EnableSnapdir {
+                dataset_name: dataset_name.to_string(),
+            }) {
+                Ok(_) => true,
+                Err(err) => {
+                    eprintln!(
+                        "Warning: httm was unable to set the snapdir of {:?} to hidden: {}",
+                        dataset_name, err
+                    );
+                    false
+                }
+            }
+        })
+        .collect();
+
+    let child_status = ExecProcess::new(std::env::current_exe()?)
+        .args(get_child_args())
+        .status();
+
+    enabled_datasets.iter().for_each(|dataset_name| {
+        if let Err(err) = exec_privileged(&PrivilegedRequest::RestoreSnapdir {
+            dataset_name: dataset_name.to_string(),
+        }) {
+            eprintln!(
+                "Warning: httm was unable to disable the snapdir of {:?} again: {}",
+                dataset_name, err
+            );
+        }
+    });
+
+    // exit with the child's status, as the child has already printed any error
+    std::process::exit(child_status?.code().unwrap_or(1))
+}
+
+// stdin may well be our input, so we ask at the terminal
+fn get_consent(disabled_datasets: &[String]) -> HttmResult<bool> {
+    let mut tty = File::options()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|_| {
+            HttmError::new(
+                "httm must be run from a terminal to ask consent to change the snapdir property of a dataset.",
+            )
+        })?;
+
+    write!(
+        tty,
+        "httm will set the snapdir property of the following ZFS datasets to \"hidden\", perform the requested lookups or restores, \
+        and then set each snapdir property to \"disabled\" again:\n{}\n\
+        Before httm changes these properties, it would like your consent. Continue? (YES/NO) ",
+        disabled_datasets.join("\n")
+    )?;
+    tty.flush()?;
+
+    let mut user_consent = String::new();
+    BufReader::new(tty).read_line(&mut user_consent)?;
+
+    Ok(matches!(
+        user_consent.trim().to_ascii_uppercase().as_ref(),
+        "YES" | "Y"
+    ))
+}
+
+// our own args, less the request to enable snapdirs
+fn get_child_args() -> Vec<OsString> {
+    std::env::args_os()
+        .skip(1)
+        .filter(|arg| arg != "--enable-snapdir")
+        .collect()
+}
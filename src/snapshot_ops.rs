@@ -15,37 +15,35 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
-use std::{collections::BTreeMap, path::Path, sync::Arc, time::SystemTime};
+use std::{collections::BTreeMap, sync::Arc, time::SystemTime};
 
 use itertools::Itertools;
-use std::process::Command as ExecProcess;
-use which::which;
 
+use crate::forensic::deny_if_forensic;
 use crate::lookup_file_mounts::{get_mounts_for_files, MountsForFiles};
+use crate::privileged_helper::{exec_privileged, PrivilegedRequest};
 use crate::utility::{get_date, print_output_buf, DateFormat, HttmError};
 use crate::{Config, HttmResult};
 
 use crate::FilesystemType;
 
 pub fn take_snapshot(config: Arc<Config>) -> HttmResult<()> {
-    fn exec_zfs_snapshot(
-        config: Arc<Config>,
-        zfs_command: &Path,
-        mounts_for_files: &MountsForFiles,
-    ) -> HttmResult<()> {
+    deny_if_forensic("take a snapshot")?;
+
+    fn exec_zfs_snapshot(config: Arc<Config>, mounts_for_files: &MountsForFiles) -> HttmResult<()> {
         // all snapshots should have the same timestamp
         let timestamp = get_date(&config, &SystemTime::now(), DateFormat::Timestamp);
 
         let vec_snapshot_names: Vec<String> = mounts_for_files
-            .iter()
-            .flat_map(|(_pathdata, datasets)| datasets)
-            .map(|mount| {
+            .values()
+            .flatten()
+            .map(|mount| -> HttmResult<String> {
             let dataset: String = match &config.dataset_collection.opt_map_of_aliases {
                 None => {
                     match config.dataset_collection.map_of_datasets.get(&mount.path_buf) {
                         Some(dataset_info) => {
                             if let FilesystemType::Zfs = dataset_info.fs_type {
-                                Ok(dataset_info.name.to_owned())
+                                dataset_info.name.to_owned()
                             } else {
                                 return Err(HttmError::new("httm does not currently support snapshot-ing non-ZFS filesystems."))
                             }
@@ -54,7 +52,7 @@ pub fn take_snapshot(config: Arc<Config>) -> HttmResult<()> {
                     }
                 }
                 Some(_) => return Err(HttmError::new("httm does not currently support snapshot-ing user defined mount points.")),
-            }?;
+            };
 
             let snapshot_name = format!(
                 "{}@snap_{}_httmSnapFileMount",
@@ -63,7 +61,7 @@ pub fn take_snapshot(config: Arc<Config>) -> HttmResult<()> {
             );
 
             Ok(snapshot_name)
-        }).collect::<Result<Vec<String>, HttmError>>()?;
+        }).collect::<HttmResult<Vec<String>>>()?;
 
         // why all this garbage with BTreeMaps, etc.? ZFS will not allow one to take snapshots
         // with the same name, at the same time, across pools.  Since we don't really care, we break
@@ -84,42 +82,25 @@ pub fn take_snapshot(config: Arc<Config>) -> HttmResult<()> {
             })
             .collect();
 
-        map_snapshot_names.iter().try_for_each( |(_pool_name, snapshot_names)| {
-            let mut process_args = vec!["snapshot".to_owned()];
-            process_args.extend_from_slice(snapshot_names);
-
-            let process_output = ExecProcess::new(zfs_command).args(&process_args).output()?;
-            let stderr_string = std::str::from_utf8(&process_output.stderr)?.trim();
-
-            // stderr_string is a string not an error, so here we build an err or output
-            if !stderr_string.is_empty() {
-                let msg = if stderr_string.contains("cannot create snapshots : permission denied") {
-                    "httm must have root privileges to snapshot a filesystem".to_owned()
-                } else {
-                    "httm was unable to take snapshots. The 'zfs' command issued the following error: ".to_owned() + stderr_string
-                };
+        map_snapshot_names
+            .iter()
+            .try_for_each(|(_pool_name, snapshot_names)| {
+                // taking a snapshot is the only privileged operation here
+                exec_privileged(&PrivilegedRequest::Snapshot {
+                    snapshot_names: snapshot_names.to_owned(),
+                })?;
 
-                Err(HttmError::new(&msg).into())
-            } else {
                 let output_buf = snapshot_names
                     .iter()
                     .map(|snap_name| format!("httm took a snapshot named: {}\n", &snap_name))
                     .collect();
                 print_output_buf(output_buf)
-            }
-        })?;
+            })?;
 
         Ok(())
     }
 
     let mounts_for_files: MountsForFiles = get_mounts_for_files(config.as_ref())?;
 
-    if let Ok(zfs_command) = which("zfs") {
-        exec_zfs_snapshot(config, &zfs_command, &mounts_for_files)
-    } else {
-        Err(
-            HttmError::new("'zfs' command not found. Make sure the command 'zfs' is in your path.")
-                .into(),
-        )
-    }
+    exec_zfs_snapshot(config, &mounts_for_files)
 }
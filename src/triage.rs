@@ -0,0 +1,308 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    fs::File,
+    io::{IsTerminal, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use rayon::prelude::*;
+
+use crate::bulk_restore::{bulk_restore_exec, ConflictPolicy, PlannedRestore};
+use crate::display::display_human_size;
+use crate::forensic::{audit, is_forensic};
+use crate::lookup_versions::get_version_entries;
+use crate::recursive::get_entries_partitioned;
+use crate::utility::{print_output_buf, HttmError, PathData};
+use crate::{Config, HttmResult, TriageMode};
+
+// a live file this small, relative to its newest snapshot version, collapsed in size
+pub const DEFAULT_SIZE_COLLAPSE_PERCENT: u64 = 10;
+// reading every byte of every file would take forever, so sample the start, middle and end of each
+const ENTROPY_SAMPLE_LEN: u64 = 16 * 1024;
+// past so few bytes, the entropy of a sample says little about its contents
+const MIN_ENTROPY_SAMPLE_LEN: usize = 512;
+// encrypted data is indistinguishable from random data, which approaches 8 bits per byte
+const ENCRYPTED_ENTROPY_BITS: f64 = 7.5;
+// a version which was already this random, like a compressed file, tells us nothing when it still is
+const PLAINTEXT_ENTROPY_BITS: f64 = 7.0;
+// the magic bytes of common document, archive and image formats, which encryption destroys
+const KNOWN_MAGIC_BYTES: [(&str, &[u8]); 9] = [
+    ("PDF", b"%PDF"),
+    ("PNG", b"\x89PNG"),
+    ("JPEG", b"\xFF\xD8\xFF"),
+    ("GIF", b"GIF8"),
+    ("ZIP", b"PK\x03\x04"),
+    ("GZIP", b"\x1F\x8B"),
+    ("ELF", b"\x7FELF"),
+    ("SQLite", b"SQLite format 3"),
+    ("OLE", b"\xD0\xCF\x11\xE0"),
+];
+
+// a live file which looks to have been damaged since its newest snapshot version
+#[derive(Debug, Clone)]
+struct Suspect {
+    live_pathdata: PathData,
+    newest_version: PathData,
+    // what exactly looks wrong, as in "1000 bytes -> 0 bytes"
+    finding: String,
+}
+
+pub fn triage_exec(config: &Config, triage_mode: &TriageMode) -> HttmResult<()> {
+    let requested_dir = config
+        .opt_requested_dir
+        .as_ref()
+        .ok_or_else(|| HttmError::new("requested_dir should never be None in a triage mode"))?;
+
+    let live_files = get_live_files(config, &requested_dir.path_buf)?;
+
+    let mut suspects: Vec<Suspect> = live_files
+        .into_par_iter()
+        .filter_map(|live_pathdata| {
+            let newest_version = get_newest_version(config, &live_pathdata)?;
+            let finding = get_finding(triage_mode, &live_pathdata, &newest_version)?;
+
+            Some(Suspect {
+                live_pathdata,
+                newest_version,
+                finding,
+            })
+        })
+        .collect();
+
+    if suspects.is_empty() {
+        eprintln!(
+            "httm found no suspect files within the directory specified: {:?}",
+            requested_dir.path_buf
+        );
+        return Ok(());
+    }
+
+    suspects.sort_by(|a, b| a.live_pathdata.cmp(&b.live_pathdata));
+
+    print_output_buf(display_suspects(config, triage_mode, &suspects))?;
+
+    // only offer to restore when someone is there to consent, otherwise this is just a report
+    if !std::io::stdout().is_terminal() || is_forensic() {
+        return Ok(());
+    }
+
+    let plan: Vec<PlannedRestore> = suspects
+        .into_iter()
+        .map(|suspect| PlannedRestore {
+            from: suspect.newest_version.path_buf,
+            opt_to: Some(suspect.live_pathdata.path_buf),
+            opt_conflict: Some(ConflictPolicy::Overwrite),
+        })
+        .collect();
+
+    bulk_restore_exec(config, &plan)
+}
+
+fn get_live_files(config: &Config, requested_dir: &Path) -> HttmResult<Vec<PathData>> {
+    let (vec_dirs, vec_files) = get_entries_partitioned(config, requested_dir)?;
+
+    // symlinks, devices, etc., have no contents of their own to damage
+    let mut live_files: Vec<PathData> = vec_files
+        .iter()
+        .filter(|basic_dir_entry_info| {
+            basic_dir_entry_info
+                .file_type
+                .map(|file_type| file_type.is_file())
+                .unwrap_or(false)
+        })
+        .map(|basic_dir_entry_info| PathData::from(basic_dir_entry_info.path.as_path()))
+        .collect();
+
+    if config.opt_recursive {
+        vec_dirs
+            .iter()
+            .map(|basic_dir_entry_info| get_live_files(config, &basic_dir_entry_info.path))
+            .collect::<HttmResult<Vec<Vec<PathData>>>>()?
+            .into_iter()
+            .for_each(|child_files| live_files.extend(child_files));
+    }
+
+    Ok(live_files)
+}
+
+fn get_newest_version(config: &Config, live_pathdata: &PathData) -> Option<PathData> {
+    get_version_entries(config, live_pathdata)
+        .into_iter()
+        .map(|version_entry| version_entry.pathdata)
+        .max_by_key(|version| version.md_infallible().modify_time)
+}
+
+fn get_finding(
+    triage_mode: &TriageMode,
+    live_pathdata: &PathData,
+    newest_version: &PathData,
+) -> Option<String> {
+    let live_size = live_pathdata.md_infallible().size;
+    let newest_size = newest_version.md_infallible().size;
+
+    match triage_mode {
+        // an empty file is always suspect, when it once had contents
+        TriageMode::SizeCollapse(percent) => (newest_size > 0
+            && (live_size == 0 || live_size * 100 <= newest_size * percent))
+            .then(|| {
+                format!(
+                    "{} -> {}",
+                    display_human_size(&newest_size),
+                    display_human_size(&live_size)
+                )
+            }),
+        TriageMode::Encryption => {
+            // an unchanged file can't have been newly encrypted, and this spares us reading most files
+            if live_pathdata.is_same_contents(newest_version) {
+                return None;
+            }
+
+            let live_sample = read_sample(&live_pathdata.path_buf).ok()?;
+            let newest_sample = read_sample(&newest_version.path_buf).ok()?;
+
+            // too small a sample, and random looking data might just be chance
+            if live_sample.len() < MIN_ENTROPY_SAMPLE_LEN
+                || newest_sample.len() < MIN_ENTROPY_SAMPLE_LEN
+            {
+                return None;
+            }
+
+            let live_entropy = get_entropy(&live_sample);
+            let newest_entropy = get_entropy(&newest_sample);
+
+            // encrypted data always looks random, but a compressed version already looked random,
+            // so, for those, a lost file format signature is the tell
+            let opt_lost_magic = get_magic(&newest_sample)
+                .filter(|_magic| get_magic(&live_sample).is_none())
+                .map(|magic| format!("{} signature lost, ", magic));
+
+            let is_newly_encrypted = live_entropy >= ENCRYPTED_ENTROPY_BITS
+                && (newest_entropy < PLAINTEXT_ENTROPY_BITS || opt_lost_magic.is_some());
+
+            is_newly_encrypted.then(|| {
+                format!(
+                    "{}entropy {:.2} -> {:.2} bits/byte",
+                    opt_lost_magic.unwrap_or_default(),
+                    newest_entropy,
+                    live_entropy
+                )
+            })
+        }
+    }
+}
+
+fn read_sample(path: &Path) -> HttmResult<Vec<u8>> {
+    audit("read", path);
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    // a small file is its own sample
+    if len <= ENTROPY_SAMPLE_LEN * 3 {
+        let mut sample = Vec::with_capacity(len as usize);
+        file.read_to_end(&mut sample)?;
+        return Ok(sample);
+    }
+
+    // the start always comes first, so any magic bytes are where we expect them
+    let offsets = [0, (len - ENTROPY_SAMPLE_LEN) / 2, len - ENTROPY_SAMPLE_LEN];
+    let mut sample = vec![0u8; (ENTROPY_SAMPLE_LEN * 3) as usize];
+
+    offsets
+        .iter()
+        .zip(sample.chunks_mut(ENTROPY_SAMPLE_LEN as usize))
+        .try_for_each(|(offset, chunk)| {
+            file.seek(SeekFrom::Start(*offset))?;
+            file.read_exact(chunk)
+        })?;
+
+    Ok(sample)
+}
+
+fn get_magic(sample: &[u8]) -> Option<&'static str> {
+    KNOWN_MAGIC_BYTES
+        .iter()
+        .find(|(_name, magic)| sample.starts_with(magic))
+        .map(|(name, _magic)| *name)
+}
+
+// shannon entropy, in bits per byte
+fn get_entropy(sample: &[u8]) -> f64 {
+    if sample.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; 256];
+    sample.iter().for_each(|byte| counts[*byte as usize] += 1);
+
+    let len = sample.len() as f64;
+
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let probability = *count as f64 / len;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+fn display_suspects(config: &Config, triage_mode: &TriageMode, suspects: &[Suspect]) -> String {
+    let lines: String = suspects
+        .iter()
+        .map(|suspect| {
+            if config.opt_no_pretty {
+                format!(
+                    "{}\t{}\t{}\n",
+                    suspect.live_pathdata.path_buf.to_string_lossy(),
+                    suspect.finding,
+                    suspect.newest_version.path_buf.to_string_lossy()
+                )
+            } else {
+                format!(
+                    "\t{:?}  {}\n\t\tnewest version: {:?}\n",
+                    suspect.live_pathdata.path_buf,
+                    suspect.finding,
+                    suspect.newest_version.path_buf
+                )
+            }
+        })
+        .collect();
+
+    if config.opt_no_pretty {
+        return lines;
+    }
+
+    let description = match triage_mode {
+        TriageMode::SizeCollapse(percent) => format!(
+            "whose live size is {}% or less of the size of their newest snapshot version",
+            percent
+        ),
+        TriageMode::Encryption => "which appear to have been newly encrypted, \
+            as their contents now look random, where their newest snapshot version did not, \
+            or had a file format signature which is now lost"
+            .to_owned(),
+    };
+
+    format!(
+        "httm found {} suspect file(s), {}:\n\n{}\n",
+        suspects.len(),
+        description,
+        lines
+    )
+}
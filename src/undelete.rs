@@ -0,0 +1,327 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use skim::prelude::*;
+
+use crate::bulk_restore::{bulk_restore_exec, ConflictPolicy, PlannedRestore};
+use crate::interactive::select_restore_view;
+use crate::lookup_deleted::deleted_lookup_exec;
+use crate::lookup_versions::get_all_search_bundles;
+use crate::parse_snaps::get_snap_creation_times;
+use crate::utility::{get_date, httm_is_dir, BasicDirEntryInfo, DateFormat, HttmError, PathData};
+use crate::{Config, HttmResult};
+
+// one coherent flow: pick what to bring back, where to, what to do upon a conflict, and then confirm the plan
+pub fn undelete_exec(config: &Config) -> HttmResult<()> {
+    let requested_dir = get_requested_dir(config)?;
+
+    let deleted = deleted_lookup_exec(config, &requested_dir.path_buf)?;
+
+    if deleted.is_empty() {
+        let msg = format!(
+            "httm could not find any deleted files to restore in the directory specified: {:?}",
+            requested_dir.path_buf
+        );
+        return Err(HttmError::new(&msg));
+    }
+
+    let last_seen = get_last_seen(config, &requested_dir, &deleted);
+
+    let selected = select_deleted(config, &deleted, &last_seen)?;
+
+    let destination_dir = select_destination_dir(config, &requested_dir)?;
+
+    let num_conflicts = selected
+        .iter()
+        .filter(|basic_dir_entry_info| {
+            destination_dir
+                .join(&basic_dir_entry_info.file_name)
+                .symlink_metadata()
+                .is_ok()
+        })
+        .count();
+
+    // only bother the user about conflicts if there are any
+    let conflict_policy = if num_conflicts == 0 {
+        ConflictPolicy::Skip
+    } else {
+        select_conflict_policy(config, num_conflicts)?
+    };
+
+    let plan: Vec<PlannedRestore> = selected
+        .iter()
+        .map(|basic_dir_entry_info| {
+            get_planned_restore(
+                config,
+                basic_dir_entry_info,
+                &destination_dir,
+                conflict_policy,
+            )
+        })
+        .collect();
+
+    bulk_restore_exec(config, &plan)
+}
+
+fn get_requested_dir(config: &Config) -> HttmResult<PathData> {
+    match config.paths.as_slice() {
+        [pathdata] if httm_is_dir(pathdata) => Ok(pathdata.clone()),
+        [_pathdata] => Err(HttmError::new(
+            "Path specified is not a directory, and therefore not suitable for undelete.",
+        )),
+        _ => Err(HttmError::new(
+            "May only specify one directory from which to undelete.",
+        )),
+    }
+}
+
+// a deleted file was last seen on the newest snapshot which still contains it, which may
+// not be the snapshot of its newest version, so look at all snapshots, not just the versions
+fn get_last_seen(
+    config: &Config,
+    requested_dir: &PathData,
+    deleted: &[BasicDirEntryInfo],
+) -> BTreeMap<OsString, SystemTime> {
+    get_all_search_bundles(config, requested_dir)
+        .iter()
+        .flat_map(|search_bundle| {
+            let opt_dataset_info = config
+                .dataset_collection
+                .map_of_datasets
+                .get(&search_bundle.dataset_of_interest);
+            let creation_times =
+                get_snap_creation_times(opt_dataset_info, &search_bundle.snap_mounts);
+
+            deleted
+                .iter()
+                .filter_map(|basic_dir_entry_info| {
+                    search_bundle
+                        .snap_mounts
+                        .iter()
+                        .filter(|snap_mount| {
+                            snap_mount
+                                .join(&search_bundle.relative_path)
+                                .join(&basic_dir_entry_info.file_name)
+                                .symlink_metadata()
+                                .is_ok()
+                        })
+                        .filter_map(|snap_mount| creation_times.get(snap_mount).copied())
+                        .max()
+                        .map(|time| (basic_dir_entry_info.file_name.clone(), time))
+                })
+                .collect::<Vec<(OsString, SystemTime)>>()
+        })
+        .fold(BTreeMap::new(), |mut map, (file_name, time)| {
+            let last_seen = map.entry(file_name).or_insert(time);
+            if time > *last_seen {
+                *last_seen = time;
+            }
+            map
+        })
+}
+
+fn select_deleted<'a>(
+    config: &Config,
+    deleted: &'a [BasicDirEntryInfo],
+    last_seen: &BTreeMap<OsString, SystemTime>,
+) -> HttmResult<Vec<&'a BasicDirEntryInfo>> {
+    // most recently deleted first, as that is most likely what the user is looking for
+    let mut entries: Vec<(SystemTime, &BasicDirEntryInfo)> = deleted
+        .iter()
+        .map(|basic_dir_entry_info| {
+            let time = last_seen
+                .get(&basic_dir_entry_info.file_name)
+                .copied()
+                .or_else(|| basic_dir_entry_info.get_modify_time())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (time, basic_dir_entry_info)
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then_with(|| a.1.file_name.cmp(&b.1.file_name))
+    });
+
+    // file names are unique among deleted entries, so each line is unique, and maps back to its entry
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|(time, basic_dir_entry_info)| {
+            let dir_suffix = if httm_is_dir(*basic_dir_entry_info) {
+                "/"
+            } else {
+                ""
+            };
+            format!(
+                "{}  {}{}",
+                get_date(config, time, DateFormat::Display),
+                basic_dir_entry_info.file_name.to_string_lossy(),
+                dir_suffix
+            )
+        })
+        .collect();
+
+    let header = "PAGE UP:    page up  | PAGE DOWN:  page down\n\
+        EXIT:       esc      | RESTORE:    enter    \n\
+        MULTI:      tab      | (select more than one deleted file)\n\
+        ─────────────────────────────────────────────\n\
+        LAST SEEN ON A SNAPSHOT         NAME";
+
+    let skim_opts = SkimOptionsBuilder::default()
+        .exact(true)
+        .multi(true)
+        .regex(false)
+        .nosort(true)
+        .header(Some(header))
+        .build()
+        .expect("Could not initialized skim options for undelete");
+
+    let item_reader = SkimItemReader::new(SkimItemReaderOption::default());
+    let items = item_reader.of_bufread(Cursor::new(lines.join("\n")));
+
+    let selected_items = match Skim::run_with(&skim_opts, Some(items)) {
+        Some(output) if output.is_abort => {
+            eprintln!("httm undelete session was aborted.  Quitting.");
+            std::process::exit(0)
+        }
+        Some(output) => output.selected_items,
+        None => return Err(HttmError::new("httm undelete session failed.")),
+    };
+
+    let selected: Vec<&BasicDirEntryInfo> = selected_items
+        .iter()
+        .filter_map(|item| {
+            let output = item.output();
+            lines
+                .iter()
+                .position(|line| line.as_str() == output)
+                .map(|idx| entries[idx].1)
+        })
+        .collect();
+
+    if selected.is_empty() {
+        return Err(HttmError::new(
+            "httm could not determine which deleted files were selected.",
+        ));
+    }
+
+    Ok(selected)
+}
+
+fn select_destination_dir(config: &Config, requested_dir: &PathData) -> HttmResult<PathBuf> {
+    // nothing to choose when the original location is where we already are
+    if requested_dir.path_buf == config.pwd.path_buf {
+        return Ok(requested_dir.path_buf.clone());
+    }
+
+    let original = requested_dir.path_buf.to_string_lossy();
+    let pwd = config.pwd.path_buf.to_string_lossy();
+
+    let preview_buffer = format!(
+        "Where should httm restore the files selected?  Either to their original location,\n\
+        or to the current working directory:\n\
+        ──────────────────────────────────────────────────────────────────────────────\n\
+        {}\n\
+        {}",
+        original, pwd
+    );
+
+    loop {
+        let selection = select_restore_view(config, &preview_buffer, true)?;
+
+        if selection == original {
+            break Ok(requested_dir.path_buf.clone());
+        } else if selection == pwd {
+            break Ok(config.pwd.path_buf.clone());
+        }
+    }
+}
+
+fn select_conflict_policy(config: &Config, num_conflicts: usize) -> HttmResult<ConflictPolicy> {
+    let preview_buffer = format!(
+        "{} of the files selected already exist at the destination.  What should httm do with those?\n\n\
+        \tSKIP:       leave the existing file be, and don't restore\n\
+        \tRENAME:     restore alongside the existing file, with a new name\n\
+        \tOVERWRITE:  restore over the existing file\n\
+        ──────────────────────────────────────────────────────────────────────────────\n\
+        SKIP\n\
+        RENAME\n\
+        OVERWRITE",
+        num_conflicts
+    );
+
+    loop {
+        let selection = select_restore_view(config, &preview_buffer, true)?.to_ascii_uppercase();
+
+        match selection.as_ref() {
+            "SKIP" => break Ok(ConflictPolicy::Skip),
+            "RENAME" => break Ok(ConflictPolicy::Rename),
+            "OVERWRITE" => break Ok(ConflictPolicy::Overwrite),
+            // if not one of the policies, then noop and continue to the next iter of loop
+            _ => {}
+        }
+    }
+}
+
+fn get_planned_restore(
+    config: &Config,
+    basic_dir_entry_info: &BasicDirEntryInfo,
+    destination_dir: &Path,
+    conflict_policy: ConflictPolicy,
+) -> PlannedRestore {
+    let from = basic_dir_entry_info.path.clone();
+    let to = destination_dir.join(&basic_dir_entry_info.file_name);
+
+    if to.symlink_metadata().is_err() {
+        return PlannedRestore {
+            from,
+            opt_to: Some(to),
+            opt_conflict: None,
+        };
+    }
+
+    let opt_to = match conflict_policy {
+        ConflictPolicy::Skip => None,
+        ConflictPolicy::Overwrite => Some(to),
+        // same naming as a restore which doesn't overwrite
+        ConflictPolicy::Rename => {
+            let modify_time = basic_dir_entry_info
+                .get_modify_time()
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            let new_filename = basic_dir_entry_info
+                .file_name
+                .to_string_lossy()
+                .into_owned()
+                + ".httm_restored."
+                + &get_date(config, &modify_time, DateFormat::Timestamp);
+            Some(destination_dir.join(new_filename))
+        }
+    };
+
+    PlannedRestore {
+        from,
+        opt_to,
+        opt_conflict: Some(conflict_policy),
+    }
+}
@@ -18,42 +18,145 @@
 use std::{
     borrow::Cow,
     cmp,
-    error::Error,
+    collections::BTreeMap,
     ffi::OsString,
     fmt,
-    fs::{copy, create_dir_all, read_dir, symlink_metadata, DirEntry, FileType, Metadata},
+    fs::{
+        copy, create_dir_all, read_dir, read_link, symlink_metadata, DirEntry, FileType, Metadata,
+    },
+    hash::{Hash, Hasher},
     io::{self, Read, Write},
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::MetadataExt,
+    },
     path::{Component::RootDir, Path, PathBuf},
-    time::SystemTime,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime},
 };
 
 use lscolors::{LsColors, Style};
+use once_cell::sync::OnceCell as SyncOnceCell;
 use once_cell::unsync::OnceCell;
-use time::{format_description, OffsetDateTime};
+use rayon::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use time::{format_description, Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
 
+use crate::forensic::audit;
+use crate::hashing::{get_comparison_hash, get_content_hash, get_hash_policy, HashPolicy};
 use crate::interactive::SelectionCandidate;
+use crate::restrict_owner::is_restricted_to_owner;
 use crate::{
     Config, FilesystemType, HttmResult, BTRFS_SNAPPER_HIDDEN_DIRECTORY, ZFS_SNAPSHOT_DIRECTORY,
 };
 
-pub fn copy_recursive(src: &Path, dst: &Path) -> io::Result<()> {
-    if PathBuf::from(src).is_dir() {
-        create_dir_all(&dst)?;
+// with should_preserve, as for any restore, each copy is given the metadata of its source, see
+// preserve_metadata, and each symlink is copied as a symlink, see copy_symlink, otherwise each
+// copy is a new file, as for a temporary copy, and each symlink is copied as what it points to
+pub fn copy_recursive(src: &Path, dst: &Path, should_preserve: bool) -> io::Result<()> {
+    if should_preserve && symlink_metadata(src)?.file_type().is_symlink() {
+        copy_symlink(src, dst)?;
+    } else if PathBuf::from(src).is_dir() {
+        create_dir_all(dst)?;
         for entry in read_dir(src)? {
             let entry = entry?;
             let file_type = entry.file_type()?;
-            if file_type.is_dir() {
-                copy_recursive(&entry.path(), &dst.join(&entry.file_name()))?;
+            if file_type.is_dir() || (should_preserve && file_type.is_symlink()) {
+                copy_recursive(&entry.path(), &dst.join(entry.file_name()), should_preserve)?;
             } else {
-                copy(&entry.path(), &dst.join(&entry.file_name()))?;
+                copy(entry.path(), dst.join(entry.file_name()))?;
+
+                if should_preserve {
+                    preserve_metadata(&entry.path(), &dst.join(entry.file_name()))?;
+                }
             }
         }
     } else {
         copy(src, dst)?;
     }
+
+    // a dir last, as copying its children changes its modify time
+    if should_preserve {
+        preserve_metadata(src, dst)?;
+    }
+
     Ok(())
 }
 
+// a temporary dir of a predictable name may already exist, made by another user, who may have
+// left a symlink within it, so each is made anew, with a random name, and a mode of 0700
+pub fn create_private_temp_dir(prefix: &str) -> io::Result<PathBuf> {
+    let template = std::env::temp_dir().join(format!("{}-XXXXXX", prefix));
+    let mut buffer = template.into_os_string().into_vec();
+    buffer.push(b'\0');
+
+    // safe, the template is NUL terminated, and mkdtemp() only rewrites its trailing X's
+    let res = unsafe { libc::mkdtemp(buffer.as_mut_ptr() as *mut libc::c_char) };
+
+    if res.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+
+    buffer.pop();
+
+    Ok(PathBuf::from(OsString::from_vec(buffer)))
+}
+
+// a symlink is restored as a symlink, pointing wherever the version's pointed, rather than as a
+// copy of whatever it happens to point to now
+pub fn copy_symlink(src: &Path, dst: &Path) -> io::Result<()> {
+    let target = read_link(src)?;
+    std::os::unix::fs::symlink(target, dst)
+}
+
+// a restored file should look like the version it was restored from, rather than a file just
+// created, so we carry over its access and modify times, its mode bits, and, as root, its owner,
+// unless restricted to the invoking user, as then what we restore is theirs, see as_invoking_user
+pub fn preserve_metadata(src: &Path, dst: &Path) -> io::Result<()> {
+    // a symlink is restored as a symlink, see copy_symlink, so we never follow one
+    let src_md = symlink_metadata(src)?;
+    let is_symlink = src_md.file_type().is_symlink();
+
+    // a chown clears any setuid and setgid bits, so it must precede the mode bits,
+    // safe, geteuid() cannot fail and has no side effects
+    if unsafe { libc::geteuid() } == 0 && !is_restricted_to_owner() {
+        std::os::unix::fs::lchown(dst, Some(src_md.uid()), Some(src_md.gid()))?;
+    }
+
+    // a symlink has no mode bits of its own, and setting them would set those of its target
+    if !is_symlink {
+        std::fs::set_permissions(dst, src_md.permissions())?;
+    }
+
+    // by path, rather than by an open file, as the mode just set may not permit us to open it
+    let c_path = std::ffi::CString::new(dst.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let times = [
+        libc::timespec {
+            tv_sec: src_md.atime(),
+            tv_nsec: src_md.atime_nsec(),
+        },
+        libc::timespec {
+            tv_sec: src_md.mtime(),
+            tv_nsec: src_md.mtime_nsec(),
+        },
+    ];
+
+    // safe, c_path is a valid C string, and times is an array of two, as utimensat(2) requires
+    match unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            c_path.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
 pub fn read_stdin() -> HttmResult<Vec<String>> {
     let stdin = std::io::stdin();
     let mut stdin = stdin.lock();
@@ -62,7 +165,6 @@ pub fn read_stdin() -> HttmResult<Vec<String>> {
 
     let broken_string: Vec<String> = std::str::from_utf8(&buffer)?
         .split_ascii_whitespace()
-        .into_iter()
         .map(|i| i.to_owned())
         .collect();
 
@@ -98,6 +200,22 @@ fn cmp_path<A: AsRef<Path>, B: AsRef<Path>>(a: A, b: B) -> Option<PathBuf> {
     }
 }
 
+pub fn get_home_dir() -> HttmResult<PathBuf> {
+    if let Ok(home) = std::env::var("HOME") {
+        if let Ok(path) = PathBuf::from(&home).canonicalize() {
+            Ok(path)
+        } else {
+            Err(HttmError::new(
+                "$HOME, as set in your environment, does not appear to exist",
+            ))
+        }
+    } else {
+        Err(HttmError::new(
+            "$HOME does not appear to be set in your environment",
+        ))
+    }
+}
+
 pub fn print_output_buf(output_buf: String) -> HttmResult<()> {
     // mutex keeps threads from writing over each other
     let out = std::io::stdout();
@@ -190,7 +308,7 @@ lazy_static! {
     static ref ENV_LS_COLORS: LsColors = LsColors::from_env().unwrap_or_default();
 }
 
-pub fn paint_string<T>(path: T, display_name: &str) -> Cow<str>
+pub fn paint_string<T>(path: T, display_name: &str) -> Cow<'_, str>
 where
     T: PaintString,
 {
@@ -218,7 +336,7 @@ impl PaintString for &PathData {
         ENV_LS_COLORS.style_for_path(self.path_buf.as_path())
     }
     fn get_is_phantom(&self) -> bool {
-        self.metadata.is_none()
+        self.metadata().is_none()
     }
 }
 
@@ -231,35 +349,120 @@ impl PaintString for &SelectionCandidate {
     }
 }
 
-#[derive(Debug)]
-pub struct HttmError {
-    pub details: String,
+#[derive(Debug, thiserror::Error)]
+pub enum HttmError {
+    #[error("{0}")]
+    Message(String),
+    #[error("{0}")]
+    NoDatasetFound(String),
+    // the path, where there is one, so that a JSON error may say which path failed
+    #[error("{msg}")]
+    NoVersions { msg: String, path: Option<PathBuf> },
+    #[error("{msg}")]
+    PermissionDenied { msg: String, path: Option<PathBuf> },
+    #[error("httm could not open {path:?} : {source}")]
+    OpenFailed { path: PathBuf, source: io::Error },
+    #[error("httm could not scan the snapshots of dataset {dataset:?} : {source}")]
+    SnapshotScanFailed { dataset: PathBuf, source: io::Error },
+    #[error("'{0}' command not found. Make sure the command '{0}' is in your path.")]
+    CommandNotFound(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error(transparent)]
+    FromUtf8(#[from] std::string::FromUtf8Error),
+    #[error(transparent)]
+    Nul(#[from] std::ffi::NulError),
+    #[error(transparent)]
+    StripPrefix(#[from] std::path::StripPrefixError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
 }
 
 impl HttmError {
     pub fn new(msg: &str) -> Self {
-        HttmError {
-            details: msg.to_owned(),
-        }
+        HttmError::Message(msg.to_owned())
     }
-    pub fn with_context(msg: &str, err: Box<dyn Error + Send + Sync>) -> Self {
+    pub fn with_context<E: fmt::Debug>(msg: &str, err: E) -> Self {
         let msg_plus_context = format!("{} : {:?}", msg, err);
-        HttmError {
-            details: msg_plus_context,
+        HttmError::Message(msg_plus_context)
+    }
+    // the exit status for each sort of failure, so scripts may tell them apart,
+    // 2 is reserved for a user abort, see PICKER_ABORT_EXIT_CODE
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HttmError::NoVersions { .. } => 3,
+            HttmError::NoDatasetFound(_) => 4,
+            HttmError::PermissionDenied { .. } => 5,
+            HttmError::Io(err) if err.kind() == io::ErrorKind::PermissionDenied => 5,
+            HttmError::OpenFailed { source, .. }
+                if source.kind() == io::ErrorKind::PermissionDenied =>
+            {
+                5
+            }
+            HttmError::SnapshotScanFailed { .. } => 6,
+            HttmError::CommandNotFound(_) => 7,
+            _ => 1,
         }
     }
-}
+    // a stable name for each sort of failure, for those who match on the JSON error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HttmError::NoVersions { .. } => "no_versions",
+            HttmError::NoDatasetFound(_) => "no_dataset_found",
+            HttmError::PermissionDenied { .. } => "permission_denied",
+            HttmError::Io(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                "permission_denied"
+            }
+            HttmError::OpenFailed { source, .. }
+                if source.kind() == io::ErrorKind::PermissionDenied =>
+            {
+                "permission_denied"
+            }
+            HttmError::OpenFailed { .. } => "io",
+            HttmError::SnapshotScanFailed { .. } => "snapshot_scan_failed",
+            HttmError::CommandNotFound(_) => "command_not_found",
+            HttmError::Io(_) => "io",
+            _ => "other",
+        }
+    }
+    pub fn to_json(&self) -> String {
+        let opt_dataset = match self {
+            HttmError::SnapshotScanFailed { dataset, .. } => Some(dataset.as_path()),
+            _ => None,
+        };
 
-impl fmt::Display for HttmError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.details)
+        let opt_path = match self {
+            HttmError::NoVersions { path, .. } | HttmError::PermissionDenied { path, .. } => {
+                path.as_deref()
+            }
+            HttmError::OpenFailed { path, .. } => Some(path.as_path()),
+            _ => None,
+        };
+
+        let json_error = JsonError {
+            code: self.exit_code(),
+            kind: self.kind(),
+            message: self.to_string(),
+            dataset: opt_dataset.map(|dataset| dataset.to_string_lossy()),
+            path: opt_path.map(|path| path.to_string_lossy()),
+        };
+
+        // a struct of only strings and integers cannot fail to serialize
+        serde_json::to_string(&json_error).unwrap_or_default()
     }
 }
 
-impl Error for HttmError {
-    fn description(&self) -> &str {
-        &self.details
-    }
+#[derive(Serialize)]
+struct JsonError<'a> {
+    code: i32,
+    kind: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dataset: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<Cow<'a, str>>,
 }
 
 // only the most basic data from a DirEntry
@@ -275,6 +478,7 @@ pub struct BasicDirEntryInfo {
 impl BasicDirEntryInfo {
     pub fn get_modify_time(&self) -> Option<SystemTime> {
         *self.modify_time.get_or_init(|| {
+            audit("stat", &self.path);
             self.path
                 .symlink_metadata()
                 .ok()
@@ -295,30 +499,97 @@ impl From<&DirEntry> for BasicDirEntryInfo {
 }
 
 // detailed info required to differentiate and display file versions
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+//
+// only the path is known up front, everything else is collected on first request,
+// so that a feature which needs, say, a hash doesn't make every other path pay for one
+#[derive(Clone, Debug)]
 pub struct PathData {
     pub path_buf: PathBuf,
-    pub metadata: Option<PathMetadata>,
+    metadata: SyncOnceCell<Option<PathMetadata>>,
+    xattrs: SyncOnceCell<Option<Xattrs>>,
+    content_hash: SyncOnceCell<Option<String>>,
+    comparison_hash: SyncOnceCell<Option<String>>,
+    dir_manifest: SyncOnceCell<Option<DirManifest>>,
+    recursive_size: SyncOnceCell<Option<RecursiveSize>>,
+    file_id: SyncOnceCell<Option<FileId>>,
+}
+
+// where a file lives on disk, and whether it is a dir, collected alongside its metadata
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct FileId {
+    dev: u64,
+    ino: u64,
+    is_dir: bool,
+}
+
+impl From<&Metadata> for FileId {
+    fn from(md: &Metadata) -> Self {
+        FileId {
+            dev: md.dev(),
+            ino: md.ino(),
+            is_dir: md.is_dir(),
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub struct PathMetadata {
     pub size: u64,
     pub modify_time: SystemTime,
+    // a chmod or a chown changes none of the above, so we keep these too
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl From<Metadata> for PathMetadata {
+    fn from(md: Metadata) -> Self {
+        PathMetadata {
+            size: md.len(),
+            // may fail on systems that don't collect a modify time
+            modify_time: md.modified().unwrap_or(PHANTOM_DATE),
+            mode: md.mode(),
+            uid: md.uid(),
+            gid: md.gid(),
+        }
+    }
 }
 
+// key: xattr name, val: xattr value
+pub type Xattrs = BTreeMap<OsString, Vec<u8>>;
+
 pub const PHANTOM_DATE: SystemTime = SystemTime::UNIX_EPOCH;
 pub const PHANTOM_SIZE: u64 = 0u64;
 
 pub const PHANTOM_PATH_METADATA: PathMetadata = PathMetadata {
     size: PHANTOM_SIZE,
     modify_time: PHANTOM_DATE,
+    mode: 0,
+    uid: 0,
+    gid: 0,
 };
 
+// two versions are the same version when they have the same path and the same metadata,
+// xattrs and hashes are derived from these and don't bear on equality
+impl PartialEq for PathData {
+    fn eq(&self, other: &Self) -> bool {
+        self.path_buf == other.path_buf && self.metadata() == other.metadata()
+    }
+}
+
+impl Eq for PathData {}
+
+impl Hash for PathData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path_buf.hash(state);
+        self.metadata().hash(state);
+    }
+}
+
 impl cmp::PartialOrd for PathData {
     #[inline]
     fn partial_cmp(&self, other: &PathData) -> Option<cmp::Ordering> {
-        Some(self.path_buf.cmp(&other.path_buf))
+        Some(self.cmp(other))
     }
 }
 
@@ -331,8 +602,7 @@ impl cmp::Ord for PathData {
 
 impl From<&Path> for PathData {
     fn from(path: &Path) -> Self {
-        let opt_metadata = symlink_metadata(path).ok();
-        PathData::from_parts(path, opt_metadata)
+        PathData::new(path)
     }
 }
 
@@ -345,7 +615,7 @@ impl From<&DirEntry> for PathData {
 }
 
 impl PathData {
-    pub fn from_parts(path: &Path, opt_metadata: Option<Metadata>) -> Self {
+    pub fn new(path: &Path) -> Self {
         let absolute_path: PathBuf = if path.is_relative() {
             // canonicalize() on any path that DNE will throw an error
             //
@@ -356,25 +626,322 @@ impl PathData {
             path.to_path_buf()
         };
 
-        // call symlink_metadata, as we need to resolve symlinks to get non-"phantom" metadata
-        let metadata = opt_metadata.map(|md| {
-            let len = md.len();
-            // may fail on systems that don't collect a modify time
-            let time = md.modified().unwrap_or(PHANTOM_DATE);
-            PathMetadata {
-                size: len,
-                modify_time: time,
-            }
-        });
-
         PathData {
             path_buf: absolute_path,
-            metadata,
+            metadata: SyncOnceCell::new(),
+            xattrs: SyncOnceCell::new(),
+            content_hash: SyncOnceCell::new(),
+            comparison_hash: SyncOnceCell::new(),
+            dir_manifest: SyncOnceCell::new(),
+            recursive_size: SyncOnceCell::new(),
+            file_id: SyncOnceCell::new(),
         }
     }
+    // for when we already have the metadata in hand, as from a DirEntry
+    pub fn from_parts(path: &Path, opt_metadata: Option<Metadata>) -> Self {
+        let pathdata = PathData::new(path);
+        let _ = pathdata
+            .file_id
+            .set(opt_metadata.as_ref().map(FileId::from));
+        let _ = pathdata.metadata.set(opt_metadata.map(PathMetadata::from));
+        pathdata
+    }
+    pub fn metadata(&self) -> Option<PathMetadata> {
+        // call symlink_metadata, as we need to resolve symlinks to get non-"phantom" metadata
+        *self.metadata.get_or_init(|| {
+            audit("stat", &self.path_buf);
+            let opt_md = symlink_metadata(&self.path_buf).ok();
+            // the file id comes from the same stat, so we never stat twice for it
+            let _ = self.file_id.set(opt_md.as_ref().map(FileId::from));
+            opt_md.map(PathMetadata::from)
+        })
+    }
+    fn file_id(&self) -> Option<FileId> {
+        *self.file_id.get_or_init(|| {
+            symlink_metadata(&self.path_buf)
+                .ok()
+                .as_ref()
+                .map(FileId::from)
+        })
+    }
     pub fn md_infallible(&self) -> PathMetadata {
-        self.metadata.unwrap_or(PHANTOM_PATH_METADATA)
+        self.metadata().unwrap_or(PHANTOM_PATH_METADATA)
+    }
+    pub fn xattrs(&self) -> Option<&Xattrs> {
+        self.xattrs
+            .get_or_init(|| get_xattrs(&self.path_buf).ok())
+            .as_ref()
+    }
+    pub fn content_hash(&self) -> Option<&str> {
+        self.content_hash
+            .get_or_init(|| get_content_hash(&self.path_buf).ok())
+            .as_deref()
+    }
+    // the hash by which versions are compared, per HASH, which is just the content hash by default
+    pub fn comparison_hash(&self) -> Option<&str> {
+        if get_hash_policy() == HashPolicy::default() {
+            return self.content_hash();
+        }
+
+        self.comparison_hash
+            .get_or_init(|| get_comparison_hash(&self.path_buf).ok())
+            .as_deref()
+    }
+    // only a dir has a manifest, and we know what is a dir from the stat we already made
+    pub fn dir_manifest(&self) -> Option<&DirManifest> {
+        self.dir_manifest
+            .get_or_init(|| match self.file_id() {
+                Some(file_id) if file_id.is_dir => get_dir_manifest(&self.path_buf).ok(),
+                _ => None,
+            })
+            .as_ref()
+    }
+    // the first call decides the bound, later calls just return what was found
+    pub fn recursive_size(&self, max_entries: usize) -> Option<RecursiveSize> {
+        *self.recursive_size.get_or_init(|| match self.file_id() {
+            Some(file_id) if file_id.is_dir => {
+                Some(get_recursive_size(&self.path_buf, max_entries))
+            }
+            _ => None,
+        })
+    }
+    // two versions have the same contents when their hashes match, but hashing is expensive,
+    // and, on ZFS, a file unchanged across snapshots is the very same object, with the same
+    // inode number and modify time, so, when we find the same object, we needn't hash at all
+    pub fn is_same_contents(&self, other: &PathData) -> bool {
+        let (md, other_md) = match (self.metadata(), other.metadata()) {
+            (Some(md), Some(other_md)) => (md, other_md),
+            _ => return false,
+        };
+
+        if md.size != other_md.size {
+            return false;
+        }
+
+        if md.modify_time == other_md.modify_time && self.is_same_object(other) {
+            return true;
+        }
+
+        match (self.comparison_hash(), other.comparison_hash()) {
+            (Some(hash), Some(other_hash)) => hash == other_hash,
+            _ => false,
+        }
     }
+    // inode numbers are only unique within a filesystem, and each snapshot is mounted as its
+    // own filesystem, so the same inode number means the same object only on the same device,
+    // or across snapshots of the same ZFS dataset
+    fn is_same_object(&self, other: &PathData) -> bool {
+        match (self.file_id(), other.file_id()) {
+            (Some(file_id), Some(other_file_id)) if file_id.ino == other_file_id.ino => {
+                file_id.dev == other_file_id.dev
+                    || matches!(
+                        (
+                            get_zfs_snapshotted_dataset(&self.path_buf),
+                            get_zfs_snapshotted_dataset(&other.path_buf)
+                        ),
+                        (Some(dataset), Some(other_dataset)) if dataset == other_dataset
+                    )
+            }
+            _ => false,
+        }
+    }
+}
+
+// the mount of the dataset for a path within a ZFS snapshot,
+// e.g. /srv for /srv/.zfs/snapshot/snap_8a86e4fc_prepApt/etc/fstab
+pub fn get_zfs_snapshotted_dataset(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .find(|ancestor| ancestor.ends_with(ZFS_SNAPSHOT_DIRECTORY))
+        .and_then(|snapshot_dir| snapshot_dir.parent())
+        .and_then(|zfs_hidden_dir| zfs_hidden_dir.parent())
+}
+
+// what a directory holds, as opposed to the metadata of the directory itself, which only changes
+// when children are added, removed or renamed, and not when a child is modified
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirManifest {
+    pub num_entries: usize,
+    pub total_size: u64,
+    // SHA-256 of the name, type, size and modify time of each child, in order of name
+    pub listing_hash: String,
+}
+
+fn get_dir_manifest(path: &Path) -> HttmResult<DirManifest> {
+    audit("list", path);
+    let mut children: Vec<(OsString, Metadata)> = read_dir(path)?
+        .flatten()
+        .filter_map(|dir_entry| {
+            // DirEntry metadata does not traverse symlinks, as we wish
+            dir_entry
+                .metadata()
+                .ok()
+                .map(|md| (dir_entry.file_name(), md))
+        })
+        .collect();
+    children.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut hasher = Sha256::new();
+
+    children.iter().for_each(|(file_name, md)| {
+        let file_type = if md.is_dir() {
+            "d"
+        } else if md.file_type().is_symlink() {
+            "l"
+        } else {
+            "f"
+        };
+        let modify_time = md
+            .modified()
+            .unwrap_or(PHANTOM_DATE)
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        hasher.update(file_name.as_bytes());
+        hasher.update(
+            format!(
+                "\0{}\0{}\0{}.{:09}\n",
+                file_type,
+                md.len(),
+                modify_time.as_secs(),
+                modify_time.subsec_nanos()
+            )
+            .as_bytes(),
+        );
+    });
+
+    let listing_hash = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    Ok(DirManifest {
+        num_entries: children.len(),
+        // only files count towards the total, as the size of a dir on disk says nothing of its contents
+        total_size: children
+            .iter()
+            .filter(|(_file_name, md)| md.is_file())
+            .map(|(_file_name, md)| md.len())
+            .sum(),
+        listing_hash,
+    })
+}
+
+// the sum of the sizes of every file beneath a directory, or of as many as we would count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecursiveSize {
+    pub total_size: u64,
+    pub is_partial: bool,
+}
+
+fn get_recursive_size(path: &Path, max_entries: usize) -> RecursiveSize {
+    // shared by every thread, so the bound is on the whole tree, not on each dir
+    let num_entries = AtomicUsize::new(0);
+
+    fn sum_dir(path: &Path, num_entries: &AtomicUsize, max_entries: usize) -> u64 {
+        audit("list", path);
+        let entries = match read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => return 0,
+        };
+
+        entries
+            .flatten()
+            .par_bridge()
+            .map(|dir_entry| {
+                if num_entries.fetch_add(1, Ordering::Relaxed) >= max_entries {
+                    return 0;
+                }
+
+                // DirEntry file types do not traverse symlinks, as we wish
+                match dir_entry.file_type() {
+                    Ok(file_type) if file_type.is_dir() => {
+                        sum_dir(&dir_entry.path(), num_entries, max_entries)
+                    }
+                    Ok(file_type) if file_type.is_file() => {
+                        dir_entry.metadata().map(|md| md.len()).unwrap_or(0)
+                    }
+                    _ => 0,
+                }
+            })
+            .sum()
+    }
+
+    let total_size = sum_dir(path, &num_entries, max_entries);
+
+    RecursiveSize {
+        total_size,
+        is_partial: num_entries.load(Ordering::Relaxed) > max_entries,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn get_xattrs(path: &Path) -> HttmResult<Xattrs> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+
+    // first ask how large a buffer we need, then fill it, the same goes for each value
+    let list_len = unsafe { libc::llistxattr(c_path.as_ptr(), std::ptr::null_mut(), 0) };
+    if list_len < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    let mut names_buf = vec![0u8; list_len as usize];
+    let list_len = unsafe {
+        libc::llistxattr(
+            c_path.as_ptr(),
+            names_buf.as_mut_ptr() as *mut libc::c_char,
+            names_buf.len(),
+        )
+    };
+    if list_len < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    names_buf.truncate(list_len as usize);
+
+    // names are delimited by nul bytes
+    let xattrs = names_buf
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| {
+            let c_name = CString::new(name).ok()?;
+
+            let value_len = unsafe {
+                libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0)
+            };
+            if value_len < 0 {
+                return None;
+            }
+
+            let mut value_buf = vec![0u8; value_len as usize];
+            let value_len = unsafe {
+                libc::lgetxattr(
+                    c_path.as_ptr(),
+                    c_name.as_ptr(),
+                    value_buf.as_mut_ptr() as *mut libc::c_void,
+                    value_buf.len(),
+                )
+            };
+            if value_len < 0 {
+                return None;
+            }
+            value_buf.truncate(value_len as usize);
+
+            Some((OsString::from_vec(name.to_vec()), value_buf))
+        })
+        .collect();
+
+    Ok(xattrs)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_xattrs(_path: &Path) -> HttmResult<Xattrs> {
+    Err(
+        HttmError::new("httm does not yet support reading extended attributes on this platform.")
+            .into(),
+    )
 }
 
 pub fn get_fs_type_from_hidden_dir(dataset_mount: &Path) -> HttmResult<FilesystemType> {
@@ -394,31 +961,41 @@ pub fn get_fs_type_from_hidden_dir(dataset_mount: &Path) -> HttmResult<Filesyste
     } else {
         return Err(HttmError::new(
                 "Requesting a filesystem type from path is only available for ZFS datasets and btrfs datasets snapshot-ed via snapper.",
-            )
-            .into());
+            ));
     };
 
     Ok(fs_type)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DateFormat {
     Display,
     Timestamp,
+    Day,
+    SmbGmt,
 }
 
 const DATE_FORMAT_DISPLAY: &str =
     "[weekday repr:short] [month repr:short] [day] [hour]:[minute]:[second] [year]";
 const DATE_FORMAT_TIMESTAMP: &str = "[year]-[month]-[day]-[hour]:[minute]:[second]";
+const DATE_FORMAT_DAY: &str = "[year]-[month]-[day]";
+// the "@GMT" token by which SMB clients, and Samba's shadow_copy2, name a previous version
+const DATE_FORMAT_SMB_GMT: &str = "@GMT-[year].[month].[day]-[hour].[minute].[second]";
 
 pub fn get_date(config: &Config, system_time: &SystemTime, format: DateFormat) -> String {
     let date_time: OffsetDateTime = (*system_time).into();
 
+    // an "@GMT" token is always in UTC, whatever the offset requested
+    let utc_offset = match format {
+        DateFormat::SmbGmt => UtcOffset::UTC,
+        _ => config.requested_utc_offset,
+    };
+
     let date_format = format_description::parse(get_date_format(format))
         .expect("timestamp date format is invalid");
 
     date_time
-        .to_offset(config.requested_utc_offset)
+        .to_offset(utc_offset)
         .format(&date_format)
         .expect("timestamp date format could not be applied to the date supplied")
 }
@@ -427,5 +1004,76 @@ fn get_date_format<'a>(format: DateFormat) -> &'a str {
     match format {
         DateFormat::Display => DATE_FORMAT_DISPLAY,
         DateFormat::Timestamp => DATE_FORMAT_TIMESTAMP,
+        DateFormat::Day => DATE_FORMAT_DAY,
+        DateFormat::SmbGmt => DATE_FORMAT_SMB_GMT,
     }
 }
+
+// like "2022-08-01" and "12:00", "12:00:00", or "12:00:00.123"
+pub fn parse_date_time(date: &str, time: &str, offset: UtcOffset) -> Option<SystemTime> {
+    let mut date_parts = date.splitn(3, '-');
+    let year: i32 = date_parts.next()?.parse().ok()?;
+    let month: u8 = date_parts.next()?.parse().ok()?;
+    let day: u8 = date_parts.next()?.parse().ok()?;
+
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time_parts = time.splitn(3, ':');
+    let hour: u8 = time_parts.next()?.parse().ok()?;
+    let minute: u8 = time_parts.next()?.parse().ok()?;
+    let second: u8 = time_parts.next().unwrap_or("0").parse().ok()?;
+
+    // a fraction of a second, to the nanosecond
+    let nanos: u32 = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<9}", &fraction[..fraction.len().min(9)])
+            .parse()
+            .ok()?
+    };
+
+    let date = Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok()?;
+    let time = Time::from_hms_nano(hour, minute, second, nanos).ok()?;
+    let date_time: OffsetDateTime = PrimitiveDateTime::new(date, time).assume_offset(offset);
+
+    Some(date_time.into())
+}
+
+// a bound of a window of versions, given as a date, like "2022-08-01", a date and a time, like
+// "2022-08-01 12:00" or "2022-08-01T12:00:00", or as a span before now, like "90m", "12h", "7d" or "2w"
+pub fn parse_date_bound(raw: &str, utc_offset: UtcOffset) -> HttmResult<SystemTime> {
+    let raw = raw.trim();
+
+    let opt_span = raw
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|idx| *idx > 0)
+        .and_then(|idx| {
+            let (num, unit) = raw.split_at(idx);
+            let secs_per_unit: u64 = match unit {
+                "s" => 1,
+                "m" => 60,
+                "h" => 60 * 60,
+                "d" => 24 * 60 * 60,
+                "w" => 7 * 24 * 60 * 60,
+                _ => return None,
+            };
+            num.parse::<u64>().ok()?.checked_mul(secs_per_unit)
+        });
+
+    if let Some(secs) = opt_span {
+        return SystemTime::now()
+            .checked_sub(Duration::from_secs(secs))
+            .ok_or_else(|| HttmError::new(&format!("The span {:?} is too long ago.", raw)));
+    }
+
+    let (date, time) = raw
+        .split_once(['T', ' '])
+        .map(|(date, time)| (date, time.trim()))
+        .unwrap_or((raw, "00:00"));
+
+    parse_date_time(date, time, utc_offset).ok_or_else(|| {
+        HttmError::new(&format!(
+            "httm could not parse {:?} as a date, like \"2022-08-01\" or \"2022-08-01 12:00\", or as a span before now, like \"7d\".",
+            raw
+        ))
+    })
+}
@@ -0,0 +1,123 @@
+//       ___           ___           ___           ___
+//      /\__\         /\  \         /\  \         /\__\
+//     /:/  /         \:\  \        \:\  \       /::|  |
+//    /:/__/           \:\  \        \:\  \     /:|:|  |
+//   /::\  \ ___       /::\  \       /::\  \   /:/|:|__|__
+//  /:/\:\  /\__\     /:/\:\__\     /:/\:\__\ /:/ |::::\__\
+//  \/__\:\/:/  /    /:/  \/__/    /:/  \/__/ \/__/~~/:/  /
+//       \::/  /    /:/  /        /:/  /            /:/  /
+//       /:/  /     \/__/         \/__/            /:/  /
+//      /:/  /                                    /:/  /
+//      \/__/                                     \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeSet,
+    fs::{create_dir_all, read_dir},
+    path::{Path, PathBuf},
+};
+
+use crate::forensic::deny_if_forensic;
+use crate::lookup_versions::get_live_path;
+use crate::restrict_owner::deny_if_restricted_to_owner;
+use crate::utility::{
+    copy_recursive, create_private_temp_dir, print_output_buf, HttmError, PathData,
+};
+use crate::{Config, HttmResult};
+
+// a fresh dir for each session, unless the user would rather name a dir, like a tmpfs or a
+// scratch dataset they have just created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkspaceDir {
+    // a new 0700 dir of a random name, within the system's temporary dir, made only once
+    // versions are selected, see create_private_temp_dir
+    Default,
+    Requested(PathBuf),
+}
+
+// we never copy over anything, a workspace is either new or empty, and we check before the
+// user bothers to select anything, and once more before we copy
+pub fn deny_unless_empty_workspace(workspace_dir: &Path) -> HttmResult<()> {
+    if workspace_dir.exists() && read_dir(workspace_dir)?.next().is_some() {
+        return Err(HttmError::new(&format!(
+            "httm will only copy versions into a new or empty workspace, and {:?} is not empty.",
+            workspace_dir
+        )));
+    }
+
+    Ok(())
+}
+
+// copy each version into the workspace at the path of its live file, so /srv/app/.zfs/snapshot/snap_1/etc/app.conf
+// becomes <workspace>/srv/app/etc/app.conf, and old code or configs may be run in place of the live tree
+pub fn materialize_workspace(
+    config: &Config,
+    versions: &[PathData],
+    workspace_dir: &WorkspaceDir,
+) -> HttmResult<()> {
+    deny_if_forensic("copy versions into a workspace")?;
+    deny_if_restricted_to_owner("copy versions into a workspace")?;
+
+    if versions.is_empty() {
+        return Err(HttmError::new(
+            "httm found no snapshot versions to copy into a workspace.",
+        ));
+    }
+
+    let workspace_dir = match workspace_dir {
+        WorkspaceDir::Default => create_private_temp_dir("httm-workspace")?,
+        WorkspaceDir::Requested(workspace_dir) => {
+            deny_unless_empty_workspace(workspace_dir)?;
+            workspace_dir.clone()
+        }
+    };
+
+    let mut workspace_paths: BTreeSet<PathBuf> = BTreeSet::new();
+
+    let copies: Vec<(&PathData, PathBuf)> = versions
+        .iter()
+        .map(|version| {
+            let live_path = get_live_path(config, version).ok_or_else(|| {
+                HttmError::new(&format!(
+                    "httm could not determine the live path of the version: {:?}",
+                    version.path_buf
+                ))
+            })?;
+
+            let workspace_path =
+                workspace_dir.join(live_path.strip_prefix("/").unwrap_or(&live_path));
+
+            // two versions of one file can't share one place in the workspace
+            if !workspace_paths.insert(workspace_path.clone()) {
+                return Err(HttmError::new(&format!(
+                    "httm can only copy one version of each file into a workspace, and more than one version of {:?} was selected.",
+                    live_path
+                )));
+            }
+
+            Ok((version, workspace_path))
+        })
+        .collect::<HttmResult<Vec<(&PathData, PathBuf)>>>()?;
+
+    copies
+        .iter()
+        .try_for_each(|(version, workspace_path)| -> HttmResult<()> {
+            if let Some(parent) = workspace_path.parent() {
+                create_dir_all(parent)?;
+            }
+
+            copy_recursive(&version.path_buf, workspace_path, false)?;
+            eprintln!("httm copied {:?} to {:?}", version.path_buf, workspace_path);
+
+            Ok(())
+        })?;
+
+    // just the workspace on stdout, so a script may cd into it
+    print_output_buf(format!("{}\n", workspace_dir.to_string_lossy()))?;
+
+    Ok(())
+}